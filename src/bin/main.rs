@@ -1,43 +1,449 @@
 extern crate clap;
 extern crate core;
+extern crate env_logger;
+#[macro_use]
+extern crate log;
+extern crate toml;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use core::data_store::DataStore;
 use core::fs_interaction::relative_path::RelativePath;
+use core::fs_interaction::virtual_fs;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::ops::{Deref, DerefMut};
 use std::path::PathBuf;
 
 fn main() {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let cli = cli_app().get_matches_from(&args);
+    init_logger(&cli);
+
+    if cli.value_of("fs") == Some("memory") {
+        if !cfg!(debug_assertions) {
+            panic!("The in-memory file system is only available in debug builds (for testing).");
+        }
+        let fs = virtual_fs::InMemoryFS::new();
+        if cli.value_of("LOCAL_PATH") == Some(SESSION_PATH) {
+            run_session(&fs);
+        } else {
+            run_command(&cli, &args, &fs);
+        }
+    } else {
+        run_command(&cli, &args, &virtual_fs::WrapperFS::new());
+    }
+}
+
+/// Local path reading a session of commands from stdin (see run_session).
+const SESSION_PATH: &str = "-";
+
+/// Runs the commands read from stdin on the in-memory FS (testing only), one command per line
+/// in the format of the command line (e.g. './store-1 sync-from ./store-2'). Arguments are
+/// separated by whitespace, lines starting with '#' are skipped. All commands share the FS and
+/// the stores opened on it, i.e. a store created by one command is used by the following ones.
+fn run_session<FS: virtual_fs::FS>(fs: &FS) {
+    use std::io::BufRead;
+
+    let stdin = std::io::stdin();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        let args: Vec<&str> = line.split_whitespace().collect();
+        if args.is_empty() || args[0].starts_with('#') {
+            continue;
+        }
+
+        let args: Vec<OsString> = std::iter::once("main")
+            .chain(args)
+            .map(OsString::from)
+            .collect();
+        let cli = cli_app().get_matches_from(&args);
+        run_command(&cli, &args, fs);
+    }
+}
+
+fn cli_app<'a, 'b>() -> App<'a, 'b> {
+    // Testing only, allows to run the CLI without touching the disk (stores only live as long
+    // as the process, use the local path '-' to run a session of commands, see run_session).
+    let fs_arg = Arg::with_name("fs")
+        .long("fs")
+        .hidden(true)
+        .global(true)
+        .takes_value(true)
+        .possible_values(&["native", "memory"])
+        .default_value("native");
     let local_path_arg = Arg::with_name("LOCAL_PATH")
         .required(true)
         .index(1)
         .help("Path of the local data store on disk");
+    let verbose_arg = Arg::with_name("verbose")
+        .long("verbose")
+        .short("v")
+        .multiple(true)
+        .global(true)
+        .help("Prints more details on what is going on (repeat for even more output).");
+    let quiet_arg = Arg::with_name("quiet")
+        .long("quiet")
+        .short("q")
+        .global(true)
+        .conflicts_with("verbose")
+        .help("Only prints errors, no warnings.");
     let cli = App::new("DataSquirrel")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about("Allows to synchronize directories p2p without restrictions on the sync order")
         .arg(local_path_arg)
+        .arg(verbose_arg)
+        .arg(quiet_arg)
+        .arg(fs_arg)
         .subcommand(create_cmd())
         .subcommand(scan_cmd())
         .subcommand(sync_from_cmd())
         .subcommand(optimize_cmd())
         .subcommand(rules_cmd())
-        .get_matches();
+        .subcommand(clean_pending_cmd())
+        .subcommand(stores_cmd())
+        .subcommand(unlock_cmd())
+        .subcommand(export_index_cmd())
+        .subcommand(export_manifest_cmd())
+        .subcommand(export_changes_cmd())
+        .subcommand(apply_changes_cmd())
+        .subcommand(divergence_cmd())
+        .subcommand(reidentify_cmd())
+        .subcommand(item_status_cmd())
+        .subcommand(ignored_cmd())
+        .subcommand(summary_cmd())
+        .subcommand(rehash_cmd())
+        .subcommand(transfer_cmd())
+        .subcommand(dedup_cmd())
+        .subcommand(dataset_cmd())
+        .subcommand(pause_cmd())
+        .subcommand(merge_from_cmd())
+        .subcommand(status_cmd())
+        .subcommand(check_pair_cmd())
+        .subcommand(mirror_from_cmd());
+
+    cli
+}
+
+fn run_command<FS: virtual_fs::FS>(cli: &ArgMatches, args: &[OsString], fs: &FS) {
+    let layered_cli = apply_config_defaults(cli, args, fs);
+    let cli = layered_cli.as_ref().unwrap_or(cli);
 
     let local_path = cli.value_of("LOCAL_PATH").unwrap();
     if let Some(create_cli) = cli.subcommand_matches("create") {
-        create_data_store(&local_path, &create_cli);
+        create_data_store(local_path, fs, create_cli);
     } else if let Some(scan_cli) = cli.subcommand_matches("scan") {
-        scan_data_store(&local_path, &scan_cli);
+        scan_data_store(local_path, fs, scan_cli);
     } else if let Some(sync_from_cli) = cli.subcommand_matches("sync-from") {
-        sync_from_remote(&local_path, &sync_from_cli);
+        sync_from_remote(local_path, fs, sync_from_cli);
     } else if let Some(cleanup_cli) = cli.subcommand_matches("optimize") {
-        optimize_data_store(&local_path, &cleanup_cli);
+        optimize_data_store(local_path, fs, cleanup_cli);
     } else if let Some(inclusion_cli) = cli.subcommand_matches("rules") {
-        manage_inclusion_rules(&local_path, inclusion_cli);
+        manage_inclusion_rules(local_path, fs, inclusion_cli);
+    } else if let Some(clean_pending_cli) = cli.subcommand_matches("clean-pending") {
+        clean_pending_files(local_path, fs, clean_pending_cli);
+    } else if let Some(stores_cli) = cli.subcommand_matches("stores") {
+        list_data_stores(local_path, fs, stores_cli);
+    } else if let Some(unlock_cli) = cli.subcommand_matches("unlock") {
+        unlock_data_store(local_path, fs, unlock_cli);
+    } else if let Some(export_index_cli) = cli.subcommand_matches("export-index") {
+        export_index(local_path, fs, export_index_cli);
+    } else if let Some(export_manifest_cli) = cli.subcommand_matches("export-manifest") {
+        export_manifest(local_path, fs, export_manifest_cli);
+    } else if let Some(export_changes_cli) = cli.subcommand_matches("export-changes") {
+        export_changes(local_path, fs, export_changes_cli);
+    } else if let Some(apply_changes_cli) = cli.subcommand_matches("apply-changes") {
+        apply_changes(local_path, fs, apply_changes_cli);
+    } else if let Some(divergence_cli) = cli.subcommand_matches("divergence") {
+        show_divergence(local_path, fs, divergence_cli);
+    } else if let Some(reidentify_cli) = cli.subcommand_matches("reidentify") {
+        reidentify_data_store(local_path, fs, reidentify_cli);
+    } else if let Some(item_status_cli) = cli.subcommand_matches("item-status") {
+        show_item_status(local_path, fs, item_status_cli);
+    } else if let Some(ignored_cli) = cli.subcommand_matches("ignored") {
+        show_ignored_items(local_path, fs, ignored_cli);
+    } else if let Some(summary_cli) = cli.subcommand_matches("summary") {
+        show_tree_summary(local_path, fs, summary_cli);
+    } else if let Some(rehash_cli) = cli.subcommand_matches("rehash") {
+        rehash_data_store(local_path, fs, rehash_cli);
+    } else if let Some(transfer_cli) = cli.subcommand_matches("transfer") {
+        toggle_transfer_store(local_path, fs, transfer_cli);
+    } else if let Some(dedup_cli) = cli.subcommand_matches("dedup") {
+        deduplicate_files(local_path, fs, dedup_cli);
+    } else if let Some(dataset_cli) = cli.subcommand_matches("dataset") {
+        manage_data_set(local_path, fs, dataset_cli);
+    } else if let Some(pause_cli) = cli.subcommand_matches("pause") {
+        pause_paths(local_path, fs, pause_cli);
+    } else if let Some(merge_from_cli) = cli.subcommand_matches("merge-from") {
+        merge_from_remote(local_path, fs, merge_from_cli);
+    } else if let Some(status_cli) = cli.subcommand_matches("status") {
+        show_status(local_path, fs, status_cli);
+    } else if let Some(check_pair_cli) = cli.subcommand_matches("check-pair") {
+        check_pair(local_path, fs, check_pair_cli);
+    } else if let Some(mirror_from_cli) = cli.subcommand_matches("mirror-from") {
+        mirror_from_remote(local_path, fs, mirror_from_cli);
     } else {
         println!("Please specify the command you want to perform on the data store.");
         println!("See --help for more information.");
     }
 }
 
+/// Layers the defaults of the store's config file (config.toml in its metadata dir) below the
+/// command line. Top-level keys apply to all subcommands knowing the flag, keys in a table named
+/// after the subcommand (e.g. [sync-from]) only apply to it and take precedence.
+/// Flags given on the command line always win, i.e. config values are skipped if the command line
+/// holds the flag or any flag of its conflict group (see CONFIG_CONFLICT_GROUPS).
+/// Config values clap rejects for the command line (e.g. conflicting config values) are skipped.
+/// The defaults are layered onto the arguments the command line was parsed from, i.e. the
+/// process arguments or a command of a session (see run_session).
+/// Returns the layered command line, None if the config file did not change it.
+fn apply_config_defaults<FS: virtual_fs::FS>(
+    cli: &ArgMatches,
+    args: &[OsString],
+    fs: &FS,
+) -> Option<ArgMatches<'static>> {
+    let (subcommand, subcommand_cli) = match cli.subcommand() {
+        (subcommand, Some(subcommand_cli)) => (subcommand, subcommand_cli),
+        _ => return None,
+    };
+    let local_path = cli.value_of("LOCAL_PATH").unwrap();
+    let config = DataStore::read_config_with_fs(local_path, fs.clone())
+        .expect("Could not read the config file")?;
+    let config: toml::value::Table = toml::from_str(&config)
+        .unwrap_or_else(|err| panic!("Invalid config file in '{}': {}", local_path, err));
+
+    let mut defaults: Vec<(&String, &toml::Value, bool)> = config
+        .iter()
+        .filter(|(_, value)| !value.is_table())
+        .map(|(flag, value)| (flag, value, false))
+        .collect();
+    if let Some(toml::Value::Table(subcommand_config)) = config.get(subcommand) {
+        defaults.retain(|(flag, _, _)| !subcommand_config.contains_key(*flag));
+        defaults.extend(
+            subcommand_config
+                .iter()
+                .map(|(flag, value)| (flag, value, true)),
+        );
+    }
+
+    // Add the defaults one by one to the command line, re-parsing it each time.
+    // This way clap checks each default against the flags given so far.
+    let mut args = args.to_vec();
+    let mut layered_cli = None;
+    for (flag, value, for_subcommand) in defaults {
+        let overridden = config_conflict_group(flag)
+            .iter()
+            .any(|group_flag| subcommand_cli.occurrences_of(group_flag) > 0);
+        if overridden {
+            continue;
+        }
+        let flag_args = match config_value_to_args(flag, value) {
+            Some(flag_args) => flag_args,
+            None => {
+                warn!(
+                    "Ignoring '{}' from the config file (unsupported value).",
+                    flag
+                );
+                continue;
+            }
+        };
+
+        let mut layered_args = args.clone();
+        layered_args.extend(flag_args.into_iter().map(OsString::from));
+        match cli_app().get_matches_from_safe(&layered_args) {
+            Ok(layered) => {
+                args = layered_args;
+                layered_cli = Some(layered);
+            }
+            // Top-level keys are expected to not fit all subcommands.
+            Err(err) if for_subcommand => warn!(
+                "Ignoring '{}' from the config file for '{}' ({:?}).",
+                flag, subcommand, err.kind
+            ),
+            Err(_) => (),
+        }
+    }
+
+    layered_cli
+}
+
+/// Flags deciding the same thing, e.g. how conflicts are resolved. A flag on the command line
+/// replaces config values of all flags in its group.
+const CONFIG_CONFLICT_GROUPS: &[&[&str]] = &[&[
+    "choose-local",
+    "choose-remote",
+    "prefer-newer",
+    "merge-command",
+]];
+
+fn config_conflict_group(flag: &str) -> Vec<&str> {
+    CONFIG_CONFLICT_GROUPS
+        .iter()
+        .find(|group| group.contains(&flag))
+        .map(|group| group.to_vec())
+        .unwrap_or_else(|| vec![flag])
+}
+
+fn config_value_to_args(flag: &str, value: &toml::Value) -> Option<Vec<String>> {
+    match value {
+        toml::Value::Boolean(true) => Some(vec![format!("--{}", flag)]),
+        toml::Value::Boolean(false) => Some(vec![]),
+        toml::Value::String(value) => Some(vec![format!("--{}={}", flag, value)]),
+        toml::Value::Integer(value) => Some(vec![format!("--{}={}", flag, value)]),
+        toml::Value::Float(value) => Some(vec![format!("--{}={}", flag, value)]),
+        // Repeatable flags, e.g. skip = ["a", "b"].
+        toml::Value::Array(values) => values
+            .iter()
+            .map(|value| config_value_to_args(flag, value))
+            .collect::<Option<Vec<_>>>()
+            .map(|flag_args| flag_args.concat()),
+        _ => None,
+    }
+}
+
+fn init_logger(cli: &ArgMatches) {
+    let level = if cli.is_present("quiet") {
+        log::LevelFilter::Error
+    } else {
+        match cli.occurrences_of("verbose") {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        }
+    };
+
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format_timestamp(None)
+        .init();
+}
+
+/// Environment variable holding the passphrase of encrypted transfer stores.
+const PASSPHRASE_VAR: &str = "SQUIRREL_PASSPHRASE";
+
+fn open_data_store<FS: virtual_fs::FS>(path: &str, fs: &FS) -> OpenedDataStore<FS> {
+    let mut data_store = if let virtual_fs::DBAccessType::InMemory = fs.db_access_type() {
+        OpenedDataStore::in_memory(
+            path,
+            take_in_memory_data_store(path)
+                .unwrap_or_else(|| create_in_memory_data_store(path, fs)),
+        )
+    } else {
+        OpenedDataStore::on_disk(DataStore::open_with_fs(PathBuf::from(path), fs.clone()).unwrap())
+    };
+    if data_store.is_encrypted() {
+        let passphrase = std::env::var(PASSPHRASE_VAR).unwrap_or_else(|_| {
+            panic!(
+                "The data store at '{}' is encrypted, set {} to its passphrase.",
+                path, PASSPHRASE_VAR
+            )
+        });
+        data_store.unlock_encryption(&passphrase).unwrap();
+    }
+    if let Some(operation) = data_store.interrupted_operation() {
+        warn!(
+            "The operation '{}' on the data store at '{}' was interrupted. The store was recovered, please re-run the operation.",
+            operation, path
+        );
+    }
+    if data_store.is_possibly_cloned() {
+        warn!(
+            "The data store at '{}' was moved or cloned. Run reidentify on clones (or reidentify --moved on moved stores).",
+            path
+        );
+    }
+    let pending_files = data_store.clean_pending(true).unwrap();
+    if pending_files.pending_files > 0 {
+        warn!(
+            "Found {} orphaned pending files ({} bytes), run clean-pending to remove them.",
+            pending_files.pending_files, pending_files.pending_bytes
+        );
+    }
+
+    data_store
+}
+
+thread_local! {
+    // Stores on the in-memory FS (testing only) can not be re-opened, their DB only lives as long
+    // as they are open. They are kept open for the rest of the process instead (see run_session).
+    static IN_MEMORY_DATA_STORES: RefCell<HashMap<String, Box<dyn Any>>> =
+        RefCell::new(HashMap::new());
+}
+
+/// A data store opened by a command. Stores on the in-memory FS are handed back to the
+/// session once the command is done with them (see IN_MEMORY_DATA_STORES).
+struct OpenedDataStore<FS: virtual_fs::FS> {
+    data_store: Option<DataStore<FS>>,
+    in_memory_path: Option<String>,
+}
+impl<FS: virtual_fs::FS> OpenedDataStore<FS> {
+    fn on_disk(data_store: DataStore<FS>) -> Self {
+        Self {
+            data_store: Some(data_store),
+            in_memory_path: None,
+        }
+    }
+    fn in_memory(path: &str, data_store: DataStore<FS>) -> Self {
+        Self {
+            data_store: Some(data_store),
+            in_memory_path: Some(path.to_string()),
+        }
+    }
+}
+impl<FS: virtual_fs::FS> Deref for OpenedDataStore<FS> {
+    type Target = DataStore<FS>;
+
+    fn deref(&self) -> &DataStore<FS> {
+        self.data_store.as_ref().unwrap()
+    }
+}
+impl<FS: virtual_fs::FS> DerefMut for OpenedDataStore<FS> {
+    fn deref_mut(&mut self) -> &mut DataStore<FS> {
+        self.data_store.as_mut().unwrap()
+    }
+}
+impl<FS: virtual_fs::FS> Drop for OpenedDataStore<FS> {
+    fn drop(&mut self) {
+        if let (Some(path), Some(data_store)) = (self.in_memory_path.take(), self.data_store.take())
+        {
+            keep_in_memory_data_store(&path, data_store);
+        }
+    }
+}
+
+fn take_in_memory_data_store<FS: virtual_fs::FS>(path: &str) -> Option<DataStore<FS>> {
+    IN_MEMORY_DATA_STORES
+        .with(|data_stores| data_stores.borrow_mut().remove(path))
+        .map(|data_store| *data_store.downcast::<DataStore<FS>>().unwrap())
+}
+
+fn keep_in_memory_data_store<FS: virtual_fs::FS>(path: &str, data_store: DataStore<FS>) {
+    IN_MEMORY_DATA_STORES.with(|data_stores| {
+        data_stores
+            .borrow_mut()
+            .insert(path.to_string(), Box::new(data_store))
+    });
+}
+
+/// Stores on the in-memory FS (testing only) that were not created earlier in the session
+/// are freshly created whenever they are opened.
+fn create_in_memory_data_store<FS: virtual_fs::FS>(path: &str, fs: &FS) -> DataStore<FS> {
+    create_in_memory_dirs(path, fs);
+    DataStore::create_with_fs(path, "in-memory", "in-memory", "in-memory", fs.clone()).unwrap()
+}
+
+fn create_in_memory_dirs<FS: virtual_fs::FS>(path: &str, fs: &FS) {
+    let ancestors: Vec<&std::path::Path> = std::path::Path::new(path).ancestors().collect();
+    for ancestor in ancestors.into_iter().rev() {
+        // The FS root (and directories created earlier) already exist.
+        if fs.metadata(ancestor).is_err() {
+            fs.create_dir(ancestor, true).unwrap();
+        }
+    }
+}
+
 fn create_cmd<'a, 'b>() -> App<'a, 'b> {
     let data_set_name_arg = Arg::with_name("name")
         .long("name")
@@ -51,34 +457,103 @@ fn create_cmd<'a, 'b>() -> App<'a, 'b> {
         .required(false)
         .takes_value(false)
         .help("Marks the store to be a transfer store. It does not index data itself, but carries it to other stores that need it.");
+    let trust_mtime_arg = Arg::with_name("trust-mtime")
+        .long("trust-mtime")
+        .required(false)
+        .takes_value(false)
+        .help("Trusts file modification times and skips hashing during scans. Faster scans, but no detection of bit-rot or content changes with unchanged modification times.");
+    let ignore_case_changes_arg = Arg::with_name("ignore-case-changes")
+        .long("ignore-case-changes")
+        .required(false)
+        .takes_value(false)
+        .help("Treats names only differing in case as equal, i.e. keeps the local case of names on syncs and does not pick up case-only renames on scans.");
+    let no_unicode_normalization_arg = Arg::with_name("no-unicode-normalization")
+        .long("no-unicode-normalization")
+        .required(false)
+        .takes_value(false)
+        .help("Treats names only differing in their unicode normalization (e.g. NFC vs. NFD as written by macOS) as different items. Can not be changed after creation.");
+    let store_name_arg = Arg::with_name("store-name")
+        .long("store-name")
+        .required(false)
+        .takes_value(true)
+        .help("The human readable name of this store, e.g. the name of the device.");
+    let store_id_arg = Arg::with_name("store-id")
+        .long("store-id")
+        .required(false)
+        .takes_value(true)
+        .help("The stable unique id of this store (a random one is generated by default). Only re-use the id of a store to deliberately restore its identity, e.g. after re-installing a device. Two live stores must never share an id.");
+    let db_path_arg = Arg::with_name("db-path")
+        .long("db-path")
+        .value_name("FILE")
+        .required(false)
+        .takes_value(true)
+        .help("Places the metadata DB at the given file path instead of the store's metadata dir, e.g. on a fast local disk while the data lives on a network volume. Later opens find it again.");
     let create_cmd = SubCommand::with_name("create")
         .about("inits a directory to be a data_store")
         .arg(data_set_name_arg)
-        .arg(transfer_store_arg);
+        .arg(store_name_arg)
+        .arg(store_id_arg)
+        .arg(transfer_store_arg)
+        .arg(trust_mtime_arg)
+        .arg(ignore_case_changes_arg)
+        .arg(no_unicode_normalization_arg)
+        .arg(db_path_arg);
 
     create_cmd
 }
 
-fn create_data_store(local_path: &str, cmd_cli: &ArgMatches) {
+fn create_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
     let data_set_name = cmd_cli.value_of("name").unwrap();
 
     println!("Creating new data store at '{}'...", local_path);
-    let result =
-        core::data_store::DefaultDataStore::create(local_path, data_set_name, "default", "default");
+    let mut options = core::data_store::DataStoreCreateOptions::new(data_set_name)
+        .data_set_human_name("default")
+        .transfer_store(cmd_cli.is_present("transfer-store"))
+        .trust_mtime(cmd_cli.is_present("trust-mtime"))
+        .propagate_case_changes(!cmd_cli.is_present("ignore-case-changes"))
+        .normalize_unicode(!cmd_cli.is_present("no-unicode-normalization"));
+    if let Some(store_name) = cmd_cli.value_of("store-name") {
+        options = options.data_store_name(store_name);
+    }
+    if let Some(store_id) = cmd_cli.value_of("store-id") {
+        options = options.data_store_id(store_id);
+    }
+    if let Some(db_path) = cmd_cli.value_of("db-path") {
+        options = options.external_db_path(db_path);
+    }
+    if let virtual_fs::DBAccessType::InMemory = fs.db_access_type() {
+        create_in_memory_dirs(local_path, fs);
+    }
+    let result = DataStore::create_with_options_and_fs(local_path, &options, fs.clone());
 
     match result {
         Ok(data_store) => {
+            let data_store = if let virtual_fs::DBAccessType::InMemory = fs.db_access_type() {
+                OpenedDataStore::in_memory(local_path, data_store)
+            } else {
+                OpenedDataStore::on_disk(data_store)
+            };
             println!(
             "Created new store (data_set_name: '{}', data_store_name: '{}', data_store_desc: '{}')!",
             data_store.data_set_name().unwrap(),
             data_store.local_data_store_name().unwrap(),
             data_store.local_data_store_desc().unwrap()
             );
-            if cmd_cli.is_present("transfer-store") {
+            if options.is_transfer_store {
+                println!(
+                    "Marked store as transfer store (it will carry data to all synced stores)."
+                );
+            }
+            if options.trust_mtime {
+                println!("Trusting modification times (files are only hashed when synced).");
+            }
+            if !options.propagate_case_changes {
+                println!("Ignoring case-only changes of names (local names keep their case).");
+            }
+            if !options.normalize_unicode {
                 println!(
-                    "Marking store as transfer store (it will carry data to all synced stores)."
+                    "Not normalizing unicode in names (NFC and NFD names are different items)."
                 );
-                data_store.mark_as_transfer_store().unwrap();
             }
         }
         Err(core::data_store::DataStoreError::FSInteractionError {
@@ -89,17 +564,147 @@ fn create_data_store(local_path: &str, cmd_cli: &ArgMatches) {
 }
 
 fn scan_cmd<'a, 'b>() -> App<'a, 'b> {
+    let min_age_arg = Arg::with_name("min-age")
+        .long("min-age")
+        .takes_value(true)
+        .value_name("SECONDS")
+        .help("Defers indexing files modified less than the given number of seconds ago (e.g. files still being written). They are picked up by a later scan, this does NOT exclude them permanently. The store remembers the setting for later scans, 0 disables it.");
+    let ignore_file_arg = Arg::with_name("ignore-file")
+        .long("ignore-file")
+        .value_name("NAME")
+        .takes_value(true)
+        .help("Reads files of the given name (e.g. .squirrelignore) as ignore rules for their directory. One glob pattern per line, patterns without '/' match names at any depth, '!pattern' re-includes items. Nested files take precedence. The store remembers the setting for later scans and syncs, an empty name disables it.");
+    let list_changes_arg = Arg::with_name("list-changes")
+        .long("list-changes")
+        .help("Prints the paths of all new, changed and deleted items found by the scan.");
+    let depth_arg = Arg::with_name("depth")
+        .long("depth")
+        .value_name("N")
+        .takes_value(true)
+        .help("Only scans items at most N levels below the store root. Deeper items keep their index entries as they are.");
+    let rebuild_arg = Arg::with_name("rebuild")
+        .long("rebuild")
+        .value_name("PATH")
+        .takes_value(true)
+        .conflicts_with("list-changes")
+        .help("Re-builds the index of the given sub-tree purely from disk, hashing every file again (e.g. after restoring an old index). Files with unchanged content keep their history.");
+    let delete_ignored_arg = Arg::with_name("delete-ignored")
+        .long("delete-ignored")
+        .conflicts_with("rebuild")
+        .help("Removes the content of ignored items still found on disk (e.g. after adding ignore rules), it is no longer synced anyway.");
+    let duplicates_arg = Arg::with_name("on-duplicates")
+        .long("on-duplicates")
+        .value_name("POLICY")
+        .possible_values(&["skip", "first", "fail"])
+        .takes_value(true)
+        .help("Treatment of items whose names only differ in case: skip all of them (default), index only the lexicographically first one or fail the scan.");
     let scan_cmd = SubCommand::with_name("scan")
-        .about("performs a scan of the given data store, indexing any changed hard drive content");
+        .about("performs a scan of the given data store, indexing any changed hard drive content")
+        .arg(min_age_arg)
+        .arg(ignore_file_arg)
+        .arg(list_changes_arg)
+        .arg(depth_arg)
+        .arg(rebuild_arg)
+        .arg(delete_ignored_arg)
+        .arg(duplicates_arg);
 
     scan_cmd
 }
 
-fn scan_data_store(local_path: &str, _cmd_cli: &ArgMatches) {
+fn scan_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
     println!("Performing full scan on data store...");
-    let local_data_store =
-        core::data_store::DefaultDataStore::open(&PathBuf::from(local_path)).unwrap();
-    let result = local_data_store.perform_full_scan().unwrap();
+    let mut local_data_store = open_data_store(local_path, fs);
+    if let Some(min_age) = cmd_cli.value_of("min-age") {
+        let min_age = min_age
+            .parse::<u64>()
+            .expect("--min-age must be a number of seconds");
+        let min_age = if min_age > 0 {
+            Some(std::time::Duration::from_secs(min_age))
+        } else {
+            None
+        };
+        local_data_store.set_min_file_age(min_age).unwrap();
+    }
+    if let Some(ignore_file) = cmd_cli.value_of("ignore-file") {
+        let ignore_file = if !ignore_file.is_empty() {
+            Some(ignore_file.to_string())
+        } else {
+            None
+        };
+        local_data_store.set_ignore_file_name(ignore_file).unwrap();
+    }
+    if let Some(depth) = cmd_cli.value_of("depth") {
+        let depth = depth.parse::<usize>().expect("--depth must be a number");
+        local_data_store.set_max_scan_depth(Some(depth));
+    }
+    if let Some(policy) = cmd_cli.value_of("on-duplicates") {
+        local_data_store.set_duplicate_policy(match policy {
+            "first" => core::data_store::DuplicatePolicy::SyncFirst,
+            "fail" => core::data_store::DuplicatePolicy::Fail,
+            _ => core::data_store::DuplicatePolicy::ReportAndSkip,
+        });
+    }
+    let mut result = if let Some(rebuild_path) = cmd_cli.value_of("rebuild") {
+        local_data_store
+            .rebuild_index(&RelativePath::from_path(rebuild_path))
+            .unwrap()
+    } else if cmd_cli.is_present("list-changes") {
+        local_data_store
+            .perform_full_scan_listing_changes()
+            .unwrap()
+    } else {
+        local_data_store.perform_full_scan().unwrap()
+    };
+
+    if let Some(changes) = result.changes.take() {
+        for (label, paths) in [
+            ("New items:", changes.new_items),
+            ("Changed items:", changes.changed_items),
+            ("Deleted items:", changes.deleted_items),
+        ] {
+            if !paths.is_empty() {
+                println!("{}", label);
+                for path in paths {
+                    println!("{}", path.to_path_buf().to_str().unwrap());
+                }
+            }
+        }
+    }
+    for duplicate_names in result.duplicate_names.iter() {
+        let names: Vec<String> = duplicate_names
+            .paths
+            .iter()
+            .map(|path| path.to_path_buf().to_str().unwrap().to_string())
+            .collect();
+        match &duplicate_names.indexed_path {
+            Some(indexed_path) => println!(
+                "WARNING: names only differ in case, only syncing '{}': {}",
+                indexed_path.to_path_buf().to_str().unwrap(),
+                names.join(", ")
+            ),
+            None => println!(
+                "WARNING: names only differ in case, not syncing any of them: {}",
+                names.join(", ")
+            ),
+        }
+    }
+    if !result.ignored_on_disk.is_empty() {
+        if cmd_cli.is_present("delete-ignored") {
+            local_data_store
+                .delete_ignored_on_disk(&result.ignored_on_disk)
+                .unwrap();
+            println!(
+                "Removed {} bytes of ignored content from disk.",
+                result.ignored_on_disk_bytes
+            );
+        } else {
+            println!(
+                "WARNING: {} ignored items ({} bytes) are no longer synced, but stay on disk. Run scan with --delete-ignored to remove them from disk.",
+                result.ignored_on_disk.len(),
+                result.ignored_on_disk_bytes
+            );
+        }
+    }
     println!("Scan Complete: {:?}", result);
 }
 
@@ -119,72 +724,446 @@ fn sync_from_cmd<'a, 'b>() -> App<'a, 'b> {
         .short("r")
         .help("Instructs the sync algorithm to choose the remote over the local item on conflicts.")
         .required(false)
+        .takes_value(false)
+        .conflicts_with("choose-local");
+    let conflict_prefer_newer = Arg::with_name("prefer-newer")
+        .long("prefer-newer")
+        .help("Chooses the item with the more recent file modification time on conflicts. Uses the (possibly wrong) wall-clock times of the machines, not the sync history!")
+        .required(false)
+        .takes_value(false)
+        .conflicts_with_all(&["choose-local", "choose-remote"]);
+    let merge_command_arg = Arg::with_name("merge-command")
+        .long("merge-command")
+        .value_name("CMD")
+        .help("Merges conflicting files by running 'CMD local base remote' (e.g. 'git merge-file'), the command writes its result into the local file. Without a known base version, the remote file is kept next to the local one.")
+        .required(false)
+        .takes_value(true)
+        .conflicts_with_all(&["choose-local", "choose-remote", "prefer-newer"]);
+    let full_walk = Arg::with_name("full")
+        .long("full")
+        .help("Forces a complete walk of the tree, even into folders that are already up to date.")
+        .required(false)
+        .takes_value(false);
+    let priority_arg = Arg::with_name("priority")
+        .long("priority")
+        .short("p")
+        .value_name("PATH")
+        .help("Syncs the given path before all others (can be repeated, earlier ones first).")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let skip_arg = Arg::with_name("skip")
+        .long("skip")
+        .value_name("PATH")
+        .help("Leaves the given path out of this sync only, a later sync picks it up again (can be repeated).")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let parallel_downloads_arg = Arg::with_name("parallel-downloads")
+        .long("parallel-downloads")
+        .value_name("N")
+        .default_value("1")
+        .help("Downloads up to N files of a folder concurrently (e.g. to hide network latency).")
+        .takes_value(true);
+    let prefetch_arg = Arg::with_name("prefetch")
+        .long("prefetch")
+        .help("Downloads the next file of a folder in the background while the current item is synced (overlaps transfers with index updates).")
+        .required(false)
+        .takes_value(false);
+    let min_free_arg = Arg::with_name("min-free")
+        .long("min-free")
+        .value_name("BYTES")
+        .help("Refuses to sync if less than the given number of bytes would remain free on disk.")
+        .required(false)
+        .takes_value(true)
+        .default_value("0");
+    let retries_arg = Arg::with_name("retries")
+        .long("retries")
+        .value_name("N")
+        .default_value("0")
+        .help("Retries file transfers failing with transient errors (e.g. interrupted network shares) up to N times.")
+        .takes_value(true);
+    let allow_type_change_arg = Arg::with_name("allow-type-change")
+        .long("allow-type-change")
+        .help("Replaces local folders by remote files (and vice versa). Replacing a folder removes ALL of its content! Without this flag, these items are left out of the sync.")
+        .required(false)
+        .takes_value(false);
+    let temp_dir_arg = Arg::with_name("temp-dir")
+        .long("temp-dir")
+        .value_name("DIR")
+        .help("Downloads files into the given directory before moving them into place (may be on a different disk).")
+        .required(false)
+        .takes_value(true);
+    let progress_arg = Arg::with_name("progress")
+        .long("progress")
+        .help("Prints a progress line for each transferred file.")
+        .required(false)
+        .takes_value(false);
+    let reindex_out_of_sync_arg = Arg::with_name("reindex-out-of-sync")
+        .long("reindex-out-of-sync")
+        .help("Re-indexes local items changed since the last scan instead of aborting the sync.")
+        .required(false)
+        .takes_value(false);
+    let depth_arg = Arg::with_name("depth")
+        .long("depth")
+        .value_name("N")
+        .help("Only syncs items at most N levels below the store root, deeper items are picked up by a later sync.")
+        .required(false)
+        .takes_value(true);
+    let max_conflicts_arg = Arg::with_name("max-conflicts")
+        .long("max-conflicts")
+        .value_name("N")
+        .help("Aborts the sync once more than N conflicts were left unresolved.")
+        .required(false)
+        .takes_value(true);
+    let respect_remote_rules_arg = Arg::with_name("respect-remote-rules")
+        .long("respect-remote-rules")
+        .help("Leaves out items the remote's own ignore rules exclude, even if it holds them (e.g. a transfer store carrying them for other stores).")
+        .required(false)
+        .takes_value(false);
+    let verify_arg = Arg::with_name("verify")
+        .long("verify")
+        .help("Compares the tree hash of both stores after the sync (re-reads all file contents on both sides) and fails if they differ.")
+        .required(false)
         .takes_value(false);
     let sync_from_cmd = SubCommand::with_name("sync-from")
         .about("syncs from the remote store to the local store (local <- remote)")
         .arg(remote_path_arg)
         .arg(conflict_choose_local)
-        .arg(conflict_choose_remote);
+        .arg(conflict_choose_remote)
+        .arg(conflict_prefer_newer)
+        .arg(merge_command_arg)
+        .arg(full_walk)
+        .arg(priority_arg)
+        .arg(skip_arg)
+        .arg(parallel_downloads_arg)
+        .arg(prefetch_arg)
+        .arg(min_free_arg)
+        .arg(retries_arg)
+        .arg(allow_type_change_arg)
+        .arg(temp_dir_arg)
+        .arg(progress_arg)
+        .arg(reindex_out_of_sync_arg)
+        .arg(depth_arg)
+        .arg(max_conflicts_arg)
+        .arg(respect_remote_rules_arg)
+        .arg(verify_arg);
 
     sync_from_cmd
 }
 
-fn sync_from_remote(local_path: &str, cmd_cli: &ArgMatches) {
+fn sync_from_remote<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
     println!("Syncing new changes FROM remote TO local data store...");
     let choose_local = cmd_cli.is_present("choose-local");
     let choose_remote = cmd_cli.is_present("choose-remote");
+    let prefer_newer = cmd_cli.is_present("prefer-newer");
+    let merge_command: Option<Vec<String>> = cmd_cli
+        .value_of("merge-command")
+        .map(|command| command.split_whitespace().map(String::from).collect());
+    let merge = merge_command.is_some();
     if choose_local && choose_remote {
         panic!("Must not choose both local and remote items on sync (use either --choose-local or --choose-remote or none)");
     }
 
-    let local_data_store =
-        core::data_store::DefaultDataStore::open(&PathBuf::from(local_path)).unwrap();
+    let mut local_data_store = open_data_store(local_path, fs);
     let remote_path = cmd_cli.value_of("REMOTE_PATH").unwrap();
-    let remote_data_store =
-        core::data_store::DefaultDataStore::open(&PathBuf::from(remote_path)).unwrap();
+    let remote_data_store = open_data_store(remote_path, fs);
 
-    use core::data_store::SyncConflictEvent::*;
-    use core::data_store::SyncConflictResolution;
+    let retries: u32 = cmd_cli
+        .value_of("retries")
+        .unwrap()
+        .parse()
+        .expect("--retries must be a number");
+    local_data_store.set_retry_policy(core::data_store::RetryPolicy::new(
+        retries + 1,
+        std::time::Duration::from_secs(1),
+    ));
     local_data_store
-        .sync_from_other_store(
-            &remote_data_store,
-            &RelativePath::from_path(""),
-            &mut |conflict| match conflict {
-                LocalDeletionRemoteFolder(db_item, _)
-                | LocalFileRemoteFolder(db_item, _)
-                | LocalDeletionRemoteFile(db_item, _)
-                | LocalItemRemoteFile(db_item, _)
-                | LocalItemRemoteDeletion(db_item, _) => {
-                    println!("Conflict: {:?}", db_item.path.to_path_buf());
-                    if choose_local {
-                        println!("Choosing local version over remote!");
-                        SyncConflictResolution::ChooseLocalItem
-                    } else if choose_remote {
-                        println!("Choosing remote version over local!");
-                        SyncConflictResolution::ChooseRemoteItem
-                    } else {
-                        println!("Do not resolve the conflict (re-run sync with --choose-local or --choose-remote)");
-                        SyncConflictResolution::DoNotResolve
-                    }
-                }
-            },
+        .set_staging_dir(cmd_cli.value_of("temp-dir"))
+        .expect("Could not set up the temp dir");
+
+    // Refuse to start the sync if it would fill up the disk.
+    let min_free: u64 = cmd_cli
+        .value_of("min-free")
+        .unwrap()
+        .parse()
+        .expect("--min-free must be a number of bytes");
+    match local_data_store.check_free_space(&remote_data_store, min_free) {
+        Ok(_) => (),
+        Err(core::data_store::DataStoreError::InsufficientSpace {
+            required,
+            available,
+        }) => {
+            println!(
+                "Can not sync, the sync requires {} bytes (including --min-free), but only {} bytes are available.",
+                required, available
+            );
+            return;
+        }
+        Err(err) => panic!("{:?}", err),
+    }
+
+    let root_path = RelativePath::from_path("");
+    let priority: Vec<RelativePath> = cmd_cli
+        .values_of("priority")
+        .map_or(vec![], |paths| paths.map(RelativePath::from_path).collect());
+    let skip: Vec<RelativePath> = cmd_cli
+        .values_of("skip")
+        .map_or(vec![], |paths| paths.map(RelativePath::from_path).collect());
+    let options = core::data_store::SyncOptions::new()
+        .full_walk(cmd_cli.is_present("full"))
+        .priority(priority)
+        .skip(skip)
+        .allow_type_changes(cmd_cli.is_present("allow-type-change"))
+        .reindex_out_of_sync_items(cmd_cli.is_present("reindex-out-of-sync"))
+        .max_depth(
+            cmd_cli
+                .value_of("depth")
+                .map(|depth| depth.parse().expect("--depth must be a number")),
         )
-        .unwrap();
-    println!("Sync Complete!");
+        .max_conflicts(cmd_cli.value_of("max-conflicts").map(|max_conflicts| {
+            max_conflicts
+                .parse()
+                .expect("--max-conflicts must be a number")
+        }))
+        .merge_command(merge_command)
+        .respect_remote_rules(cmd_cli.is_present("respect-remote-rules"))
+        .parallel_downloads(
+            cmd_cli
+                .value_of("parallel-downloads")
+                .unwrap()
+                .parse()
+                .expect("--parallel-downloads must be a number"),
+        )
+        .pipelined_prefetch(cmd_cli.is_present("prefetch"));
+    let show_progress = cmd_cli.is_present("progress");
+    let result = local_data_store.sync_from_other_store_with_progress(
+        &remote_data_store,
+        &root_path,
+        &options,
+        &mut |conflict| {
+            resolve_sync_conflict(conflict, choose_local, choose_remote, prefer_newer, merge)
+        },
+        &mut |event| {
+            if let core::data_store::SyncProgressEvent::FileFinished {
+                path,
+                size,
+                transferred_files,
+                transferred_bytes,
+            } = event
+            {
+                if show_progress {
+                    println!(
+                        "[{} files, {} bytes] {} ({} bytes)",
+                        transferred_files,
+                        transferred_bytes,
+                        path.to_path_buf().to_str().unwrap(),
+                        size
+                    );
+                }
+            }
+        },
+    );
+    match result {
+        Ok(result) => {
+            for type_change in &result.type_changes {
+                print_type_change("WARNING: Replaced", type_change);
+            }
+            for type_change in &result.refused_type_changes {
+                print_type_change("WARNING: Left out", type_change);
+            }
+            if !result.refused_type_changes.is_empty() {
+                println!("Re-run the sync with --allow-type-change to apply the left out items.");
+            }
+            for merged_file in &result.merged_files {
+                println!("Merged {}", merged_file.to_path_buf().to_str().unwrap());
+            }
+            for conflict_copy in &result.conflict_copies {
+                println!(
+                    "WARNING: Kept both versions, the remote one is stored as {} (no base version to merge)",
+                    conflict_copy.to_path_buf().to_str().unwrap()
+                );
+            }
+            for unavailable_item in &result.unavailable_items {
+                println!(
+                    "WARNING: Left out {} (content unavailable on the remote)",
+                    unavailable_item.to_path_buf().to_str().unwrap()
+                );
+            }
+            for explanation in &result.explanations {
+                println!(
+                    "Note: {} {}",
+                    explanation.path.to_path_buf().to_str().unwrap(),
+                    explanation.reason
+                );
+            }
+            if let Some(warning) = &result.stalled_sync_warning {
+                println!(
+                    "WARNING: The last {} syncs from this store transferred nothing, but did not converge.",
+                    warning.stalled_syncs
+                );
+                for blocking_path in &warning.blocking_paths {
+                    println!(
+                        "Likely blocked by {} (ignored or unavailable on the remote), sync it in from a store holding it.",
+                        blocking_path.to_path_buf().to_str().unwrap()
+                    );
+                }
+            }
+            println!(
+                "Sync Complete: visited {} items ({} already up to date), transferred {} files ({} bytes).",
+                result.visited_items,
+                result.up_to_date_items,
+                result.transferred_files,
+                result.transferred_bytes
+            );
+
+            if cmd_cli.is_present("verify") {
+                verify_tree_hash(&local_data_store, &remote_data_store, &root_path);
+            }
+        }
+        Err(core::data_store::DataStoreError::DataSetMismatch {
+            local_data_set,
+            remote_data_set,
+        }) => println!(
+            "Can not sync, the stores belong to different data sets (local: '{}', remote: '{}').",
+            local_data_set, remote_data_set
+        ),
+        Err(core::data_store::DataStoreError::SameDataStore) => {
+            println!("Can not sync, the local and remote path point to the same data store.")
+        }
+        Err(core::data_store::DataStoreError::DiskOutOfSync { path }) => println!(
+            "Can not sync, '{}' changed since the last scan. Re-run the scan (or the sync with --reindex-out-of-sync).",
+            path.to_path_buf().to_str().unwrap()
+        ),
+        Err(core::data_store::DataStoreError::TooManyConflicts { count }) => println!(
+            "Aborted the sync after {} unresolved conflicts. Items synced so far are kept, investigate the conflicts and re-run the sync.",
+            count
+        ),
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
+fn verify_tree_hash<FS: virtual_fs::FS>(
+    local_data_store: &DataStore<FS>,
+    remote_data_store: &DataStore<FS>,
+    path: &RelativePath,
+) {
+    match local_data_store.verify_tree_hash(remote_data_store, path) {
+        Ok(()) => println!("Verified: both stores hold the same content."),
+        Err(core::data_store::DataStoreError::TreeHashMismatch {
+            local_hash,
+            remote_hash,
+            ..
+        }) => panic!(
+            "Verification failed, the stores hold different content (local tree hash {}, remote tree hash {})!",
+            local_hash, remote_hash
+        ),
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
+fn print_type_change(prefix: &str, type_change: &core::data_store::TypeChange) {
+    use core::data_store::TypeChange::*;
+
+    let description = match type_change {
+        FileBecameFolder(_) => "local file by remote folder",
+        FolderBecameFile(_) => "local folder (and ALL its content) by remote file",
+    };
+    println!(
+        "{} {}: {}",
+        prefix,
+        description,
+        type_change.path().to_path_buf().to_str().unwrap()
+    );
+}
+
+fn resolve_sync_conflict(
+    conflict: core::data_store::SyncConflictEvent,
+    choose_local: bool,
+    choose_remote: bool,
+    prefer_newer: bool,
+    merge: bool,
+) -> core::data_store::SyncConflictResolution {
+    use core::data_store::SyncConflictEvent::*;
+    use core::data_store::SyncConflictResolution;
+
+    match conflict {
+        LocalDeletionRemoteFolder(db_item, _)
+        | LocalFileRemoteFolder(db_item, _)
+        | LocalDeletionRemoteFile(db_item, _)
+        | LocalItemRemoteFile(db_item, _)
+        | LocalItemRemoteDeletion(db_item, _) => {
+            println!("Conflict: {:?}", db_item.path.to_path_buf());
+            if choose_local {
+                println!("Choosing local version over remote!");
+                SyncConflictResolution::ChooseLocalItem
+            } else if choose_remote {
+                println!("Choosing remote version over local!");
+                SyncConflictResolution::ChooseRemoteItem
+            } else if prefer_newer {
+                println!("Choosing the version with the more recent modification time!");
+                SyncConflictResolution::MostRecentMtime
+            } else if merge {
+                println!("Merging the local and remote version!");
+                SyncConflictResolution::ExternalMerge
+            } else {
+                println!("Do not resolve the conflict (re-run sync with --choose-local, --choose-remote, --prefer-newer or --merge-command)");
+                SyncConflictResolution::DoNotResolve
+            }
+        }
+    }
 }
 
 fn optimize_cmd<'a, 'b>() -> App<'a, 'b> {
+    let trash_max_age_arg = Arg::with_name("trash-max-age")
+        .long("trash-max-age")
+        .takes_value(true)
+        .value_name("SECONDS")
+        .help("Permanently removes conflict copies (kept by keep-both conflict resolutions) created more than the given number of seconds ago.");
+    let trash_max_size_arg = Arg::with_name("trash-max-size")
+        .long("trash-max-size")
+        .takes_value(true)
+        .value_name("BYTES")
+        .help("Permanently removes the oldest conflict copies until all remaining ones use at most the given number of bytes.");
     let optimize_cmd = SubCommand::with_name("optimize")
-        .about("optimizes the underlying SQLite database (can save space and speed up operations)");
+        .about("optimizes the underlying SQLite database (can save space and speed up operations)")
+        .arg(trash_max_age_arg)
+        .arg(trash_max_size_arg);
 
     optimize_cmd
 }
 
-fn optimize_data_store(local_path: &str, _cmd_cli: &ArgMatches) {
+fn optimize_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let mut local_data_store = open_data_store(local_path, fs);
+    let max_age = cmd_cli.value_of("trash-max-age").map(|max_age| {
+        let max_age = max_age
+            .parse::<u64>()
+            .expect("--trash-max-age must be a number of seconds");
+        std::time::Duration::from_secs(max_age)
+    });
+    let max_size = cmd_cli.value_of("trash-max-size").map(|max_size| {
+        max_size
+            .parse::<u64>()
+            .expect("--trash-max-size must be a number of bytes")
+    });
+    local_data_store
+        .set_retention_policy(core::data_store::RetentionPolicy::new(max_age, max_size));
+    if max_age.is_some() || max_size.is_some() {
+        println!("Removing conflict copies beyond the retention limits...");
+        let result = local_data_store.clean_retention().unwrap();
+        println!(
+            "Removed {} conflict copies ({} bytes).",
+            result.removed_files, result.removed_bytes
+        );
+    }
+
     println!("Optimizing database file...");
-    let local_data_store =
-        core::data_store::DefaultDataStore::open(&PathBuf::from(local_path)).unwrap();
-    local_data_store.optimize_database().unwrap();
+    let orphaned_rows = local_data_store.optimize_database().unwrap();
+    if orphaned_rows > 0 {
+        println!("Removed {} orphaned metadata rows.", orphaned_rows);
+    }
     println!("Optimization done!");
 }
 
@@ -194,6 +1173,12 @@ fn rules_cmd<'a, 'b>() -> App<'a, 'b> {
         .help("Executes the command as a DRY run, not performing any actual changes.")
         .required(false)
         .takes_value(false);
+    let delete_ignored_arg = Arg::with_name("delete-ignored")
+        .long("delete-ignored")
+        .help("Removes the content of newly ignored items from disk (by default it is only no longer synced).")
+        .required(false)
+        .takes_value(false)
+        .conflicts_with("dry-run");
     let remove_rule_arg = Arg::with_name("remove-rule")
         .long("remove-rule")
         .takes_value(true)
@@ -210,21 +1195,40 @@ fn rules_cmd<'a, 'b>() -> App<'a, 'b> {
         .long("print")
         .required(false)
         .takes_value(false);
+    let compact_arg = Arg::with_name("compact")
+        .long("compact")
+        .help("Removes duplicate and redundant rules (never changing which items are included).")
+        .required(false)
+        .takes_value(false);
+    let compare_arg = Arg::with_name("compare")
+        .long("compare")
+        .value_name("REMOTE_PATH")
+        .help("Lists the rules that differ between this and the given remote data store (does not change any rules).")
+        .required(false)
+        .takes_value(true)
+        .conflicts_with_all(&["remove-rule", "ignore-rule", "inclusion-rule"]);
     let inclusion_rule_cmd = SubCommand::with_name("rules")
         .about("Manipulates the inclusion and ignore rules of the data store.")
         .arg(dry_run_arg)
+        .arg(delete_ignored_arg)
         .arg(remove_rule_arg)
         .arg(inclusion_rule_arg)
         .arg(ignore_rule_arg)
-        .arg(print_rule_arg);
+        .arg(print_rule_arg)
+        .arg(compact_arg)
+        .arg(compare_arg);
 
     inclusion_rule_cmd
 }
 
-fn manage_inclusion_rules(local_path: &str, cmd_cli: &ArgMatches) {
+fn manage_inclusion_rules<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    if let Some(remote_path) = cmd_cli.value_of("compare") {
+        compare_inclusion_rules(local_path, remote_path, fs);
+        return;
+    }
+
     println!("Changing inclusion/ignore rules of data_store...");
-    let mut local_data_store =
-        core::data_store::DefaultDataStore::open(&PathBuf::from(local_path)).unwrap();
+    let mut local_data_store = open_data_store(local_path, fs);
     let mut rules = local_data_store.get_inclusion_rules().clone();
 
     if cmd_cli.is_present("print") {
@@ -245,34 +1249,996 @@ fn manage_inclusion_rules(local_path: &str, cmd_cli: &ArgMatches) {
             rules.remove_rule(&removed_rule);
         }
     }
-    if let Some(ignore_rules) = cmd_cli.values_of("ignore-rule") {
-        println!("Adding ignore rules: ");
-        for ignore_rule in ignore_rules {
-            println!("{}", ignore_rule);
-            rules.add_ignore_rule(glob::Pattern::new(&ignore_rule).unwrap());
+    // The last matching rule wins, i.e. we add them in the order given on the command line.
+    let mut added_rules = Vec::new();
+    for (arg_name, include) in &[("ignore-rule", false), ("inclusion-rule", true)] {
+        if let (Some(values), Some(indices)) =
+            (cmd_cli.values_of(arg_name), cmd_cli.indices_of(arg_name))
+        {
+            added_rules.extend(
+                indices
+                    .zip(values)
+                    .map(|(index, rule)| (index, rule, *include)),
+            );
+        }
+    }
+    added_rules.sort_by_key(|(index, _, _)| *index);
+    if !added_rules.is_empty() {
+        println!("Adding rules: ");
+    }
+    for (_, rule, include) in added_rules {
+        if include {
+            println!("+ {}", rule);
+            rules.add_inclusion_rule(glob::Pattern::new(rule).unwrap());
+        } else {
+            println!("- {}", rule);
+            rules.add_ignore_rule(glob::Pattern::new(rule).unwrap());
         }
     }
-    if let Some(inclusion_rules) = cmd_cli.values_of("inclusion-rule") {
-        println!("Adding inclusion rules: ");
-        for inclusion_rule in inclusion_rules {
-            println!("{}", inclusion_rule);
-            rules.add_inclusion_rule(glob::Pattern::new(&inclusion_rule).unwrap());
+
+    if cmd_cli.is_present("compact") {
+        println!("Removing redundant rules: ");
+        for rule in rules.compact() {
+            if rule.include {
+                println!("+ {}", rule.rule.as_str());
+            } else {
+                println!("- {}", rule.rule.as_str());
+            }
         }
     }
 
     let dry_run = cmd_cli.is_present("dry-run");
-    let (added_items, removed_items) = local_data_store
-        .update_inclusion_rules(rules, dry_run)
-        .unwrap();
+    let delete_ignored = cmd_cli.is_present("delete-ignored");
+    let plan = local_data_store.plan_inclusion_rules(&rules).unwrap();
     if dry_run {
         println!("DRY RUN - NO ACTUAL CHANGES TO DB");
+    } else {
+        local_data_store
+            .update_inclusion_rules(rules, false)
+            .unwrap();
     }
     println!("No longer ignored items:");
-    for added_item in added_items {
+    for added_item in &plan.no_longer_ignored {
         println!("{}", added_item.path.to_path_buf().to_str().unwrap());
     }
     println!("Newly ignored items:");
-    for removed_item in removed_items {
+    for removed_item in &plan.newly_ignored {
         println!("{}", removed_item.path.to_path_buf().to_str().unwrap());
     }
+
+    if delete_ignored {
+        local_data_store.delete_ignored_content(&plan).unwrap();
+        println!(
+            "Removed {} bytes of newly ignored content from disk.",
+            plan.disk_cleanup_bytes
+        );
+    } else if !plan.disk_cleanup.is_empty() {
+        println!(
+            "WARNING: The newly ignored items ({} bytes) are no longer synced, but stay on disk. Run with --delete-ignored to remove them from disk.",
+            plan.disk_cleanup_bytes
+        );
+    }
+}
+
+fn compare_inclusion_rules<FS: virtual_fs::FS>(local_path: &str, remote_path: &str, fs: &FS) {
+    let local_data_store = open_data_store(local_path, fs);
+    let remote_data_store = open_data_store(remote_path, fs);
+
+    let rule_diff = local_data_store
+        .compare_inclusion_rules(&remote_data_store)
+        .unwrap();
+    if rule_diff.is_empty() {
+        println!("Both stores use the same inclusion/ignore rules.");
+        return;
+    }
+    println!("Rules only used by the local store:");
+    for rule in &rule_diff.only_local {
+        if rule.include {
+            println!("+ {}", rule.rule.as_str());
+        } else {
+            println!("- {}", rule.rule.as_str());
+        }
+    }
+    println!("Rules only used by the remote store:");
+    for rule in &rule_diff.only_remote {
+        if rule.include {
+            println!("+ {}", rule.rule.as_str());
+        } else {
+            println!("- {}", rule.rule.as_str());
+        }
+    }
+}
+
+fn clean_pending_cmd<'a, 'b>() -> App<'a, 'b> {
+    let dry_run_arg = Arg::with_name("dry-run")
+        .long("dry-run")
+        .help("Only reports orphaned pending files, not removing them.")
+        .required(false)
+        .takes_value(false);
+    let clean_pending_cmd = SubCommand::with_name("clean-pending")
+        .about("removes orphaned files left in the pending files directory (e.g. by crashed syncs)")
+        .arg(dry_run_arg);
+
+    clean_pending_cmd
+}
+
+fn clean_pending_files<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    println!("Cleaning orphaned pending files...");
+    let local_data_store = open_data_store(local_path, fs);
+    let dry_run = cmd_cli.is_present("dry-run");
+    let result = local_data_store.clean_pending(dry_run).unwrap();
+    if dry_run {
+        println!("DRY RUN - NO ACTUAL CHANGES TO DISK");
+    }
+    println!(
+        "Reclaimed {} bytes in {} pending files.",
+        result.pending_bytes, result.pending_files
+    );
+}
+
+fn stores_cmd<'a, 'b>() -> App<'a, 'b> {
+    let stores_cmd = SubCommand::with_name("stores").about(
+        "lists all data stores known to the local store and when they were last synced from",
+    );
+
+    stores_cmd
+}
+
+fn list_data_stores<FS: virtual_fs::FS>(local_path: &str, fs: &FS, _cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    println!("Known data stores:");
+    for data_store in local_data_store.get_data_stores().unwrap() {
+        // The last sync is the wall-clock time of the last completed sync-from the store,
+        // the time is the logical counter of changes we know of on the store.
+        let last_sync = if data_store.is_this_store {
+            "local store".to_string()
+        } else if let Some(last_sync) = data_store.last_sync_completed {
+            format!("last synced {}", last_sync)
+        } else {
+            "never synced".to_string()
+        };
+        println!(
+            "{} ({}, time: {}, {})",
+            data_store.unique_name, data_store.human_name, data_store.time, last_sync
+        );
+    }
+}
+
+fn item_status_cmd<'a, 'b>() -> App<'a, 'b> {
+    let path_arg = Arg::with_name("PATH")
+        .required(true)
+        .index(1)
+        .help("Path of the item relative to the data store's root");
+    let item_status_cmd = SubCommand::with_name("item-status")
+        .about("shows which store last modified an item, its version vectors and which known stores hold its latest version")
+        .arg(path_arg);
+
+    item_status_cmd
+}
+
+fn show_item_status<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    let item_path = RelativePath::from_path(cmd_cli.value_of("PATH").unwrap());
+
+    let last_modification = local_data_store.last_modified_by(&item_path).unwrap();
+    println!(
+        "Last modified by {} ({}, time: {}) at '{}'.",
+        last_modification.data_store_human_name,
+        last_modification.data_store_name,
+        last_modification.data_store_time,
+        last_modification.path.to_path_buf().to_str().unwrap()
+    );
+
+    let replication_state = local_data_store.item_replication_state(&item_path).unwrap();
+    println!(
+        "Modifications: {}",
+        format_version_vector(&replication_state.mod_time)
+    );
+    println!(
+        "Sync time: {}",
+        format_version_vector(&replication_state.sync_time)
+    );
+    println!("Replication state:");
+    for store in replication_state.stores {
+        let state = if !store.is_included {
+            "excludes the item"
+        } else if store.has_current_version {
+            "holds the latest version"
+        } else {
+            "misses the latest version"
+        };
+        let local_marker = if store.is_this_store {
+            ", local store"
+        } else {
+            ""
+        };
+        println!(
+            "{} ({}{}): {}",
+            store.data_store_name, store.data_store_human_name, local_marker, state
+        );
+    }
+}
+
+fn format_version_vector(version_vector: &core::version_vector::VersionVector<String>) -> String {
+    let mut entries: Vec<String> = version_vector
+        .iter()
+        .map(|(data_store_name, time)| format!("{}={}", data_store_name, time))
+        .collect();
+    entries.sort();
+    if entries.is_empty() {
+        "-".to_string()
+    } else {
+        entries.join(", ")
+    }
+}
+
+fn ignored_cmd<'a, 'b>() -> App<'a, 'b> {
+    let list_arg = Arg::with_name("list")
+        .long("list")
+        .help("Lists the paths of all ignored items (instead of only counting them).")
+        .required(false)
+        .takes_value(false);
+    let ignored_cmd = SubCommand::with_name("ignored")
+        .about("shows the items currently ignored by the data store (e.g. to verify its rules)")
+        .arg(list_arg);
+
+    ignored_cmd
+}
+
+fn show_ignored_items<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+
+    let ignored_items = local_data_store.ignored_items().unwrap();
+    if cmd_cli.is_present("list") {
+        for ignored_item in &ignored_items {
+            println!("{}", ignored_item.path.to_path_buf().to_str().unwrap());
+        }
+    }
+    println!("{} items are ignored.", ignored_items.len());
+}
+
+fn summary_cmd<'a, 'b>() -> App<'a, 'b> {
+    let path_arg = Arg::with_name("PATH")
+        .required(false)
+        .index(1)
+        .default_value("")
+        .help(
+            "Path of the sub-tree relative to the data store's root (defaults to the whole store)",
+        );
+    let summary_cmd = SubCommand::with_name("summary")
+        .about(
+            "summarizes the indexed content of a sub-tree (file counts, total size, largest files) and the size of the store's metadata",
+        )
+        .arg(path_arg);
+
+    summary_cmd
+}
+
+fn show_tree_summary<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    let tree_path = RelativePath::from_path(cmd_cli.value_of("PATH").unwrap());
+
+    let summary = local_data_store.tree_summary(&tree_path).unwrap();
+    println!(
+        "{} files in {} folders, {} bytes in total.",
+        summary.files, summary.directories, summary.total_bytes
+    );
+    if !summary.largest_files.is_empty() {
+        println!("Largest files:");
+        for (file_path, size) in &summary.largest_files {
+            println!("{:>15} {}", size, file_path.to_path_buf().to_str().unwrap());
+        }
+    }
+
+    let footprint = local_data_store.metadata_footprint().unwrap();
+    println!(
+        "Metadata uses {} bytes (DB: {}, pending files: {}, snapshots: {}).",
+        footprint.total_bytes(),
+        footprint.db_bytes,
+        footprint.pending_bytes,
+        footprint.snapshot_bytes
+    );
+    if footprint.pending_bytes > 0 {
+        println!("Run clean-pending to remove left over pending files.");
+    }
+}
+
+fn dedup_cmd<'a, 'b>() -> App<'a, 'b> {
+    let report_arg = Arg::with_name("report")
+        .long("report")
+        .required_unless("hardlink")
+        .help("Lists all groups of files with identical content (largest files first).");
+    let hardlink_arg = Arg::with_name("hardlink")
+        .long("hardlink")
+        .help("Replaces duplicate files by hard links to one copy (requires a file system supporting hard links).");
+    let dedup_cmd = SubCommand::with_name("dedup")
+        .about("finds (and optionally hard links) indexed files with identical content")
+        .arg(report_arg)
+        .arg(hardlink_arg);
+
+    dedup_cmd
+}
+
+fn deduplicate_files<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+
+    if cmd_cli.is_present("report") {
+        let duplicates = local_data_store.find_duplicates().unwrap();
+        for (hash, paths) in &duplicates {
+            println!("{}:", hash);
+            for path in paths {
+                println!("    {}", path.to_path_buf().to_str().unwrap());
+            }
+        }
+        println!("Found {} groups of duplicate files.", duplicates.len());
+    }
+    if cmd_cli.is_present("hardlink") {
+        let linked_files = local_data_store.hard_link_duplicates().unwrap();
+        println!("Replaced {} duplicate files by hard links.", linked_files);
+    }
+}
+
+fn rehash_cmd<'a, 'b>() -> App<'a, 'b> {
+    let algorithm_arg = Arg::with_name("algorithm")
+        .long("algorithm")
+        .value_name("ALGORITHM")
+        .possible_values(&["SHA256", "SHA512"])
+        .required(true)
+        .takes_value(true)
+        .help("The hash algorithm to convert all stored content hashes to.");
+    let rehash_cmd = SubCommand::with_name("rehash")
+        .about(
+            "re-hashes all files with a different hash algorithm (resumes an interrupted re-hash)",
+        )
+        .arg(algorithm_arg);
+
+    rehash_cmd
+}
+
+fn rehash_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use core::fs_interaction::HashAlgorithm;
+
+    let mut local_data_store = open_data_store(local_path, fs);
+    let hash_algorithm = HashAlgorithm::from_name(cmd_cli.value_of("algorithm").unwrap()).unwrap();
+
+    println!("Re-hashing all files with {}...", hash_algorithm.name());
+    local_data_store.set_hash_algorithm(hash_algorithm);
+    let rehashed_files = local_data_store.rehash_all().unwrap();
+    println!("Re-hashed {} files.", rehashed_files);
+}
+
+fn transfer_cmd<'a, 'b>() -> App<'a, 'b> {
+    let enable_arg = Arg::with_name("enable")
+        .long("enable")
+        .required_unless("disable")
+        .conflicts_with("disable")
+        .help("Marks the store as transfer store. Only possible for stores that did not index or sync any data yet.");
+    let disable_arg = Arg::with_name("disable")
+        .long("disable")
+        .help("Turns the transfer store into a regular store. It starts indexing its own data and the next syncs deliver ALL data to it (not only the data other stores need).");
+    let encrypt_arg = Arg::with_name("encrypt")
+        .long("encrypt")
+        .requires("enable")
+        .help("Encrypts the file contents carried by the transfer store with the passphrase in SQUIRREL_PASSPHRASE (required to open the store from now on).");
+    let transfer_cmd = SubCommand::with_name("transfer")
+        .about("marks the store as (or turns it back from) a transfer store")
+        .arg(enable_arg)
+        .arg(disable_arg)
+        .arg(encrypt_arg);
+
+    transfer_cmd
+}
+
+fn toggle_transfer_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use core::data_store::DataStoreError;
+
+    let mut local_data_store = open_data_store(local_path, fs);
+    if cmd_cli.is_present("enable") {
+        match local_data_store.mark_as_transfer_store() {
+            Ok(()) => println!(
+                "Marked store as transfer store (it will carry data to all synced stores)."
+            ),
+            Err(DataStoreError::OnlyCleanStoresCanBecomeTransfer) => {
+                println!("Can not mark the store as transfer store, it already holds data.");
+                return;
+            }
+            Err(err) => panic!("{:?}", err),
+        }
+
+        if cmd_cli.is_present("encrypt") {
+            let passphrase = std::env::var(PASSPHRASE_VAR)
+                .unwrap_or_else(|_| panic!("Set {} to the passphrase to use.", PASSPHRASE_VAR));
+            match local_data_store.enable_encryption(&passphrase) {
+                Ok(()) => println!("Encrypted the file contents carried by the transfer store."),
+                Err(DataStoreError::OnlyCleanTransferStoresCanBeEncrypted) => {
+                    println!("Can not encrypt the transfer store, it already carries data.")
+                }
+                Err(err) => panic!("{:?}", err),
+            }
+        }
+    } else {
+        local_data_store.unmark_as_transfer_store().unwrap();
+        println!("The store is a regular store again, scan it and sync to receive all data.");
+    }
+}
+
+fn unlock_cmd<'a, 'b>() -> App<'a, 'b> {
+    let force_arg = Arg::with_name("force")
+        .long("force")
+        .help("Removes the lock even if the process holding it might still be running.")
+        .required(false)
+        .takes_value(false);
+    let unlock_cmd = SubCommand::with_name("unlock")
+        .about("removes a stale lock of the data store, e.g. left behind by a crashed process")
+        .arg(force_arg);
+
+    unlock_cmd
+}
+
+fn unlock_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use core::data_store::DataStoreError;
+    use core::fs_interaction::FSInteractionError;
+
+    let force = cmd_cli.is_present("force");
+    match DataStore::unlock_with_fs(local_path, fs.clone(), force) {
+        Ok(Some(pid)) => println!("Removed lock held by process {}.", pid),
+        Ok(None) => println!("Removed lock held by an unknown process."),
+        Err(DataStoreError::FSInteractionError {
+            source: FSInteractionError::MetadataDirNotLocked,
+        }) => println!("The data store is not locked."),
+        Err(DataStoreError::FSInteractionError {
+            source: FSInteractionError::LockStillHeld { pid },
+        }) => {
+            match pid {
+                Some(pid) => println!("The lock is held by the running process {}.", pid),
+                None => println!("The lock is held by an unknown process."),
+            }
+            println!("Re-run with --force to remove it anyway.");
+        }
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
+fn export_index_cmd<'a, 'b>() -> App<'a, 'b> {
+    let output_arg = Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .value_name("FILE")
+        .help("File to write the index to (defaults to the standard output).")
+        .required(false)
+        .takes_value(true);
+    let export_index_cmd = SubCommand::with_name("export-index")
+        .about("exports all items of the local index as JSON lines, e.g. for backups or tooling")
+        .arg(output_arg);
+
+    export_index_cmd
+}
+
+fn export_index<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use std::io::Write;
+
+    let local_data_store = open_data_store(local_path, fs);
+    let mut output: Box<dyn Write> = match cmd_cli.value_of("output") {
+        Some(output_path) => Box::new(std::fs::File::create(output_path).unwrap()),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let exported_items = local_data_store
+        .export_index(|line| writeln!(output, "{}", line).unwrap())
+        .unwrap();
+    if cmd_cli.is_present("output") {
+        println!("Exported {} items.", exported_items);
+    }
+}
+
+fn export_manifest_cmd<'a, 'b>() -> App<'a, 'b> {
+    let output_arg = Arg::with_name("output")
+        .long("output")
+        .short("o")
+        .value_name("FILE")
+        .help("File to write the manifest to (defaults to the standard output).")
+        .required(false)
+        .takes_value(true);
+    let export_manifest_cmd = SubCommand::with_name("export-manifest")
+        .about(
+            "exports the hashes of all files in the format of sha256sum/sha512sum (verify with sha256sum -c)",
+        )
+        .arg(output_arg);
+
+    export_manifest_cmd
+}
+
+fn export_manifest<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use std::io::Write;
+
+    let local_data_store = open_data_store(local_path, fs);
+    let mut output: Box<dyn Write> = match cmd_cli.value_of("output") {
+        Some(output_path) => Box::new(std::fs::File::create(output_path).unwrap()),
+        None => Box::new(std::io::stdout()),
+    };
+
+    let exported_files = local_data_store.export_manifest(&mut output).unwrap();
+    if cmd_cli.is_present("output") {
+        let algorithm = local_data_store.hash_algorithm().name().to_lowercase();
+        println!(
+            "Exported the hashes of {} files (verify with '{}sum -c').",
+            exported_files, algorithm
+        );
+    }
+}
+
+fn export_changes_cmd<'a, 'b>() -> App<'a, 'b> {
+    let bundle_arg = Arg::with_name("BUNDLE")
+        .required(true)
+        .index(1)
+        .help("File to write the change bundle to");
+    let since_arg = Arg::with_name("since")
+        .long("since")
+        .value_name("STORE=TIME")
+        .help("Only exports changes the target already knows up to the given time of the store (see the times listed by 'stores', can be repeated). Exports everything by default.")
+        .required(false)
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1);
+    let export_changes_cmd = SubCommand::with_name("export-changes")
+        .about("exports the local changes as a bundle that other stores can apply without a connection to this one")
+        .arg(bundle_arg)
+        .arg(since_arg);
+
+    export_changes_cmd
+}
+
+fn export_changes<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    use std::io::Write;
+
+    let local_data_store = open_data_store(local_path, fs);
+    let data_stores = local_data_store.get_data_stores().unwrap();
+
+    let mut since = core::version_vector::VersionVector::new();
+    for store_time in cmd_cli.values_of("since").into_iter().flatten() {
+        let mut parts = store_time.rsplitn(2, '=');
+        let time: i64 = parts
+            .next()
+            .unwrap()
+            .parse()
+            .expect("--since must be given as STORE=TIME");
+        let store_name = parts.next().expect("--since must be given as STORE=TIME");
+        match data_stores
+            .iter()
+            .find(|data_store| data_store.unique_name == store_name)
+        {
+            Some(data_store) => since[&data_store.id] = time,
+            None => {
+                println!("Unknown data store '{}' (see 'stores').", store_name);
+                return;
+            }
+        }
+    }
+
+    let bundle_path = cmd_cli.value_of("BUNDLE").unwrap();
+    let mut bundle = std::io::BufWriter::new(std::fs::File::create(bundle_path).unwrap());
+    let exported_files = local_data_store
+        .export_changes(&since, &mut bundle)
+        .unwrap();
+    bundle.flush().unwrap();
+    println!("Exported {} changed files.", exported_files);
+}
+
+fn apply_changes_cmd<'a, 'b>() -> App<'a, 'b> {
+    let bundle_arg = Arg::with_name("BUNDLE")
+        .required(true)
+        .index(1)
+        .help("Change bundle written by export-changes of another store");
+    let conflict_choose_local = Arg::with_name("choose-local")
+        .long("choose-local")
+        .short("l")
+        .help("Chooses the local over the bundled item on conflicts.")
+        .required(false)
+        .takes_value(false);
+    let conflict_choose_remote = Arg::with_name("choose-remote")
+        .long("choose-remote")
+        .short("r")
+        .help("Chooses the bundled over the local item on conflicts.")
+        .required(false)
+        .takes_value(false)
+        .conflicts_with("choose-local");
+    let conflict_prefer_newer = Arg::with_name("prefer-newer")
+        .long("prefer-newer")
+        .help("Chooses the item with the more recent file modification time on conflicts.")
+        .required(false)
+        .takes_value(false)
+        .conflicts_with_all(&["choose-local", "choose-remote"]);
+    let apply_changes_cmd = SubCommand::with_name("apply-changes")
+        .about("applies a change bundle of another store like a sync from it (local <- bundle)")
+        .arg(bundle_arg)
+        .arg(conflict_choose_local)
+        .arg(conflict_choose_remote)
+        .arg(conflict_prefer_newer);
+
+    apply_changes_cmd
+}
+
+fn apply_changes<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let choose_local = cmd_cli.is_present("choose-local");
+    let choose_remote = cmd_cli.is_present("choose-remote");
+    let prefer_newer = cmd_cli.is_present("prefer-newer");
+
+    let local_data_store = open_data_store(local_path, fs);
+    let bundle_path = cmd_cli.value_of("BUNDLE").unwrap();
+    let mut bundle = std::io::BufReader::new(std::fs::File::open(bundle_path).unwrap());
+    let result = local_data_store.apply_changes(&mut bundle, &mut |conflict| {
+        resolve_sync_conflict(conflict, choose_local, choose_remote, prefer_newer, false)
+    });
+    match result {
+        Ok(result) => {
+            for unavailable_item in &result.unavailable_items {
+                println!(
+                    "WARNING: Left out {} (content not in the bundle, export it with an earlier --since)",
+                    unavailable_item.to_path_buf().to_str().unwrap()
+                );
+            }
+            println!(
+                "Applied changes: visited {} items ({} already up to date), transferred {} files ({} bytes).",
+                result.visited_items,
+                result.up_to_date_items,
+                result.transferred_files,
+                result.transferred_bytes
+            );
+        }
+        Err(core::data_store::DataStoreError::DataSetMismatch {
+            local_data_set,
+            remote_data_set,
+        }) => println!(
+            "Can not apply the changes, they belong to a different data set (local: '{}', bundle: '{}').",
+            local_data_set, remote_data_set
+        ),
+        Err(core::data_store::DataStoreError::InvalidChangeBundle { message }) => {
+            println!("Can not apply the changes: {}", message)
+        }
+        Err(core::data_store::DataStoreError::DiskOutOfSync { path }) => println!(
+            "Can not apply the changes, '{}' changed since the last scan. Re-run the scan.",
+            path.to_path_buf().to_str().unwrap()
+        ),
+        Err(err) => panic!("{:?}", err),
+    }
+}
+
+fn divergence_cmd<'a, 'b>() -> App<'a, 'b> {
+    let remote_path_arg = Arg::with_name("REMOTE_PATH")
+        .required(true)
+        .index(1)
+        .help("Path of the remote data store on disk");
+    let divergence_cmd = SubCommand::with_name("divergence")
+        .about("compares the version vectors of the local and remote store per data store")
+        .arg(remote_path_arg);
+
+    divergence_cmd
+}
+
+fn show_divergence<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    let remote_path = cmd_cli.value_of("REMOTE_PATH").unwrap();
+    let remote_data_store = open_data_store(remote_path, fs);
+
+    let report = local_data_store.divergence(&remote_data_store).unwrap();
+    for entry in &report.entries {
+        let state = if entry.is_local_ahead() {
+            "local is ahead"
+        } else if entry.is_remote_ahead() {
+            "remote is ahead"
+        } else {
+            "in sync"
+        };
+        println!(
+            "{}: {} (sync time local {} / remote {}, mod time local {} / remote {})",
+            entry.data_store_name,
+            state,
+            entry.local_sync_time,
+            entry.remote_sync_time,
+            entry.local_mod_time,
+            entry.remote_mod_time
+        );
+    }
+    if report.is_converged() {
+        println!("Both stores know about the same changes.");
+    }
+}
+
+fn dataset_cmd<'a, 'b>() -> App<'a, 'b> {
+    let show_arg = Arg::with_name("show")
+        .long("show")
+        .required_unless("set-name")
+        .help("Prints the unique and the human readable name of the data set.");
+    let set_name_arg = Arg::with_name("set-name")
+        .long("set-name")
+        .value_name("NAME")
+        .takes_value(true)
+        .help("Changes the human readable name of the data set (a label kept by this store). The unique name given on create is fixed, it identifies the data set on syncs.");
+    let dataset_cmd = SubCommand::with_name("dataset")
+        .about("shows or renames the data set the store belongs to")
+        .arg(show_arg)
+        .arg(set_name_arg);
+
+    dataset_cmd
+}
+
+fn manage_data_set<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    if let Some(human_name) = cmd_cli.value_of("set-name") {
+        local_data_store
+            .set_data_set_human_name(human_name)
+            .unwrap();
+        println!("Renamed the data set to '{}'.", human_name);
+    }
+    if cmd_cli.is_present("show") {
+        println!(
+            "Data set: unique name '{}', human name '{}'",
+            local_data_store.data_set_name().unwrap(),
+            local_data_store.data_set_human_name().unwrap()
+        );
+    }
+}
+
+fn pause_cmd<'a, 'b>() -> App<'a, 'b> {
+    let path_arg = Arg::with_name("PATH")
+        .required(false)
+        .index(1)
+        .help("Path of the sub-tree relative to the data store's root (lists all paused paths if omitted)");
+    let resume_arg = Arg::with_name("resume")
+        .long("resume")
+        .requires("PATH")
+        .help("Resumes indexing of the paused path, the next scan picks up its changes.");
+    let pause_cmd = SubCommand::with_name("pause")
+        .about(
+            "pauses indexing of a sub-tree (its items keep their sync state, unlike ignored ones)",
+        )
+        .arg(path_arg)
+        .arg(resume_arg);
+
+    pause_cmd
+}
+
+fn pause_paths<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let mut local_data_store = open_data_store(local_path, fs);
+    if let Some(path) = cmd_cli.value_of("PATH") {
+        let relative_path = RelativePath::from_path(path);
+        if cmd_cli.is_present("resume") {
+            if local_data_store.resume_path(&relative_path).unwrap() {
+                println!("Resumed indexing of '{}'.", path);
+            } else {
+                println!("'{}' was not paused.", path);
+            }
+        } else if local_data_store.pause_path(&relative_path).unwrap() {
+            println!("Paused indexing of '{}'.", path);
+        } else {
+            println!("'{}' is already paused.", path);
+        }
+    } else {
+        for paused_path in local_data_store.paused_paths() {
+            println!("{}", paused_path.to_path_buf().display());
+        }
+    }
+}
+
+fn merge_from_cmd<'a, 'b>() -> App<'a, 'b> {
+    let remote_path_arg = Arg::with_name("REMOTE_PATH")
+        .required(true)
+        .index(1)
+        .help("Path of the remote data store on disk");
+    let merge_from_cmd = SubCommand::with_name("merge-from")
+        .about("recovers items from another store of the data set (imports newer and missing items, never deletes local ones)")
+        .arg(remote_path_arg);
+
+    merge_from_cmd
+}
+
+fn merge_from_remote<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    let remote_path = cmd_cli.value_of("REMOTE_PATH").unwrap();
+    let remote_data_store = open_data_store(remote_path, fs);
+
+    println!("Merging items FROM remote INTO local data store...");
+    let result = local_data_store
+        .merge_index_from(&remote_data_store)
+        .unwrap();
+    println!(
+        "Merged the remote store ({} items visited, {} files transferred).",
+        result.visited_items, result.transferred_files
+    );
+}
+
+fn status_cmd<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name("status").about(
+        "gives an overview of the data store (identity, item counts, rules, metadata size and left over work)",
+    )
+}
+
+fn show_status<FS: virtual_fs::FS>(local_path: &str, fs: &FS, _cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+
+    println!(
+        "Data set: {} ({})",
+        local_data_store.data_set_name().unwrap(),
+        local_data_store.data_set_human_name().unwrap()
+    );
+    let local_store = local_data_store
+        .get_data_stores()
+        .unwrap()
+        .into_iter()
+        .find(|data_store| data_store.is_this_store)
+        .unwrap();
+    println!(
+        "Local store: {} ({}), time: {}",
+        local_store.unique_name,
+        local_store.human_name,
+        local_data_store.local_time().unwrap()
+    );
+    if local_data_store.is_transfer_store().unwrap() {
+        println!("Store type: transfer store");
+    } else {
+        println!("Store type: regular store");
+    }
+    println!(
+        "Known data stores: {}",
+        local_data_store.get_data_stores().unwrap().len()
+    );
+
+    let summary = local_data_store
+        .tree_summary(&RelativePath::from_path(""))
+        .unwrap();
+    println!(
+        "Items: {} files, {} folders ({} bytes)",
+        summary.files, summary.directories, summary.total_bytes
+    );
+
+    let rules = local_data_store.get_inclusion_rules();
+    let inclusion_rules = rules.iter().filter(|rule| rule.include).count();
+    let ignore_rules = rules.iter().filter(|rule| !rule.include).count();
+    println!(
+        "Rules: {} inclusion rules, {} ignore rules, {} paused paths",
+        inclusion_rules,
+        ignore_rules,
+        local_data_store.paused_paths().len()
+    );
+
+    let footprint = local_data_store.metadata_footprint().unwrap();
+    println!(
+        "Metadata: {} bytes (DB: {}, pending files: {}, snapshots: {})",
+        footprint.total_bytes(),
+        footprint.db_bytes,
+        footprint.pending_bytes,
+        footprint.snapshot_bytes
+    );
+
+    // Interrupted operations are recovered when opening the store, but must be re-run.
+    let pending_files = local_data_store.clean_pending(true).unwrap();
+    match local_data_store.interrupted_operation() {
+        Some(operation) => println!(
+            "Unresolved: the operation '{}' was interrupted, please re-run it",
+            operation
+        ),
+        None if pending_files.pending_files > 0 => println!(
+            "Unresolved: {} orphaned pending files, run clean-pending to remove them",
+            pending_files.pending_files
+        ),
+        None => println!("Unresolved: nothing, the last operation completed"),
+    }
+}
+
+fn check_pair_cmd<'a, 'b>() -> App<'a, 'b> {
+    let other_path_arg = Arg::with_name("OTHER_PATH")
+        .required(true)
+        .index(1)
+        .help("Path to the other data store");
+    let check_pair_cmd = SubCommand::with_name("check-pair")
+        .about("checks that both data stores belong to the same data set (without opening or locking them)")
+        .arg(other_path_arg);
+
+    check_pair_cmd
+}
+
+fn check_pair<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let other_path = cmd_cli.value_of("OTHER_PATH").unwrap();
+
+    // Stores on the in-memory FS only exist while they are open.
+    let peek_data_set_name = |path: &str| match fs.db_access_type() {
+        virtual_fs::DBAccessType::InMemory => open_data_store(path, fs).data_set_name().unwrap(),
+        _ => DataStore::peek_data_set_name_with_fs(path, fs.clone()).unwrap(),
+    };
+    let local_data_set = peek_data_set_name(local_path);
+    let other_data_set = peek_data_set_name(other_path);
+    if local_data_set == other_data_set {
+        println!(
+            "Compatible: both stores belong to the data set '{}'.",
+            local_data_set
+        );
+    } else {
+        println!(
+            "Incompatible: the stores belong to different data sets ('{}' and '{}').",
+            local_data_set, other_data_set
+        );
+    }
+}
+
+fn mirror_from_cmd<'a, 'b>() -> App<'a, 'b> {
+    let remote_path_arg = Arg::with_name("REMOTE_PATH")
+        .required(true)
+        .index(1)
+        .help("Path of the remote data store on disk");
+    let confirm_deletions_arg = Arg::with_name("confirm-deletions")
+        .long("confirm-deletions")
+        .help("Confirms that local items the remote does not hold are deleted, including independently created ones. Without it, the deletions are only listed.")
+        .required(false)
+        .takes_value(false);
+    let mirror_from_cmd = SubCommand::with_name("mirror-from")
+        .about("makes the local store an exact mirror of the remote (remote wins all conflicts, discards independent local changes)")
+        .arg(remote_path_arg)
+        .arg(confirm_deletions_arg);
+
+    mirror_from_cmd
+}
+
+fn mirror_from_remote<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let local_data_store = open_data_store(local_path, fs);
+    let remote_path = cmd_cli.value_of("REMOTE_PATH").unwrap();
+    let remote_data_store = open_data_store(remote_path, fs);
+    let root_path = RelativePath::from_path("");
+
+    if !cmd_cli.is_present("confirm-deletions") {
+        let missing_items = local_data_store
+            .plan_mirror(&remote_data_store, &root_path)
+            .unwrap();
+        println!("DRY RUN - NO ACTUAL CHANGES TO DISK");
+        for item in missing_items.iter() {
+            println!("Would delete: {}", item.path.to_path_buf().display());
+        }
+        println!(
+            "{} local items are not held by the remote.",
+            missing_items.len()
+        );
+        println!("Please re-run with --confirm-deletions to mirror the remote store.");
+        return;
+    }
+
+    println!("Mirroring remote INTO local data store...");
+    let result = local_data_store
+        .mirror_from(&remote_data_store, &root_path)
+        .unwrap();
+    for path in result.mirrored_deletions.iter() {
+        println!("Deleted: {}", path.to_path_buf().display());
+    }
+    println!(
+        "Mirrored the remote store ({} items visited, {} files transferred, {} local items deleted).",
+        result.visited_items,
+        result.transferred_files,
+        result.mirrored_deletions.len()
+    );
+}
+
+fn reidentify_cmd<'a, 'b>() -> App<'a, 'b> {
+    let moved_arg = Arg::with_name("moved")
+        .long("moved")
+        .help("The store was only moved, keep its identity and record the new location.")
+        .required(false)
+        .takes_value(false);
+    let reidentify_cmd = SubCommand::with_name("reidentify")
+        .about("assigns a new identity to a cloned store (clones must not share the same identity)")
+        .arg(moved_arg);
+
+    reidentify_cmd
+}
+
+fn reidentify_data_store<FS: virtual_fs::FS>(local_path: &str, fs: &FS, cmd_cli: &ArgMatches) {
+    let mut local_data_store = open_data_store(local_path, fs);
+    if cmd_cli.is_present("moved") {
+        local_data_store.confirm_location().unwrap();
+        println!("Recorded the new location of the data store.");
+    } else {
+        let old_name = local_data_store.local_data_store_name().unwrap();
+        let new_name = local_data_store.reidentify().unwrap();
+        println!(
+            "Re-identified the data store '{}' as '{}'.",
+            old_name, new_name
+        );
+    }
 }
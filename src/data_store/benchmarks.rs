@@ -0,0 +1,204 @@
+// Simple benchmark harness for scans, measuring the time and heap allocations of
+// perform_full_scan on synthetic trees. The benchmarks themselves are ignored by default,
+// run them with (tree sizes can be set with SQUIRREL_BENCH_{DEPTH,FOLDERS,FILES,FILE_SIZE}):
+//   cargo test --release benchmarks -- --ignored --test-threads=1
+use super::*;
+use fs_interaction::virtual_fs::InMemoryFS;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+// Counts heap allocations per thread, i.e. tests running in parallel do not influence
+// each others measurements.
+struct CountingAllocator;
+thread_local! {
+    static ALLOCATIONS: Cell<(u64, u64)> = const { Cell::new((0, 0)) };
+}
+fn count_allocation(size: usize) {
+    // Fails while the thread is torn down, we are not interested in these allocations.
+    let _ = ALLOCATIONS.try_with(|allocations| {
+        let (count, bytes) = allocations.get();
+        allocations.set((count + 1, bytes + size as u64));
+    });
+}
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Debug, Clone, Copy)]
+struct Measurement {
+    duration: Duration,
+    allocations: u64,
+    allocated_bytes: u64,
+}
+
+fn measure<R, F: FnOnce() -> R>(func: F) -> (R, Measurement) {
+    let (allocations_before, bytes_before) = ALLOCATIONS.with(Cell::get);
+    let start = Instant::now();
+    let result = func();
+    let duration = start.elapsed();
+    let (allocations_after, bytes_after) = ALLOCATIONS.with(Cell::get);
+
+    let measurement = Measurement {
+        duration,
+        allocations: allocations_after - allocations_before,
+        allocated_bytes: bytes_after - bytes_before,
+    };
+    (result, measurement)
+}
+
+// Synthetic tree of folders nested depth levels deep. Each folder holds folders_per_folder
+// sub-folders (none on the deepest level) and files_per_folder files of file_size bytes.
+struct ScanBenchmark {
+    depth: usize,
+    folders_per_folder: usize,
+    files_per_folder: usize,
+    file_size: usize,
+}
+impl ScanBenchmark {
+    // Small enough to run along with the regular tests.
+    fn small() -> Self {
+        Self {
+            depth: 2,
+            folders_per_folder: 3,
+            files_per_folder: 5,
+            file_size: 16 * 1024,
+        }
+    }
+
+    fn from_env() -> Self {
+        let env_or = |name: &str, default: usize| {
+            std::env::var(name).map_or(default, |value| {
+                value.parse().expect("Benchmark sizes must be numbers")
+            })
+        };
+
+        Self {
+            depth: env_or("SQUIRREL_BENCH_DEPTH", 3),
+            folders_per_folder: env_or("SQUIRREL_BENCH_FOLDERS", 10),
+            files_per_folder: env_or("SQUIRREL_BENCH_FILES", 20),
+            file_size: env_or("SQUIRREL_BENCH_FILE_SIZE", 4 * 1024),
+        }
+    }
+
+    // Number of files and folders in the tree (including the root folder).
+    fn item_count(&self) -> usize {
+        let mut folders = 0;
+        let mut folders_on_level = 1;
+        for _ in 0..=self.depth {
+            folders += folders_on_level;
+            folders_on_level *= self.folders_per_folder;
+        }
+        folders * (1 + self.files_per_folder)
+    }
+
+    fn build_tree<FS: virtual_fs::FS>(&self, fs: &FS, root: &Path) {
+        self.build_folder(fs, root, 0);
+    }
+
+    fn build_folder<FS: virtual_fs::FS>(&self, fs: &FS, folder: &Path, level: usize) {
+        for i in 0..self.files_per_folder {
+            let file_path = folder.join(format!("file-{}", i));
+            let content = vec![(i % 251) as u8; self.file_size];
+            fs.create_file(&file_path).unwrap();
+            fs.overwrite_file(&file_path, Box::new(io::Cursor::new(content)))
+                .unwrap();
+        }
+        if level < self.depth {
+            for i in 0..self.folders_per_folder {
+                let sub_folder = folder.join(format!("folder-{}", i));
+                fs.create_dir(&sub_folder, false).unwrap();
+                self.build_folder(fs, &sub_folder, level + 1);
+            }
+        }
+    }
+
+    // Measures the initial scan of the tree and a re-scan without any changes.
+    fn run<FS: virtual_fs::FS>(&self, fs: FS, root: &Path) -> (Measurement, Measurement) {
+        let data_store =
+            DataStore::create_with_fs(root, "XYZ", "XYZ", "bench", fs.clone()).unwrap();
+        self.build_tree(&fs, root);
+
+        let (result, initial_scan) = measure(|| data_store.perform_full_scan().unwrap());
+        // The root folder exists along with the data store and is never reported as new.
+        assert_eq!(result.new_items, self.item_count() - 1);
+        let (result, rescan) = measure(|| data_store.perform_full_scan().unwrap());
+        assert_eq!(result.new_items + result.changed_items, 0);
+
+        (initial_scan, rescan)
+    }
+
+    fn report(&self, name: &str, (initial_scan, rescan): (Measurement, Measurement)) {
+        let mut stderr = io::stderr();
+        writeln!(
+            stderr,
+            "{}: {} items ({} bytes per file)",
+            name,
+            self.item_count(),
+            self.file_size
+        )
+        .unwrap();
+        for (scan, measurement) in [("initial scan", initial_scan), ("re-scan", rescan)] {
+            writeln!(
+                stderr,
+                "  {}: {:?}, {:.0} items/s, {} allocations ({} bytes)",
+                scan,
+                measurement.duration,
+                self.item_count() as f64 / measurement.duration.as_secs_f64(),
+                measurement.allocations,
+                measurement.allocated_bytes
+            )
+            .unwrap();
+        }
+    }
+}
+
+#[test]
+fn no_op_rescan_is_cheaper_than_initial_scan() {
+    let benchmark = ScanBenchmark::small();
+    let (initial_scan, rescan) = benchmark.run(InMemoryFS::new(), Path::new(""));
+
+    // Unchanged files are neither hashed nor written to the DB again. Allocations are
+    // deterministic (unlike timings), thus we guard against regressions with them.
+    assert!(
+        rescan.allocated_bytes * 4 < initial_scan.allocated_bytes,
+        "Re-scan allocated {} bytes, the initial scan {} bytes",
+        rescan.allocated_bytes,
+        initial_scan.allocated_bytes
+    );
+    assert!(rescan.allocations < initial_scan.allocations);
+}
+
+#[test]
+#[ignore]
+fn benchmark_scan_in_memory() {
+    let benchmark = ScanBenchmark::from_env();
+    let measurements = benchmark.run(InMemoryFS::new(), Path::new(""));
+    benchmark.report("In-memory FS", measurements);
+}
+
+#[test]
+#[ignore]
+fn benchmark_scan_on_disk() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let benchmark = ScanBenchmark::from_env();
+    let measurements = benchmark.run(virtual_fs::WrapperFS::new(), test_dir.path());
+    benchmark.report("Native FS", measurements);
+}
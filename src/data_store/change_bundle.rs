@@ -0,0 +1,166 @@
+// Change bundles (see DataStore::export_changes) are a sequence of entries, each introduced by
+// a header line and followed by exactly the given number of bytes of content:
+//   data_squirrel change bundle 1
+//   index <length>          copy of the exporting store's metadata DB
+//   file <length> <path>    content of one file of the index (any number of these)
+//   end
+use super::{DataStoreError, Result};
+use fs_interaction::relative_path::RelativePath;
+use fs_interaction::FSInteractionError;
+use std::io;
+use std::path::Path;
+
+const HEADER: &str = "data_squirrel change bundle 1";
+// Name of the index copy while packing/unpacking a bundle.
+pub const INDEX_FILE: &str = "index.sqlite";
+
+// The index copy is opened by its path as a string, the temp dir holding it might not be UTF-8.
+pub fn index_path(tmp_dir: &Path) -> Result<String> {
+    tmp_dir
+        .join(INDEX_FILE)
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| {
+            FSInteractionError::from(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "The temp dir path is not valid UTF-8",
+            ))
+            .into()
+        })
+}
+
+pub enum BundleEntry {
+    Index { length: u64 },
+    File { path: RelativePath, length: u64 },
+}
+
+pub fn write_header<W: io::Write>(writer: &mut W) -> Result<()> {
+    writeln!(writer, "{}", HEADER).map_err(FSInteractionError::from)?;
+    Ok(())
+}
+
+pub fn write_entry<W: io::Write, R: io::Read>(
+    writer: &mut W,
+    entry: &BundleEntry,
+    content: &mut R,
+) -> Result<()> {
+    let length = match entry {
+        BundleEntry::Index { length } => {
+            writeln!(writer, "index {}", length).map_err(FSInteractionError::from)?;
+            *length
+        }
+        BundleEntry::File { path, length } => {
+            writeln!(writer, "file {} {}", length, escape_path(path))
+                .map_err(FSInteractionError::from)?;
+            *length
+        }
+    };
+
+    copy_content(content, writer, length)
+}
+
+pub fn write_end<W: io::Write>(writer: &mut W) -> Result<()> {
+    writeln!(writer, "end").map_err(FSInteractionError::from)?;
+    Ok(())
+}
+
+pub fn read_header<R: io::Read>(reader: &mut R) -> Result<()> {
+    if read_line(reader)? != HEADER {
+        return Err(invalid_bundle(
+            "Not a change bundle (or one of an unsupported version)!",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads the header line of the next entry, None once the end of the bundle is reached.
+/// The caller must consume the entry's content (see copy_content) before reading the next one.
+pub fn read_entry<R: io::Read>(reader: &mut R) -> Result<Option<BundleEntry>> {
+    let line = read_line(reader)?;
+    let parts: Vec<&str> = line.splitn(3, ' ').collect();
+    let length = || {
+        parts
+            .get(1)
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| invalid_bundle("Malformed entry in change bundle!"))
+    };
+
+    match parts[0] {
+        "end" => Ok(None),
+        "index" => Ok(Some(BundleEntry::Index { length: length()? })),
+        "file" => match parts.get(2) {
+            Some(path) => Ok(Some(BundleEntry::File {
+                path: unescape_path(path),
+                length: length()?,
+            })),
+            None => Err(invalid_bundle("Malformed entry in change bundle!")),
+        },
+        _ => Err(invalid_bundle("Unknown entry in change bundle!")),
+    }
+}
+
+/// Copies exactly length bytes from the reader to the writer.
+pub fn copy_content<R: io::Read, W: io::Write>(
+    reader: &mut R,
+    writer: &mut W,
+    length: u64,
+) -> Result<()> {
+    let copied =
+        io::copy(&mut io::Read::take(reader, length), writer).map_err(FSInteractionError::from)?;
+    if copied != length {
+        return Err(invalid_bundle("Change bundle is truncated!"));
+    }
+
+    Ok(())
+}
+
+// Header lines are short, read them byte by byte to not consume any of the following content.
+fn read_line<R: io::Read>(reader: &mut R) -> Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        match reader.read_exact(&mut byte) {
+            Ok(()) if byte[0] == b'\n' => break,
+            Ok(()) => line.push(byte[0]),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Err(invalid_bundle("Change bundle is truncated!"));
+            }
+            Err(e) => return Err(FSInteractionError::from(e).into()),
+        }
+    }
+
+    String::from_utf8(line).map_err(|_| invalid_bundle("Malformed entry in change bundle!"))
+}
+
+// Like in manifests, backslashes and newlines in paths are escaped.
+fn escape_path(path: &RelativePath) -> String {
+    path.get_path_components()[1..]
+        .join("/")
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+}
+
+fn unescape_path(escaped_path: &str) -> RelativePath {
+    let mut path = String::with_capacity(escaped_path.len());
+    let mut characters = escaped_path.chars();
+    while let Some(character) = characters.next() {
+        match (character, characters.clone().next()) {
+            ('\\', Some('n')) => {
+                path.push('\n');
+                characters.next();
+            }
+            ('\\', Some('\\')) => {
+                path.push('\\');
+                characters.next();
+            }
+            (character, _) => path.push(character),
+        }
+    }
+
+    RelativePath::from_path(path)
+}
+
+fn invalid_bundle(message: &'static str) -> DataStoreError {
+    DataStoreError::InvalidChangeBundle { message }
+}
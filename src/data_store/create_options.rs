@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+/// Options used when creating a new data store.
+///
+/// Construct it with the unique name of the data set the store belongs to and adjust the
+/// remaining settings (all having sensible defaults) using the builder methods, e.g.
+/// DataStoreCreateOptions::new("XYZ").data_store_name("laptop").transfer_store(true).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataStoreCreateOptions {
+    pub data_set_unique_name: String,
+    pub data_set_human_name: String,
+    pub data_store_name: String,
+    pub data_store_id: Option<String>,
+    pub location_note: String,
+
+    pub is_transfer_store: bool,
+    pub trust_mtime: bool,
+    pub propagate_case_changes: bool,
+    pub normalize_unicode: bool,
+    pub external_db_path: Option<PathBuf>,
+}
+impl DataStoreCreateOptions {
+    pub fn new(data_set_unique_name: &str) -> Self {
+        Self {
+            data_set_unique_name: data_set_unique_name.to_string(),
+            data_set_human_name: data_set_unique_name.to_string(),
+            data_store_name: "default".to_string(),
+            data_store_id: None,
+            location_note: "".to_string(),
+
+            is_transfer_store: false,
+            trust_mtime: false,
+            propagate_case_changes: true,
+            normalize_unicode: true,
+            external_db_path: None,
+        }
+    }
+
+    pub fn data_set_human_name(mut self, data_set_human_name: &str) -> Self {
+        self.data_set_human_name = data_set_human_name.to_string();
+        self
+    }
+
+    pub fn data_store_name(mut self, data_store_name: &str) -> Self {
+        self.data_store_name = data_store_name.to_string();
+        self
+    }
+
+    /// Uses the given stable unique id for the store instead of a randomly generated one.
+    /// Only re-use the id of a store to deliberately restore its identity (e.g. after
+    /// re-installing a device), two live stores sharing one id corrupt the sync metadata.
+    /// Syncs refuse partners using the same id (see DataStore::sync_data_store_list).
+    pub fn data_store_id(mut self, data_store_id: &str) -> Self {
+        self.data_store_id = Some(data_store_id.to_string());
+        self
+    }
+
+    pub fn location_note(mut self, location_note: &str) -> Self {
+        self.location_note = location_note.to_string();
+        self
+    }
+
+    pub fn transfer_store(mut self, is_transfer_store: bool) -> Self {
+        self.is_transfer_store = is_transfer_store;
+        self
+    }
+
+    pub fn trust_mtime(mut self, trust_mtime: bool) -> Self {
+        self.trust_mtime = trust_mtime;
+        self
+    }
+
+    pub fn propagate_case_changes(mut self, propagate_case_changes: bool) -> Self {
+        self.propagate_case_changes = propagate_case_changes;
+        self
+    }
+
+    /// Treats names only differing in their unicode normalization (e.g. NFC vs. NFD) as the
+    /// same item. This changes how items are keyed, i.e. it can only be chosen on creation.
+    pub fn normalize_unicode(mut self, normalize_unicode: bool) -> Self {
+        self.normalize_unicode = normalize_unicode;
+        self
+    }
+
+    /// Places the metadata DB at the given file path outside of the store (see
+    /// FSInteraction::set_external_db_path), e.g. on a faster disk than the data.
+    pub fn external_db_path<P: AsRef<Path>>(mut self, external_db_path: P) -> Self {
+        self.external_db_path = Some(external_db_path.as_ref().to_path_buf());
+        self
+    }
+}
@@ -0,0 +1,122 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// Ignore rules read from per-directory ignore files (similar to .gitignore) on scans and syncs.
+///
+/// Each line of an ignore file is a glob pattern, empty lines and lines starting with '#' are
+/// skipped. Patterns apply to the sub-tree of the directory holding the ignore file only:
+/// - patterns without a '/' match the name of items at any depth (e.g. '*.tmp'),
+/// - patterns with a '/' match the path relative to the directory (e.g. 'build/**'),
+/// - patterns starting with '!' re-include items ignored by earlier patterns.
+///
+/// The last matching pattern decides, patterns of nested ignore files are checked after the
+/// ones of their parent directories (i.e. they take precedence).
+/// Ignore files only add to the store-global inclusion rules: items ignored by the global rules
+/// can NOT be re-included by them. Matching items are marked ignored in the index, just like
+/// items ignored by the global rules.
+#[derive(Debug, Clone)]
+pub struct DirIgnoreRules {
+    scopes: Vec<IgnoreScope>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreScope {
+    dir_components: Vec<String>,
+    patterns: Vec<IgnorePattern>,
+}
+
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    pattern: glob::Pattern,
+    negated: bool,
+    name_only: bool,
+}
+
+impl DirIgnoreRules {
+    pub fn new() -> Self {
+        Self { scopes: vec![] }
+    }
+
+    /// Returns the rules with the ignore file content of the given directory layered on top.
+    /// Invalid patterns are skipped.
+    pub fn with_ignore_file(&self, dir: &RelativePath, content: &str) -> Self {
+        let patterns = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, line) = if let Some(line) = line.strip_prefix('!') {
+                    (true, line)
+                } else {
+                    (false, line)
+                };
+                let line = line.trim_start_matches('/').to_lowercase();
+                glob::Pattern::new(&line).ok().map(|pattern| IgnorePattern {
+                    pattern,
+                    negated,
+                    name_only: !line.contains('/'),
+                })
+            })
+            .collect();
+
+        let mut result = self.clone();
+        result.scopes.push(IgnoreScope {
+            dir_components: dir.to_lower_case().get_path_components().clone(),
+            patterns,
+        });
+        result
+    }
+
+    pub fn is_ignored(&self, path: &RelativePath) -> bool {
+        let path = path.to_lower_case();
+        let path_components = path.get_path_components();
+
+        let mut ignored = false;
+        for scope in &self.scopes {
+            if path_components.len() <= scope.dir_components.len()
+                || !path_components.starts_with(&scope.dir_components)
+            {
+                continue;
+            }
+
+            let relative_path = path_components[scope.dir_components.len()..].join("/");
+            for pattern in &scope.patterns {
+                let matches = if pattern.name_only {
+                    pattern.pattern.matches(path.name())
+                } else {
+                    pattern.pattern.matches(&relative_path)
+                };
+                if matches {
+                    ignored = !pattern.negated;
+                }
+            }
+        }
+
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_ignore_files() {
+        let rules = DirIgnoreRules::new()
+            .with_ignore_file(
+                &RelativePath::from_path(""),
+                "# Temporary files\n*.tmp\n\nbuild/**\n",
+            )
+            .with_ignore_file(&RelativePath::from_path("sub"), "!keep.tmp\n*.log");
+
+        assert!(rules.is_ignored(&RelativePath::from_path("file.tmp")));
+        assert!(rules.is_ignored(&RelativePath::from_path("sub/deep/file.TMP")));
+        assert!(rules.is_ignored(&RelativePath::from_path("build/output")));
+        assert!(!rules.is_ignored(&RelativePath::from_path("sub/build/output")));
+        assert!(!rules.is_ignored(&RelativePath::from_path("file.log")));
+        assert!(rules.is_ignored(&RelativePath::from_path("sub/file.log")));
+
+        // Negations in nested files re-include items of their sub-tree only.
+        assert!(!rules.is_ignored(&RelativePath::from_path("sub/keep.tmp")));
+        assert!(rules.is_ignored(&RelativePath::from_path("keep.tmp")));
+    }
+}
@@ -0,0 +1,53 @@
+/// Compares the version vectors of the root folders of two data stores, i.e. shows which store
+/// knows about more changes of which other store. Mainly useful to debug stores that do not
+/// converge after syncing.
+#[derive(Debug, PartialEq)]
+pub struct DivergenceReport {
+    pub entries: Vec<DivergenceEntry>,
+}
+impl Default for DivergenceReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DivergenceReport {
+    pub fn new() -> Self {
+        Self { entries: vec![] }
+    }
+
+    pub fn entry(&self, data_store_name: &str) -> Option<&DivergenceEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.data_store_name == data_store_name)
+    }
+
+    /// Both stores know about exactly the same changes.
+    pub fn is_converged(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|entry| !entry.is_local_ahead() && !entry.is_remote_ahead())
+    }
+}
+
+/// The root's sync/mod vector components for a single data store (identified by its name).
+#[derive(Debug, PartialEq)]
+pub struct DivergenceEntry {
+    pub data_store_name: String,
+
+    pub local_sync_time: i64,
+    pub remote_sync_time: i64,
+    pub local_mod_time: i64,
+    pub remote_mod_time: i64,
+}
+impl DivergenceEntry {
+    /// The local store knows about changes of the data store that the remote does not.
+    /// Sync times alone can not tell, as a store's own sync time advances with every scan.
+    pub fn is_local_ahead(&self) -> bool {
+        self.local_mod_time > self.remote_sync_time
+    }
+    /// The remote store knows about changes of the data store that the local one does not.
+    pub fn is_remote_ahead(&self) -> bool {
+        self.remote_mod_time > self.local_sync_time
+    }
+}
@@ -0,0 +1,27 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// Decides how scans treat items of a folder whose names only differ in case (or unicode
+/// normalization), e.g. files synced from a case-sensitive file system onto a store that does
+/// not tell these names apart. Such items can not be stored side by side in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DuplicatePolicy {
+    /// Leaves all of the items out of the index (they are not synced) and reports them.
+    #[default]
+    ReportAndSkip,
+    /// Indexes the item whose name comes first lexicographically, leaves out the others and
+    /// reports them. Only the indexed item is synced.
+    SyncFirst,
+    /// Fails the scan with DuplicateNames.
+    Fail,
+}
+
+/// Items of a folder whose names only differ in case, found during a scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplicateNames {
+    /// All items sharing the name, the first one has the smallest name.
+    pub paths: Vec<RelativePath>,
+    /// The policy the scan applied to the items.
+    pub policy: DuplicatePolicy,
+    /// The item that was indexed anyway (see DuplicatePolicy::SyncFirst).
+    pub indexed_path: Option<RelativePath>,
+}
@@ -1,4 +1,5 @@
 use crate::fs_interaction;
+use crate::fs_interaction::relative_path::RelativePath;
 use crate::metadata_db;
 
 #[derive(Debug)]
@@ -16,8 +17,57 @@ pub enum DataStoreError {
     SyncError {
         message: &'static str,
     },
+    DataSetMismatch {
+        local_data_set: String,
+        remote_data_set: String,
+    },
+    SameDataStore,
+    SubtreeBusy {
+        path: RelativePath,
+    },
+    DiskOutOfSync {
+        path: RelativePath,
+    },
     MustNotScanTransferStores,
     OnlyCleanStoresCanBecomeTransfer,
+    OnlyTransferStoresCarryData,
+    OnlyCleanTransferStoresCanBeEncrypted,
+    BitRotCheckRequiresHashes,
+    NothingToUndelete,
+    NotDeleted {
+        path: RelativePath,
+    },
+    InvalidDataStoreId {
+        data_store_id: String,
+        message: &'static str,
+    },
+    InsufficientSpace {
+        required: u64,
+        available: u64,
+    },
+    ContentUnavailable {
+        path: RelativePath,
+    },
+    TooManyConflicts {
+        count: usize,
+    },
+    InvalidChangeBundle {
+        message: &'static str,
+    },
+    MissingConflictResolution {
+        path: RelativePath,
+    },
+    InvalidItemName {
+        name: String,
+    },
+    DuplicateNames {
+        paths: Vec<RelativePath>,
+    },
+    TreeHashMismatch {
+        path: RelativePath,
+        local_hash: String,
+        remote_hash: String,
+    },
 }
 pub type Result<T> = std::result::Result<T, DataStoreError>;
 
@@ -1,12 +1,18 @@
 use super::Result;
+use filetime::FileTime;
 use fs_interaction::relative_path::RelativePath;
 use metadata_db::{DBInclusionRule, DataStore, MetadataDB};
 use std::slice::Iter;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct InclusionRules {
     rules: Vec<DBInclusionRule>,
     data_store: DataStore,
+    // Only affects the timing of local scans, persisted with the local data store.
+    min_age: Option<Duration>,
+    // Name of the per-directory ignore files of the local data store, persisted with it.
+    ignore_file_name: Option<String>,
 }
 
 impl InclusionRules {
@@ -14,6 +20,10 @@ impl InclusionRules {
         Self {
             rules: vec![],
             data_store: data_store.clone(),
+            min_age: data_store
+                .min_file_age
+                .map(|min_age| Duration::from_secs(min_age as u64)),
+            ignore_file_name: data_store.ignore_file_name.clone(),
         }
     }
 
@@ -35,17 +45,46 @@ impl InclusionRules {
         Ok(())
     }
 
+    /// Rules are evaluated in order and the LAST matching rule decides (like in .gitignore),
+    /// paths matching no rule at all are not included. A later inclusion rule can therefore
+    /// re-include parts of an earlier, broad ignore rule, e.g. '/build/keep.txt' after '/build/**'.
     pub fn is_included(&self, path: &RelativePath) -> bool {
         let path_string = path.get_path_components().join("/");
-        let mut matches_inclusion_rule = false;
-        for rule in &self.rules {
-            if rule.include {
-                matches_inclusion_rule |= rule.rule.matches(&path_string);
-            } else if rule.rule.matches(&path_string) {
-                return false;
-            }
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.rule.matches(&path_string))
+            .is_some_and(|rule| rule.include)
+    }
+
+    /// Files modified less than min_age ago are not yet indexed during scans, as they might
+    /// still be written to. This only DEFERS indexing to a later scan once the file settled,
+    /// it never excludes files permanently (in contrast to ignore rules).
+    pub fn set_min_age(&mut self, min_age: Option<Duration>) {
+        self.min_age = min_age;
+    }
+
+    pub fn min_age(&self) -> Option<Duration> {
+        self.min_age
+    }
+
+    pub fn is_settled(&self, mod_time: &FileTime) -> bool {
+        if let Some(min_age) = self.min_age {
+            let age = FileTime::now().unix_seconds() - mod_time.unix_seconds();
+            age >= min_age.as_secs() as i64
+        } else {
+            true
         }
-        matches_inclusion_rule
+    }
+
+    /// Files of the given name are read as per-directory ignore rules by scans and syncs
+    /// (see DirIgnoreRules), None disables them.
+    pub fn set_ignore_file_name(&mut self, ignore_file_name: Option<String>) {
+        self.ignore_file_name = ignore_file_name;
+    }
+
+    pub fn ignore_file_name(&self) -> Option<&str> {
+        self.ignore_file_name.as_deref()
     }
 
     pub fn add_ignore_rule(&mut self, rule: glob::Pattern) {
@@ -56,18 +95,12 @@ impl InclusionRules {
         self.add_rule(rule, true)
     }
 
+    // Rules are appended, i.e. they take precedence over all existing ones.
+    // Adding an existing pattern again moves it to the end.
     fn add_rule(&mut self, rule: glob::Pattern, include: bool) {
-        let mut already_exists = false;
         self.rules
-            .iter_mut()
-            .filter(|existing_rule| existing_rule.rule == rule)
-            .for_each(|existing_rule| {
-                existing_rule.include = include;
-                already_exists = true
-            });
-        if !already_exists {
-            self.rules.push(DBInclusionRule { include, rule });
-        }
+            .retain(|existing_rule| existing_rule.rule != rule);
+        self.rules.push(DBInclusionRule { include, rule });
     }
 
     pub fn remove_rule(&mut self, pattern: &str) {
@@ -78,6 +111,59 @@ impl InclusionRules {
             .cloned()
             .collect();
     }
+
+    /// Removes rules that provably never change the outcome of is_included and returns them.
+    /// These are exact duplicates and rules subsumed by another rule of the same polarity that
+    /// either comes later (it always wins over the removed rule) or comes earlier with no
+    /// rule of the opposite polarity in between (it decides the same way without the rule).
+    /// Rules are only considered subsumed in simple, syntactically provable cases.
+    pub fn compact(&mut self) -> Vec<DBInclusionRule> {
+        let mut removed_rules = Vec::new();
+        let mut i = 0;
+        while i < self.rules.len() {
+            if self.is_redundant(i) {
+                removed_rules.push(self.rules.remove(i));
+            } else {
+                i += 1;
+            }
+        }
+
+        removed_rules
+    }
+
+    fn is_redundant(&self, index: usize) -> bool {
+        let rule = &self.rules[index];
+        let shadowed_by_later_rule = self.rules[index + 1..].iter().any(|later_rule| {
+            later_rule.include == rule.include && Self::subsumes(&later_rule.rule, &rule.rule)
+        });
+        let covered_by_earlier_rule = self.rules[..index]
+            .iter()
+            .rev()
+            .take_while(|earlier_rule| earlier_rule.include == rule.include)
+            .any(|earlier_rule| Self::subsumes(&earlier_rule.rule, &rule.rule));
+
+        shadowed_by_later_rule || covered_by_earlier_rule
+    }
+
+    // Checks if every path matched by narrow is also matched by broad (false if unsure).
+    fn subsumes(broad: &glob::Pattern, narrow: &glob::Pattern) -> bool {
+        let (broad, narrow) = (broad.as_str(), narrow.as_str());
+        if broad == narrow || broad == "**" {
+            return true;
+        }
+        // A pattern without special characters matches exactly one path.
+        if glob::Pattern::escape(narrow) == narrow {
+            return glob::Pattern::new(broad).is_ok_and(|broad| broad.matches(narrow));
+        }
+        // '/dir/**' matches everything below a literal '/dir/'.
+        if broad.ends_with("/**") {
+            let broad_prefix = &broad[..broad.len() - 2];
+            return glob::Pattern::escape(broad_prefix) == broad_prefix
+                && narrow.starts_with(broad_prefix);
+        }
+
+        false
+    }
 }
 
 #[cfg(test)]
@@ -123,12 +209,125 @@ mod tests {
         assert!(!rules.is_included(&RelativePath::from_path("dir/test-1.txt")));
         assert!(rules.is_included(&RelativePath::from_path("dir/test-2.txt")));
 
-        // Now include everything expect the ignored test-1
+        // Now include everything expect the ignored test-1 (later rules take precedence)
         rules.add_inclusion_rule(Pattern::new("**").unwrap());
+        assert!(rules.is_included(&RelativePath::from_path("dir/test-1.txt")));
+        rules.add_ignore_rule(Pattern::new("**/test-1.txt").unwrap());
         assert!(!rules.is_included(&RelativePath::from_path("test-1.txt")));
         assert!(rules.is_included(&RelativePath::from_path("test-2.txt")));
         assert!(rules.is_included(&RelativePath::from_path("dir")));
         assert!(!rules.is_included(&RelativePath::from_path("dir/test-1.txt")));
         assert!(rules.is_included(&RelativePath::from_path("dir/test-2.txt")));
     }
+
+    #[test]
+    fn re_include_file_of_ignored_dir() {
+        let db = crate::metadata_db::tests::open_metadata_store();
+        let (_data_set, data_store) = crate::metadata_db::tests::insert_sample_data_set(&db);
+
+        let mut rules = InclusionRules::new(&data_store);
+        rules.add_inclusion_rule(Pattern::new("**").unwrap());
+        rules.add_ignore_rule(Pattern::new("/build/**").unwrap());
+        rules.add_inclusion_rule(Pattern::new("/build/keep.txt").unwrap());
+
+        let check_rules = |rules: &InclusionRules| {
+            assert!(rules.is_included(&RelativePath::from_path("src/main.rs")));
+            assert!(rules.is_included(&RelativePath::from_path("build")));
+            assert!(rules.is_included(&RelativePath::from_path("build/keep.txt")));
+            assert!(!rules.is_included(&RelativePath::from_path("build/out.o")));
+            assert!(!rules.is_included(&RelativePath::from_path("build/sub/keep.txt")));
+        };
+        check_rules(&rules);
+
+        // The order survives storing the rules.
+        rules.store_to_db(&db).unwrap();
+        let mut rules = InclusionRules::new(&data_store);
+        rules.load_from_db(&db).unwrap();
+        check_rules(&rules);
+
+        // Re-adding the broad ignore rule moves it to the end, i.e. it wins again.
+        rules.add_ignore_rule(Pattern::new("/build/**").unwrap());
+        assert!(!rules.is_included(&RelativePath::from_path("build/keep.txt")));
+    }
+
+    #[test]
+    fn compact_rules() {
+        let db = crate::metadata_db::tests::open_metadata_store();
+        let (_data_set, data_store) = crate::metadata_db::tests::insert_sample_data_set(&db);
+
+        // Duplicates can not be added through add_rule, but might be stored in old DBs.
+        let mut rules = InclusionRules::new(&data_store);
+        for (pattern, include) in &[
+            ("**", true),
+            ("/sub/foo", false),
+            ("**/foo", false),
+            ("/keep/**", true),
+            ("/keep/file", true),
+            ("/build/**", false),
+            ("/build/**", false),
+            ("/build/keep.txt", true),
+            ("/build/**/tmp", false),
+        ] {
+            rules.rules.push(DBInclusionRule {
+                include: *include,
+                rule: Pattern::new(pattern).unwrap(),
+            });
+        }
+        let paths: Vec<_> = vec![
+            "file",
+            "sub/foo",
+            "other/foo",
+            "keep/file",
+            "keep/foo",
+            "build/out.o",
+            "build/keep.txt",
+            "build/sub/tmp",
+        ]
+        .into_iter()
+        .map(RelativePath::from_path)
+        .collect();
+        let included_before: Vec<_> = paths.iter().map(|path| rules.is_included(path)).collect();
+
+        let removed_rules: Vec<_> = rules
+            .compact()
+            .into_iter()
+            .map(|rule| rule.rule.as_str().to_string())
+            .collect();
+        assert_eq!(removed_rules, vec!["/sub/foo", "/keep/file", "/build/**"]);
+        let remaining_rules: Vec<_> = rules.iter().map(|rule| rule.rule.as_str()).collect();
+        assert_eq!(
+            remaining_rules,
+            vec![
+                "**",
+                "**/foo",
+                "/keep/**",
+                "/build/**",
+                "/build/keep.txt",
+                "/build/**/tmp"
+            ]
+        );
+        let included_after: Vec<_> = paths.iter().map(|path| rules.is_included(path)).collect();
+        assert_eq!(included_before, included_after);
+
+        // Nothing left to remove.
+        assert!(rules.compact().is_empty());
+    }
+
+    #[test]
+    fn min_age_rule() {
+        let db = crate::metadata_db::tests::open_metadata_store();
+        let (_data_set, data_store) = crate::metadata_db::tests::insert_sample_data_set(&db);
+
+        let now = FileTime::now();
+        let an_hour_ago = FileTime::from_unix_time(now.unix_seconds() - 3600, 0);
+
+        // Without a minimum age everything is settled.
+        let mut rules = InclusionRules::new(&data_store);
+        assert!(rules.is_settled(&now));
+        assert!(rules.is_settled(&an_hour_ago));
+
+        rules.set_min_age(Some(Duration::from_secs(60)));
+        assert!(!rules.is_settled(&now));
+        assert!(rules.is_settled(&an_hour_ago));
+    }
 }
@@ -0,0 +1,42 @@
+use fs_interaction::relative_path::RelativePath;
+use metadata_db::DBItem;
+use std::collections::HashSet;
+
+/// Effect of changing the inclusion rules of a data store (see DataStore::plan_inclusion_rules).
+/// Applying the rules only changes the DB. Newly ignored items are no longer synced, but their
+/// content stays on disk until it is removed (see DataStore::delete_ignored_content).
+pub struct InclusionRulesPlan {
+    // DB only changes (applied by DataStore::update_inclusion_rules).
+    pub no_longer_ignored: Vec<DBItem>,
+    pub newly_ignored: Vec<DBItem>,
+
+    // Suggested disk cleanup, i.e. the top-most newly ignored items (removing a folder removes
+    // all of its content) and the total size of their files in bytes.
+    pub disk_cleanup: Vec<RelativePath>,
+    pub disk_cleanup_bytes: u64,
+}
+impl InclusionRulesPlan {
+    pub fn new(no_longer_ignored: Vec<DBItem>, newly_ignored: Vec<DBItem>) -> Self {
+        let newly_ignored_paths: HashSet<_> = newly_ignored
+            .iter()
+            .map(|item| item.path.to_lower_case())
+            .collect();
+        let disk_cleanup = newly_ignored
+            .iter()
+            .filter(|item| !newly_ignored_paths.contains(&item.path.parent().to_lower_case()))
+            .map(|item| item.path.clone())
+            .collect();
+        let disk_cleanup_bytes = newly_ignored
+            .iter()
+            .filter(|item| item.is_file())
+            .map(|item| item.metadata().size)
+            .sum();
+
+        Self {
+            no_longer_ignored,
+            newly_ignored,
+            disk_cleanup,
+            disk_cleanup_bytes,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::version_vector::VersionVector;
+use metadata_db::{DBItem, ItemFSMetadata, ItemType};
+use std::collections::HashMap;
+
+/// Renders a DB item as a single line of JSON for exporting the index.
+///
+/// Data store IDs are only meaningful within the local DB, thus all version vectors are keyed
+/// by the unique names of the data stores (given in store_names) to keep the export portable.
+pub fn item_to_json_line(item: &DBItem, store_names: &HashMap<i64, String>) -> String {
+    let path = item.path.get_path_components()[1..].join("/");
+    let mut fields = serde_json::Map::new();
+    fields.insert("path".to_string(), json!(path));
+    fields.insert(
+        "sync_time".to_string(),
+        version_vector_to_json(&item.sync_time, store_names),
+    );
+
+    match &item.content {
+        ItemType::DELETION => {
+            fields.insert("type".to_string(), json!("deletion"));
+        }
+        ItemType::FILE {
+            metadata,
+            creation_time,
+            last_mod_time,
+        } => {
+            fields.insert("type".to_string(), json!("file"));
+            fields.insert(
+                "creation_time".to_string(),
+                version_vector_to_json(creation_time, store_names),
+            );
+            fields.insert(
+                "last_mod_time".to_string(),
+                version_vector_to_json(last_mod_time, store_names),
+            );
+            fields.insert("metadata".to_string(), metadata_to_json(metadata));
+        }
+        ItemType::FOLDER {
+            metadata,
+            creation_time,
+            last_mod_time,
+            mod_time,
+        } => {
+            fields.insert("type".to_string(), json!("folder"));
+            fields.insert(
+                "creation_time".to_string(),
+                version_vector_to_json(creation_time, store_names),
+            );
+            fields.insert(
+                "last_mod_time".to_string(),
+                version_vector_to_json(last_mod_time, store_names),
+            );
+            fields.insert(
+                "mod_time".to_string(),
+                version_vector_to_json(mod_time, store_names),
+            );
+            fields.insert("metadata".to_string(), metadata_to_json(metadata));
+        }
+        ItemType::IGNORED {
+            creation_time,
+            last_mod_time,
+            mod_time,
+        } => {
+            fields.insert("type".to_string(), json!("ignored"));
+            fields.insert(
+                "creation_time".to_string(),
+                version_vector_to_json(creation_time, store_names),
+            );
+            fields.insert(
+                "last_mod_time".to_string(),
+                version_vector_to_json(last_mod_time, store_names),
+            );
+            fields.insert(
+                "mod_time".to_string(),
+                version_vector_to_json(mod_time, store_names),
+            );
+        }
+    }
+
+    serde_json::Value::Object(fields).to_string()
+}
+
+/// Renders a file as a line of a checksum manifest in the format of sha256sum, i.e.
+/// '<hex hash>  <relative/path>'. Returns None for items without a content hash.
+///
+/// Like sha256sum, paths containing backslashes or newlines are escaped and the line is
+/// prefixed with a backslash.
+pub fn item_to_manifest_line(item: &DBItem) -> Option<String> {
+    if !item.is_file() || item.metadata().hash.is_empty() {
+        return None;
+    }
+
+    let path = item.path.get_path_components()[1..].join("/");
+    let hash = item.metadata().hash.to_lowercase();
+    if path.contains('\\') || path.contains('\n') {
+        let escaped_path = path.replace('\\', "\\\\").replace('\n', "\\n");
+        Some(format!("\\{}  {}", hash, escaped_path))
+    } else {
+        Some(format!("{}  {}", hash, path))
+    }
+}
+
+fn metadata_to_json(metadata: &ItemFSMetadata) -> serde_json::Value {
+    json!({
+        "name": metadata.case_sensitive_name,
+        "creation_time": metadata.creation_time.to_string(),
+        "mod_time": metadata.mod_time.to_string(),
+        "hash": metadata.hash,
+        "size": metadata.size,
+        "read_only": metadata.is_read_only,
+        "hard_link_group": metadata.hard_link_group,
+    })
+}
+
+fn version_vector_to_json(
+    vector: &VersionVector<i64>,
+    store_names: &HashMap<i64, String>,
+) -> serde_json::Value {
+    let entries = vector
+        .to_pairs()
+        .into_iter()
+        .map(|(id, time)| {
+            // Entries of stores missing in the DB should not happen, keep them by their ID.
+            let name = store_names
+                .get(&id)
+                .cloned()
+                .unwrap_or_else(|| format!("#{}", id));
+            (name, json!(time))
+        })
+        .collect();
+
+    serde_json::Value::Object(entries)
+}
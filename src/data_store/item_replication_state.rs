@@ -0,0 +1,26 @@
+use crate::fs_interaction::relative_path::RelativePath;
+use crate::version_vector::VersionVector;
+
+/// The known version state of a single item across all stores of the data set
+/// (see DataStore::item_replication_state). Version vectors are keyed by the stores' unique names.
+#[derive(Debug)]
+pub struct ItemReplicationState {
+    pub path: RelativePath,
+    /// The modifications contained in the item's current version.
+    pub mod_time: VersionVector<String>,
+    /// The changes of the item's path the local store knows of.
+    pub sync_time: VersionVector<String>,
+    pub stores: Vec<StoreReplicationState>,
+}
+
+/// Whether a known store holds the current version of an item, as far as we learned from the
+/// stores' sync times. Stores may have synced since, i.e. this can be too pessimistic.
+#[derive(Debug, PartialEq)]
+pub struct StoreReplicationState {
+    pub data_store_name: String,
+    pub data_store_human_name: String,
+    pub is_this_store: bool,
+    /// Stores excluding the item by their inclusion rules never hold it.
+    pub is_included: bool,
+    pub has_current_version: bool,
+}
@@ -0,0 +1,14 @@
+use crate::fs_interaction::relative_path::RelativePath;
+
+/// Attributes the last modification of an item to the data store that performed it.
+/// For folders this is the latest modification among all items in their sub-tree.
+#[derive(Debug, PartialEq)]
+pub struct LastModification {
+    /// The item that was modified (for folders one of their children).
+    pub path: RelativePath,
+
+    pub data_store_name: String,
+    pub data_store_human_name: String,
+    /// Logical time of the modifying data store at the modification.
+    pub data_store_time: i64,
+}
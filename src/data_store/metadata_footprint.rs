@@ -0,0 +1,15 @@
+/// Disk space used by the metadata directory of a data store in bytes, e.g. to decide when to
+/// optimize the DB (see DataStore::optimize_database) or to clean out pending files.
+#[derive(Debug, PartialEq)]
+pub struct MetadataFootprint {
+    /// The SQLite DB including its WAL and shared memory files.
+    pub db_bytes: u64,
+    /// Downloaded files not yet moved into place (see DataStore::clean_pending).
+    pub pending_bytes: u64,
+    pub snapshot_bytes: u64,
+}
+impl MetadataFootprint {
+    pub fn total_bytes(&self) -> u64 {
+        self.db_bytes + self.pending_bytes + self.snapshot_bytes
+    }
+}
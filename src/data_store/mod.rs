@@ -1,9 +1,13 @@
 use chrono::NaiveDateTime;
 use filetime::FileTime;
-use std::collections::HashSet;
+use std::cell::RefCell;
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::path::Path;
 
-use crate::fs_interaction::relative_path::RelativePath;
+use crate::fs_interaction;
+use crate::fs_interaction::relative_path::{self, RelativePath};
 use crate::fs_interaction::virtual_fs;
 use crate::fs_interaction::FSInteraction;
 use crate::metadata_db;
@@ -12,27 +16,111 @@ use crate::version_vector::VersionVector;
 
 mod inclusion_rules;
 use self::inclusion_rules::*;
+mod dir_ignore_rules;
+use self::dir_ignore_rules::DirIgnoreRules;
+mod change_bundle;
+mod index_export;
 mod synchronization_messages;
 use self::synchronization_messages::*;
+mod create_options;
+pub use self::create_options::DataStoreCreateOptions;
+mod divergence_report;
+pub use self::divergence_report::{DivergenceEntry, DivergenceReport};
+mod inclusion_rules_plan;
+pub use self::inclusion_rules_plan::InclusionRulesPlan;
+mod rule_diff;
+pub use self::rule_diff::RuleDiff;
+mod sync_options;
+pub use self::sync_options::SyncOptions;
+mod transfer_plan;
+pub use self::transfer_plan::TransferPlan;
+mod paused_paths;
+use self::paused_paths::PausedPaths;
+mod subtree_locks;
+pub use self::subtree_locks::{SubtreeGuard, SubtreeLocks};
+mod scan_reader;
+use self::scan_reader::ScanChange;
+pub use self::scan_reader::{PendingScan, ScanReader};
+mod retry_policy;
+pub use self::retry_policy::RetryPolicy;
+mod optimize_schedule;
+pub use self::optimize_schedule::{Clock, OptimizeSchedule, SystemClock};
+mod last_modification;
+pub use self::last_modification::LastModification;
+mod item_replication_state;
+pub use self::item_replication_state::{ItemReplicationState, StoreReplicationState};
+mod metadata_footprint;
+pub use self::metadata_footprint::MetadataFootprint;
+mod pending_files_result;
+pub use self::pending_files_result::PendingFilesResult;
+mod duplicate_policy;
+pub use self::duplicate_policy::{DuplicateNames, DuplicatePolicy};
+mod retention_policy;
+pub use self::retention_policy::{RetentionPolicy, RetentionResult};
 mod scan_result;
-pub use self::scan_result::ScanResult;
+pub use self::scan_result::{ScanChanges, ScanResult};
+mod sync_result;
+pub use self::sync_result::{StalledSyncWarning, SyncExplanation, SyncResult, TypeChange};
 mod scan_event;
 pub use self::scan_event::*;
 mod sync_conflict_event;
 pub use self::sync_conflict_event::*;
+mod sync_progress_event;
+pub use self::sync_progress_event::{SyncProgressEvent, SyncedItemAction};
 mod errors;
 pub use self::errors::*;
 use data_store::ScanEvent::DeletedItem;
 use data_store::SyncConflictEvent::*;
-use fs_interaction::DataItem;
+use fs_interaction::{DataItem, Issue};
+pub use metadata_db::TreeSummary;
 use metadata_db::{DBItem, ItemFSMetadata};
 
+// Number of files listed in a TreeSummary.
+const LARGEST_FILES_IN_SUMMARY: usize = 10;
+// Number of files re-hashed per DB transaction (the progress is stored after each batch).
+const REHASH_BATCH_SIZE: usize = 1000;
+// Consecutive non-converging syncs from the same store before we warn about them.
+const STALLED_SYNCS_WARNING_THRESHOLD: i64 = 3;
+// Suffix of local items backed up in the pending files dir (see apply_resolutions).
+const BACKUP_FILE_SUFFIX: &str = ".backup";
+
 pub struct DataStore<FS: virtual_fs::FS> {
-    fs_access: FSInteraction<FS>,
+    // Dropped before fs_access, i.e. the DB is closed before its working copy is written back
+    // and the store's lock is released.
     db_access: MetadataDB,
+    fs_access: FSInteraction<FS>,
     local_inclusion_rules: InclusionRules,
+    interrupted_operation: Option<String>,
+    possibly_cloned: bool,
+    retry_policy: RetryPolicy,
+    // Scans do not descend below this depth (number of path components below the root).
+    max_scan_depth: Option<usize>,
+    // Sub-trees left out of scans until they are resumed (see pause_path).
+    paused_paths: PausedPaths,
+    // Files downloaded ahead of time into the pending dir, keyed by their lower case path.
+    prefetched_files: RefCell<HashMap<RelativePath, RelativePath>>,
+    // Sub-trees currently synced or scanned (shared with scan readers on other threads).
+    subtree_locks: SubtreeLocks,
+    // Decides which stores we learn about from sync partners (see set_data_store_approval).
+    data_store_approval: Option<DataStoreApproval>,
+    // Limits for the conflict copies kept by keep-both (see clean_retention).
+    retention_policy: RetentionPolicy,
+    // Treatment of items whose names only differ in case (see set_duplicate_policy).
+    duplicate_policy: DuplicatePolicy,
+    // Scans of scan readers record their DB updates here instead of writing them (see
+    // apply_scan_change), the store they were created from applies them.
+    recorded_scan_changes: Option<RefCell<Vec<ScanChange>>>,
 }
 pub type DefaultDataStore = DataStore<virtual_fs::WrapperFS>;
+/// Decides whether a store learned from a sync partner is approved (see set_data_store_approval).
+pub type DataStoreApproval = Box<dyn Fn(&metadata_db::DataStore) -> bool + Send>;
+// Pending path, path and hash of the remote file and the running copy of a background prefetch.
+type BackgroundPrefetch = (
+    RelativePath,
+    RelativePath,
+    String,
+    virtual_fs::BackgroundCopy,
+);
 
 impl<FS: virtual_fs::FS> DataStore<FS> {
     /// Same as open_with_fs, but uses the default FS abstraction (OS native calls).
@@ -42,20 +130,158 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     /// Opens a data_store at a given path on the local disk.
     /// Makes sure that the required metadata directories and database are present.
     ///
+    /// If a previous multi-step operation was interrupted (e.g. by a crash), the store is
+    /// recovered and the interrupted operation is reported by interrupted_operation().
+    ///
     /// Returns errors if the data_store is already opened or does not exist.
     pub fn open_with_fs<P: AsRef<Path>>(path: P, fs: FS) -> Result<Self> {
+        Self::open_with_locking(path, fs, false)
+    }
+
+    /// Same as open_shared_with_fs, but uses the default FS abstraction.
+    pub fn open_shared<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_shared_with_fs(&path, FS::default())
+    }
+    /// Same as open_with_fs, but does not lock the DB exclusively, allowing scan readers
+    /// on other threads (see scan_reader).
+    pub fn open_shared_with_fs<P: AsRef<Path>>(path: P, fs: FS) -> Result<Self> {
+        Self::open_with_locking(path, fs, true)
+    }
+
+    fn open_with_locking<P: AsRef<Path>>(path: P, fs: FS, allow_readers: bool) -> Result<Self> {
         let fs_interaction = FSInteraction::open_with_fs(&path, fs)?;
-        let metadata_db = MetadataDB::open(fs_interaction.metadata_db_path().to_str().unwrap())?;
+        let db_path = fs_interaction.metadata_db_path();
+        let metadata_db = if allow_readers {
+            MetadataDB::open_shared(db_path.to_str().unwrap())?
+        } else {
+            MetadataDB::open(db_path.to_str().unwrap())?
+        };
 
-        let mut inclusion_rules = InclusionRules::new(&metadata_db.get_local_data_store()?);
+        let interrupted_operation = fs_interaction.interrupted_operation()?;
+        if interrupted_operation.is_some() {
+            // All DB changes of multi-step operations are bundled in a transaction, i.e. they
+            // are rolled back on a crash. Clean out any left over information and carry on.
+            metadata_db.clean_up_db()?;
+            fs_interaction.finish_operation()?;
+        }
+
+        Self::from_opened(fs_interaction, metadata_db, interrupted_operation)
+    }
+
+    /// Same as open_read_only_with_fs, but uses the default FS abstraction.
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_read_only_with_fs(&path, FS::default())
+    }
+    /// Opens a data_store for queries only, e.g. on a read-only mount (a snapshot or CD image).
+    /// Neither a lock nor metadata directories are created and the DB is opened read-only.
+    /// Any operation changing the store fails.
+    ///
+    /// Interrupted operations can not be recovered, they are only reported.
+    pub fn open_read_only_with_fs<P: AsRef<Path>>(path: P, fs: FS) -> Result<Self> {
+        let fs_interaction = FSInteraction::open_read_only_with_fs(&path, fs)?;
+        let metadata_db =
+            MetadataDB::open_read_only(fs_interaction.metadata_db_path().to_str().unwrap())?;
+        let interrupted_operation = fs_interaction.interrupted_operation()?;
+
+        Self::from_opened(fs_interaction, metadata_db, interrupted_operation)
+    }
+
+    /// Same as peek_data_set_name_with_fs, but uses the default FS abstraction.
+    pub fn peek_data_set_name<P: AsRef<Path>>(path: P) -> Result<String> {
+        Self::peek_data_set_name_with_fs(&path, FS::default())
+    }
+    /// Reads the unique name of the data set the store at the given path belongs to, e.g. to
+    /// check that two stores can be synced before opening them. Like open_read_only, this
+    /// neither locks the store nor changes it, i.e. it also works while the store is in use.
+    pub fn peek_data_set_name_with_fs<P: AsRef<Path>>(path: P, fs: FS) -> Result<String> {
+        let fs_interaction = FSInteraction::open_read_only_with_fs(&path, fs)?;
+        let metadata_db =
+            MetadataDB::open_read_only(fs_interaction.metadata_db_path().to_str().unwrap())?;
+
+        Ok(metadata_db.get_data_set()?.unique_name)
+    }
+
+    fn from_opened(
+        mut fs_interaction: FSInteraction<FS>,
+        metadata_db: MetadataDB,
+        interrupted_operation: Option<String>,
+    ) -> Result<Self> {
+        let hash_algorithm = fs_interaction::HashAlgorithm::from_name(
+            &metadata_db.get_hash_algorithm()?,
+        )
+        .ok_or(DataStoreError::UnexpectedState {
+            source: "Unknown hash algorithm stored in DB!",
+        })?;
+        fs_interaction.set_hash_algorithm(hash_algorithm);
+        fs_interaction.set_normalize_unicode(metadata_db.is_normalize_unicode()?);
+
+        // A store found at a different location than it was created at was either moved or
+        // cloned (e.g. restored from a backup). Clones MUST NOT share the same identity.
+        let local_data_store = metadata_db.get_local_data_store()?;
+        let possibly_cloned =
+            local_data_store.path_on_device != fs_interaction.root_path().to_str().unwrap();
+
+        let mut inclusion_rules = InclusionRules::new(&local_data_store);
         inclusion_rules.load_from_db(&metadata_db)?;
+        let paused_paths = fs_interaction
+            .read_paused_paths()?
+            .map_or_else(PausedPaths::new, |content| PausedPaths::parse(&content));
         Ok(Self {
             fs_access: fs_interaction,
             local_inclusion_rules: inclusion_rules,
             db_access: metadata_db,
+            interrupted_operation,
+            possibly_cloned,
+            retry_policy: RetryPolicy::none(),
+            max_scan_depth: None,
+            paused_paths,
+            prefetched_files: RefCell::new(HashMap::new()),
+            subtree_locks: SubtreeLocks::new(),
+            data_store_approval: None,
+            retention_policy: RetentionPolicy::unlimited(),
+            duplicate_policy: DuplicatePolicy::default(),
+            recorded_scan_changes: None,
+        })
+    }
+
+    // Creates a second handle to this store for scans on a different thread (see ScanReader).
+    // It reads through its own DB connection and records its changes instead of writing them.
+    fn scan_worker(&self) -> Result<Self> {
+        Ok(Self {
+            db_access: self.db_access.open_reader()?,
+            fs_access: self.fs_access.unlocked_view(),
+            local_inclusion_rules: self.local_inclusion_rules.clone(),
+            interrupted_operation: None,
+            possibly_cloned: false,
+            retry_policy: RetryPolicy::none(),
+            max_scan_depth: self.max_scan_depth,
+            paused_paths: self.paused_paths.clone(),
+            prefetched_files: RefCell::new(HashMap::new()),
+            subtree_locks: self.subtree_locks.clone(),
+            data_store_approval: None,
+            retention_policy: RetentionPolicy::unlimited(),
+            duplicate_policy: self.duplicate_policy,
+            recorded_scan_changes: Some(RefCell::new(vec![])),
         })
     }
 
+    /// Same as unlock_with_fs, but uses the default FS abstraction (OS native calls).
+    pub fn unlock<P: AsRef<Path>>(path: P, force: bool) -> Result<Option<u32>> {
+        Self::unlock_with_fs(path, FS::default(), force)
+    }
+    /// Removes a stale lock of the data_store at the given path, e.g. left behind by a crash.
+    /// Returns the PID of the process that held the lock (if known).
+    /// Locks that might still be held by a live process are only removed when forced.
+    pub fn unlock_with_fs<P: AsRef<Path>>(path: P, fs: FS, force: bool) -> Result<Option<u32>> {
+        Ok(FSInteraction::<FS>::unlock_with_fs(&path, fs, force)?)
+    }
+
+    /// Reads the (optional) config file of the data_store at the given path, e.g. holding
+    /// defaults for CLI flags. Returns None if the store has no config file.
+    pub fn read_config_with_fs<P: AsRef<Path>>(path: P, fs: FS) -> Result<Option<String>> {
+        Ok(FSInteraction::<FS>::read_config_with_fs(&path, fs)?)
+    }
+
     /// Same as create_with_fs, but uses the default FS abstraction (OS native FS calls).
     pub fn create<P: AsRef<Path>>(
         path: P,
@@ -71,11 +297,8 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             FS::default(),
         )
     }
-    /// Creates a new data_store at the given path on disk.
-    /// Requires to be connected to a data_set by a unique identifier.
-    /// Can be initialized with different FS abstractions (e.g. for testing).
-    ///
-    /// Returns errors if e.g. the data_store already exists.
+    /// Same as create_with_options_and_fs, taking only the names of the store.
+    /// All other options are left at their defaults.
     pub fn create_with_fs<P: AsRef<Path>>(
         path: P,
         data_set_unique_name: &str,
@@ -83,30 +306,68 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         data_store_name: &str,
         fs: FS,
     ) -> Result<Self> {
-        let fs_interaction = FSInteraction::create_with_fs(path.as_ref(), fs)?;
+        let options = DataStoreCreateOptions::new(data_set_unique_name)
+            .data_set_human_name(data_set_human_name)
+            .data_store_name(data_store_name);
+        Self::create_with_options_and_fs(&path, &options, fs)
+    }
+    /// Same as create_with_options_and_fs, but uses the default FS abstraction.
+    pub fn create_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &DataStoreCreateOptions,
+    ) -> Result<Self> {
+        Self::create_with_options_and_fs(&path, options, FS::default())
+    }
+    /// Creates a new data_store at the given path on disk.
+    /// Requires to be connected to a data_set by a unique identifier (see DataStoreCreateOptions).
+    /// Can be initialized with different FS abstractions (e.g. for testing).
+    ///
+    /// Returns errors if e.g. the data_store already exists.
+    pub fn create_with_options_and_fs<P: AsRef<Path>>(
+        path: P,
+        options: &DataStoreCreateOptions,
+        fs: FS,
+    ) -> Result<Self> {
+        let mut fs_interaction = FSInteraction::create_with_fs(path.as_ref(), fs)?;
+        if let Some(external_db_path) = &options.external_db_path {
+            fs_interaction.set_external_db_path(external_db_path)?;
+        }
         let metadata_db = MetadataDB::open(fs_interaction.metadata_db_path().to_str().unwrap())?;
 
         // Make sure we got an initial data_set created (might be a copy from a different store or
         // a newly created one, details are up to the application/ui flow).
-        let data_set = metadata_db.create_data_set(&data_set_unique_name)?;
-        metadata_db.update_data_set_name(&data_set_human_name)?;
+        let data_set = metadata_db.create_data_set(&options.data_set_unique_name)?;
+        metadata_db.update_data_set_name(&options.data_set_human_name)?;
 
         // Create an entry for our local data_store. Others might be added when interacting with
         // different disks to gain knowledge of them.
-        let unique_id = uuid::Uuid::new_v4();
+        let unique_name = if let Some(data_store_id) = &options.data_store_id {
+            Self::validate_data_store_id(data_store_id)?;
+            data_store_id.clone()
+        } else {
+            format!("{:}-{:}", options.data_store_name, uuid::Uuid::new_v4())
+        };
         metadata_db.create_data_store(&metadata_db::data_store::InsertFull {
             data_set_id: data_set.id,
-            unique_name: &format!("{:}-{:}", data_store_name, unique_id),
-            human_name: data_store_name,
+            unique_name: &unique_name,
+            human_name: &options.data_store_name,
             creation_date: &chrono::Utc::now().naive_local(),
 
             is_this_store: true,
-            is_transfer_store: false,
+            is_transfer_store: options.is_transfer_store,
 
             path_on_device: fs_interaction.root_path().to_str().unwrap(),
-            location_note: "",
+            location_note: &options.location_note,
             time: 0,
         })?;
+        if options.trust_mtime {
+            metadata_db.set_trust_mtime(true)?;
+        }
+        if !options.propagate_case_changes {
+            metadata_db.set_propagate_case_changes(false)?;
+        }
+        metadata_db.set_normalize_unicode(options.normalize_unicode)?;
+        fs_interaction.set_normalize_unicode(options.normalize_unicode);
 
         let mut inclusion_rules = InclusionRules::new(&metadata_db.get_local_data_store()?);
         inclusion_rules.load_from_db(&metadata_db)?;
@@ -114,19 +375,105 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             fs_access: fs_interaction,
             local_inclusion_rules: inclusion_rules,
             db_access: metadata_db,
+            interrupted_operation: None,
+            possibly_cloned: false,
+            retry_policy: RetryPolicy::none(),
+            max_scan_depth: None,
+            paused_paths: PausedPaths::new(),
+            prefetched_files: RefCell::new(HashMap::new()),
+            subtree_locks: SubtreeLocks::new(),
+            data_store_approval: None,
+            retention_policy: RetentionPolicy::unlimited(),
+            duplicate_policy: DuplicatePolicy::default(),
+            recorded_scan_changes: None,
         })
     }
 
+    /// Explicitly chosen store ids must be usable as names. A new store does not know any other
+    /// stores yet, collisions with them are detected when syncing (see sync_data_store_list).
+    fn validate_data_store_id(data_store_id: &str) -> Result<()> {
+        if data_store_id.trim().is_empty() {
+            return Err(DataStoreError::InvalidDataStoreId {
+                data_store_id: data_store_id.to_string(),
+                message: "Data store ids must not be empty!",
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The store was opened at a different location than it was created at, i.e. it was moved
+    /// or cloned. Clones must be re-identified (see reidentify), moved stores can simply
+    /// confirm their new location (see confirm_location).
+    pub fn is_possibly_cloned(&self) -> bool {
+        self.possibly_cloned
+    }
+
+    /// Records the current location of the store as its regular location, e.g. after moving it.
+    pub fn confirm_location(&mut self) -> Result<()> {
+        self.db_access
+            .update_path_on_device(self.fs_access.root_path().to_str().unwrap())?;
+        self.possibly_cloned = false;
+
+        Ok(())
+    }
+
+    /// Assigns a fresh identity (unique name) to the local store, e.g. after cloning its
+    /// directory. Partners treat the store as a brand-new store afterwards, all existing
+    /// versions stay attributed to the old identity. Returns the new unique name.
+    pub fn reidentify(&mut self) -> Result<String> {
+        let human_name = self.db_access.get_local_data_store()?.human_name;
+        let new_unique_name = format!("{:}-{:}", human_name, uuid::Uuid::new_v4());
+
+        let local_data_store = self.db_access.reidentify_local_data_store(
+            &new_unique_name,
+            self.fs_access.root_path().to_str().unwrap(),
+        )?;
+        self.local_inclusion_rules
+            .change_data_store(local_data_store);
+        self.possibly_cloned = false;
+
+        Ok(new_unique_name)
+    }
+
+    /// The name of a multi-step operation that was interrupted before the store was opened.
+    /// The store was left in an unclean state and recovered when opening it.
+    pub fn interrupted_operation(&self) -> Option<&str> {
+        self.interrupted_operation.as_deref()
+    }
+
     /// The local, logical time maintained in this data_store.
     pub fn local_time(&self) -> Result<i64> {
         Ok(self.db_access.get_local_data_store()?.time)
     }
 
+    /// The aggregated modification time of the root folder, i.e. the maximum of all
+    /// modification times in this data_store. If it is dominated by a partner's sync time,
+    /// nothing changed since the partner's last sync and no full sync is necessary.
+    pub fn root_mod_time(&self) -> Result<VersionVector<i64>> {
+        let root_item = self
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(""), true)?;
+        Ok(root_item.mod_time().clone())
+    }
+
     /// The unique name of the data set. Must equal the unique name of any sync partner.
     pub fn data_set_name(&self) -> Result<String> {
         Ok(self.db_access.get_data_set()?.unique_name)
     }
 
+    /// The human readable name of the data set. Unlike the unique name (fixed when creating the
+    /// store), it is a mere label that can be changed at any time.
+    pub fn data_set_human_name(&self) -> Result<String> {
+        Ok(self.db_access.get_data_set()?.human_name)
+    }
+
+    /// Changes the human readable name of the data set and bumps the local time.
+    /// Note that each store keeps its own label, syncs only compare the unique names.
+    pub fn set_data_set_human_name(&self, human_name: &str) -> Result<()> {
+        Ok(self.db_access.rename_data_set(human_name)?)
+    }
+
     /// The unique name of this local data store. Must be unique throughout all sync partners.
     pub fn local_data_store_name(&self) -> Result<String> {
         Ok(self.db_access.get_local_data_store()?.unique_name)
@@ -137,10 +484,439 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         Ok(self.db_access.get_local_data_store()?.human_name)
     }
 
+    /// Lists all data stores known to the local store (including itself).
+    pub fn get_data_stores(&self) -> Result<Vec<metadata_db::DataStore>> {
+        Ok(self.db_access.get_data_stores()?)
+    }
+
+    /// Exports every item of the local index (excluding deletion notices) as JSON lines,
+    /// calling func with one line per item. Version vectors are keyed by data store names.
+    /// Returns the number of exported items.
+    pub fn export_index<F: FnMut(&str)>(&self, mut func: F) -> Result<usize> {
+        let store_names = self
+            .db_access
+            .get_data_stores()?
+            .into_iter()
+            .map(|data_store| (data_store.id, data_store.unique_name))
+            .collect();
+
+        let mut exported_items = 0;
+        self.db_access.for_each_item(|item| {
+            func(&index_export::item_to_json_line(item, &store_names));
+            exported_items += 1;
+        })?;
+
+        Ok(exported_items)
+    }
+
+    /// Writes a checksum manifest of all indexed files in the format of sha256sum, i.e. the
+    /// synced tree can be verified with 'sha256sum -c' from within the store's directory
+    /// ('sha512sum -c' for stores using SHA512, see hash_algorithm).
+    /// Uses the hashes stored in the index, files that were not hashed yet (stores trusting
+    /// modification times) are hashed first. Fails with ContentUnavailable if a file can not be
+    /// hashed (stores holding only part of their content). Returns the number of files written.
+    pub fn export_manifest<W: std::io::Write>(&self, writer: &mut W) -> Result<usize> {
+        use fs_interaction::FSInteractionError;
+        self.hash_placeholder_files()?;
+
+        let mut written_files = 0;
+        let mut unhashed_file = None;
+        let mut write_result = Ok(());
+        self.db_access.for_each_item(|item| {
+            if !item.is_file() || unhashed_file.is_some() || write_result.is_err() {
+                return;
+            }
+            match index_export::item_to_manifest_line(item) {
+                Some(line) => {
+                    write_result = writeln!(writer, "{}", line);
+                    written_files += 1;
+                }
+                None => unhashed_file = Some(item.path.clone()),
+            }
+        })?;
+        if let Some(path) = unhashed_file {
+            return Err(DataStoreError::ContentUnavailable { path });
+        }
+        write_result.map_err(FSInteractionError::from)?;
+
+        Ok(written_files)
+    }
+
+    /// Exports the changes since the given sync point (a version vector of this store) as a
+    /// self-contained change bundle, e.g. to carry them to another store of the data set without
+    /// a live connection (see apply_changes). The bundle holds a copy of the index and the
+    /// content of all files modified since the sync point. Returns the number of bundled files.
+    pub fn export_changes<W: std::io::Write>(
+        &self,
+        since: &VersionVector<i64>,
+        writer: &mut W,
+    ) -> Result<usize> {
+        use fs_interaction::FSInteractionError;
+        self.fs_access.check_content_access()?;
+
+        let root_item = self
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(""), true)?;
+        let mut changed_files = Vec::new();
+        self.collect_changed_files(&root_item, since, &mut changed_files)?;
+
+        // Copy the index only after collecting the files, as this might hash some of them.
+        let tmp_dir = tempfile::tempdir().map_err(FSInteractionError::from)?;
+        let index_copy = change_bundle::index_path(tmp_dir.path())?;
+        self.db_access.dump_db(&index_copy)?;
+        let mut index_file = std::fs::File::open(&index_copy).map_err(FSInteractionError::from)?;
+        let index_length = index_file
+            .metadata()
+            .map_err(FSInteractionError::from)?
+            .len();
+
+        change_bundle::write_header(writer)?;
+        change_bundle::write_entry(
+            writer,
+            &change_bundle::BundleEntry::Index {
+                length: index_length,
+            },
+            &mut index_file,
+        )?;
+        for changed_file in &changed_files {
+            change_bundle::write_entry(
+                writer,
+                &change_bundle::BundleEntry::File {
+                    path: changed_file.path.clone(),
+                    length: changed_file.metadata().size,
+                },
+                &mut self.fs_access.read_file(&changed_file.path)?,
+            )?;
+        }
+        change_bundle::write_end(writer)?;
+
+        Ok(changed_files.len())
+    }
+
+    fn collect_changed_files(
+        &self,
+        item: &DBItem,
+        since: &VersionVector<i64>,
+        changed_files: &mut Vec<DBItem>,
+    ) -> Result<()> {
+        if item.is_deletion() || item.is_ignored() || item.mod_time() <= since {
+            return Ok(());
+        }
+        if !self.does_disk_item_match_db_item(item, false)? {
+            return Err(DataStoreError::DiskOutOfSync {
+                path: item.path.clone(),
+            });
+        }
+
+        if item.is_folder() {
+            for child_item in self.db_access.get_local_child_items(&item.path, true)? {
+                self.collect_changed_files(&child_item, since, changed_files)?;
+            }
+        } else {
+            let mut changed_file = item.clone();
+            // Stores trusting modification times only hash files once they are synced.
+            if changed_file.metadata().hash.is_empty() && self.db_access.is_trust_mtime()? {
+                let hash = self.fs_access.calculate_hash(&changed_file.path)?;
+                self.db_access
+                    .update_local_data_item_hash(&changed_file.path, &hash)?;
+                changed_file.metadata_mut().hash = hash;
+            }
+            changed_files.push(changed_file);
+        }
+
+        Ok(())
+    }
+
+    /// Applies a change bundle written by export_changes of another store of the data set.
+    /// The changes are synced with the same semantics as a live sync from the exporting store,
+    /// e.g. conflicts are reported to sync_conflict. Files that are not part of the bundle (as
+    /// they did not change since its sync point) are reported as unavailable_items if this
+    /// store does not know them yet, i.e. the sync point should not be ahead of this store.
+    pub fn apply_changes<R, F>(&self, reader: &mut R, sync_conflict: &mut F) -> Result<SyncResult>
+    where
+        R: std::io::Read,
+        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+    {
+        let tmp_dir = tempfile::tempdir().map_err(fs_interaction::FSInteractionError::from)?;
+        let replica = Self::unpack_change_bundle(reader, tmp_dir.path())?;
+
+        self.sync_from_other_store(&replica, &RelativePath::from_path(""), sync_conflict)
+    }
+
+    // Unpacks the bundle into a replica of the exporting store below the given directory.
+    fn unpack_change_bundle<R: std::io::Read>(reader: &mut R, tmp_dir: &Path) -> Result<Self> {
+        use fs_interaction::FSInteractionError;
+
+        change_bundle::read_header(reader)?;
+        let index_copy = change_bundle::index_path(tmp_dir)?;
+        match change_bundle::read_entry(reader)? {
+            Some(change_bundle::BundleEntry::Index { length }) => {
+                let mut index_file =
+                    std::fs::File::create(&index_copy).map_err(FSInteractionError::from)?;
+                change_bundle::copy_content(reader, &mut index_file, length)?;
+            }
+            _ => {
+                return Err(DataStoreError::InvalidChangeBundle {
+                    message: "Change bundles must start with a copy of the index!",
+                })
+            }
+        }
+
+        // The FS does not necessarily share the view of the OS (e.g. in memory ones).
+        let fs = FS::default();
+        let replica_root = tmp_dir.join("content");
+        // The file system root always exists, thus it is left out.
+        for dir in replica_root
+            .ancestors()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .skip(1)
+        {
+            fs.create_dir(dir, true).map_err(FSInteractionError::from)?;
+        }
+        let fs_interaction = FSInteraction::create_with_fs(&replica_root, fs)?;
+        let metadata_db = MetadataDB::open(&index_copy)?;
+        let replica = Self::from_opened(fs_interaction, metadata_db, None)?;
+        // Replicas only hold the content of the bundled files on disk, all other items are
+        // answered from the index alone.
+        replica.set_partial_content(true)?;
+
+        loop {
+            match change_bundle::read_entry(reader)? {
+                Some(change_bundle::BundleEntry::File { path, length }) => {
+                    replica.unpack_bundled_file(reader, &path, length)?
+                }
+                Some(change_bundle::BundleEntry::Index { .. }) => {
+                    return Err(DataStoreError::InvalidChangeBundle {
+                        message: "Change bundles must hold exactly one copy of the index!",
+                    })
+                }
+                None => return Ok(replica),
+            }
+        }
+    }
+
+    fn unpack_bundled_file<R: std::io::Read>(
+        &self,
+        reader: &mut R,
+        path: &RelativePath,
+        length: u64,
+    ) -> Result<()> {
+        let item = self.db_access.get_local_data_item(path, false)?;
+        if !item.is_file() || path.is_root() {
+            return Err(DataStoreError::InvalidChangeBundle {
+                message: "Change bundle holds content for an item that is no file in its index!",
+            });
+        }
+        let mut dir = RelativePath::from_path("");
+        for component in &path.parent().get_path_components()[1..] {
+            dir = dir.join_mut(component.clone());
+            if self.fs_access.metadata(&dir).is_err() {
+                self.fs_access.create_dir(&dir)?;
+            }
+        }
+        // The content is streamed to disk, i.e. the length in the header is not trusted.
+        self.fs_access.create_file(path)?;
+        let mut content = std::io::Read::take(reader, length);
+        self.fs_access.write_file(path, Box::new(&mut content))?;
+        if content.limit() > 0 {
+            return Err(DataStoreError::InvalidChangeBundle {
+                message: "Change bundle is truncated!",
+            });
+        }
+        let mod_time = item.metadata().mod_time.and_utc();
+        self.fs_access.set_metadata(
+            path,
+            FileTime::from_unix_time(mod_time.timestamp(), mod_time.timestamp_subsec_nanos()),
+            item.metadata().is_read_only,
+        )?;
+
+        Ok(())
+    }
+
     /// Tries to optimize the database file.
     /// This generally shrinks its size and slightly improves performance.
-    pub fn optimize_database(&self) -> Result<()> {
-        self.db_access.optimize_db()?;
+    /// Returns the number of orphaned metadata rows that were removed from the database.
+    pub fn optimize_database(&self) -> Result<usize> {
+        Ok(self.db_access.optimize_db()?)
+    }
+
+    /// Optimizes the database if the schedule is due, e.g. called regularly by long running
+    /// processes that never run a manual optimize (not wired up yet, nothing in the CLI runs
+    /// long enough, see OptimizeSchedule). Skipped while a sync or scan is running
+    /// (the schedule stays due, i.e. the next call tries again).
+    /// Conflict copies beyond the retention policy (see set_retention_policy) are removed, too.
+    /// Returns the number of removed orphaned rows if the database was optimized.
+    pub fn optimize_database_if_due<C: Clock>(
+        &self,
+        schedule: &mut OptimizeSchedule<C>,
+    ) -> Result<Option<usize>> {
+        if !schedule.is_due() {
+            return Ok(None);
+        }
+        let _guard = match self.subtree_locks.lock(&RelativePath::from_path("")) {
+            Some(guard) => guard,
+            None => return Ok(None),
+        };
+
+        if !self.retention_policy.is_unlimited() {
+            self.clean_retention_unlocked()?;
+        }
+        let removed_rows = self.optimize_database()?;
+        schedule.schedule_next();
+
+        Ok(Some(removed_rows))
+    }
+
+    /// Sets the limits for the store's trash, i.e. the conflict copies kept next to files when
+    /// keeping both versions of a conflict (see clean_retention).
+    pub fn set_retention_policy(&mut self, retention_policy: RetentionPolicy) {
+        self.retention_policy = retention_policy;
+    }
+
+    /// Permanently removes conflict copies beyond the retention policy and records them as
+    /// deleted, i.e. other stores remove their copies on the next sync.
+    ///
+    /// The age of a copy is taken from its creation time on disk (the time the conflicting
+    /// version was downloaded), its modification time is the one of the remote version.
+    /// The newest copies are kept first, e.g. a size limit removes the oldest copies.
+    pub fn clean_retention(&self) -> Result<RetentionResult> {
+        let _guard = self.lock_subtree(&RelativePath::from_path(""))?;
+        self.clean_retention_unlocked()
+    }
+
+    fn clean_retention_unlocked(&self) -> Result<RetentionResult> {
+        let mut result = RetentionResult::new();
+        if self.retention_policy.is_unlimited() {
+            return Ok(result);
+        }
+
+        let suffixes: Vec<String> = self
+            .get_data_stores()?
+            .into_iter()
+            .map(|data_store| conflict_copy_suffix(&data_store.human_name).to_lowercase())
+            .collect();
+        let mut conflict_copies = Vec::new();
+        self.collect_conflict_copies(
+            &RelativePath::from_path(""),
+            &suffixes,
+            &mut conflict_copies,
+        )?;
+
+        // Newest first, the ones exceeding a limit are the oldest.
+        let now = FileTime::now().unix_seconds();
+        conflict_copies
+            .sort_by_key(|(_, metadata)| -metadata.creation_or_mod_time().unix_seconds());
+        let mut kept_bytes = 0;
+        for (path, metadata) in conflict_copies {
+            let age = std::cmp::max(now - metadata.creation_or_mod_time().unix_seconds(), 0) as u64;
+            let too_old = match self.retention_policy.max_age {
+                Some(max_age) => age > max_age.as_secs(),
+                None => false,
+            };
+            let too_large = match self.retention_policy.max_bytes {
+                Some(max_bytes) => kept_bytes + metadata.size() > max_bytes,
+                None => false,
+            };
+
+            if too_old || too_large {
+                self.fs_access.delete_file(&path)?;
+                self.db_access.delete_local_data_item(&path)?;
+                result.removed_files += 1;
+                result.removed_bytes += metadata.size();
+            } else {
+                kept_bytes += metadata.size();
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn collect_conflict_copies(
+        &self,
+        dir: &RelativePath,
+        suffixes: &[String],
+        conflict_copies: &mut Vec<(RelativePath, virtual_fs::Metadata)>,
+    ) -> Result<()> {
+        for child_item in self.db_access.get_local_child_items(dir, false)? {
+            if child_item.is_folder() {
+                self.collect_conflict_copies(&child_item.path, suffixes, conflict_copies)?;
+            } else if child_item.is_file() {
+                let name = child_item.path.name().to_lowercase();
+                if suffixes
+                    .iter()
+                    .any(|suffix| name.ends_with(suffix.as_str()))
+                    && self.disk_item_exists(&child_item.path)?
+                {
+                    let metadata = self.fs_access.metadata(&child_item.path)?;
+                    conflict_copies.push((child_item.path, metadata));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports the disk space used by the store's metadata (DB, pending files and snapshots).
+    pub fn metadata_footprint(&self) -> Result<MetadataFootprint> {
+        Ok(MetadataFootprint {
+            db_bytes: self.fs_access.metadata_db_size()?,
+            pending_bytes: self.fs_access.pending_files_size()?,
+            snapshot_bytes: self.fs_access.snapshots_size()?,
+        })
+    }
+
+    /// Removes orphaned files from the pending files directory, e.g. left behind by a crashed
+    /// download, and reports how many files/bytes were reclaimed.
+    /// No operation can be in progress while the store is opened exclusively by us, i.e. all
+    /// pending files found are orphaned.
+    ///
+    /// With dry_run set the orphaned files are only reported, not removed.
+    pub fn clean_pending(&self, dry_run: bool) -> Result<PendingFilesResult> {
+        let mut result = PendingFilesResult::new();
+        for pending_file in self.fs_access.list_pending_files()? {
+            result.pending_files += 1;
+            if let Some(metadata) = &pending_file.metadata {
+                result.pending_bytes += metadata.size();
+            }
+
+            if !dry_run {
+                self.delete_pending_file(&pending_file)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Removes the files staged by a failed sync from the pending files dir. Backups of a running
+    // batch of conflict resolutions are kept, the batch restores them (see apply_resolutions).
+    fn discard_pending_downloads(&self) -> Result<()> {
+        for pending_file in self.fs_access.list_pending_files()? {
+            if !pending_file
+                .relative_path
+                .name()
+                .ends_with(BACKUP_FILE_SUFFIX)
+            {
+                self.delete_pending_file(&pending_file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn delete_pending_file(&self, pending_file: &DataItem) -> Result<()> {
+        if pending_file
+            .metadata
+            .as_ref()
+            .is_some_and(|metadata| metadata.is_dir())
+        {
+            self.fs_access
+                .delete_directory(&pending_file.relative_path)?;
+        } else {
+            self.fs_access.delete_file(&pending_file.relative_path)?;
+        }
+
         Ok(())
     }
 
@@ -154,13 +930,290 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         Ok(())
     }
 
+    /// Turns a transfer store back into a regular store, i.e. it starts to index its own data.
+    /// Scans then record its disk content and syncs deliver all included items to it, not only
+    /// the ones other stores are missing. Items the store did not carry are reset, so the next
+    /// syncs fill them in (the store becomes a full copy of the data set).
+    pub fn unmark_as_transfer_store(&self) -> Result<()> {
+        if !self.is_transfer_store()? {
+            return Ok(());
+        }
+
+        let transaction_result: Result<_> = self.db_access.run_bundled(|| {
+            for item in self.db_access.get_ignored_items()? {
+                if self.local_inclusion_rules.is_included(&item.path) {
+                    self.db_access.reset_local_data_item(&item.path)?;
+                }
+            }
+            // Transfer stores might ignore their complete root folder, scans need it as folder.
+            // It is reset like any other item we did not carry, i.e. syncs fill it in again.
+            let root_path = RelativePath::from_path("");
+            if !self
+                .db_access
+                .get_local_data_item(&root_path, false)?
+                .is_folder()
+            {
+                self.db_access.reset_local_data_item(&root_path)?;
+                let root_item = DataItem {
+                    metadata: Some(self.fs_access.metadata(&root_path)?),
+                    relative_path: root_path,
+                    issue: None,
+                };
+                self.update_db_item(&root_item, "")?;
+            }
+            self.db_access.unmark_as_transfer_store()?;
+
+            Ok(())
+        })?;
+        transaction_result
+    }
+
+    /// Encrypts the file contents carried by this transfer store (e.g. on a USB stick) with a
+    /// key derived from the passphrase. Only possible for transfer stores not carrying any
+    /// files yet. The DB keeps the hashes of the plain contents, i.e. other stores verify the
+    /// carried files as usual.
+    pub fn enable_encryption(&mut self, passphrase: &str) -> Result<()> {
+        if !self.is_transfer_store()? || self.tree_summary(&RelativePath::from_path(""))?.files > 0
+        {
+            return Err(DataStoreError::OnlyCleanTransferStoresCanBeEncrypted);
+        }
+
+        Ok(self.fs_access.enable_encryption(passphrase)?)
+    }
+
+    /// Unlocks the file contents of an encrypted store, must be done before syncing with it.
+    pub fn unlock_encryption(&mut self, passphrase: &str) -> Result<()> {
+        Ok(self.fs_access.unlock_encryption(passphrase)?)
+    }
+
+    /// Queries if the store encrypts its file contents (see enable_encryption).
+    pub fn is_encrypted(&self) -> bool {
+        self.fs_access.is_encrypted()
+    }
+
+    /// Queries if the local store is a transfer store (see mark_as_transfer_store).
+    pub fn is_transfer_store(&self) -> Result<bool> {
+        Ok(self.db_access.is_transfer_store()?)
+    }
+
+    /// Sets if the local store trusts file modification times.
+    ///
+    /// A store trusting modification times does NOT hash new or changed files during scans,
+    /// it records a placeholder hash and only hashes a file lazily once its content hash is
+    /// needed, e.g. when it is synced, exported to a manifest or checked for duplicates.
+    /// This trades integrity guarantees (e.g. detection of bit-rot or content changes that do
+    /// not touch the modification time) for scan speed. Bit-rot checks are refused in this mode.
+    pub fn set_trust_mtime(&self, trust_mtime: bool) -> Result<()> {
+        self.db_access.set_trust_mtime(trust_mtime)?;
+        Ok(())
+    }
+
+    /// Queries if the local store trusts file modification times (see set_trust_mtime).
+    pub fn trusts_mtime(&self) -> Result<bool> {
+        Ok(self.db_access.is_trust_mtime()?)
+    }
+
+    /// Sets if the local store only holds the content of some of its indexed files
+    /// (e.g. after it was filled by a metadata-only sync or mid-carry as a transfer store).
+    ///
+    /// Such a store answers syncs from its index alone, i.e. it does not check the disk
+    /// against the index. Syncing stores skip files it can not supply and pick them up on a
+    /// later sync (see SyncResult::unavailable_items).
+    pub fn set_partial_content(&self, partial_content: bool) -> Result<()> {
+        self.db_access.set_partial_content(partial_content)?;
+        Ok(())
+    }
+
+    /// Queries if the local store only holds some of its content (see set_partial_content).
+    pub fn has_partial_content(&self) -> Result<bool> {
+        Ok(self.db_access.is_partial_content()?)
+    }
+
+    /// Sets if the local store propagates case-only changes of item names (the default).
+    ///
+    /// A store not propagating them treats names only differing in case as equal, i.e. it
+    /// keeps the local case of items on syncs and does not record case-only renames on scans.
+    /// This avoids repeated renames between stores disagreeing on the case of a name
+    /// (e.g. on case-insensitive file systems).
+    pub fn set_propagate_case_changes(&self, propagate_case_changes: bool) -> Result<()> {
+        self.db_access
+            .set_propagate_case_changes(propagate_case_changes)?;
+        Ok(())
+    }
+
+    /// Queries if the local store propagates case-only changes of item names
+    /// (see set_propagate_case_changes).
+    pub fn propagates_case_changes(&self) -> Result<bool> {
+        Ok(self.db_access.is_propagate_case_changes()?)
+    }
+
+    /// Queries if the local store treats names only differing in their unicode normalization
+    /// as the same item (see DataStoreCreateOptions::normalize_unicode).
+    pub fn normalizes_unicode(&self) -> Result<bool> {
+        Ok(self.db_access.is_normalize_unicode()?)
+    }
+
+    /// Sets the hash algorithm used for new content hashes (by default the algorithm the
+    /// store's existing hashes are computed with). Existing hashes are converted by rehash_all,
+    /// which MUST be run before scanning or syncing the store with the new algorithm.
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: fs_interaction::HashAlgorithm) {
+        self.fs_access.set_hash_algorithm(hash_algorithm);
+    }
+
+    /// Queries the hash algorithm used for new content hashes (see set_hash_algorithm).
+    pub fn hash_algorithm(&self) -> fs_interaction::HashAlgorithm {
+        self.fs_access.hash_algorithm()
+    }
+
+    /// Re-computes the content hashes of all files with the configured hash algorithm (see
+    /// set_hash_algorithm) WITHOUT recording modifications, as their contents did not change.
+    /// Only runs if the stored hashes are computed with a different algorithm.
+    ///
+    /// The progress is committed in batches, i.e. an interrupted re-hash resumes where it
+    /// stopped when run again with the same algorithm. Returns the number of re-hashed files.
+    pub fn rehash_all(&self) -> Result<usize> {
+        let _guard = self.lock_subtree(&RelativePath::from_path(""))?;
+        let hash_algorithm = self.fs_access.hash_algorithm();
+        if self.db_access.get_hash_algorithm()? == hash_algorithm.name() {
+            return Ok(0);
+        }
+
+        let mut rehashed_files = 0;
+        let mut last_item_id = self.db_access.get_rehash_progress()?.unwrap_or(0);
+        loop {
+            let files = self
+                .db_access
+                .get_local_files_after(last_item_id, REHASH_BATCH_SIZE)?;
+            if files.is_empty() {
+                break;
+            }
+
+            let transaction_result: Result<_> = self.db_access.run_bundled(|| {
+                let mut batch_rehashed_files = 0;
+                for (_, path) in &files {
+                    let file_item = self.db_access.get_local_data_item(path, false)?;
+                    // Placeholder hashes of stores trusting modification times stay empty.
+                    if !file_item.metadata().hash.is_empty() {
+                        let hash = self.fs_access.calculate_hash(&file_item.path)?;
+                        self.db_access
+                            .update_local_data_item_hash(&file_item.path, &hash)?;
+                        batch_rehashed_files += 1;
+                    }
+                }
+                self.db_access
+                    .set_rehash_progress(files.last().unwrap().0)?;
+
+                Ok(batch_rehashed_files)
+            })?;
+            rehashed_files += transaction_result?;
+            last_item_id = files.last().unwrap().0;
+        }
+        self.db_access.set_hash_algorithm(hash_algorithm.name())?;
+
+        Ok(rehashed_files)
+    }
+
+    /// Sets the minimum age of file modifications to be indexed during scans.
+    /// Files modified more recently are skipped (they might still be written to) and picked up
+    /// by a later scan. This only affects the timing of indexing, it does not exclude files.
+    /// The setting is persisted, i.e. it applies to all later scans of the store.
+    pub fn set_min_file_age(&mut self, min_age: Option<std::time::Duration>) -> Result<()> {
+        self.db_access
+            .set_min_file_age(min_age.map(|min_age| min_age.as_secs() as i64))?;
+        self.local_inclusion_rules.set_min_age(min_age);
+        Ok(())
+    }
+
+    /// Queries the minimum age of file modifications to be indexed (see set_min_file_age).
+    pub fn min_file_age(&self) -> Option<std::time::Duration> {
+        self.local_inclusion_rules.min_age()
+    }
+
+    /// Reads files of the given name as per-directory ignore rules, e.g. '.squirrelignore'
+    /// (see DirIgnoreRules for their semantics). None disables them.
+    /// The setting is persisted. The next scan marks already indexed items matching the rules
+    /// as ignored (and re-indexes items no longer matching them), syncs skip matching items.
+    pub fn set_ignore_file_name(&mut self, ignore_file_name: Option<String>) -> Result<()> {
+        self.db_access
+            .set_ignore_file_name(ignore_file_name.as_deref())?;
+        self.local_inclusion_rules
+            .set_ignore_file_name(ignore_file_name);
+        Ok(())
+    }
+
+    /// Queries the name of the per-directory ignore files (see set_ignore_file_name).
+    pub fn ignore_file_name(&self) -> Option<&str> {
+        self.local_inclusion_rules.ignore_file_name()
+    }
+
+    /// Decides how scans treat items whose names only differ in case (see DuplicatePolicy).
+    /// All such items found are reported in the scan result. Defaults to ReportAndSkip.
+    pub fn set_duplicate_policy(&mut self, duplicate_policy: DuplicatePolicy) {
+        self.duplicate_policy = duplicate_policy;
+        self.fs_access
+            .set_index_first_duplicate(duplicate_policy == DuplicatePolicy::SyncFirst);
+    }
+
+    /// Limits scans to items at most max_depth levels below the store root (e.g. 1 only indexes
+    /// the root's direct children). Deeper items keep their DB entries as they are, i.e. they
+    /// are neither updated nor marked as deleted. None scans the complete tree.
+    pub fn set_max_scan_depth(&mut self, max_depth: Option<usize>) {
+        self.max_scan_depth = max_depth;
+    }
+
+    /// Pauses indexing of the sub-tree at the given path, e.g. while a VM image in it is written.
+    /// Scans leave paused items as they are in the DB (including their version vectors), i.e.
+    /// changes on disk are neither indexed nor synced until the path is resumed.
+    /// Unlike ignore rules this does not change the sync state of any item.
+    /// Returns false if the path was already paused.
+    pub fn pause_path(&mut self, path: &RelativePath) -> Result<bool> {
+        if !self.paused_paths.pause(path) {
+            return Ok(false);
+        }
+        self.fs_access
+            .write_paused_paths(&self.paused_paths.serialize())?;
+
+        Ok(true)
+    }
+
+    /// Resumes indexing of a path paused by pause_path, the next scan picks up its changes.
+    /// Returns false if the path was not paused.
+    pub fn resume_path(&mut self, path: &RelativePath) -> Result<bool> {
+        if !self.paused_paths.resume(path) {
+            return Ok(false);
+        }
+        self.fs_access
+            .write_paused_paths(&self.paused_paths.serialize())?;
+
+        Ok(true)
+    }
+
+    /// Lists all paths currently paused by pause_path.
+    pub fn paused_paths(&self) -> Vec<RelativePath> {
+        self.paused_paths.paths().clone()
+    }
+
+    /// Sets how often file transfers into this store are retried on transient errors.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Downloads files into the given directory before moving them into place, instead of
+    /// using the store's metadata dir. It may live on a different volume than the store and
+    /// be shared by several stores (each one uses its own sub-directory in it).
+    pub fn set_staging_dir<P: AsRef<Path>>(&mut self, staging_dir: Option<P>) -> Result<()> {
+        let store_name = self.local_data_store_name()?;
+        self.fs_access.set_staging_dir(staging_dir, &store_name)?;
+        Ok(())
+    }
+
     /// Gets the local inclusion rules.
     pub fn get_inclusion_rules(&self) -> &InclusionRules {
         &self.local_inclusion_rules
     }
 
     /// Updates the local inclusion rules.
-    /// To be included, a file must match AT LEAST ONE inclusion rule and NO exclusion rule.
+    /// Rules are evaluated in order, the last matching rule decides if a file is included.
     ///
     /// Updating the inclusion rules will have direct affect on the DB content.
     /// Newly ignored items (e.g. files that now match an ignore pattern or files that no longer
@@ -170,11 +1223,17 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     ///
     /// If you want to preview the changes made by new ignore rules before, set dry_run=true.
     /// No changes are made to the DB, only the affected DBItems are returned.
+    ///
+    /// Redundant rules can be dropped by calling InclusionRules::compact on the new rules
+    /// before updating them (this never changes the returned items).
     pub fn update_inclusion_rules(
         &mut self,
         new_rules: InclusionRules,
         dry_run: bool,
     ) -> Result<(Vec<DBItem>, Vec<DBItem>)> {
+        if !dry_run {
+            self.fs_access.begin_operation("update_inclusion_rules")?;
+        }
         let transaction_result: Result<_> = self.db_access.run_bundled(|| {
             let (no_longer_ignored, newly_ignored) =
                 self.find_inclusion_status_changes(&new_rules, &RelativePath::from_path(""))?;
@@ -203,10 +1262,56 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         let changed_items = transaction_result?;
         if !dry_run {
             self.local_inclusion_rules = new_rules;
+            self.fs_access.finish_operation()?;
         }
         Ok(changed_items)
     }
 
+    /// Same as a dry run of update_inclusion_rules, but additionally reports the on disk content
+    /// of newly ignored items, which stays on disk when applying the rules.
+    pub fn plan_inclusion_rules(&self, new_rules: &InclusionRules) -> Result<InclusionRulesPlan> {
+        let (no_longer_ignored, newly_ignored) =
+            self.find_inclusion_status_changes(new_rules, &RelativePath::from_path(""))?;
+
+        Ok(InclusionRulesPlan::new(no_longer_ignored, newly_ignored))
+    }
+
+    /// Removes the on disk content suggested for clean up by the given plan.
+    /// Must only be called after the plan's rules were applied, i.e. all of the content
+    /// must belong to ignored items.
+    pub fn delete_ignored_content(&self, plan: &InclusionRulesPlan) -> Result<()> {
+        self.delete_ignored_on_disk(&plan.disk_cleanup)
+    }
+
+    /// Removes the on disk content of the given ignored items, e.g. the ones a scan found still
+    /// on disk (see ScanResult::ignored_on_disk). Fails if any of the items is not ignored.
+    pub fn delete_ignored_on_disk(&self, paths: &[RelativePath]) -> Result<()> {
+        for path in paths {
+            if !self
+                .db_access
+                .get_local_data_item(path, false)?
+                .is_ignored()
+            {
+                return Err(DataStoreError::UnexpectedState {
+                    source: "Must only delete the disk content of ignored items!",
+                });
+            }
+
+            let metadata = match self.fs_access.metadata(path) {
+                Ok(metadata) => metadata,
+                Err(error) if error.is_io_not_found() => continue,
+                Err(error) => return Err(error.into()),
+            };
+            if metadata.is_dir() {
+                self.fs_access.delete_directory(path)?;
+            } else {
+                self.fs_access.delete_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
     fn find_inclusion_status_changes(
         &self,
         new_rules: &InclusionRules,
@@ -261,7 +1366,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     }
 
     /// Adds a glob rule to include files matching the given pattern.
-    /// To be included, a file must match AT LEAST ONE inclusion rule and NO exclusion rule.
+    /// Rules are evaluated in order, the last matching rule decides if a file is included.
     pub fn add_inclusion_rule(
         &mut self,
         rule: glob::Pattern,
@@ -271,6 +1376,54 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         self.update_inclusion_rules(new_rules, false)
     }
 
+    /// Ignores exactly the items at the given paths (including all content of folders), e.g. a
+    /// selection made in a UI. Adds an ignore rule matching only the path for each of them.
+    pub fn ignore_paths(&mut self, paths: &[RelativePath]) -> Result<(Vec<DBItem>, Vec<DBItem>)> {
+        let mut new_rules = self.local_inclusion_rules.clone();
+        for path in paths {
+            let (_, rule) = self.exact_path_rule(path)?;
+            new_rules.add_ignore_rule(rule);
+        }
+        self.update_inclusion_rules(new_rules, false)
+    }
+
+    /// Reverts ignore_paths for the given paths. Path rules added by ignore_paths are removed,
+    /// paths that are still ignored by other rules are included explicitly (folders with all
+    /// their content).
+    pub fn unignore_paths(&mut self, paths: &[RelativePath]) -> Result<(Vec<DBItem>, Vec<DBItem>)> {
+        let mut new_rules = self.local_inclusion_rules.clone();
+        for path in paths {
+            let (db_path, rule) = self.exact_path_rule(path)?;
+            new_rules.remove_rule(rule.as_str());
+            if !new_rules.is_included(&db_path) {
+                new_rules.add_inclusion_rule(rule.clone());
+                new_rules.add_inclusion_rule(
+                    glob::Pattern::new(&format!("{}/**", rule.as_str())).unwrap(),
+                );
+            }
+        }
+        self.update_inclusion_rules(new_rules, false)
+    }
+
+    // Pattern matching exactly the given path (as it is named in the DB), i.e. no component
+    // is interpreted as a glob. Returns the path as it is named in the DB as well.
+    fn exact_path_rule(&self, path: &RelativePath) -> Result<(RelativePath, glob::Pattern)> {
+        if path.is_root() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Can not ignore the root of a data store!",
+            });
+        }
+
+        let db_path = self.db_access.get_local_data_item(path, false)?.path;
+        let rule = db_path
+            .get_path_components()
+            .iter()
+            .map(|component| glob::Pattern::escape(component))
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok((db_path, glob::Pattern::new(&rule).unwrap()))
+    }
+
     /// Re-indexes the data stored in this data_store.
     ///
     /// Traverses the data directory and performs the following actions for the metadata DB:
@@ -282,6 +1435,266 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     /// While doing these actions at all times the modification times in the DB are kept up to date,
     /// i.e. the local time counter is kept and attached to new or changed files.
     pub fn perform_full_scan(&self) -> Result<ScanResult> {
+        self.full_scan(false)
+    }
+
+    /// Same as perform_full_scan, but additionally lists the paths of all new, changed and
+    /// deleted items in the result. Keeps all of them in memory, i.e. use with care on scans
+    /// that might find a huge number of changes.
+    pub fn perform_full_scan_listing_changes(&self) -> Result<ScanResult> {
+        self.full_scan(true)
+    }
+
+    /// Creates a scanner for the sub-tree at the given path that can run on a different thread,
+    /// e.g. to scan a sub-tree while syncing another one. Its changes are written by apply_scan.
+    /// Requires a store opened with open_shared (additional DB connections are opened).
+    pub fn scan_reader(&self, path: &RelativePath) -> Result<ScanReader<FS>> {
+        ScanReader::new(self, path)
+    }
+
+    /// Writes the changes found by a scan reader of this store into the DB (as one transaction)
+    /// and returns the scan's result. The scanned sub-tree is unlocked afterwards.
+    pub fn apply_scan(&self, pending_scan: PendingScan) -> Result<ScanResult> {
+        if !pending_scan.guard.belongs_to(&self.subtree_locks) {
+            return Err(DataStoreError::UnexpectedState {
+                source: "The scan was performed on a different data store!",
+            });
+        }
+
+        let transaction_result: Result<_> = self.db_access.run_bundled(|| {
+            for change in pending_scan.changes.iter() {
+                self.execute_scan_change(change)?;
+            }
+            Ok(())
+        })?;
+        transaction_result?;
+
+        Ok(pending_scan.scan_result)
+    }
+
+    /// Re-indexes only the item at the given path (including its content if it is a folder),
+    /// e.g. after an application reported a change to a single file.
+    /// Parent folders that are not indexed yet are re-indexed as a whole.
+    pub fn reindex_path(&self, path: &RelativePath) -> Result<ScanResult> {
+        let _guard = self.lock_subtree(path)?;
+        self.reindex_path_unlocked(path)
+    }
+
+    /// Re-indexes only the items at the given paths, e.g. the changes reported by a file system
+    /// watcher, without walking the tree. Files are indexed on their own, folders together with
+    /// their direct entries (their sub-folders are not descended into). Paths no longer on disk
+    /// are recorded as deleted. Duplicate paths are indexed once.
+    /// Paths in folders that are not indexed yet re-index these as a whole (see reindex_path).
+    pub fn scan_paths(&self, paths: &[RelativePath]) -> Result<ScanResult> {
+        let _guard = self.lock_subtree(&RelativePath::from_path(""))?;
+        if self.db_access.is_transfer_store()? {
+            return Err(DataStoreError::MustNotScanTransferStores);
+        }
+
+        // Parents are scanned first, their entries are not indexed a second time.
+        let mut paths: Vec<RelativePath> = paths.to_vec();
+        paths.sort_by_key(|path| path.path_component_number());
+        let mut scanned_paths = HashSet::new();
+        let mut scan_result = ScanResult::new();
+        for path in paths {
+            let lower_case_path = path.to_lower_case();
+            if scanned_paths.contains(&lower_case_path) {
+                continue;
+            }
+            let scan_item = path.is_root() || !scanned_paths.contains(&lower_case_path.parent());
+            scanned_paths.insert(lower_case_path);
+
+            let path_result = self.scan_path_unlocked(&path, scan_item)?;
+            scan_result = scan_result.combine(&path_result);
+        }
+
+        Ok(scan_result)
+    }
+
+    /// Re-builds the index of the sub-tree at the given path purely from disk, e.g. after an old
+    /// DB was restored onto a newer tree. Unlike a normal scan, no file is assumed unchanged
+    /// because of matching FS metadata: each indexed file is hashed again. Files still holding
+    /// the content known to the DB keep their creation and modification history (only their
+    /// FS metadata is refreshed), all others are recorded as changed.
+    /// Items no longer on disk are deleted and new items are added as with a normal scan.
+    pub fn rebuild_index(&self, path: &RelativePath) -> Result<ScanResult> {
+        let _guard = self.lock_subtree(path)?;
+        if self.db_access.is_transfer_store()? {
+            return Err(DataStoreError::MustNotScanTransferStores);
+        }
+
+        let mut rebuild_result = ScanResult::new();
+        let item = self.db_access.get_local_data_item(path, false)?;
+        self.rebuild_file_entries(&item, &mut rebuild_result)?;
+        let scan_result = self.reindex_path_unlocked(path)?;
+
+        Ok(scan_result.combine(&rebuild_result))
+    }
+
+    // Compares all indexed files below the item with their content on disk, the following scan
+    // then only needs to handle items that changed their type, got added or got deleted.
+    fn rebuild_file_entries(&self, item: &DBItem, scan_result: &mut ScanResult) -> Result<()> {
+        if item.is_folder() {
+            for child_item in self.db_access.get_local_child_items(&item.path, false)? {
+                self.rebuild_file_entries(&child_item, scan_result)?;
+            }
+            return Ok(());
+        }
+        // Placeholder hashes (stores trusting mod times) can not be compared, the scan decides.
+        if !item.is_file() || item.metadata().hash.is_empty() {
+            return Ok(());
+        }
+
+        let fs_item = DataItem {
+            metadata: match self.fs_access.metadata(&item.path) {
+                Ok(metadata) if metadata.is_file() => Some(metadata),
+                _ => return Ok(()),
+            },
+            relative_path: item.path.clone(),
+            issue: None,
+        };
+        let fs_mod_time =
+            Self::fs_to_date_time(&fs_item.metadata.as_ref().unwrap().last_mod_time());
+        let hash = self.fs_access.calculate_hash(&item.path)?;
+        if hash == item.metadata().hash {
+            if fs_mod_time != item.metadata().mod_time {
+                self.db_access
+                    .update_local_data_item_mod_time(&item.path, fs_mod_time)?;
+            }
+        } else {
+            self.update_db_item(&fs_item, &hash)?;
+            scan_result.changed_items += 1;
+        }
+
+        Ok(())
+    }
+
+    fn reindex_path_unlocked(&self, path: &RelativePath) -> Result<ScanResult> {
+        if self.db_access.is_transfer_store()? {
+            return Err(DataStoreError::MustNotScanTransferStores);
+        }
+        if path.is_root() {
+            return self.full_scan_unlocked(false);
+        }
+
+        // Items can only be indexed into folders known to the DB.
+        let parent_path = path.parent();
+        if !self
+            .db_access
+            .get_local_data_item(&parent_path, false)?
+            .is_folder()
+        {
+            return self.reindex_path_unlocked(&parent_path);
+        }
+
+        let mut scan_result = ScanResult::new();
+        self.scan_single_item(path, self.max_scan_depth, &mut scan_result)?;
+        self.measure_ignored_on_disk(&mut scan_result)?;
+
+        Ok(scan_result)
+    }
+
+    // Scans the item at the given path (and its content, if it is a folder) without looking at
+    // its siblings. Items no longer on disk are recorded as deleted.
+    // The item's parent folder must be indexed.
+    fn scan_single_item(
+        &self,
+        path: &RelativePath,
+        max_depth: Option<usize>,
+        scan_result: &mut ScanResult,
+    ) -> Result<()> {
+        // Same conditions under which a scan of the parent folder leaves out the item.
+        let parent_path = path.parent();
+        let below_max_depth =
+            max_depth.is_some_and(|max_depth| Self::path_depth(&parent_path) >= max_depth);
+        if self.fs_access.is_internal_path(&parent_path)
+            || self.fs_access.is_internal_path(path)
+            || below_max_depth
+            || self.paused_paths.is_paused(path)
+        {
+            return Ok(());
+        }
+
+        let mut listener = |event: ScanEvent| {
+            Self::record_scan_event(scan_result, &event);
+            true
+        };
+        match self.disk_data_item(path) {
+            Ok(item) => {
+                let dir_rules = self.dir_ignore_rules_above(path)?;
+                self.scan_item(&item, &dir_rules, max_depth, &mut listener)?;
+            }
+            Err(DataStoreError::FSInteractionError { ref source }) if source.is_io_not_found() => {
+                let db_item = self.db_access.get_local_data_item(path, false)?;
+                if !db_item.is_deletion()
+                    && !db_item.is_ignored()
+                    && listener(ScanEvent::DeletedItem(&db_item))
+                {
+                    self.apply_scan_change(ScanChange::DeleteItem(db_item.path.clone()))?;
+                }
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(())
+    }
+
+    // Scans the item at the given path (if scan_item is set) and, if it is a folder, its direct
+    // entries. Nothing below is descended into.
+    fn scan_path_unlocked(&self, path: &RelativePath, scan_item: bool) -> Result<ScanResult> {
+        let depth = Self::path_depth(path);
+        let limit_depth = |max_depth: usize| {
+            Some(
+                self.max_scan_depth
+                    .map_or(max_depth, |limit| min(limit, max_depth)),
+            )
+        };
+
+        let mut scan_result = ScanResult::new();
+        if scan_item && !path.is_root() {
+            // Items can only be indexed into folders known to the DB.
+            let parent_path = path.parent();
+            if !self
+                .db_access
+                .get_local_data_item(&parent_path, false)?
+                .is_folder()
+            {
+                return self.reindex_path_unlocked(path);
+            }
+
+            if !self.disk_item_exists(&parent_path)? {
+                // The parent is gone, it is picked up as deleted by a scan of its parent.
+                return self.scan_path_unlocked(&parent_path, true);
+            }
+            self.scan_single_item(path, limit_depth(depth), &mut scan_result)?;
+        }
+
+        // Folders additionally get their direct entries scanned.
+        let db_item = self.db_access.get_local_data_item(path, false)?;
+        if db_item.is_folder() {
+            let dir_data_item = self.disk_data_item(&db_item.path)?;
+            let dir_rules = self.dir_ignore_rules_above(path)?;
+            self.perform_scan(
+                &dir_data_item,
+                &dir_rules,
+                limit_depth(depth + 1),
+                &mut |event| {
+                    Self::record_scan_event(&mut scan_result, &event);
+                    true
+                },
+            )?;
+        }
+        self.measure_ignored_on_disk(&mut scan_result)?;
+
+        Ok(scan_result)
+    }
+
+    fn full_scan(&self, list_changes: bool) -> Result<ScanResult> {
+        let _guard = self.lock_subtree(&RelativePath::from_path(""))?;
+        self.full_scan_unlocked(list_changes)
+    }
+
+    fn full_scan_unlocked(&self, list_changes: bool) -> Result<ScanResult> {
         if self.db_access.is_transfer_store()? {
             return Err(DataStoreError::MustNotScanTransferStores);
         }
@@ -296,37 +1709,174 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         };
 
         let mut scan_result = ScanResult::new();
-        self.perform_scan(&root_data_item, &mut |event| {
-            scan_result.indexed_items += 1;
+        if list_changes {
+            scan_result.changes = Some(ScanChanges::new());
+        }
+        let max_depth = self.max_scan_depth;
+        self.perform_scan(
+            &root_data_item,
+            &DirIgnoreRules::new(),
+            max_depth,
+            &mut |event| {
+                Self::record_scan_event(&mut scan_result, &event);
+                true
+            },
+        )?;
+        self.measure_ignored_on_disk(&mut scan_result)?;
+
+        Ok(scan_result)
+    }
+
+    // Sums up the disk content of the ignored items found by a scan.
+    fn measure_ignored_on_disk(&self, scan_result: &mut ScanResult) -> Result<()> {
+        for path in scan_result.ignored_on_disk.iter() {
+            scan_result.ignored_on_disk_bytes += self.fs_access.item_size(path)?;
+        }
 
+        Ok(())
+    }
+
+    // Collects the statistics (and optionally the changed paths) of an applied scan event.
+    fn record_scan_event(scan_result: &mut ScanResult, event: &ScanEvent) {
+        // Reported in addition to the events of the individual items.
+        if let ScanEvent::IssueDuplicateNames(duplicate_names) = event {
+            scan_result.duplicate_names.push((*duplicate_names).clone());
+            return;
+        }
+
+        scan_result.indexed_items += 1;
+        if let ScanEvent::IgnoredExistingItem(fs_item) = event {
+            scan_result
+                .ignored_on_disk
+                .push(fs_item.relative_path.clone());
+        }
+
+        if let Some(changes) = scan_result.changes.as_mut() {
             match event {
-                ScanEvent::NewFolder(..) | ScanEvent::NewFile(..) => scan_result.new_items += 1,
-                ScanEvent::ChangedFolder(..) | ScanEvent::ChangedFile(..) => {
-                    scan_result.changed_items += 1
+                ScanEvent::NewFolder(fs_item) | ScanEvent::NewFile(fs_item) => {
+                    changes.new_items.push(fs_item.relative_path.clone())
                 }
-                ScanEvent::ChangedFolderToFile { .. } | ScanEvent::ChangedFileToFolder { .. } => {
-                    scan_result.deleted_items += 1;
-                    scan_result.new_items += 1;
+                ScanEvent::ChangedFolder(fs_item, _) | ScanEvent::ChangedFile(fs_item, _) => {
+                    changes.changed_items.push(fs_item.relative_path.clone())
                 }
-                ScanEvent::DeletedItem(..) => scan_result.deleted_items += 1,
+                ScanEvent::ChangedFolderToFile(fs_item, _)
+                | ScanEvent::ChangedFileToFolder(fs_item, _) => {
+                    changes.deleted_items.push(fs_item.relative_path.clone());
+                    changes.new_items.push(fs_item.relative_path.clone());
+                }
+                ScanEvent::DeletedItem(db_item) => changes.deleted_items.push(db_item.path.clone()),
                 _ => (),
             };
+        }
 
-            true
-        })?;
+        match event {
+            ScanEvent::NewFolder(fs_item)
+            | ScanEvent::NewFile(fs_item)
+            | ScanEvent::ChangedFolder(fs_item, _)
+            | ScanEvent::ChangedFile(fs_item, _)
+            | ScanEvent::ChangedFolderToFile(fs_item, _)
+            | ScanEvent::ChangedFileToFolder(fs_item, _) => {
+                let (_, unreliable) =
+                    Self::sanitize_creation_time(fs_item.metadata.as_ref().unwrap());
+                if unreliable {
+                    debug!(
+                        "Unreliable creation time on '{}', using its modification time.",
+                        fs_item.relative_path.get_path_components().join("/")
+                    );
+                    scan_result.unreliable_creation_times += 1;
+                }
+            }
+            _ => (),
+        };
 
-        Ok(scan_result)
+        match event {
+            ScanEvent::NewFolder(..) | ScanEvent::NewFile(..) => scan_result.new_items += 1,
+            ScanEvent::ChangedFolder(..) | ScanEvent::ChangedFile(..) => {
+                scan_result.changed_items += 1
+            }
+            ScanEvent::ChangedFolderToFile { .. } | ScanEvent::ChangedFileToFolder { .. } => {
+                scan_result.deleted_items += 1;
+                scan_result.new_items += 1;
+            }
+            ScanEvent::DeletedItem(..) => scan_result.deleted_items += 1,
+            _ => (),
+        };
+    }
+
+    // Collects the rules of all ignore files in the parent folders of dir, i.e. the rules a
+    // full scan applies when it reaches dir (dir's own ignore file is read by perform_scan).
+    // Syncs use them to decide if the item at dir is ignored (see should_ignore_item).
+    fn dir_ignore_rules_above(&self, dir: &RelativePath) -> Result<DirIgnoreRules> {
+        let mut dir_rules = DirIgnoreRules::new();
+        let ignore_file_name = match self.local_inclusion_rules.ignore_file_name() {
+            Some(ignore_file_name) => ignore_file_name.to_string(),
+            None => return Ok(dir_rules),
+        };
+
+        let mut ancestor = RelativePath::from_path("");
+        for component in dir.get_path_components().iter().skip(1) {
+            let ignore_file = ancestor.join(ignore_file_name.clone());
+            if let Ok(mut reader) = self.fs_access.read_file(&ignore_file) {
+                let mut content = String::new();
+                reader
+                    .read_to_string(&mut content)
+                    .map_err(fs_interaction::FSInteractionError::from)?;
+                dir_rules = dir_rules.with_ignore_file(&ancestor, &content);
+            }
+            ancestor = ancestor.join_mut(component.clone());
+        }
+
+        Ok(dir_rules)
+    }
+
+    /// Sets a callback deciding if we learn about a data store that a sync partner knows, but
+    /// we do not (given its unique name, human name and location note). Rejected stores are not
+    /// imported. Their versions can not be tracked locally, i.e. files they created or last
+    /// modified are left out of syncs (like skipped items) until the store is approved.
+    /// The sync partner itself is always learned. None (the default) learns about all stores.
+    pub fn set_data_store_approval(&mut self, approval: Option<DataStoreApproval>) {
+        self.data_store_approval = approval;
     }
 
     /// Includes the data stores given into the local database and returns a list of all
-    /// stores known after the operation.
+    /// stores known after the operation (only their IDs, see SyncHandshake).
     /// This should be done before an item or folder is synced to make sure both data stores
     /// know about the same data stores related to the given data set.
+    /// Unknown stores are only included if approved (see set_data_store_approval).
+    /// Fails if the sender uses the same unique id as this store.
     pub fn sync_data_store_list(&self, sync_handshake: SyncHandshake) -> Result<SyncHandshake> {
+        match &self.data_store_approval {
+            Some(approval) => {
+                self.sync_data_store_list_approving(sync_handshake, &mut |store| approval(store))
+            }
+            None => self.sync_data_store_list_approving(sync_handshake, &mut |_| true),
+        }
+    }
+
+    /// Same as sync_data_store_list, but only includes unknown stores the given callback
+    /// approves (the sync partner itself is always included).
+    pub fn sync_data_store_list_approving<A>(
+        &self,
+        sync_handshake: SyncHandshake,
+        approve: &mut A,
+    ) -> Result<SyncHandshake>
+    where
+        A: FnMut(&metadata_db::DataStore) -> bool,
+    {
         let local_data_set = self.get_data_set()?;
         if local_data_set.unique_name != sync_handshake.data_set_name {
-            return Err(DataStoreError::SyncError {
-                message: "Must only sync matching data_sets!",
+            return Err(DataStoreError::DataSetMismatch {
+                local_data_set: local_data_set.unique_name,
+                remote_data_set: sync_handshake.data_set_name,
+            });
+        }
+
+        // Two live stores sharing an id would mix up their versions, refuse such partners.
+        let local_data_store = self.db_access.get_local_data_store()?;
+        if local_data_store.unique_name == sync_handshake.data_store_name {
+            return Err(DataStoreError::InvalidDataStoreId {
+                data_store_id: local_data_store.unique_name,
+                message: "Data store id is already used by the sync partner!",
             });
         }
 
@@ -334,7 +1884,9 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             let local_data_store = self
                 .db_access
                 .get_data_store(&remote_data_store.unique_name)?;
-            if local_data_store.is_none() {
+            if local_data_store.is_none()
+                && (remote_data_store.is_this_store || approve(&remote_data_store))
+            {
                 self.db_access
                     .create_data_store(&metadata_db::data_store::InsertFull {
                         data_set_id: local_data_set.id,
@@ -350,10 +1902,10 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             }
         }
 
-        Ok(SyncHandshake {
-            data_set_name: local_data_set.unique_name,
-            data_stores: self.db_access.get_data_stores()?,
-        })
+        Ok(SyncHandshake::new(
+            local_data_set.unique_name,
+            &self.db_access.get_data_stores()?,
+        ))
     }
 
     /// Ask the data store to synchronize a single item.
@@ -374,8 +1926,14 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         let local_item = self
             .db_access
             .get_local_data_item(&sync_request.item_path, true)?;
-        if !self.does_disk_item_match_db_item(&local_item, false)? {
-            panic!("Must not sync if disk content is not correctly indexed in DB.");
+        // File contents are only read if they are actually transferred, the syncing store
+        // verifies that it got the indexed content (see verify_downloaded_file).
+        if !self.db_access.is_partial_content()?
+            && !self.does_disk_metadata_match_db_item(&local_item, false)?
+        {
+            return Err(DataStoreError::DiskOutOfSync {
+                path: local_item.path,
+            });
         }
 
         if local_item.is_deletion() {
@@ -385,7 +1943,9 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     IntDeletionSyncContent {},
                 )),
             })
-        } else if local_item.mod_time() <= &sync_request.item_sync_time {
+        } else if local_item.mod_time() <= &sync_request.item_sync_time
+            && !(sync_request.full_walk && local_item.is_folder())
+        {
             Ok(IntSyncResponse {
                 sync_time: local_item.sync_time,
                 action: IntSyncAction::UpToDate,
@@ -394,19 +1954,24 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             // The actual interesting case where an update/data transfer is required.
             match local_item.content {
                 metadata_db::ItemType::FILE {
-                    metadata: local_metadata,
+                    metadata: mut local_metadata,
                     creation_time: local_creation_time,
                     last_mod_time: local_last_mod_time,
-                } => Ok(IntSyncResponse {
-                    sync_time: local_item.sync_time,
-                    action: IntSyncAction::UpdateRequired(IntSyncContent::File(
-                        IntFileSyncContent {
-                            last_mod_time: local_last_mod_time,
-                            creation_time: local_creation_time,
-                            fs_metadata: local_metadata,
-                        },
-                    )),
-                }),
+                } => {
+                    // Stores trusting modification times only hash files once they are synced.
+                    local_metadata.hash = self.file_hash(&local_item.path, &local_metadata.hash)?;
+
+                    Ok(IntSyncResponse {
+                        sync_time: local_item.sync_time,
+                        action: IntSyncAction::UpdateRequired(IntSyncContent::File(
+                            IntFileSyncContent {
+                                last_mod_time: local_last_mod_time,
+                                creation_time: local_creation_time,
+                                fs_metadata: local_metadata,
+                            },
+                        )),
+                    })
+                }
                 metadata_db::ItemType::FOLDER {
                     last_mod_time: local_last_mod_time,
                     mod_time: local_mod_time,
@@ -458,7 +2023,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         &self,
         from_other: &Self,
         path: &RelativePath,
-    ) -> Result<()> {
+    ) -> Result<SyncResult> {
         use self::SyncConflictEvent::*;
 
         self.sync_from_other_store(&from_other, &path, &mut |conflict| {
@@ -484,29 +2049,715 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
 
     // Synchronizes in the direction from_other -> self, i.e. self will contain all changes done
     // in from_other after the operation completes successfully.
+    //
+    // Sub-trees that the remote reports as up to date are skipped, i.e. unchanged folders are
+    // not walked child-by-child (see SyncOptions::full_walk to walk them anyway).
     pub fn sync_from_other_store<F>(
         &self,
         from_other: &Self,
         path: &RelativePath,
         sync_conflict: &mut F,
-    ) -> Result<()>
+    ) -> Result<SyncResult>
+    where
+        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+    {
+        self.sync_from_other_store_with_options(
+            from_other,
+            path,
+            &SyncOptions::new(),
+            sync_conflict,
+        )
+    }
+
+    // Merges the other store into this one for disaster recovery, e.g. to reconstruct the most
+    // complete state from two partial backups of the data set. Items the other store holds in a
+    // newer version (or that are missing locally) are imported including their content, local
+    // items are never deleted. Concurrent versions keep the one modified most recently.
+    pub fn merge_index_from(&self, other: &Self) -> Result<SyncResult> {
+        self.sync_from_other_store_with_options(
+            other,
+            &RelativePath::from_path(""),
+            &SyncOptions::new().full_walk(true).keep_local_items(true),
+            &mut |_| SyncConflictResolution::MostRecentMtime,
+        )
+    }
+
+    // Same as sync_from_other_store, but allows to adjust the sync run using the given
+    // options (see SyncOptions), e.g. to skip sub-trees for this sync only.
+    pub fn sync_from_other_store_with_options<F>(
+        &self,
+        from_other: &Self,
+        path: &RelativePath,
+        options: &SyncOptions,
+        sync_conflict: &mut F,
+    ) -> Result<SyncResult>
+    where
+        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+    {
+        self.sync_from_other_store_with_progress(
+            from_other,
+            path,
+            options,
+            sync_conflict,
+            &mut |_| (),
+        )
+    }
+
+    // Same as sync_from_other_store_with_options, but reports each transferred file to the
+    // progress listener (see SyncProgressEvent). The listener does not influence the sync.
+    pub fn sync_from_other_store_with_progress<F, P>(
+        &self,
+        from_other: &Self,
+        path: &RelativePath,
+        options: &SyncOptions,
+        sync_conflict: &mut F,
+        progress: &mut P,
+    ) -> Result<SyncResult>
     where
         F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+        P: FnMut(SyncProgressEvent),
     {
+        // Fail early (before touching any data) if we would sync with ourselves or the stores
+        // do not belong to the same data set.
+        self.check_distinct_data_stores(from_other)?;
+        self.check_matching_data_set(from_other)?;
+        self.fs_access.check_content_access()?;
+        from_other.fs_access.check_content_access()?;
+        let _guard = self.lock_subtree(path)?;
+
         // Step 0) Handshake so both stores know about the same data_stores and can map their
         //         data base ID's to each others local view.
         let (local_mapper, remote_mapper) = self.sync_data_store_lists(&from_other)?;
 
         // Perform Actual Synchronization
-        self.sync_from_other_store_recursive(
+        let mut sync_result = SyncResult::new();
+        let mut synced_items = 0;
+        let fully_synced = match self.sync_from_other_store_recursive(
             &from_other,
             &path,
             &local_mapper,
             &remote_mapper,
+            options,
+            &mut sync_result,
             sync_conflict,
+            &mut |event| {
+                if let SyncProgressEvent::ItemSynced { .. } = event {
+                    synced_items += 1;
+                }
+                progress(event);
+            },
+        ) {
+            Ok(fully_synced) => fully_synced,
+            Err(error) => {
+                // Files downloaded ahead of time (see SyncOptions::parallel_downloads) are
+                // never picked up by the failed sync, do not leave them behind.
+                self.prefetched_files.borrow_mut().clear();
+                if let Err(clean_error) = self.discard_pending_downloads() {
+                    warn!("Cleaning pending files failed: {:?}", clean_error);
+                }
+                return Err(error);
+            }
+        };
+
+        // Remember when we last heard from the other store (wall-clock time for users).
+        let remote_store_name = from_other.db_access.get_local_data_store()?.unique_name;
+        self.db_access
+            .update_last_sync_completed(&remote_store_name, chrono::Utc::now().naive_local())?;
+
+        // Only syncs of the whole store are expected to take up the remote's root sync time.
+        let whole_store = path.is_root()
+            && options.skip.is_empty()
+            && options.only.is_empty()
+            && options.max_depth.is_none()
+            && !options.respect_remote_rules
+            && !options.keep_local_items;
+        if whole_store {
+            self.check_stalled_syncs(
+                &remote_store_name,
+                fully_synced,
+                synced_items,
+                &mut sync_result,
+            )?;
+        }
+
+        Ok(sync_result)
+    }
+
+    /// Makes the sub-tree at the given path a strict mirror of the other store's sub-tree, i.e.
+    /// a one-directional sync where the remote wins all conflicts, followed by deleting all local
+    /// items the remote does not hold (see plan_mirror for a preview of these deletions).
+    ///
+    /// WARNING: This discards independent local changes, including items created only in this
+    ///          store. The deletions are recorded as local modifications and thus propagate to
+    ///          other stores on later syncs. Items the remote ignores are kept, as are locally
+    ///          ignored items (and the folders holding them).
+    pub fn mirror_from(&self, from_other: &Self, path: &RelativePath) -> Result<SyncResult> {
+        let mut sync_result = self.sync_from_other_store(from_other, path, &mut |_| {
+            SyncConflictResolution::ChooseRemoteItem
+        })?;
+
+        let _guard = self.lock_subtree(path)?;
+        let missing_items = self.plan_mirror(from_other, path)?;
+        // Nothing is deleted unless the disk matches the DB for all deleted sub-trees, i.e. we
+        // never delete content the DB (and thus the plan) does not know about.
+        for local_item in &missing_items {
+            self.check_mirror_deletion(local_item)?;
+        }
+        for local_item in &missing_items {
+            let mut deleted_paths = Vec::new();
+            self.delete_mirrored_item(local_item, &mut deleted_paths)?;
+            for deleted_path in deleted_paths {
+                sync_result.touched_paths.insert(deleted_path.clone());
+                sync_result.mirrored_deletions.push(deleted_path);
+            }
+        }
+
+        Ok(sync_result)
+    }
+
+    /// Lists the local items that a mirror from the other store would delete, as the remote does
+    /// not hold them (see mirror_from). Folders are listed without their content.
+    pub fn plan_mirror(&self, from_other: &Self, path: &RelativePath) -> Result<Vec<DBItem>> {
+        self.check_distinct_data_stores(from_other)?;
+        self.check_matching_data_set(from_other)?;
+
+        let mappers = self.sync_data_store_lists(from_other)?;
+        let mut missing_items = Vec::new();
+        let local_item = self.db_access.get_local_data_item(path, false)?;
+        self.collect_items_missing_remotely(from_other, &mappers, &local_item, &mut missing_items)?;
+
+        Ok(missing_items)
+    }
+
+    fn collect_items_missing_remotely(
+        &self,
+        from_other: &Self,
+        mappers: &(DataStoreIDMapper, DataStoreIDMapper),
+        local_item: &DBItem,
+        missing_items: &mut Vec<DBItem>,
+    ) -> Result<()> {
+        if local_item.is_deletion() || local_item.is_ignored() {
+            return Ok(());
+        }
+
+        // An empty sync time never leaves the remote item 'up to date', we get its full content.
+        let (local_mapper, remote_mapper) = mappers;
+        let sync_request = IntSyncRequest {
+            item_path: local_item.path.clone(),
+            item_sync_time: VersionVector::new(),
+            full_walk: true,
+        };
+        let sync_response = from_other
+            .sync_item(sync_request.externalize(local_mapper), remote_mapper)?
+            .internalize(local_mapper);
+
+        match sync_response.action {
+            IntSyncAction::UpdateRequired(IntSyncContent::Deletion(_))
+                if !local_item.path.is_root() =>
+            {
+                missing_items.push(local_item.clone());
+            }
+            IntSyncAction::UpdateRequired(IntSyncContent::Folder(_)) if local_item.is_folder() => {
+                for child_item in self
+                    .db_access
+                    .get_local_child_items(&local_item.path, false)?
+                {
+                    self.collect_items_missing_remotely(
+                        from_other,
+                        mappers,
+                        &child_item,
+                        missing_items,
+                    )?;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    /// Makes sure the disk holds exactly the indexed content of the local item's sub-tree,
+    /// failing with DiskOutOfSync otherwise. Disk items that are not indexed are only
+    /// allowed if they are ignored locally (these are kept by the mirror).
+    fn check_mirror_deletion(&self, local_item: &DBItem) -> Result<()> {
+        if local_item.is_deletion() || local_item.is_ignored() {
+            return Ok(());
+        }
+        if !self.does_disk_item_match_db_item(local_item, false)? {
+            return Err(DataStoreError::DiskOutOfSync {
+                path: local_item.path.clone(),
+            });
+        }
+
+        if local_item.is_folder() && self.disk_item_exists(&local_item.path)? {
+            let child_items = self
+                .db_access
+                .get_local_child_items(&local_item.path, false)?;
+            let indexed_names: HashSet<String> = child_items
+                .iter()
+                .filter(|child_item| !child_item.is_deletion())
+                .map(|child_item| self.fs_access.name_key(child_item.path.name()))
+                .collect();
+            for disk_item in self.fs_access.index(&local_item.path)? {
+                let name_key = self.fs_access.name_key(disk_item.relative_path.name());
+                if !indexed_names.contains(&name_key)
+                    && !self.is_locally_ignored(&disk_item.relative_path)?
+                {
+                    return Err(DataStoreError::DiskOutOfSync {
+                        path: disk_item.relative_path,
+                    });
+                }
+            }
+
+            for child_item in child_items {
+                self.check_mirror_deletion(&child_item)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the local item's sub-tree on disk and in the DB, keeping all ignored items
+    /// (see check_mirror_deletion). Folders that hold kept items stay, their deleted content
+    /// is reported instead of them. Returns if the item was deleted completely.
+    fn delete_mirrored_item(
+        &self,
+        local_item: &DBItem,
+        deleted_paths: &mut Vec<RelativePath>,
+    ) -> Result<bool> {
+        if local_item.is_deletion() {
+            return Ok(true);
+        }
+        if local_item.is_ignored() {
+            return Ok(false);
+        }
+
+        let item_exists = self.disk_item_exists(&local_item.path)?;
+        if local_item.is_folder() {
+            let deleted_before = deleted_paths.len();
+            let mut keeps_items = false;
+            for child_item in self
+                .db_access
+                .get_local_child_items(&local_item.path, false)?
+            {
+                keeps_items |= !self.delete_mirrored_item(&child_item, deleted_paths)?;
+            }
+            // The remaining disk items are not indexed, i.e. ignored (see check_mirror_deletion).
+            keeps_items |= item_exists && !self.fs_access.index(&local_item.path)?.is_empty();
+            if keeps_items {
+                return Ok(false);
+            }
+            deleted_paths.truncate(deleted_before);
+        }
+
+        if item_exists {
+            self.delete_local_item(&local_item.path)?;
+        }
+        self.db_access.delete_local_data_item(&local_item.path)?;
+        deleted_paths.push(local_item.path.clone());
+
+        Ok(true)
+    }
+
+    // Items excluded by the local inclusion rules or by ignore files in the folders above them.
+    fn is_locally_ignored(&self, path: &RelativePath) -> Result<bool> {
+        Ok(!self.includes_item(path) || self.dir_ignore_rules_above(path)?.is_ignored(path))
+    }
+
+    /// Lists all conflicts of syncing the sub-tree at the given path from the other store, e.g.
+    /// to decide on all of them before applying any (see apply_resolutions).
+    /// NOTE: This performs a sync leaving all conflicts unresolved, i.e. changes that do not
+    ///       conflict are applied. The listed paths are exactly the items left to resolve.
+    pub fn find_conflicts(
+        &self,
+        from_other: &Self,
+        path: &RelativePath,
+    ) -> Result<Vec<RelativePath>> {
+        let mut conflicts = Vec::new();
+        self.sync_from_other_store(from_other, path, &mut |event| {
+            conflicts.push(event.local_item().path.clone());
+            SyncConflictResolution::DoNotResolve
+        })?;
+
+        Ok(conflicts)
+    }
+
+    /// Applies the given resolutions for conflicts found by find_conflicts on the same sub-tree
+    /// as a unit, i.e. either all of them are applied or none. Conflicts without a resolution
+    /// (e.g. ones that arose since listing them) fail the batch with MissingConflictResolution.
+    ///
+    /// Like find_conflicts, this first applies all changes that do not conflict. The batch then
+    /// only syncs the conflicting items: their DB changes run in a single transaction and the
+    /// local items are backed up in the pending files dir first. The backups are restored if
+    /// any resolution fails and removed once all resolutions are applied.
+    pub fn apply_resolutions(
+        &self,
+        from_other: &Self,
+        path: &RelativePath,
+        resolutions: Vec<(RelativePath, SyncConflictResolution)>,
+    ) -> Result<SyncResult> {
+        let mut resolutions: HashMap<RelativePath, SyncConflictResolution> = resolutions
+            .into_iter()
+            .map(|(path, resolution)| (path.to_lower_case(), resolution))
+            .collect();
+        if resolutions.is_empty() {
+            return Ok(SyncResult::new());
+        }
+
+        // Every conflict of the sub-tree must be part of the batch.
+        let conflicts = self.find_conflicts(from_other, path)?;
+        for conflict in conflicts.iter() {
+            if !resolutions.contains_key(&conflict.to_lower_case()) {
+                return Err(DataStoreError::MissingConflictResolution {
+                    path: conflict.clone(),
+                });
+            }
+        }
+        // Items inside of other conflicting items are synced together with these.
+        let conflicts: Vec<RelativePath> = conflicts
+            .iter()
+            .filter(|conflict| {
+                !conflicts.iter().any(|other| {
+                    other != *conflict
+                        && conflict
+                            .to_lower_case()
+                            .get_path_components()
+                            .starts_with(other.to_lower_case().get_path_components())
+                })
+            })
+            .cloned()
+            .collect();
+
+        let mut backups = Vec::with_capacity(conflicts.len());
+        for conflict in conflicts.iter() {
+            let backup_path = self.backup_file_path(conflict);
+            if self.disk_item_exists(conflict)? {
+                self.backup_local_item(conflict, &backup_path)?;
+                backups.push((conflict.clone(), Some(backup_path)));
+            } else {
+                backups.push((conflict.clone(), None));
+            }
+        }
+
+        let mut missing_resolution = None;
+        let transaction_result: Result<_> = self.db_access.run_bundled(|| {
+            let mut sync_result = SyncResult::new();
+            for conflict in conflicts.iter() {
+                let conflict_result =
+                    self.sync_from_other_store(from_other, conflict, &mut |event| {
+                        let path = event.local_item().path.clone();
+                        match resolutions.remove(&path.to_lower_case()) {
+                            Some(resolution) => resolution,
+                            None => {
+                                missing_resolution.get_or_insert(path);
+                                SyncConflictResolution::DoNotResolve
+                            }
+                        }
+                    })?;
+                if let Some(path) = missing_resolution.take() {
+                    return Err(DataStoreError::MissingConflictResolution { path });
+                }
+                sync_result = sync_result.combine(&conflict_result);
+            }
+
+            Ok(sync_result)
+        })?;
+
+        match transaction_result {
+            Ok(sync_result) => {
+                for (_, backup_path) in backups {
+                    if let Some(backup_path) = backup_path {
+                        self.delete_local_item(&backup_path)?;
+                    }
+                }
+                Ok(sync_result)
+            }
+            Err(error) => {
+                // Restore as many items as possible, even if single ones fail.
+                let mut restore_error = None;
+                for (path, backup_path) in backups {
+                    if let Err(error) = self.restore_backup(&path, backup_path.as_ref()) {
+                        warn!(
+                            "Could not restore {:?} after a failed batch: {:?}",
+                            path, error
+                        );
+                        restore_error.get_or_insert(error);
+                    }
+                }
+                Err(restore_error.unwrap_or(error))
+            }
+        }
+    }
+
+    // Replaces the local item with its backup (see backup_local_item), removing it if there is
+    // no backup (i.e. the item did not exist before).
+    fn restore_backup(
+        &self,
+        path: &RelativePath,
+        backup_path: Option<&RelativePath>,
+    ) -> Result<()> {
+        if self.disk_item_exists(path)? {
+            self.delete_local_item(path)?;
+        }
+        if let Some(backup_path) = backup_path {
+            self.fs_access.rename_file_or_directory(backup_path, path)?;
+        }
+
+        Ok(())
+    }
+
+    // Copies a local file or folder (with all its content) keeping the files' metadata.
+    fn backup_local_item(&self, path: &RelativePath, backup_path: &RelativePath) -> Result<()> {
+        let metadata = self.fs_access.metadata(path)?;
+        if metadata.is_dir() {
+            self.fs_access.create_dir(backup_path)?;
+            for child_item in self.fs_access.index(path)? {
+                let child_backup_path = backup_path
+                    .clone()
+                    .join_mut(child_item.relative_path.name().to_string());
+                self.backup_local_item(&child_item.relative_path, &child_backup_path)?;
+            }
+        } else {
+            self.copy_local_file(path, backup_path)?;
+            self.fs_access.set_metadata(
+                backup_path,
+                metadata.last_mod_time(),
+                metadata.read_only(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn delete_local_item(&self, path: &RelativePath) -> Result<()> {
+        if self.fs_access.metadata(path)?.is_dir() {
+            self.fs_access.delete_directory(path)?;
+        } else {
+            self.fs_access.delete_file(path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts consecutive syncs from the remote that synced no item, but still did not take up
+    /// the remote's sync time. These never converge, i.e. each further sync walks the same items
+    /// again. Warns about them (and the items likely blocking them) past a threshold.
+    fn check_stalled_syncs(
+        &self,
+        remote_store_name: &str,
+        fully_synced: bool,
+        synced_items: usize,
+        sync_result: &mut SyncResult,
+    ) -> Result<()> {
+        let stalled_syncs = if !fully_synced && synced_items == 0 {
+            self.db_access
+                .get_data_store(remote_store_name)?
+                .map_or(0, |data_store| data_store.stalled_syncs)
+                + 1
+        } else {
+            0
+        };
+        self.db_access
+            .update_stalled_syncs(remote_store_name, stalled_syncs)?;
+
+        if stalled_syncs >= STALLED_SYNCS_WARNING_THRESHOLD {
+            let blocking_paths = [
+                &sync_result.remotely_ignored_items[..],
+                &sync_result.unavailable_items[..],
+            ]
+            .concat();
+            warn!(
+                "The last {} syncs from store '{}' did not converge, likely blocked by: {:?}",
+                stalled_syncs, remote_store_name, blocking_paths
+            );
+            sync_result.stalled_sync_warning = Some(StalledSyncWarning {
+                stalled_syncs,
+                blocking_paths,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Compares the sync and mod vectors of the local and the other store's root folder,
+    /// reporting which store is ahead on which component (rendered with data store names).
+    /// Performs the regular handshake, i.e. both stores learn about each others data stores.
+    pub fn divergence(&self, other: &Self) -> Result<DivergenceReport> {
+        self.check_matching_data_set(other)?;
+        let (local_mapper, remote_mapper) = self.sync_data_store_lists(other)?;
+
+        // Request the root WITHOUT any sync time, i.e. the other store answers with its
+        // mod time (unless it never saw any change at all).
+        let root_path = RelativePath::from_path("");
+        let local_item = self.db_access.get_local_data_item(&root_path, true)?;
+        let sync_request = IntSyncRequest {
+            item_path: root_path,
+            item_sync_time: VersionVector::new(),
+            full_walk: false,
+        };
+        let sync_response = other
+            .sync_item(sync_request.externalize(&local_mapper), &remote_mapper)?
+            .internalize(&local_mapper);
+        let remote_mod_time = match sync_response.action {
+            IntSyncAction::UpdateRequired(IntSyncContent::Folder(content)) => content.mod_time,
+            _ => VersionVector::new(),
+        };
+
+        let mut report = DivergenceReport::new();
+        for data_store in self.db_access.get_data_stores()? {
+            report.entries.push(DivergenceEntry {
+                local_sync_time: local_item.sync_time[&data_store.id],
+                remote_sync_time: sync_response.sync_time[&data_store.id],
+                local_mod_time: local_item.mod_time()[&data_store.id],
+                remote_mod_time: remote_mod_time[&data_store.id],
+
+                data_store_name: data_store.unique_name,
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Lists the inclusion/ignore rules that are only used by the local or only by the other
+    /// store. Performs the regular handshake, i.e. both stores learn about each others data stores.
+    pub fn compare_inclusion_rules(&self, other: &Self) -> Result<RuleDiff> {
+        self.check_matching_data_set(other)?;
+        self.sync_data_store_lists(other)?;
+
+        let local_rules: Vec<_> = self.local_inclusion_rules.iter().cloned().collect();
+        let remote_rules: Vec<_> = other.local_inclusion_rules.iter().cloned().collect();
+
+        Ok(RuleDiff::new(&local_rules, &remote_rules))
+    }
+
+    /// Estimates the number of bytes a sync from the other store would download, i.e. the size
+    /// of all files on the other store that we do not hold in the same version and that are
+    /// not excluded by our inclusion rules. Performs the regular handshake.
+    /// This is an upper bound, e.g. files that are later re-created as hard links are counted.
+    pub fn estimate_sync_size(&self, from_other: &Self) -> Result<u64> {
+        self.check_matching_data_set(from_other)?;
+        let mappers = self.sync_data_store_lists(from_other)?;
+
+        let mut required_space = 0;
+        self.walk_remote_files(
+            from_other,
+            &mappers,
+            &RelativePath::from_path(""),
+            true,
+            &mut |path, content| {
+                if self
+                    .local_inclusion_rules
+                    .is_included(&path.to_lower_case())
+                    && !self.holds_file(path, &content.fs_metadata.hash)?
+                {
+                    required_space += content.fs_metadata.size;
+                }
+                Ok(())
+            },
+        )?;
+
+        Ok(required_space)
+    }
+
+    /// Makes sure that a sync from the other store fits onto the local volume, keeping at least
+    /// min_free bytes available. Should be run before the sync, as a full disk fails it midway.
+    /// Returns the estimated number of bytes required by the sync.
+    pub fn check_free_space(&self, from_other: &Self, min_free: u64) -> Result<u64> {
+        let required = self.estimate_sync_size(from_other)?;
+        let available = self.fs_access.available_space()?;
+        if required.saturating_add(min_free) > available {
+            return Err(DataStoreError::InsufficientSpace {
+                required: required.saturating_add(min_free),
+                available,
+            });
+        }
+
+        Ok(required)
+    }
+
+    /// Restores a locally deleted file from the other store, i.e. 'undoes' the deletion.
+    ///
+    /// Only files the other store still holds in a version created before the local deletion
+    /// can be restored, otherwise NothingToUndelete is returned. Items that are not deleted
+    /// locally are refused with NotDeleted.
+    /// Unlike choosing the remote item on a LocalDeletionRemoteFile conflict, the restored file
+    /// is recorded as a NEW local modification. Stores that already received our deletion know
+    /// a newer state than the remote's file and would otherwise never get the file back.
+    #[allow(clippy::neg_cmp_op_on_partial_ord)] // Version vectors are only partially ordered.
+    pub fn undelete(&self, from_other: &Self, path: &RelativePath) -> Result<()> {
+        let local_item = self.db_access.get_local_data_item(path, true)?;
+        if !local_item.is_deletion() {
+            return Err(DataStoreError::NotDeleted { path: path.clone() });
+        }
+        if !self.does_disk_item_match_db_item(&local_item, true)? {
+            return Err(DataStoreError::DiskOutOfSync { path: path.clone() });
+        }
+
+        // Request the item WITHOUT any sync time, i.e. the other store always answers with
+        // its full content (the item is most likely 'up to date' from the remote's view).
+        let (local_mapper, remote_mapper) = self.sync_data_store_lists(from_other)?;
+        let sync_request = IntSyncRequest {
+            item_path: path.clone(),
+            item_sync_time: VersionVector::new(),
+            full_walk: false,
+        };
+        let sync_request = sync_request.externalize(&local_mapper);
+        let sync_response = from_other
+            .sync_item(sync_request, &remote_mapper)?
+            .internalize(&local_mapper);
+
+        let remote_sync_time = sync_response.sync_time;
+        let sync_content = match sync_response.action {
+            IntSyncAction::UpdateRequired(IntSyncContent::File(content)) => content,
+            _ => return Err(DataStoreError::NothingToUndelete),
+        };
+        if !(sync_content.creation_time <= local_item.sync_time) {
+            // The remote file is unknown to us, i.e. a normal sync will deliver it.
+            return Err(DataStoreError::NothingToUndelete);
+        }
+
+        // Download the file and move it into place...
+        let remote_path = path
+            .parent()
+            .join_mut(sync_content.fs_metadata.case_sensitive_name.clone());
+        let tmp_file_path = self
+            .retry_policy
+            .run(|| self.download_file(from_other, path))?;
+        let mod_time = sync_content.fs_metadata.mod_time.and_utc();
+        self.fs_access.set_metadata(
+            &tmp_file_path,
+            FileTime::from_unix_time(mod_time.timestamp(), mod_time.timestamp_subsec_nanos()),
+            sync_content.fs_metadata.is_read_only,
         )?;
+        self.retry_policy.run(|| {
+            Ok(self
+                .fs_access
+                .rename_file_or_directory(&tmp_file_path, &remote_path)?)
+        })?;
 
-        Ok(())
+        // ...and record it as a local change that also includes the remote's knowledge on it.
+        // Both updates happen as a unit, i.e. the restored item never lacks the remote's state.
+        let transaction_result: Result<_> = self.db_access.run_bundled(|| {
+            self.db_access.update_local_data_item(
+                &remote_path,
+                sync_content.fs_metadata.creation_time,
+                sync_content.fs_metadata.mod_time,
+                true,
+                &sync_content.fs_metadata.hash,
+                sync_content.fs_metadata.is_read_only,
+                sync_content.fs_metadata.hard_link_group.as_deref(),
+                sync_content.fs_metadata.size,
+            )?;
+            let restored_item = self.db_access.get_local_data_item(&remote_path, true)?;
+            self.increase_item_sync_time(
+                restored_item,
+                remote_sync_time.clone(),
+                &mut SyncResult::new(),
+            )?;
+            Ok(())
+        })?;
+
+        transaction_result
     }
 
     // Queries all 'newer' significant sync time info known by the other store.
@@ -523,10 +2774,11 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
 
         let mut transferred_vectors = 0;
         for other_store in from_other.db_access.get_data_stores()? {
-            let local_store = self
-                .db_access
-                .get_data_store(&other_store.unique_name)?
-                .unwrap(); // We did the handshake earlier. This 'could' be a timed race, fix later.
+            // We did the handshake earlier, only stores we rejected to learn about are unknown.
+            let local_store = match self.db_access.get_data_store(&other_store.unique_name)? {
+                Some(local_store) => local_store,
+                None => continue,
+            };
             if local_store.time < other_store.time {
                 // Other store has something changed. Copy it to our DB.
                 // TODO: Copy all relevant properties (also human name, path, description, ...)
@@ -557,15 +2809,531 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         Ok(transferred_vectors)
     }
 
+    /// Plans which files this transfer store fetches from the other store to carry them to its
+    /// destinations, i.e. all files not yet held locally that at least one destination requires
+    /// (see get_significant_sync_times_from_other). Performs the regular handshake and walks
+    /// the other store's files through the sync protocol.
+    pub fn plan_transfer(&self, from_other: &Self) -> Result<TransferPlan> {
+        self.check_matching_data_set(from_other)?;
+        let mappers = self.sync_data_store_lists(from_other)?;
+
+        let mut plan = TransferPlan::new(&from_other.local_data_store_name()?);
+        let root_path = RelativePath::from_path("");
+        self.walk_remote_files(
+            from_other,
+            &mappers,
+            &root_path,
+            false,
+            &mut |path, content| {
+                let hash = content.fs_metadata.hash;
+                if !self.holds_file(path, &hash)?
+                    && !self.should_ignore_item(&path.to_lower_case(), &content.last_mod_time)?
+                {
+                    plan.files.push((path.clone(), hash));
+                }
+
+                Ok(())
+            },
+        )?;
+
+        Ok(plan)
+    }
+
+    /// Fetches the files of the transfer plan (see plan_transfer) from the other store.
+    /// The plan is persisted before fetching, i.e. if the carry is interrupted (e.g. by a crash)
+    /// the next call resumes with exactly the remaining files of the plan instead of
+    /// re-planning. A plan left over for a different source store is replaced.
+    ///
+    /// Returns the plan of the files fetched by this call.
+    pub fn carry_from_other_store<F>(
+        &self,
+        from_other: &Self,
+        sync_conflict: &mut F,
+    ) -> Result<TransferPlan>
+    where
+        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+    {
+        if !self.db_access.is_transfer_store()? {
+            return Err(DataStoreError::OnlyTransferStoresCarryData);
+        }
+
+        let source_store = from_other.local_data_store_name()?;
+        let persisted_plan = self
+            .fs_access
+            .read_transfer_plan()?
+            .and_then(|content| TransferPlan::parse(&content));
+        let mut plan = match persisted_plan {
+            Some(plan) if plan.source_store == source_store => plan,
+            _ => {
+                let plan = self.plan_transfer(from_other)?;
+                self.fs_access.write_transfer_plan(&plan.serialize())?;
+                plan
+            }
+        };
+
+        // Files fetched before an interruption are done already.
+        let mut remaining_files = Vec::with_capacity(plan.files.len());
+        for (path, hash) in plan.files {
+            if !self.holds_file(&path, &hash)? {
+                remaining_files.push((path, hash));
+            }
+        }
+        plan.files = remaining_files;
+
+        if !plan.files.is_empty() {
+            let options = SyncOptions::new().only(plan.paths());
+            self.sync_from_other_store_with_options(
+                from_other,
+                &RelativePath::from_path(""),
+                &options,
+                sync_conflict,
+            )?;
+        }
+        self.fs_access.clear_transfer_plan()?;
+
+        Ok(plan)
+    }
+
+    // Walks the sub-tree at the given path of the other store through the sync protocol (like a
+    // sync, but without changing anything), calling func for every file the other store sends.
+    // With only_changes, sub-trees the other store reports as up to date are skipped.
+    fn walk_remote_files<F>(
+        &self,
+        from_other: &Self,
+        mappers: &(DataStoreIDMapper, DataStoreIDMapper),
+        path: &RelativePath,
+        only_changes: bool,
+        func: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(&RelativePath, IntFileSyncContent) -> Result<()>,
+    {
+        let (local_mapper, remote_mapper) = mappers;
+        let item_sync_time = if only_changes {
+            self.db_access.get_local_data_item(path, true)?.sync_time
+        } else {
+            VersionVector::new()
+        };
+        let sync_request = IntSyncRequest {
+            item_path: path.clone(),
+            item_sync_time,
+            full_walk: !only_changes,
+        };
+        let sync_response = from_other
+            .sync_item(sync_request.externalize(local_mapper), remote_mapper)?
+            .internalize(local_mapper);
+
+        match sync_response.action {
+            IntSyncAction::UpdateRequired(IntSyncContent::File(content)) => func(path, content)?,
+            IntSyncAction::UpdateRequired(IntSyncContent::Folder(content)) => {
+                for child_name in content.child_items {
+                    Self::check_remote_name(&child_name)?;
+                    let child_path = path.join(child_name);
+                    if !self.fs_access.is_internal_path(&child_path) {
+                        self.walk_remote_files(
+                            from_other,
+                            mappers,
+                            &child_path,
+                            only_changes,
+                            func,
+                        )?;
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    // Files without a known hash (e.g. replicas without content) are never held.
+    fn holds_file(&self, path: &RelativePath, hash: &str) -> Result<bool> {
+        let local_item = self.db_access.get_local_data_item(path, false)?;
+        Ok(local_item.is_file()
+            && !hash.is_empty()
+            && self.file_hash(&local_item.path, &local_item.metadata().hash)? == hash)
+    }
+
+    /// Returns the content hash of the file at the given path with the given indexed hash,
+    /// hashing it first if the index only holds a placeholder (stores trusting modification
+    /// times, see set_trust_mtime).
+    /// Stores holding only part of their content can not hash, their placeholders stay empty.
+    fn file_hash(&self, path: &RelativePath, indexed_hash: &str) -> Result<String> {
+        if !indexed_hash.is_empty()
+            || !self.db_access.is_trust_mtime()?
+            || self.db_access.is_partial_content()?
+        {
+            return Ok(indexed_hash.to_string());
+        }
+
+        let hash = self.fs_access.calculate_hash(path)?;
+        self.db_access.update_local_data_item_hash(path, &hash)?;
+        Ok(hash)
+    }
+
+    // Hashes all files the index only holds placeholder hashes for (see file_hash), e.g. before
+    // working with the content hashes of all files.
+    fn hash_placeholder_files(&self) -> Result<()> {
+        if !self.db_access.is_trust_mtime()? {
+            return Ok(());
+        }
+
+        let mut placeholder_files = Vec::new();
+        self.db_access.for_each_item(|item| {
+            if item.is_file() && item.metadata().hash.is_empty() {
+                placeholder_files.push(item.path.clone());
+            }
+        })?;
+        for path in placeholder_files {
+            self.file_hash(&path, "")?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists all items currently in the ignored state, e.g. to verify the inclusion rules.
+    pub fn ignored_items(&self) -> Result<Vec<DBItem>> {
+        Ok(self.db_access.get_ignored_items()?)
+    }
+
+    /// Groups all indexed files with identical content (same hash and size) by their hash, e.g.
+    /// to find duplicate photos. Largest files come first, files that are already hard linked
+    /// to each other are only listed once. Files not hashed yet are hashed first.
+    pub fn find_duplicates(&self) -> Result<Vec<(String, Vec<RelativePath>)>> {
+        self.hash_placeholder_files()?;
+        let mut duplicates = self.db_access.get_duplicate_files()?;
+        // The DB only knows lower case paths, report the names as they are on disk.
+        for (_, paths) in duplicates.iter_mut() {
+            for path in paths.iter_mut() {
+                *path = self.db_access.get_local_data_item(path, false)?.path;
+            }
+        }
+
+        Ok(duplicates)
+    }
+
+    /// Replaces duplicate files (see find_duplicates) by hard links to the first file of their
+    /// group. Returns the number of replaced files.
+    /// Files changed since the last scan are left as they are. Linked files are recorded as
+    /// local modifications, so other stores learn about the hard link group (their content
+    /// does not change). Fails if the file system does not support hard links.
+    pub fn hard_link_duplicates(&self) -> Result<usize> {
+        let _guard = self.lock_subtree(&RelativePath::from_path(""))?;
+
+        let mut linked_files = 0;
+        for (hash, paths) in self.find_duplicates()? {
+            let source_item = self.db_access.get_local_data_item(&paths[0], false)?;
+            if !self.does_disk_item_match_db_item(&source_item, false)? {
+                continue;
+            }
+
+            let mut linked_group_files = 0;
+            for path in &paths[1..] {
+                let item = self.db_access.get_local_data_item(path, false)?;
+                // Hard links share their permissions, i.e. they must agree on them.
+                if item.metadata().is_read_only != source_item.metadata().is_read_only
+                    || !self.does_disk_item_match_db_item(&item, false)?
+                {
+                    continue;
+                }
+
+                // Link into the pending dir first, the duplicate stays intact if linking fails.
+                let pending_path = self.pending_file_path(path);
+                self.fs_access
+                    .create_hard_link(&source_item.path, &pending_path)?;
+                self.fs_access.delete_file(&item.path)?;
+                self.fs_access
+                    .rename_file_or_directory(&pending_path, &item.path)?;
+                self.update_db_item(&self.disk_data_item(&item.path)?, &hash)?;
+                linked_group_files += 1;
+            }
+            if linked_group_files > 0 {
+                self.update_db_item(&self.disk_data_item(&source_item.path)?, &hash)?;
+                linked_files += linked_group_files;
+            }
+        }
+
+        Ok(linked_files)
+    }
+
+    /// Reports whether the file at the given path is read-only (as recorded in the index).
+    pub fn is_read_only(&self, path: &RelativePath) -> Result<bool> {
+        let item = self.db_access.get_local_data_item(path, false)?;
+        if !item.is_file() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Only existing files have a read-only bit!",
+            });
+        }
+
+        Ok(item.metadata().is_read_only)
+    }
+
+    /// Sets or clears the read-only bit of the file at the given path. The change is applied on
+    /// disk and recorded as a local modification, i.e. it propagates on the next sync and the
+    /// next scan does not detect it as a change.
+    /// Fails with DiskOutOfSync if the file changed since the last scan.
+    pub fn set_read_only(&self, path: &RelativePath, read_only: bool) -> Result<()> {
+        let _guard = self.lock_subtree(path)?;
+
+        let item = self.db_access.get_local_data_item(path, false)?;
+        if !item.is_file() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Only existing files have a read-only bit!",
+            });
+        }
+        if !self.does_disk_item_match_db_item(&item, false)? {
+            return Err(DataStoreError::DiskOutOfSync {
+                path: item.path.clone(),
+            });
+        }
+        if item.metadata().is_read_only == read_only {
+            return Ok(());
+        }
+
+        let fs_metadata = self.fs_access.metadata(&item.path)?;
+        self.fs_access
+            .set_metadata(&item.path, fs_metadata.last_mod_time(), read_only)?;
+        let hash = item.metadata().hash.clone();
+        self.update_db_item(&self.disk_data_item(&item.path)?, &hash)?;
+
+        Ok(())
+    }
+
+    /// Summarizes the sub-tree at the given path from the index (file/folder counts, total size
+    /// and its largest files). Ignored and deleted items are left out.
+    pub fn tree_summary(&self, path: &RelativePath) -> Result<TreeSummary> {
+        let mut summary = self
+            .db_access
+            .get_tree_summary(path, LARGEST_FILES_IN_SUMMARY)?;
+        // The DB only knows lower case paths, report the names as they are on disk.
+        for (file_path, _) in summary.largest_files.iter_mut() {
+            *file_path = self.db_access.get_local_data_item(file_path, false)?.path;
+        }
+
+        Ok(summary)
+    }
+
+    /// Computes a deterministic hash over the sub-tree at the given path, e.g. to verify that two
+    /// stores hold the same content after a sync (see verify_tree_hash).
+    ///
+    /// Files contribute the hash of their content as currently found on disk (NOT the indexed
+    /// one), i.e. corrupted files are detected. A folder's hash is the SHA-256 over the lines
+    /// 'name<TAB>type<TAB>hash' of all its children, sorted by name. The result thus does not
+    /// depend on the order items are listed in. Ignored and deleted items are left out.
+    /// Only compare hashes of stores using the same hash algorithm for their contents.
+    pub fn compute_tree_hash(&self, path: &RelativePath) -> Result<String> {
+        let item = self.db_access.get_local_data_item(path, false)?;
+        if item.is_deletion() || item.is_ignored() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Can not compute the tree hash of a deleted or ignored item!",
+            });
+        }
+
+        self.tree_hash_of(&item)
+    }
+    fn tree_hash_of(&self, item: &DBItem) -> Result<String> {
+        use data_encoding::HEXUPPER;
+        use ring::digest::{Context, SHA256};
+
+        if item.is_file() {
+            return Ok(self.fs_access.calculate_hash(&item.path)?);
+        }
+
+        let mut child_lines = Vec::new();
+        for child_item in self.db_access.get_local_child_items(&item.path, false)? {
+            if child_item.is_deletion() || child_item.is_ignored() {
+                continue;
+            }
+
+            let child_type = if child_item.is_file() { "file" } else { "dir" };
+            child_lines.push(format!(
+                "{}\t{}\t{}\n",
+                child_item.path.name(),
+                child_type,
+                self.tree_hash_of(&child_item)?
+            ));
+        }
+        child_lines.sort();
+
+        let mut context = Context::new(&SHA256);
+        for child_line in child_lines {
+            context.update(child_line.as_bytes());
+        }
+        Ok(HEXUPPER.encode(context.finish().as_ref()))
+    }
+
+    /// Compares the tree hashes (see compute_tree_hash) of the sub-tree at the given path in
+    /// this and the other store, e.g. after syncing it. Fails with TreeHashMismatch if they
+    /// differ, i.e. if the stores do not hold the exact same content.
+    pub fn verify_tree_hash(&self, other: &Self, path: &RelativePath) -> Result<()> {
+        let local_hash = self.compute_tree_hash(path)?;
+        let remote_hash = other.compute_tree_hash(path)?;
+        if local_hash != remote_hash {
+            return Err(DataStoreError::TreeHashMismatch {
+                path: path.clone(),
+                local_hash,
+                remote_hash,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reports which data store last modified the item at the given path. Folders report the
+    /// latest modification (by the files' modification dates) among their sub-tree and only
+    /// fall back to the folder itself if it holds no files.
+    pub fn last_modified_by(&self, path: &RelativePath) -> Result<LastModification> {
+        let local_item = self.db_access.get_local_data_item(path, true)?;
+        if local_item.is_deletion() || local_item.is_ignored() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Can not query the last modification of a deleted or ignored item!",
+            });
+        }
+
+        let mut latest_item = None;
+        if local_item.is_folder() {
+            self.find_latest_modified_child(&local_item.path, &mut latest_item)?;
+        }
+        let latest_item = latest_item.unwrap_or(local_item);
+
+        let modifying_store = self
+            .db_access
+            .get_data_stores()?
+            .into_iter()
+            .find(|data_store| data_store.id == latest_item.last_mod_store_id())
+            .ok_or(DataStoreError::UnexpectedState {
+                source: "Item is attributed to an unknown data store!",
+            })?;
+
+        Ok(LastModification {
+            path: latest_item.path.clone(),
+            data_store_name: modifying_store.unique_name,
+            data_store_human_name: modifying_store.human_name,
+            data_store_time: latest_item.last_mod_store_time(),
+        })
+    }
+    fn find_latest_modified_child(
+        &self,
+        dir_path: &RelativePath,
+        latest_item: &mut Option<DBItem>,
+    ) -> Result<()> {
+        for child_item in self.db_access.get_local_child_items(dir_path, true)? {
+            if child_item.is_deletion() || child_item.is_ignored() {
+                continue;
+            }
+
+            if child_item.is_folder() {
+                self.find_latest_modified_child(&child_item.path, latest_item)?;
+            } else if latest_item.as_ref().is_none_or(|latest_item| {
+                latest_item.metadata().mod_time < child_item.metadata().mod_time
+            }) {
+                *latest_item = Some(child_item);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists the unique names of all stores that do not yet hold the latest changes of the
+    /// item at the given path, i.e. whose (known) sync time does not cover the item's mod time.
+    /// Only stores including the item are considered, which requires their status to be
+    /// learned first (see get_significant_sync_times_from_other).
+    #[allow(clippy::neg_cmp_op_on_partial_ord)] // Version vectors are only partially ordered.
+    pub fn stores_missing(&self, path: &RelativePath) -> Result<Vec<String>> {
+        let local_item = self.db_access.get_local_data_item(path, true)?;
+        if local_item.is_deletion() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Can not query which stores miss a deleted item!",
+            });
+        }
+
+        let mut missing_stores = Vec::new();
+        for data_store in self.db_access.get_data_stores()? {
+            if data_store.is_this_store {
+                continue;
+            }
+
+            let mut store_inclusion_rules = InclusionRules::new(&data_store);
+            store_inclusion_rules.load_from_db(&self.db_access)?;
+            if !store_inclusion_rules.is_included(&path.to_lower_case()) {
+                continue;
+            }
+            let store_sync_time = self.db_access.find_sync_time(&data_store, path)?;
+            if !(local_item.mod_time() <= &store_sync_time) {
+                missing_stores.push(data_store.unique_name);
+            }
+        }
+
+        Ok(missing_stores)
+    }
+
+    /// Reports the version vectors of the item at the given path and, for every known store,
+    /// whether it holds the item's current version. Like stores_missing this relies on the
+    /// sync times learned from other stores (see get_significant_sync_times_from_other).
+    pub fn item_replication_state(&self, path: &RelativePath) -> Result<ItemReplicationState> {
+        let local_item = self.db_access.get_local_data_item(path, true)?;
+        if local_item.is_deletion() {
+            return Err(DataStoreError::UnexpectedState {
+                source: "Can not query the replication state of a deleted item!",
+            });
+        }
+
+        let data_stores = self.db_access.get_data_stores()?;
+        let store_names: HashMap<i64, String> = data_stores
+            .iter()
+            .map(|data_store| (data_store.id, data_store.unique_name.clone()))
+            .collect();
+        let by_store_name = |vector: &VersionVector<i64>| {
+            VersionVector::from_pairs(
+                vector
+                    .iter()
+                    .filter_map(|(id, time)| store_names.get(id).map(|name| (name.clone(), *time))),
+            )
+        };
+
+        let mut stores = Vec::with_capacity(data_stores.len());
+        for data_store in data_stores.iter() {
+            let (is_included, has_current_version) = if data_store.is_this_store {
+                (!local_item.is_ignored(), !local_item.is_ignored())
+            } else {
+                let mut store_inclusion_rules = InclusionRules::new(data_store);
+                store_inclusion_rules.load_from_db(&self.db_access)?;
+                if store_inclusion_rules.is_included(&path.to_lower_case()) {
+                    let store_sync_time = self.db_access.find_sync_time(data_store, path)?;
+                    (true, local_item.mod_time() <= &store_sync_time)
+                } else {
+                    (false, false)
+                }
+            };
+
+            stores.push(StoreReplicationState {
+                data_store_name: data_store.unique_name.clone(),
+                data_store_human_name: data_store.human_name.clone(),
+                is_this_store: data_store.is_this_store,
+                is_included,
+                has_current_version,
+            });
+        }
+
+        Ok(ItemReplicationState {
+            path: local_item.path.clone(),
+            mod_time: by_store_name(local_item.mod_time()),
+            sync_time: by_store_name(&local_item.sync_time),
+            stores,
+        })
+    }
+
     /// Cleans out all local items that are no longer required on this transfer store, i.e.
     /// the data was delivered to all targeted peers.
     pub fn clean_transfer_store(&self) -> Result<()> {
         // TODO: Recurse into local data items that are NOT ignored.
         //       Check if we should ignore them. If so, do it and delete disk content.
+        self.fs_access.begin_operation("clean_transfer_store")?;
         let root_item = self
             .db_access
             .get_local_data_item(&RelativePath::from_path(""), true)?;
         self.clean_transfer_store_recursive(&root_item)?;
+        self.fs_access.finish_operation()?;
 
         Ok(())
     }
@@ -607,20 +3375,42 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         Ok(())
     }
 
+    /// Makes sure that both stores hold the same data set, reporting both names otherwise.
+    fn lock_subtree(&self, path: &RelativePath) -> Result<SubtreeGuard> {
+        self.subtree_locks
+            .lock(path)
+            .ok_or_else(|| DataStoreError::SubtreeBusy { path: path.clone() })
+    }
+
+    // Both handles could point to the same store, e.g. if it is opened through a symlink.
+    fn check_distinct_data_stores(&self, other: &Self) -> Result<()> {
+        if self.fs_access.is_same_location(&other.fs_access)
+            || self.local_data_store_name()? == other.local_data_store_name()?
+        {
+            return Err(DataStoreError::SameDataStore);
+        }
+
+        Ok(())
+    }
+
+    fn check_matching_data_set(&self, other: &Self) -> Result<()> {
+        let local_data_set = self.get_data_set()?;
+        let remote_data_set = other.get_data_set()?;
+        if local_data_set.unique_name != remote_data_set.unique_name {
+            return Err(DataStoreError::DataSetMismatch {
+                local_data_set: local_data_set.unique_name,
+                remote_data_set: remote_data_set.unique_name,
+            });
+        }
+
+        Ok(())
+    }
+
     fn sync_data_store_lists(
         &self,
         remote: &Self,
     ) -> Result<(DataStoreIDMapper, DataStoreIDMapper)> {
-        let local_data_set = self.db_access.get_data_set()?;
-        let local_sync_handshake = SyncHandshake {
-            data_set_name: local_data_set.unique_name.clone(),
-            data_stores: self.db_access.get_data_stores()?,
-        };
-        let remote_data_set = remote.get_data_set()?;
-        let remote_sync_handshake = SyncHandshake {
-            data_set_name: remote_data_set.unique_name,
-            data_stores: remote.db_access.get_data_stores()?,
-        };
+        let (local_sync_handshake, remote_sync_handshake) = self.sync_handshakes(remote)?;
 
         let local_response = remote.sync_data_store_list(local_sync_handshake)?;
         let remote_response = self.sync_data_store_list(remote_sync_handshake)?;
@@ -630,17 +3420,63 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         Ok((local_mapper, remote_mapper))
     }
 
-    fn sync_from_other_store_recursive<F>(
+    // Prepares the handshakes of both stores. They only carry the full records of stores the
+    // other side misses, i.e. none at all if both know the same stores (equal digests).
+    fn sync_handshakes(&self, remote: &Self) -> Result<(SyncHandshake, SyncHandshake)> {
+        let local_data_stores = self.db_access.get_data_stores()?;
+        let mut local_sync_handshake = SyncHandshake::new(
+            self.db_access.get_data_set()?.unique_name,
+            &local_data_stores,
+        );
+        let remote_data_stores = remote.db_access.get_data_stores()?;
+        let mut remote_sync_handshake =
+            SyncHandshake::new(remote.get_data_set()?.unique_name, &remote_data_stores);
+
+        if local_sync_handshake.data_stores_digest != remote_sync_handshake.data_stores_digest {
+            local_sync_handshake.data_stores = local_data_stores
+                .into_iter()
+                .filter(|data_store| {
+                    !remote_sync_handshake.knows_data_store(&data_store.unique_name)
+                })
+                .collect();
+            remote_sync_handshake.data_stores = remote_data_stores
+                .into_iter()
+                .filter(|data_store| {
+                    !local_sync_handshake.knows_data_store(&data_store.unique_name)
+                })
+                .collect();
+        }
+
+        Ok((local_sync_handshake, remote_sync_handshake))
+    }
+
+    #[allow(clippy::too_many_arguments)] // The sync state is threaded through the recursion.
+    fn sync_from_other_store_recursive<F, P>(
         &self,
         from_other: &Self,
         path: &RelativePath,
         local_mapper: &DataStoreIDMapper,
         remote_mapper: &DataStoreIDMapper,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
         sync_conflict: &mut F,
+        progress: &mut P,
     ) -> Result<bool>
     where
         F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+        P: FnMut(SyncProgressEvent),
     {
+        // Skipped sub-trees are left as they are, i.e. their parents keep their sync times.
+        // The same holds for items the remote ignores itself (if asked to respect its rules),
+        // we must not record them as ignored, as this would spread as our own ignore notice.
+        if Self::is_skipped(path, options)
+            || self.fs_access.is_internal_path(path)
+            || (options.respect_remote_rules && !from_other.includes_item(path))
+        {
+            return Ok(false);
+        }
+        sync_result.visited_items += 1;
+
         // In case of a transfer store, we want to re-transfer ignored items
         // if there is an update for them. Resetting them does this for us.
         // Note: The current 'just delete' is somewhat in-efficient (we always check the ignore
@@ -651,6 +3487,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             let should_ignore_item = self.should_ignore_item(&path, &local_item.mod_time())?;
             if !should_ignore_item {
                 self.db_access.reset_local_data_item(&path)?;
+                sync_result.touched_paths.insert(local_item.path.clone());
                 local_item = self.db_access.get_local_data_item(&path, true)?;
             }
         }
@@ -660,13 +3497,34 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             .clone()
             .parent_mut()
             .join_mut(local_item.path.name().to_owned());
-        let sync_request = IntSyncRequest {
-            item_path: path.clone(),
-            item_sync_time: local_item.sync_time.clone(),
+        // The remote reads its disk to answer (e.g. to check it is in sync with its index).
+        let sync_response = self.retry_policy.run(|| {
+            let sync_request = IntSyncRequest {
+                item_path: path.clone(),
+                item_sync_time: local_item.sync_time.clone(),
+                full_walk: options.full_walk,
+            };
+            from_other.sync_item(sync_request.externalize(local_mapper), remote_mapper)
+        })?;
+        // Versions of stores we rejected to learn about (see set_data_store_approval) can not
+        // be tracked locally, files changed by them are left out. Folders are only left out if
+        // we do not have them yet, otherwise their children are checked individually.
+        let (last_mod_time, creation_time) = match &sync_response.action {
+            ExtSyncAction::UpdateRequired(ExtSyncContent::File(content)) => {
+                (Some(&content.last_mod_time), Some(&content.creation_time))
+            }
+            ExtSyncAction::UpdateRequired(ExtSyncContent::Folder(content))
+                if !local_item.is_folder() =>
+            {
+                (Some(&content.last_mod_time), Some(&content.creation_time))
+            }
+            _ => (None, None),
         };
-        let sync_request = sync_request.externalize(&local_mapper);
-
-        let sync_response = from_other.sync_item(sync_request, &remote_mapper)?;
+        if let (Some(last_mod_time), Some(creation_time)) = (last_mod_time, creation_time) {
+            if !local_mapper.knows_all(last_mod_time) || !local_mapper.knows_all(creation_time) {
+                return Ok(false);
+            }
+        }
         let sync_response = sync_response.internalize(&local_mapper);
 
         // STEP 2) Use the response in combination with our local knowledge to perform the actual
@@ -675,12 +3533,27 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             IntSyncAction::UpToDate => {
                 // If we are up-to-date it is rather simple, we integrate the knowledge that
                 // of the other device on 'how up to date' the directory is and we are done.
-                self.increase_item_sync_time(local_item, sync_response.sync_time)?;
+                // We do NOT descend any further, as nothing below this item can be newer.
+                sync_result.up_to_date_items += 1;
+                self.increase_item_sync_time(local_item, sync_response.sync_time, sync_result)?;
                 Ok(true)
             }
             IntSyncAction::UpdateRequired(sync_content) => {
+                // The remote's answer stays valid if we re-index the item, i.e. the retry only
+                // needs to re-evaluate conflicts against our (now up to date) local item.
                 if !self.does_disk_item_match_db_item(&local_item, true)? {
-                    panic!("Must not sync if disk content is not correctly indexed in DB.");
+                    if !options.reindex_out_of_sync_items {
+                        return Err(DataStoreError::DiskOutOfSync {
+                            path: local_item.path,
+                        });
+                    }
+                    self.reindex_path_unlocked(path)?;
+                    local_item = self.db_access.get_local_data_item(path, true)?;
+                    if !self.does_disk_item_match_db_item(&local_item, true)? {
+                        return Err(DataStoreError::DiskOutOfSync {
+                            path: local_item.path,
+                        });
+                    }
                 }
 
                 match sync_content {
@@ -690,7 +3563,10 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                         localized_path,
                         sync_response.sync_time,
                         content,
+                        options,
+                        sync_result,
                         sync_conflict,
+                        progress,
                     ),
                     IntSyncContent::File(content) => self.sync_file(
                         &from_other,
@@ -698,7 +3574,10 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                         localized_path,
                         sync_response.sync_time,
                         content,
+                        options,
+                        sync_result,
                         sync_conflict,
+                        progress,
                     ),
                     IntSyncContent::Folder(content) => self.sync_folder(
                         &from_other,
@@ -708,7 +3587,10 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                         content,
                         &local_mapper,
                         &remote_mapper,
+                        options,
+                        sync_result,
                         sync_conflict,
+                        progress,
                     ),
                     IntSyncContent::Ignore(content) => self.sync_ignored(
                         &from_other,
@@ -716,6 +3598,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                         localized_path,
                         sync_response.sync_time,
                         content,
+                        sync_result,
                         sync_conflict,
                     ),
                 }
@@ -749,33 +3632,62 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             Ok(true)
         } else {
             let is_included = self.local_inclusion_rules.is_included(&item_path);
-            Ok(!is_included)
+            Ok(!is_included
+                || self
+                    .dir_ignore_rules_above(item_path)?
+                    .is_ignored(item_path))
         }
     }
 
-    fn increase_item_sync_time(&self, item: DBItem, sync_time: VersionVector<i64>) -> Result<()> {
+    fn increase_item_sync_time(
+        &self,
+        item: DBItem,
+        sync_time: VersionVector<i64>,
+        sync_result: &mut SyncResult,
+    ) -> Result<()> {
         let mut target_item = item;
         target_item.sync_time.max(&sync_time);
+        self.write_synced_item(&target_item.path, &target_item, sync_result)
+    }
+
+    // All DB writes of a sync go through here, so the result lists every touched item.
+    fn write_synced_item(
+        &self,
+        localized_path: &RelativePath,
+        target_item: &DBItem,
+        sync_result: &mut SyncResult,
+    ) -> Result<()> {
         self.db_access
-            .sync_local_data_item(&target_item.path, &target_item)?;
+            .sync_local_data_item(localized_path, target_item)?;
+        sync_result.touched_paths.insert(target_item.path.clone());
 
         Ok(())
     }
 
-    fn sync_folder<F>(
+    #[allow(clippy::too_many_arguments)] // The sync state is threaded through the recursion.
+    fn sync_folder<F, P>(
         &self,
         from_other: &Self,
         local_item: DBItem,
         localized_path: RelativePath,
         sync_time: VersionVector<i64>,
-        sync_content: IntFolderSyncContent,
+        mut sync_content: IntFolderSyncContent,
         local_mapper: &DataStoreIDMapper,
         remote_mapper: &DataStoreIDMapper,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
         sync_conflict: &mut F,
+        progress: &mut P,
     ) -> Result<bool>
     where
         F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+        P: FnMut(SyncProgressEvent),
     {
+        // The root folder has no name, every other folder is named by the remote.
+        if !localized_path.is_root() {
+            Self::check_remote_name(&sync_content.fs_metadata.case_sensitive_name)?;
+        }
+        self.keep_local_name_case(&local_item, &mut sync_content.fs_metadata)?;
         let remote_path = localized_path
             .parent()
             .join_mut(sync_content.fs_metadata.case_sensitive_name.clone());
@@ -791,34 +3703,49 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                 // NOTE: This case does not handle all combinations that the other store can know
                 //       about our deletion and return the file to us. We are fine with these cases.
             } else {
-                match sync_conflict(LocalDeletionRemoteFolder(&local_item, &sync_content)) {
+                match Self::resolve_by_mtime(
+                    sync_conflict(LocalDeletionRemoteFolder(&local_item, &sync_content)),
+                    None,
+                    Some(&sync_content.fs_metadata),
+                ) {
                     SyncConflictResolution::ChooseLocalItem => {
-                        self.increase_item_sync_time(local_item, sync_time)?;
+                        self.increase_item_sync_time(local_item, sync_time, sync_result)?;
                         return Ok(true);
                     }
                     SyncConflictResolution::ChooseRemoteItem => {
                         // Do nothing, the 'normal' sync procedure will do.
                     }
-                    SyncConflictResolution::DoNotResolve => {
+                    SyncConflictResolution::DoNotResolve
+                    | SyncConflictResolution::ExternalMerge => {
+                        Self::record_unresolved_conflict(options, sync_result)?;
                         return Ok(false);
                     }
+                    SyncConflictResolution::MostRecentMtime => {
+                        unreachable!("Resolved by mod time!")
+                    }
                 }
             }
         }
         if local_item.is_file() && !(local_item.mod_time() <= &sync_time) {
             // The remote has a new change, but does not know everything about
             // our local changes...
-            match sync_conflict(LocalFileRemoteFolder(&local_item, &sync_content)) {
+            match Self::resolve_by_mtime(
+                sync_conflict(LocalFileRemoteFolder(&local_item, &sync_content)),
+                Self::fs_metadata_of(&local_item),
+                Some(&sync_content.fs_metadata),
+            ) {
                 SyncConflictResolution::ChooseLocalItem => {
-                    self.increase_item_sync_time(local_item, sync_time)?;
+                    self.increase_item_sync_time(local_item, sync_time, sync_result)?;
                     return Ok(true);
                 }
                 SyncConflictResolution::ChooseRemoteItem => {
                     // Do nothing, the 'normal' sync procedure will do.
                 }
-                SyncConflictResolution::DoNotResolve => {
+                SyncConflictResolution::DoNotResolve | SyncConflictResolution::ExternalMerge => {
+                    Self::record_unresolved_conflict(options, sync_result)?;
                     return Ok(false);
                 }
+                SyncConflictResolution::MostRecentMtime => unreachable!("Resolved by mod time!"),
             }
         }
 
@@ -833,8 +3760,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     mod_time: sync_content.mod_time,
                 },
             };
-            self.db_access
-                .sync_local_data_item(&localized_path, &target_item)?;
+            self.write_synced_item(&localized_path, &target_item, sync_result)?;
 
             return Ok(true);
         }
@@ -842,6 +3768,12 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         // Make sure the folder exists.
         // In case it was a file before, it is going to be deleted.
         if local_item.is_file() {
+            let type_change = TypeChange::FileBecameFolder(localized_path.clone());
+            if !options.allow_type_changes {
+                sync_result.refused_type_changes.push(type_change);
+                return Ok(false);
+            }
+            sync_result.type_changes.push(type_change);
             self.fs_access.delete_file(&localized_path)?;
         }
 
@@ -870,40 +3802,84 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     mod_time: VersionVector::new(),
                 },
             };
-            self.db_access
-                .sync_local_data_item(&localized_path, &folder_before_sync)?;
+            self.write_synced_item(&localized_path, &folder_before_sync, sync_result)?;
+            progress(SyncProgressEvent::ItemSynced {
+                path: &remote_path,
+                action: SyncedItemAction::Created,
+            });
         }
 
-        // Recurse into items present on the other store...
-        let mut all_children_synced = true;
+        // Recurse into items present on the other store and also into local items (these
+        // should simply get deleted, but we can optimize this later on after the basic works).
+        // Children on prioritized paths are synced first, all others in the canonical order of
+        // their names (see relative_path::cmp_names).
         let mut visited_items = HashSet::with_capacity(sync_content.child_items.len());
-        for remote_child_item in sync_content.child_items {
-            visited_items.insert(remote_child_item.to_lowercase());
-
-            let child_synced = self.sync_from_other_store_recursive(
-                &from_other,
-                &localized_path.join(remote_child_item),
-                &local_mapper,
-                &remote_mapper,
-                sync_conflict,
-            )?;
-            all_children_synced = all_children_synced && child_synced;
+        let mut child_paths = Vec::with_capacity(sync_content.child_items.len());
+        // Internal paths (e.g. our staging dir) are left out, even if the remote has them.
+        for remote_child_item in sync_content.child_items {
+            Self::check_remote_name(&remote_child_item)?;
+            visited_items.insert(self.fs_access.name_key(&remote_child_item));
+            child_paths.push(localized_path.join(remote_child_item));
         }
-        // ...and also into local items (these should simply get deleted,
-        // but we can optimize this later on after the basic works).
         for local_child in self
             .db_access
             .get_local_child_items(&localized_path, true)?
         {
-            if !visited_items.contains(&local_child.path.name().to_lowercase()) {
+            if !visited_items.contains(&self.fs_access.name_key(local_child.path.name())) {
+                child_paths.push(local_child.path);
+            }
+        }
+        child_paths.retain(|child_path| !self.fs_access.is_internal_path(child_path));
+        child_paths.sort_by(|a, b| self.fs_access.cmp_names(a.name(), b.name()));
+        child_paths.sort_by_key(|child_path| Self::sync_priority(child_path, &options.priority));
+
+        // Files are downloaded in batches of parallel_downloads, all DB and FS updates are
+        // still applied one child after the other (exactly as without prefetching).
+        // A pipelined prefetch downloads the next child while the current one is synced.
+        let mut all_children_synced = true;
+        for (batch_index, batch) in child_paths.chunks(options.parallel_downloads).enumerate() {
+            if options.parallel_downloads > 1 {
+                self.prefetch_files(from_other, batch, local_mapper, remote_mapper, options)?;
+            }
+
+            for (index, child_path) in batch.iter().enumerate() {
+                let next_path =
+                    child_paths.get(batch_index * options.parallel_downloads + index + 1);
+                let background_prefetch = match next_path {
+                    Some(next_path) if options.pipelined_prefetch => self
+                        .start_background_prefetch(
+                            from_other,
+                            next_path,
+                            local_mapper,
+                            remote_mapper,
+                            options,
+                        )?,
+                    _ => None,
+                };
+
                 let child_synced = self.sync_from_other_store_recursive(
-                    &from_other,
-                    &local_child.path,
-                    &local_mapper,
-                    &remote_mapper,
+                    from_other,
+                    child_path,
+                    local_mapper,
+                    remote_mapper,
+                    options,
+                    sync_result,
                     sync_conflict,
+                    progress,
                 )?;
                 all_children_synced = all_children_synced && child_synced;
+
+                // Prefetched files might not be needed in the end (e.g. on conflicts).
+                let pending_path = self
+                    .prefetched_files
+                    .borrow_mut()
+                    .remove(&child_path.to_lower_case());
+                if let Some(pending_path) = pending_path {
+                    self.fs_access.delete_file(&pending_path)?;
+                }
+                if let Some(background_prefetch) = background_prefetch {
+                    self.finish_background_prefetch(background_prefetch)?;
+                }
             }
         }
 
@@ -922,6 +3898,18 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             ),
             false,
         )?;
+        // Folders created or changed by stores we rejected to learn about keep their local
+        // history (see sync_from_other_store_recursive).
+        let (creation_time, last_mod_time) = if sync_content.creation_time.iter().next().is_none()
+            || sync_content.last_mod_time.iter().next().is_none()
+        {
+            (
+                local_item.creation_time().clone(),
+                local_item.last_mod_time().clone(),
+            )
+        } else {
+            (sync_content.creation_time, sync_content.last_mod_time)
+        };
         let folder_after_sync = metadata_db::DBItem {
             path: remote_path,
             // TODO: We are very conservative here. If we can not sync EVERY SINGLE child element,
@@ -936,29 +3924,49 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             },
             content: metadata_db::ItemType::FOLDER {
                 metadata: sync_content.fs_metadata,
-                creation_time: sync_content.creation_time,
-                last_mod_time: sync_content.last_mod_time,
+                creation_time,
+                last_mod_time,
                 mod_time: VersionVector::new(),
             },
         };
-        self.db_access
-            .sync_local_data_item(&localized_path, &folder_after_sync)?;
+        self.write_synced_item(&localized_path, &folder_after_sync, sync_result)?;
 
-        Ok(true)
+        // Parents must not take up the sync time either if we left out some of our children
+        // (e.g. children below the sync's depth limit), as it also covers all their children.
+        Ok(all_children_synced)
+    }
+
+    /// Names of items are sent by the remote store, reject any that would not address a direct
+    /// child of the synced folder (e.g. '..') instead of writing outside of the store.
+    fn check_remote_name(name: &str) -> Result<()> {
+        if relative_path::is_valid_name(name) {
+            Ok(())
+        } else {
+            Err(DataStoreError::InvalidItemName {
+                name: name.to_string(),
+            })
+        }
     }
 
-    fn sync_file<F>(
+    #[allow(clippy::too_many_arguments)] // The sync state is threaded through the recursion.
+    fn sync_file<F, P>(
         &self,
         from_other: &Self,
         local_item: DBItem,
         localized_path: RelativePath,
         sync_time: VersionVector<i64>,
-        sync_content: IntFileSyncContent,
+        mut sync_content: IntFileSyncContent,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
         sync_conflict: &mut F,
+        progress: &mut P,
     ) -> Result<bool>
     where
         F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+        P: FnMut(SyncProgressEvent),
     {
+        Self::check_remote_name(&sync_content.fs_metadata.case_sensitive_name)?;
+        self.keep_local_name_case(&local_item, &mut sync_content.fs_metadata)?;
         let remote_path = localized_path
             .parent()
             .join_mut(sync_content.fs_metadata.case_sensitive_name.clone());
@@ -974,34 +3982,61 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                 // NOTE: This case does not handle all combinations that the other store can know
                 //       about our deletion and return the file to us. We are fine with these cases.
             } else {
-                match sync_conflict(LocalDeletionRemoteFile(&local_item, &sync_content)) {
+                match Self::resolve_by_mtime(
+                    sync_conflict(LocalDeletionRemoteFile(&local_item, &sync_content)),
+                    None,
+                    Some(&sync_content.fs_metadata),
+                ) {
                     SyncConflictResolution::ChooseLocalItem => {
-                        self.increase_item_sync_time(local_item, sync_time)?;
+                        self.increase_item_sync_time(local_item, sync_time, sync_result)?;
                         return Ok(true);
                     }
                     SyncConflictResolution::ChooseRemoteItem => {
                         // Do nothing, the 'normal' sync procedure will do.
                     }
-                    SyncConflictResolution::DoNotResolve => {
+                    SyncConflictResolution::DoNotResolve
+                    | SyncConflictResolution::ExternalMerge => {
+                        Self::record_unresolved_conflict(options, sync_result)?;
                         return Ok(false);
                     }
+                    SyncConflictResolution::MostRecentMtime => {
+                        unreachable!("Resolved by mod time!")
+                    }
                 }
             }
         }
         if !local_item.is_deletion() && !(local_item.mod_time() <= &sync_time) {
             // The remote has a new change, but does not know everything about
             // our local changes...
-            match sync_conflict(LocalItemRemoteFile(&local_item, &sync_content)) {
+            match Self::resolve_by_mtime(
+                sync_conflict(LocalItemRemoteFile(&local_item, &sync_content)),
+                Self::fs_metadata_of(&local_item),
+                Some(&sync_content.fs_metadata),
+            ) {
                 SyncConflictResolution::ChooseLocalItem => {
-                    self.increase_item_sync_time(local_item, sync_time)?;
+                    self.increase_item_sync_time(local_item, sync_time, sync_result)?;
                     return Ok(true);
                 }
                 SyncConflictResolution::ChooseRemoteItem => {
                     // Do nothing, the 'normal' sync procedure will do.
                 }
                 SyncConflictResolution::DoNotResolve => {
+                    Self::record_unresolved_conflict(options, sync_result)?;
                     return Ok(false);
                 }
+                SyncConflictResolution::ExternalMerge => {
+                    return self.merge_file(
+                        from_other,
+                        local_item,
+                        &localized_path,
+                        sync_time,
+                        &sync_content,
+                        options,
+                        sync_result,
+                        progress,
+                    );
+                }
+                SyncConflictResolution::MostRecentMtime => unreachable!("Resolved by mod time!"),
             }
         }
 
@@ -1016,20 +4051,79 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     mod_time: sync_content.last_mod_time,
                 },
             };
-            self.db_access
-                .sync_local_data_item(&localized_path, &target_item)?;
+            self.write_synced_item(&localized_path, &target_item, sync_result)?;
+
+            return Ok(true);
+        }
+
+        // Replacing a folder by a file removes all its content, only do so if allowed.
+        if local_item.is_folder() && !localized_path.is_root() {
+            let type_change = TypeChange::FolderBecameFile(localized_path.clone());
+            if !options.allow_type_changes {
+                sync_result.refused_type_changes.push(type_change);
+                return Ok(false);
+            }
+            sync_result.type_changes.push(type_change);
+        }
+
+        // Only the file's metadata changed (e.g. its read-only bit), apply it without
+        // transferring the content. Hard links share their metadata, they are replaced as usual.
+        if local_item.is_file()
+            && !sync_content.fs_metadata.hash.is_empty()
+            && local_item.metadata().hash == sync_content.fs_metadata.hash
+            && local_item.metadata().size == sync_content.fs_metadata.size
+            && local_item.metadata().case_sensitive_name
+                == sync_content.fs_metadata.case_sensitive_name
+            && local_item.metadata().hard_link_group.is_none()
+        {
+            let mod_time = sync_content.fs_metadata.mod_time.and_utc();
+            self.fs_access.set_metadata(
+                &localized_path,
+                FileTime::from_unix_time(mod_time.timestamp(), mod_time.timestamp_subsec_nanos()),
+                sync_content.fs_metadata.is_read_only,
+            )?;
+            let target_item = metadata_db::DBItem {
+                path: localized_path.clone(),
+                sync_time,
+                content: metadata_db::ItemType::FILE {
+                    metadata: sync_content.fs_metadata,
+                    creation_time: sync_content.creation_time,
+                    last_mod_time: sync_content.last_mod_time,
+                },
+            };
+            self.write_synced_item(&localized_path, &target_item, sync_result)?;
+            progress(SyncProgressEvent::ItemSynced {
+                path: &localized_path,
+                action: SyncedItemAction::Updated,
+            });
 
             return Ok(true);
         }
 
-        // For non ignored content, download the file.
-        let tmp_file_path = self.download_file(&from_other, &localized_path)?;
+        // For non ignored content, download the file (or link it to a local copy).
+        let file_size = sync_content.fs_metadata.size;
+        progress(SyncProgressEvent::FileStarted {
+            path: &remote_path,
+            size: file_size,
+        });
+        let (tmp_file_path, reused_bytes) = match self.download_or_link_file(
+            from_other,
+            &localized_path,
+            &sync_content.fs_metadata,
+        ) {
+            Err(DataStoreError::ContentUnavailable { .. }) => {
+                // Leave the item (and therefore the parent's sync time) as it is,
+                // a later sync picks it up once the remote holds the content.
+                sync_result.unavailable_items.push(remote_path);
+                return Ok(false);
+            }
+            result => result?,
+        };
+        self.verify_downloaded_file(from_other, &tmp_file_path, &remote_path, &sync_content)?;
+        let mod_time = sync_content.fs_metadata.mod_time.and_utc();
         self.fs_access.set_metadata(
             &tmp_file_path,
-            FileTime::from_unix_time(
-                sync_content.fs_metadata.mod_time.timestamp(),
-                sync_content.fs_metadata.mod_time.timestamp_subsec_nanos(),
-            ),
+            FileTime::from_unix_time(mod_time.timestamp(), mod_time.timestamp_subsec_nanos()),
             sync_content.fs_metadata.is_read_only,
         )?;
 
@@ -1045,8 +4139,11 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             metadata_db::ItemType::IGNORED { .. } => (),  // Nothing to do
         }
         // ... move the downloaded file over it.
-        self.fs_access
-            .rename_file_or_directory(&tmp_file_path, &remote_path)?;
+        self.retry_policy.run(|| {
+            Ok(self
+                .fs_access
+                .rename_file_or_directory(&tmp_file_path, &remote_path)?)
+        })?;
 
         // Insert the appropriate file item into our local db.
         let target_item = metadata_db::DBItem {
@@ -1058,29 +4155,299 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                 last_mod_time: sync_content.last_mod_time,
             },
         };
-        self.db_access
-            .sync_local_data_item(&localized_path, &target_item)?;
+        self.write_synced_item(&localized_path, &target_item, sync_result)?;
+        if options.merge_command.is_some() {
+            self.record_merge_base(&localized_path)?;
+        }
+        progress(SyncProgressEvent::ItemSynced {
+            path: &remote_path,
+            action: if local_item.is_file() {
+                SyncedItemAction::Updated
+            } else {
+                SyncedItemAction::Created
+            },
+        });
+
+        sync_result.transferred_files += 1;
+        sync_result.transferred_bytes += file_size - reused_bytes;
+        progress(SyncProgressEvent::FileFinished {
+            path: &remote_path,
+            size: file_size,
+            transferred_files: sync_result.transferred_files,
+            transferred_bytes: sync_result.transferred_bytes,
+        });
+
+        Ok(true)
+    }
+
+    /// Resolves a file conflict with the external merge command of the options. The merged
+    /// result becomes a local change that includes the remote's knowledge, i.e. it dominates
+    /// both versions. Without a recorded merge base for the file, we keep our local version and
+    /// store the remote one next to it (as a new local file).
+    /// Conflicts that can not be merged (no command, no local file, failed merge) stay unresolved.
+    #[allow(clippy::too_many_arguments)] // Merges need the sync state of the conflicting file.
+    fn merge_file<P>(
+        &self,
+        from_other: &Self,
+        local_item: DBItem,
+        localized_path: &RelativePath,
+        sync_time: VersionVector<i64>,
+        sync_content: &IntFileSyncContent,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
+        progress: &mut P,
+    ) -> Result<bool>
+    where
+        P: FnMut(SyncProgressEvent),
+    {
+        let merge_command = match &options.merge_command {
+            Some(merge_command) if local_item.is_file() => merge_command,
+            _ => {
+                Self::record_unresolved_conflict(options, sync_result)?;
+                return Ok(false);
+            }
+        };
+
+        let (remote_version, _) =
+            match self.download_or_link_file(from_other, localized_path, &sync_content.fs_metadata)
+            {
+                Err(DataStoreError::ContentUnavailable { .. }) => {
+                    sync_result.unavailable_items.push(localized_path.clone());
+                    return Ok(false);
+                }
+                result => result?,
+            };
+        self.verify_downloaded_file(from_other, &remote_version, localized_path, sync_content)?;
+
+        let merge_base = self.merge_base_path(localized_path);
+        if !self.disk_item_exists(&merge_base)? {
+            return self.keep_both_files(
+                from_other,
+                local_item,
+                localized_path,
+                sync_time,
+                &remote_version,
+                &sync_content.fs_metadata,
+                options,
+                sync_result,
+                progress,
+            );
+        }
+
+        // The tool works on copies, i.e. a failed merge leaves our local file untouched.
+        let pending_path = self.pending_file_path(localized_path);
+        let local_version = pending_path
+            .parent()
+            .join_mut(format!("{}-local", pending_path.name()));
+        let base_version = pending_path
+            .parent()
+            .join_mut(format!("{}-base", pending_path.name()));
+        self.copy_local_file(localized_path, &local_version)?;
+        self.copy_local_file(&merge_base, &base_version)?;
+        let merged = self.fs_access.run_merge_command(
+            merge_command,
+            &local_version,
+            &base_version,
+            &remote_version,
+        )?;
+        self.fs_access.delete_file(&base_version)?;
+        self.fs_access.delete_file(&remote_version)?;
+        if !merged {
+            self.fs_access.delete_file(&local_version)?;
+            Self::record_unresolved_conflict(options, sync_result)?;
+            return Ok(false);
+        }
+
+        // Move the merged file into place and index it as a local change...
+        self.fs_access.delete_file(localized_path)?;
+        self.retry_policy.run(|| {
+            Ok(self
+                .fs_access
+                .rename_file_or_directory(&local_version, localized_path)?)
+        })?;
+        let merged_item = self.disk_data_item(localized_path)?;
+        let hash = self.fs_access.calculate_hash(localized_path)?;
+        self.update_db_item(&merged_item, &hash)?;
+        self.record_merge_base(localized_path)?;
+
+        // ...that also includes the remote's knowledge on it.
+        let merged_item = self.db_access.get_local_data_item(localized_path, true)?;
+        self.increase_item_sync_time(merged_item, sync_time, sync_result)?;
+        sync_result.merged_files.push(localized_path.clone());
+        progress(SyncProgressEvent::ItemSynced {
+            path: localized_path,
+            action: SyncedItemAction::Updated,
+        });
+
+        Ok(true)
+    }
+
+    // Keeps our local file as it is and stores the downloaded remote version next to it.
+    #[allow(clippy::too_many_arguments)] // Merges need the sync state of the conflicting file.
+    fn keep_both_files<P>(
+        &self,
+        from_other: &Self,
+        local_item: DBItem,
+        localized_path: &RelativePath,
+        sync_time: VersionVector<i64>,
+        remote_version: &RelativePath,
+        remote_metadata: &ItemFSMetadata,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
+        progress: &mut P,
+    ) -> Result<bool>
+    where
+        P: FnMut(SyncProgressEvent),
+    {
+        let copy_path = localized_path.parent().join_mut(format!(
+            "{}{}",
+            localized_path.name(),
+            conflict_copy_suffix(&from_other.local_data_store_desc()?)
+        ));
+        if self.disk_item_exists(&copy_path)?
+            || !self
+                .db_access
+                .get_local_data_item(&copy_path, false)?
+                .is_deletion()
+        {
+            // Do not overwrite anything, an earlier conflict copy must be resolved first.
+            self.fs_access.delete_file(remote_version)?;
+            Self::record_unresolved_conflict(options, sync_result)?;
+            return Ok(false);
+        }
 
+        let mod_time = remote_metadata.mod_time.and_utc();
+        self.fs_access.set_metadata(
+            remote_version,
+            FileTime::from_unix_time(mod_time.timestamp(), mod_time.timestamp_subsec_nanos()),
+            remote_metadata.is_read_only,
+        )?;
+        self.retry_policy.run(|| {
+            Ok(self
+                .fs_access
+                .rename_file_or_directory(remote_version, &copy_path)?)
+        })?;
+        let copy_item = self.disk_data_item(&copy_path)?;
+        let hash = self.fs_access.calculate_hash(&copy_path)?;
+        self.update_db_item(&copy_item, &hash)?;
+        sync_result.touched_paths.insert(copy_path.clone());
+        progress(SyncProgressEvent::ItemSynced {
+            path: &copy_path,
+            action: SyncedItemAction::Created,
+        });
+        sync_result.conflict_copies.push(copy_path);
+
+        self.increase_item_sync_time(local_item, sync_time, sync_result)?;
         Ok(true)
     }
 
-    fn sync_deletion<F>(
+    // Keeps a copy of the file's current content as the base for merging later conflicts.
+    fn record_merge_base(&self, path: &RelativePath) -> Result<()> {
+        self.copy_local_file(path, &self.merge_base_path(path))
+    }
+
+    fn copy_local_file(&self, from: &RelativePath, to: &RelativePath) -> Result<()> {
+        match self.fs_access.create_file(to) {
+            Err(error) if !error.is_io_already_exists() => return Err(error.into()),
+            _ => (),
+        }
+        let content = self.fs_access.read_file(from)?;
+        self.fs_access.write_file(to, content)?;
+
+        Ok(())
+    }
+
+    // Turns a MostRecentMtime resolution into choosing the side with the newer FS mod time.
+    // Sides without metadata (deletions, ignored items) count as older than any existing item.
+    fn resolve_by_mtime(
+        resolution: SyncConflictResolution,
+        local_metadata: Option<&ItemFSMetadata>,
+        remote_metadata: Option<&ItemFSMetadata>,
+    ) -> SyncConflictResolution {
+        match resolution {
+            SyncConflictResolution::MostRecentMtime => {
+                let local_mod_time = local_metadata.map(|metadata| metadata.mod_time);
+                let remote_mod_time = remote_metadata.map(|metadata| metadata.mod_time);
+                if remote_mod_time > local_mod_time {
+                    SyncConflictResolution::ChooseRemoteItem
+                } else {
+                    SyncConflictResolution::ChooseLocalItem
+                }
+            }
+            resolution => resolution,
+        }
+    }
+
+    fn fs_metadata_of(item: &DBItem) -> Option<&ItemFSMetadata> {
+        if item.is_file() || item.is_folder() {
+            Some(item.metadata())
+        } else {
+            None
+        }
+    }
+
+    // Counts a conflict left unresolved, aborting the sync once more are left than allowed.
+    fn record_unresolved_conflict(
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
+    ) -> Result<()> {
+        sync_result.unresolved_conflicts += 1;
+        match options.max_conflicts {
+            Some(max_conflicts) if sync_result.unresolved_conflicts > max_conflicts => {
+                Err(DataStoreError::TooManyConflicts {
+                    count: sync_result.unresolved_conflicts,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // Stores not propagating case changes keep the name of existing local items, i.e. case-only
+    // differences to the remote's name neither rename the item nor change its recorded name.
+    fn keep_local_name_case(
+        &self,
+        local_item: &DBItem,
+        remote_metadata: &mut ItemFSMetadata,
+    ) -> Result<()> {
+        if (local_item.is_file() || local_item.is_folder())
+            && !self.db_access.is_propagate_case_changes()?
+            && Self::names_match(
+                &remote_metadata.case_sensitive_name,
+                local_item.path.name(),
+                false,
+            )
+        {
+            remote_metadata.case_sensitive_name = local_item.path.name().to_owned();
+        }
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)] // The sync state is threaded through the recursion.
+    fn sync_deletion<F, P>(
         &self,
         _from_other: &Self,
         local_item: DBItem,
         localized_path: RelativePath,
         sync_time: VersionVector<i64>,
         sync_content: IntDeletionSyncContent,
+        options: &SyncOptions,
+        sync_result: &mut SyncResult,
         sync_conflict: &mut F,
+        progress: &mut P,
     ) -> Result<bool>
     where
         F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+        P: FnMut(SyncProgressEvent),
     {
         if local_item.is_deletion() {
             // Both agree that the file should be deleted. Ignore any potential
             // conflicts, just settle and be happy that we agree on the state.
-            self.increase_item_sync_time(local_item, sync_time)?;
+            self.increase_item_sync_time(local_item, sync_time, sync_result)?;
             Ok(true)
+        } else if options.keep_local_items && !local_item.is_ignored() {
+            // Keep the local item, without taking up the sync time of the deletion notice.
+            Ok(false)
         } else if local_item.creation_time() <= &sync_time {
             // The remote deletion notice is targeting our local file/folder.
             if !(local_item.mod_time() <= &sync_time) {
@@ -1092,17 +4459,26 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     return Ok(false);
                 } else {
                     // ...we actually have a real conflict. Try to resolve it.
-                    match sync_conflict(LocalItemRemoteDeletion(&local_item, &sync_content)) {
+                    match Self::resolve_by_mtime(
+                        sync_conflict(LocalItemRemoteDeletion(&local_item, &sync_content)),
+                        Self::fs_metadata_of(&local_item),
+                        None,
+                    ) {
                         SyncConflictResolution::ChooseLocalItem => {
-                            self.increase_item_sync_time(local_item, sync_time)?;
+                            self.increase_item_sync_time(local_item, sync_time, sync_result)?;
                             return Ok(true);
                         }
                         SyncConflictResolution::ChooseRemoteItem => {
                             // Do nothing, the 'normal' sync procedure will do.
                         }
-                        SyncConflictResolution::DoNotResolve => {
+                        SyncConflictResolution::DoNotResolve
+                        | SyncConflictResolution::ExternalMerge => {
+                            Self::record_unresolved_conflict(options, sync_result)?;
                             return Ok(false);
                         }
+                        SyncConflictResolution::MostRecentMtime => {
+                            unreachable!("Resolved by mod time!")
+                        }
                     }
                 }
             } else {
@@ -1124,82 +4500,427 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                 path: localized_path.clone(),
                 sync_time: sync_time.clone(),
 
-                content: metadata_db::ItemType::DELETION,
+                content: metadata_db::ItemType::DELETION,
+            };
+            self.write_synced_item(&localized_path, &target_item, sync_result)?;
+            if !local_item.is_ignored() {
+                progress(SyncProgressEvent::ItemSynced {
+                    path: &localized_path,
+                    action: SyncedItemAction::Deleted,
+                });
+            }
+
+            Ok(true)
+        } else {
+            // The deletion notice does not 'target' our file, i.e. it does
+            // not know about our local file, as the local file was created
+            // logically independent of the other copy.
+            // Just do nothing more than take up the target sync time.
+            if !local_item.is_ignored() {
+                sync_result.explanations.push(SyncExplanation {
+                    path: localized_path.clone(),
+                    reason:
+                        "kept because your copy was created independently of the remote deletion",
+                });
+            }
+            self.increase_item_sync_time(local_item, sync_time, sync_result)?;
+            Ok(true)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)] // The sync state is threaded through the recursion.
+    fn sync_ignored<F>(
+        &self,
+        _from_other: &Self,
+        local_item: DBItem,
+        localized_path: RelativePath,
+        sync_time: VersionVector<i64>,
+        sync_content: IntIgnoreSyncContent,
+        sync_result: &mut SyncResult,
+        _sync_conflict: &mut F,
+    ) -> Result<bool>
+    where
+        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
+    {
+        if local_item.is_ignored() {
+            // If our local item is also ignored, we can take the remote data and proceed with
+            // the sync as planned.
+            let target_item = metadata_db::DBItem {
+                path: localized_path.clone(),
+                sync_time: sync_time,
+                content: metadata_db::ItemType::IGNORED {
+                    creation_time: sync_content.creation_time,
+                    last_mod_time: sync_content.last_mod_time.clone(),
+                    mod_time: sync_content.mod_time,
+                },
+            };
+            self.write_synced_item(&localized_path, &target_item, sync_result)?;
+
+            Ok(true)
+        } else {
+            // If our local item is NOT ignored, we can not really use the information about an
+            // ignored item that is more up-to-date than our local copy.
+            // Report that the sync CAN NOT update the parent items sync time.
+            sync_result.remotely_ignored_items.push(localized_path);
+            Ok(false)
+        }
+    }
+
+    ///////////////////////////////////
+    // 'private' helpers start here
+    ///////////////////////////////////
+
+    fn pending_file_path(&self, path: &RelativePath) -> RelativePath {
+        self.fs_access
+            .pending_files_relative()
+            .join_mut(Self::path_hash(path))
+    }
+
+    fn backup_file_path(&self, path: &RelativePath) -> RelativePath {
+        self.fs_access.pending_files_relative().join_mut(format!(
+            "{}{}",
+            Self::path_hash(&path.to_lower_case()),
+            BACKUP_FILE_SUFFIX
+        ))
+    }
+
+    fn merge_base_path(&self, path: &RelativePath) -> RelativePath {
+        self.fs_access
+            .merge_bases_relative()
+            .join_mut(Self::path_hash(&path.to_lower_case()))
+    }
+
+    fn path_hash(path: &RelativePath) -> String {
+        use data_encoding::HEXUPPER;
+        use ring::digest::{Context, SHA256};
+
+        let mut context = Context::new(&SHA256);
+        for path_component in path.get_path_components() {
+            context.update(path_component.as_bytes());
+        }
+        let hash = context.finish();
+        HEXUPPER.encode(hash.as_ref())
+    }
+
+    /// Hard linked files are re-created as hard links if we already hold a local file of the
+    /// same hard link group. Falls back to an independent copy if linking is not possible
+    /// (e.g. the FS does not support hard links).
+    /// Returns the path of the pending file and the number of its bytes that were taken from
+    /// our local version of the file (i.e. that did not need to be transferred).
+    fn download_or_link_file(
+        &self,
+        other: &Self,
+        path: &RelativePath,
+        fs_metadata: &ItemFSMetadata,
+    ) -> Result<(RelativePath, u64)> {
+        if let Some(hard_link_group) = &fs_metadata.hard_link_group {
+            if let Some(link_path) = self.db_access.find_local_hard_link(hard_link_group)? {
+                let link_item = self.db_access.get_local_data_item(&link_path, false)?;
+                if link_item.is_file()
+                    && link_item.metadata().hash == fs_metadata.hash
+                    && self.does_disk_item_match_db_item(&link_item, false)?
+                {
+                    let target_local_path = self.pending_file_path(path);
+                    if self
+                        .fs_access
+                        .create_hard_link(&link_item.path, &target_local_path)
+                        .is_ok()
+                    {
+                        return Ok((target_local_path, 0));
+                    }
+                }
+            }
+        }
+
+        if let Some(appended_file) = self
+            .retry_policy
+            .run(|| self.download_appended_file(other, path, fs_metadata))?
+        {
+            return Ok(appended_file);
+        }
+        let target_local_path = self.retry_policy.run(|| self.download_file(other, path))?;
+
+        Ok((target_local_path, 0))
+    }
+
+    // The remote might know about a file without holding its content, e.g. after a metadata
+    // only sync or on transfer stores that did not yet receive it.
+    fn remote_read_error(
+        path: &RelativePath,
+        error: crate::fs_interaction::FSInteractionError,
+    ) -> DataStoreError {
+        if error.is_io_not_found() {
+            DataStoreError::ContentUnavailable { path: path.clone() }
+        } else {
+            error.into()
+        }
+    }
+
+    /// Files often only grow at their end (e.g. logs). If the remote file starts with the
+    /// content of our local version (same stored size and hash of that prefix), we copy the
+    /// local content and only download the appended tail. Returns None if this is not the case.
+    fn download_appended_file(
+        &self,
+        other: &Self,
+        path: &RelativePath,
+        fs_metadata: &ItemFSMetadata,
+    ) -> Result<Option<(RelativePath, u64)>> {
+        if self
+            .prefetched_files
+            .borrow()
+            .contains_key(&path.to_lower_case())
+        {
+            return Ok(None);
+        }
+        let local_item = self.db_access.get_local_data_item(path, false)?;
+        if !local_item.is_file() || local_item.metadata().hash.is_empty() {
+            return Ok(None);
+        }
+        let local_size = local_item.metadata().size;
+        if local_size == 0
+            || local_size >= fs_metadata.size
+            || self.fs_access.metadata(&local_item.path)?.size() != local_size
+            || self.fs_access.hash_algorithm() != other.fs_access.hash_algorithm()
+        {
+            return Ok(None);
+        }
+
+        let other_db_item = other.db_access.get_local_data_item(path, false)?;
+        let remote_prefix_hash = other
+            .fs_access
+            .calculate_prefix_hash(&other_db_item.path, local_size)
+            .map_err(|error| Self::remote_read_error(path, error))?;
+        if remote_prefix_hash != local_item.metadata().hash {
+            return Ok(None);
+        }
+
+        let target_local_path = self.pending_file_path(path);
+        let local_content = self.fs_access.read_file(&local_item.path)?;
+        let remote_tail = other
+            .fs_access
+            .read_file_from(&other_db_item.path, local_size)
+            .map_err(|error| Self::remote_read_error(path, error))?;
+        match self.fs_access.create_file(&target_local_path) {
+            Err(error) if !error.is_io_already_exists() => return Err(error.into()),
+            _ => (),
+        }
+        self.fs_access.write_file(
+            &target_local_path,
+            Box::new(local_content.chain(remote_tail)),
+        )?;
+
+        Ok(Some((target_local_path, local_size)))
+    }
+
+    /// Downloads the remote files of the given paths that differ from our local version
+    /// concurrently into the pending dir. download_file picks them up instead of downloading
+    /// them again. Failed downloads are ignored here, download_file retries and reports them.
+    fn prefetch_files(
+        &self,
+        from_other: &Self,
+        paths: &[RelativePath],
+        local_mapper: &DataStoreIDMapper,
+        remote_mapper: &DataStoreIDMapper,
+        options: &SyncOptions,
+    ) -> Result<()> {
+        let mut prefetch_paths = Vec::new();
+        let mut jobs = Vec::new();
+        for path in paths {
+            if let Some((remote_path, _, pending_path)) =
+                self.select_prefetch(from_other, path, local_mapper, remote_mapper, options)?
+            {
+                prefetch_paths.push(path.to_lower_case());
+                jobs.push((remote_path, pending_path));
+            }
+        }
+
+        let results = self.fs_access.copy_files_from(
+            &from_other.fs_access,
+            &jobs,
+            options.parallel_downloads,
+        );
+        for ((path, (_, pending_path)), result) in prefetch_paths.into_iter().zip(jobs).zip(results)
+        {
+            match result {
+                Ok(_) => {
+                    self.prefetched_files
+                        .borrow_mut()
+                        .insert(path, pending_path);
+                }
+                Err(error) => debug!("Prefetching a file failed: {:?}", error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts downloading the remote file at the given path into the pending dir in the
+    /// background (see SyncOptions::pipelined_prefetch), if it differs from our local version.
+    /// Returns the pending path, the path and hash of the remote file and the running copy,
+    /// see finish_background_prefetch.
+    fn start_background_prefetch(
+        &self,
+        from_other: &Self,
+        path: &RelativePath,
+        local_mapper: &DataStoreIDMapper,
+        remote_mapper: &DataStoreIDMapper,
+        options: &SyncOptions,
+    ) -> Result<Option<BackgroundPrefetch>> {
+        // The content is verified by its hash, which needs the same algorithm on both sides.
+        if self.fs_access.hash_algorithm() != from_other.fs_access.hash_algorithm() {
+            return Ok(None);
+        }
+        let (remote_path, remote_hash, pending_path) =
+            match self.select_prefetch(from_other, path, local_mapper, remote_mapper, options)? {
+                Some(prefetch) => prefetch,
+                None => return Ok(None),
             };
-            self.db_access
-                .sync_local_data_item(&localized_path, &target_item)?;
 
-            Ok(true)
+        let jobs = [(remote_path.clone(), pending_path.clone())];
+        Ok(self
+            .fs_access
+            .start_copy_files_from(&from_other.fs_access, &jobs, 1)
+            .map(|copy| (pending_path, remote_path, remote_hash, copy)))
+    }
+
+    /// Waits for a prefetch started by start_background_prefetch and hands the file over to
+    /// download_file. Failed downloads and content not matching the remote's hash (e.g. as the
+    /// file changed meanwhile) are discarded, download_file downloads these files again.
+    fn finish_background_prefetch(&self, background_prefetch: BackgroundPrefetch) -> Result<()> {
+        let (pending_path, remote_path, remote_hash, copy) = background_prefetch;
+        // Failed copies remove their unfinished pending file (see BackgroundCopy::join).
+        for result in copy.join() {
+            if let Err(error) = result {
+                debug!("Prefetching a file failed: {:?}", error);
+                return Ok(());
+            }
+        }
+
+        if self.fs_access.calculate_hash(&pending_path)? == remote_hash {
+            self.prefetched_files
+                .borrow_mut()
+                .insert(remote_path.to_lower_case(), pending_path);
         } else {
-            // The deletion notice does not 'target' our file, i.e. it does
-            // not know about our local file, as the local file was created
-            // logically independent of the other copy.
-            // Just do nothing more than take up the target sync time.
-            self.increase_item_sync_time(local_item, sync_time)?;
-            Ok(true)
+            debug!(
+                "Discarding prefetched file '{}', its content changed.",
+                remote_path.get_path_components().join("/")
+            );
+            self.fs_access.delete_file(&pending_path)?;
         }
+
+        Ok(())
     }
 
-    fn sync_ignored<F>(
+    /// Checks if the remote file at the given path is worth downloading ahead of time, i.e. if
+    /// it differs from our local version and the regular sync would download it. Asks the
+    /// other store through a regular sync request (the sync itself repeats the request).
+    /// Returns the path and hash of the remote file and the pending path to download it to.
+    fn select_prefetch(
         &self,
-        _from_other: &Self,
-        local_item: DBItem,
-        localized_path: RelativePath,
-        sync_time: VersionVector<i64>,
-        sync_content: IntIgnoreSyncContent,
-        _sync_conflict: &mut F,
-    ) -> Result<bool>
-    where
-        F: FnMut(SyncConflictEvent) -> SyncConflictResolution,
-    {
-        if local_item.is_ignored() {
-            // If our local item is also ignored, we can take the remote data and proceed with
-            // the sync as planned.
-            let target_item = metadata_db::DBItem {
-                path: localized_path.clone(),
-                sync_time: sync_time,
-                content: metadata_db::ItemType::IGNORED {
-                    creation_time: sync_content.creation_time,
-                    last_mod_time: sync_content.last_mod_time.clone(),
-                    mod_time: sync_content.mod_time,
-                },
-            };
-            self.db_access
-                .sync_local_data_item(&localized_path, &target_item)?;
+        from_other: &Self,
+        path: &RelativePath,
+        local_mapper: &DataStoreIDMapper,
+        remote_mapper: &DataStoreIDMapper,
+        options: &SyncOptions,
+    ) -> Result<Option<(RelativePath, String, RelativePath)>> {
+        // Transfer stores decide on the items they carry based on all other stores,
+        // leave that to the regular sync.
+        if self.db_access.get_local_data_store()?.is_transfer_store {
+            return Ok(None);
+        }
+        if Self::is_skipped(path, options)
+            || self.fs_access.is_internal_path(path)
+            || !self
+                .local_inclusion_rules
+                .is_included(&path.to_lower_case())
+            || self
+                .prefetched_files
+                .borrow()
+                .contains_key(&path.to_lower_case())
+        {
+            return Ok(None);
+        }
 
-            Ok(true)
-        } else {
-            // If our local item is NOT ignored, we can not really use the information about an
-            // ignored item that is more up-to-date than our local copy.
-            // Report that the sync CAN NOT update the parent items sync time.
-            Ok(false)
+        let local_item = self.db_access.get_local_data_item(path, true)?;
+        let sync_request = IntSyncRequest {
+            item_path: path.clone(),
+            item_sync_time: local_item.sync_time.clone(),
+            full_walk: false,
+        };
+        // Errors are left to the regular sync, it retries and reports them.
+        let sync_response =
+            match from_other.sync_item(sync_request.externalize(local_mapper), remote_mapper) {
+                Ok(sync_response) => sync_response.internalize(local_mapper),
+                Err(error) => {
+                    debug!("Checking a file for prefetching failed: {:?}", error);
+                    return Ok(None);
+                }
+            };
+        let remote_metadata = match sync_response.action {
+            IntSyncAction::UpdateRequired(IntSyncContent::File(content)) => content.fs_metadata,
+            _ => return Ok(None),
+        };
+        // Hard links are re-created locally, i.e. they must not be downloaded.
+        if remote_metadata.hash.is_empty() || remote_metadata.hard_link_group.is_some() {
+            return Ok(None);
         }
+        if local_item.is_file() && local_item.metadata().hash == remote_metadata.hash {
+            return Ok(None);
+        }
+
+        let remote_path = path
+            .clone()
+            .parent_mut()
+            .join_mut(remote_metadata.case_sensitive_name);
+        let pending_path = self.pending_file_path(path);
+        Ok(Some((remote_path, remote_metadata.hash, pending_path)))
     }
 
-    ///////////////////////////////////
-    // 'private' helpers start here
-    ///////////////////////////////////
+    /// The remote does not read its file when answering a sync request, i.e. the content might
+    /// have changed since its last scan (without touching the modification time). The downloaded
+    /// file must have the indexed content, otherwise it is dropped and the sync is aborted.
+    fn verify_downloaded_file(
+        &self,
+        other: &Self,
+        tmp_file_path: &RelativePath,
+        remote_path: &RelativePath,
+        sync_content: &IntFileSyncContent,
+    ) -> Result<()> {
+        // Hashes of different algorithms can not be compared.
+        if sync_content.fs_metadata.hash.is_empty()
+            || self.fs_access.hash_algorithm() != other.fs_access.hash_algorithm()
+        {
+            return Ok(());
+        }
 
-    fn download_file(&self, other: &Self, path: &RelativePath) -> Result<RelativePath> {
-        use data_encoding::HEXUPPER;
-        use ring::digest::{Context, SHA256};
+        if self.fs_access.calculate_hash(tmp_file_path)? != sync_content.fs_metadata.hash {
+            self.fs_access.delete_file(tmp_file_path)?;
+            return Err(DataStoreError::DiskOutOfSync {
+                path: remote_path.clone(),
+            });
+        }
+        Ok(())
+    }
 
-        let mut context = Context::new(&SHA256);
-        for path_component in path.get_path_components() {
-            context.update(path_component.as_bytes());
+    fn download_file(&self, other: &Self, path: &RelativePath) -> Result<RelativePath> {
+        let prefetched_file = self
+            .prefetched_files
+            .borrow_mut()
+            .remove(&path.to_lower_case());
+        if let Some(pending_path) = prefetched_file {
+            return Ok(pending_path);
         }
-        let hash = context.finish();
-        let path_hash = HEXUPPER.encode(hash.as_ref());
 
-        let target_local_path = self.fs_access.pending_files_relative().join_mut(path_hash);
+        let target_local_path = self.pending_file_path(path);
 
         // TODO: This should later on be further abstracted to allow actual downloads/streaming.
-        let other_db_item = other.db_access.get_local_data_item(&path, false)?;
-        let stream_from_other = other.fs_access.read_file(&other_db_item.path)?;
-
-        self.fs_access.create_file(&target_local_path)?;
-        self.fs_access
-            .write_file(&target_local_path, stream_from_other)?;
+        // Retried downloads overwrite the pending file left behind by the failed attempt.
+        // The FS copies the file, e.g. keeping holes of sparse files (see FS::start_copy_files_from).
+        let other_db_item = other.db_access.get_local_data_item(path, false)?;
+        let jobs = [(other_db_item.path, target_local_path.clone())];
+        for result in self.fs_access.copy_files_from(&other.fs_access, &jobs, 1) {
+            result.map_err(|error| Self::remote_read_error(path, error))?;
+        }
 
         Ok(target_local_path)
     }
@@ -1208,6 +4929,62 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         self.db_access.get_data_set()
     }
 
+    /// Rank of the given path in the sync order, lower ranks are synced first.
+    /// Paths on the way to or inside of a priority path get the rank of that priority entry,
+    /// all other paths are synced last.
+    fn sync_priority(path: &RelativePath, priority: &[RelativePath]) -> usize {
+        let path = path.to_lower_case();
+        let path_components = path.get_path_components();
+        priority
+            .iter()
+            .position(|priority_path| {
+                let priority_path = priority_path.to_lower_case();
+                let priority_components = priority_path.get_path_components();
+                let common_length = min(path_components.len(), priority_components.len());
+                path_components[..common_length] == priority_components[..common_length]
+            })
+            .unwrap_or(priority.len())
+    }
+
+    /// Paths equal to or inside of a skipped path are left out of the sync.
+    /// If the sync is limited to some paths, all paths not on the way to or inside of
+    /// them are left out.
+    fn is_skipped(path: &RelativePath, options: &SyncOptions) -> bool {
+        let path = path.to_lower_case();
+        let path_components = path.get_path_components();
+        let is_skipped = options.skip.iter().any(|skip_path| {
+            let skip_path = skip_path.to_lower_case();
+            let skip_components = skip_path.get_path_components();
+            path_components.starts_with(skip_components)
+        });
+        let is_outside_of_only = !options.only.is_empty()
+            && !options.only.iter().any(|only_path| {
+                let only_path = only_path.to_lower_case();
+                let only_components = only_path.get_path_components();
+                path_components.starts_with(only_components)
+                    || only_components.starts_with(path_components)
+            });
+
+        let is_too_deep = options
+            .max_depth
+            .is_some_and(|max_depth| Self::path_depth(&path) > max_depth);
+
+        is_skipped || is_outside_of_only || is_too_deep
+    }
+
+    // Checks if the local inclusion rules include the item (the root is always included).
+    fn includes_item(&self, path: &RelativePath) -> bool {
+        path.is_root()
+            || self
+                .local_inclusion_rules
+                .is_included(&path.to_lower_case())
+    }
+
+    // Number of path components below the store root, i.e. the root has a depth of zero.
+    fn path_depth(path: &RelativePath) -> usize {
+        path.path_component_number() - 1
+    }
+
     fn fs_to_date_time(fs_time: &filetime::FileTime) -> NaiveDateTime {
         NaiveDateTime::from_timestamp(fs_time.unix_seconds(), fs_time.nanoseconds())
     }
@@ -1221,6 +4998,19 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     /// Effectively, this returning false means that the file should be re-indexed before performing
     /// any synchronization operations on it.
     fn does_disk_item_match_db_item(&self, db_item: &DBItem, check_folder: bool) -> Result<bool> {
+        Ok(
+            self.does_disk_metadata_match_db_item(db_item, check_folder)?
+                && self.does_disk_content_match_db_item(db_item)?,
+        )
+    }
+
+    /// Same as does_disk_item_match_db_item, but does NOT read the content of files,
+    /// i.e. only their type and modification time are compared.
+    fn does_disk_metadata_match_db_item(
+        &self,
+        db_item: &DBItem,
+        check_folder: bool,
+    ) -> Result<bool> {
         // Root directory is always fine.
         if db_item.path.is_root() {
             return Ok(true);
@@ -1232,22 +5022,21 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
 
         let disk_metadata = if check_folder {
             // We need to re-index the folder to be sure there is no duplicate entry in the directory.
-            let folder_content = self.fs_access.index(&db_item.path.parent());
-            if folder_content.is_err() {
-                if folder_content.as_ref().err().unwrap().is_io_not_found() {
-                    return Ok(db_item.is_deletion());
-                }
-                if folder_content.as_ref().err().unwrap().is_io_no_directory() {
+            let folder_content = match self.fs_access.index(&db_item.path.parent()) {
+                Ok(folder_content) => folder_content,
+                Err(ref e) if e.is_io_not_found() || e.is_io_no_directory() => {
                     return Ok(db_item.is_deletion());
                 }
-                println!("Encountered unexpected FS error.");
-            }
+                // Any other FS error is reported to the caller, it does not tell us anything
+                // about the disk being in sync with the DB.
+                Err(e) => return Err(e.into()),
+            };
 
             // In case of a deletion in the DB there must be NO entry on disk.
-            let folder_content = folder_content?;
             if db_item.is_deletion() {
                 let has_item_on_disk = folder_content.into_iter().any(|item| {
-                    item.relative_path.name().to_lowercase() == db_item.path.name().to_lowercase()
+                    self.fs_access.name_key(item.relative_path.name())
+                        == self.fs_access.name_key(db_item.path.name())
                 });
                 return Ok(!has_item_on_disk);
             }
@@ -1283,32 +5072,128 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
         if Self::fs_to_date_time(&disk_metadata.last_mod_time()) != db_item.metadata().mod_time {
             return Ok(false);
         }
-        if disk_metadata.is_file() {
-            let hash = self.fs_access.calculate_hash(&db_item.path);
-            if hash.is_err() || hash.unwrap() != db_item.metadata().hash {
-                return Ok(false);
-            }
-        }
 
         Ok(true)
     }
 
-    fn has_metadata_changed(db_metadata: &ItemFSMetadata, fs_item: &DataItem) -> bool {
+    // Compares the content hash of files on disk with the one in the DB.
+    fn does_disk_content_match_db_item(&self, db_item: &DBItem) -> Result<bool> {
+        if !db_item.is_file() {
+            return Ok(true);
+        }
+        // Placeholder hashes of stores trusting modification times can not be compared.
+        if db_item.metadata().hash.is_empty() && self.db_access.is_trust_mtime()? {
+            return Ok(true);
+        }
+        match self.fs_access.calculate_hash(&db_item.path) {
+            Ok(hash) => Ok(hash == db_item.metadata().hash),
+            // The file vanished since we read its metadata.
+            Err(ref e) if e.is_io_not_found() => Ok(false),
+            // Other errors (e.g. transient ones) tell nothing about the index, report them.
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn has_metadata_changed(
+        db_metadata: &ItemFSMetadata,
+        fs_item: &DataItem,
+        compare_name_case: bool,
+    ) -> bool {
         let fs_mod_time =
             Self::fs_to_date_time(&fs_item.metadata.as_ref().unwrap().last_mod_time());
         let fs_metadata = fs_item.metadata.as_ref().unwrap();
 
         db_metadata.mod_time != fs_mod_time
-            || db_metadata.case_sensitive_name != fs_item.relative_path.name()
+            || !Self::names_match(
+                &db_metadata.case_sensitive_name,
+                fs_item.relative_path.name(),
+                compare_name_case,
+            )
             || db_metadata.is_read_only != fs_metadata.read_only()
     }
 
+    fn names_match(name: &str, other_name: &str, compare_case: bool) -> bool {
+        if compare_case {
+            name == other_name
+        } else {
+            name.to_lowercase() == other_name.to_lowercase()
+        }
+    }
+
+    /// Some file systems report no creation times (e.g. ext4 without crtime or network mounts)
+    /// or bogus ones, e.g. zero or a time after the last modification for copied files.
+    /// Such creation times are replaced by the modification time, the returned flag is set if
+    /// the fallback was used. The fallback is recorded in the DB and synced like any other
+    /// creation time, i.e. stores on different file systems agree on it.
+    fn sanitize_creation_time(fs_metadata: &virtual_fs::Metadata) -> (NaiveDateTime, bool) {
+        let mod_time = fs_metadata.last_mod_time();
+
+        match fs_metadata.creation_time() {
+            Some(creation_time)
+                if creation_time != FileTime::zero() && creation_time <= mod_time =>
+            {
+                (Self::fs_to_date_time(&creation_time), false)
+            }
+            _ => (Self::fs_to_date_time(&mod_time), true),
+        }
+    }
+
+    // Unchanged items might still differ in the case of their name (if the store does not
+    // propagate case changes). The name is kept up to date WITHOUT recording a modification.
+    fn update_db_item_name_case(
+        &self,
+        db_metadata: &ItemFSMetadata,
+        fs_item: &DataItem,
+    ) -> Result<()> {
+        if db_metadata.case_sensitive_name != fs_item.relative_path.name() {
+            self.db_access.update_local_data_item_name(
+                &fs_item.relative_path,
+                fs_item.relative_path.name(),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Writes a DB update found by a scan, scans of scan readers only record it (see apply_scan).
+    fn apply_scan_change(&self, change: ScanChange) -> Result<()> {
+        if let Some(recorded_changes) = self.recorded_scan_changes.as_ref() {
+            recorded_changes.borrow_mut().push(change);
+            return Ok(());
+        }
+
+        self.execute_scan_change(&change)
+    }
+
+    fn execute_scan_change(&self, change: &ScanChange) -> Result<()> {
+        match change {
+            ScanChange::UpdateItem(fs_item, hash) => self.update_db_item(fs_item, hash)?,
+            ScanChange::UpdateItemNameCase(db_metadata, fs_item) => {
+                self.update_db_item_name_case(db_metadata, fs_item)?
+            }
+            ScanChange::DeleteItem(path) => self.db_access.delete_local_data_item(path)?,
+            ScanChange::ResetItem(path) => self.db_access.reset_local_data_item(path)?,
+            ScanChange::IgnoreItem(path) => self.db_access.ignore_local_data_item(path)?,
+        };
+
+        Ok(())
+    }
+
     fn update_db_item(&self, fs_item: &DataItem, hash: &str) -> Result<()> {
-        let fs_creation_time =
-            Self::fs_to_date_time(&fs_item.metadata.as_ref().unwrap().creation_time());
-        let fs_mod_time =
-            Self::fs_to_date_time(&fs_item.metadata.as_ref().unwrap().last_mod_time());
         let fs_metadata = fs_item.metadata.as_ref().unwrap();
+        let (mut fs_creation_time, _) = Self::sanitize_creation_time(fs_metadata);
+        if fs_metadata.creation_time().is_none() {
+            // Keep the creation time known for the item (e.g. learned from a remote during a
+            // sync), the mod time fallback is only used for items new to the DB.
+            let db_item = self
+                .db_access
+                .get_local_data_item(&fs_item.relative_path, false)?;
+            if db_item.is_file() || db_item.is_folder() {
+                fs_creation_time = db_item.metadata().creation_time;
+            }
+        }
+        let fs_mod_time = Self::fs_to_date_time(&fs_metadata.last_mod_time());
+        let hard_link_group = self.hard_link_group(fs_metadata)?;
 
         self.db_access.update_local_data_item(
             &fs_item.relative_path,
@@ -1317,11 +5202,64 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             fs_metadata.is_file(),
             &hash,
             fs_metadata.read_only(),
+            hard_link_group.as_deref(),
+            if fs_metadata.is_file() {
+                fs_metadata.size()
+            } else {
+                0
+            },
         )?;
 
         Ok(())
     }
 
+    fn disk_item_exists(&self, path: &RelativePath) -> Result<bool> {
+        match self.fs_access.metadata(path) {
+            Err(error) if error.is_io_not_found() => Ok(false),
+            Err(error) => Err(error.into()),
+            Ok(_) => Ok(true),
+        }
+    }
+
+    fn disk_data_item(&self, path: &RelativePath) -> Result<DataItem> {
+        Ok(DataItem {
+            metadata: Some(self.fs_access.metadata(path)?),
+            relative_path: path.clone(),
+            issue: None,
+        })
+    }
+
+    /// Files with multiple hard links are assigned to a hard link group, identified by the local
+    /// store and the files inode. Other stores use it to re-create the files as hard links.
+    /// NOTE: Only (re-)indexed files get assigned to groups, i.e. adding a new link to an
+    ///       unchanged file does not add the unchanged file to the group.
+    fn hard_link_group(&self, fs_metadata: &virtual_fs::Metadata) -> Result<Option<String>> {
+        if !fs_metadata.is_file() || fs_metadata.link_count() <= 1 {
+            return Ok(None);
+        }
+
+        if let Some((device, inode)) = fs_metadata.inode() {
+            Ok(Some(format!(
+                "{}:{}-{}",
+                self.db_access.get_local_data_store()?.unique_name,
+                device,
+                inode
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Hashes a file found during a scan. Stores trusting modification times skip reading
+    /// the file and record an empty placeholder hash instead.
+    fn scan_hash(&self, fs_item: &DataItem, trust_mtime: bool) -> Result<String> {
+        if trust_mtime {
+            Ok(String::new())
+        } else {
+            Ok(self.fs_access.calculate_hash(&fs_item.relative_path)?)
+        }
+    }
+
     #[allow(clippy::collapsible_if)]
     /// Indexes the given dir into the DB, i.e. updates the db to contain the current FS content.
     /// Return's true if the indexed directory requires a recursive FS scan.
@@ -1331,6 +5269,7 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     {
         use self::ScanEvent::*;
 
+        let compare_name_case = self.db_access.is_propagate_case_changes()?;
         let db_item = self
             .db_access
             .get_local_data_item(&fs_item.relative_path, false)?;
@@ -1339,21 +5278,27 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             metadata_db::ItemType::FILE { .. } => {
                 if listener(ChangedFileToFolder(&fs_item, &db_item)) {
                     // Delete the existing file db entry...
-                    self.db_access
-                        .delete_local_data_item(&fs_item.relative_path)?;
+                    self.apply_scan_change(ScanChange::DeleteItem(fs_item.relative_path.clone()))?;
                     // ... replace it with a directory...
-                    self.update_db_item(&fs_item, "")?;
+                    self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), String::new()))?;
                     return Ok(true);
                 }
             }
             metadata_db::ItemType::FOLDER { ref metadata, .. } => {
-                if Self::has_metadata_changed(&metadata, &fs_item) {
+                if Self::has_metadata_changed(metadata, fs_item, compare_name_case) {
                     if listener(ChangedFolder(&fs_item, &db_item)) {
-                        self.update_db_item(&fs_item, "")?;
+                        self.apply_scan_change(ScanChange::UpdateItem(
+                            fs_item.clone(),
+                            String::new(),
+                        ))?;
                         return Ok(true);
                     }
                 } else {
                     if listener(UnchangedFolder(&fs_item, &db_item)) {
+                        self.apply_scan_change(ScanChange::UpdateItemNameCase(
+                            metadata.clone(),
+                            fs_item.clone(),
+                        ))?;
                         return Ok(true);
                     }
                 }
@@ -1367,14 +5312,24 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                     listener(IgnoredNewItem(&fs_item));
                 } else {
                     if listener(NewFolder(&fs_item)) {
-                        self.update_db_item(&fs_item, "")?;
+                        self.apply_scan_change(ScanChange::UpdateItem(
+                            fs_item.clone(),
+                            String::new(),
+                        ))?;
                         return Ok(true);
                     }
                 }
             }
             metadata_db::ItemType::IGNORED { .. } => {
-                // Mark it as ignored by the DB entry.
-                listener(IgnoredExistingItem(&fs_item));
+                if !self.includes_item(&fs_item.relative_path) {
+                    // Mark it as ignored by the DB entry.
+                    listener(IgnoredExistingItem(fs_item));
+                } else if listener(NewFolder(fs_item)) {
+                    // Ignored by an ignore file that no longer matches it (see perform_scan).
+                    self.apply_scan_change(ScanChange::ResetItem(fs_item.relative_path.clone()))?;
+                    self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), String::new()))?;
+                    return Ok(true);
+                }
             }
         };
 
@@ -1389,19 +5344,36 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     {
         use self::ScanEvent::*;
 
+        let trust_mtime = self.db_access.is_trust_mtime()?;
+        if bitrot && trust_mtime {
+            return Err(DataStoreError::BitRotCheckRequiresHashes);
+        }
+        let compare_name_case = self.db_access.is_propagate_case_changes()?;
+
         let db_item = self
             .db_access
             .get_local_data_item(&fs_item.relative_path, false)?;
+        // Files that were modified very recently might still be written to, we defer them.
+        let is_settled = self
+            .local_inclusion_rules
+            .is_settled(&fs_item.metadata.as_ref().unwrap().last_mod_time());
 
         match db_item.content {
             metadata_db::ItemType::FILE { ref metadata, .. } => {
-                if Self::has_metadata_changed(&metadata, &fs_item) {
-                    if listener(ChangedFile(&fs_item, &db_item)) {
-                        let hash = self.fs_access.calculate_hash(&fs_item.relative_path)?;
-                        self.update_db_item(&fs_item, &hash)?;
+                if Self::has_metadata_changed(metadata, fs_item, compare_name_case) {
+                    if !is_settled {
+                        listener(DeferredUnsettledFile(fs_item));
+                    } else if listener(ChangedFile(fs_item, &db_item)) {
+                        let hash = self.scan_hash(fs_item, trust_mtime)?;
+                        self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), hash))?;
                     }
                 } else {
-                    listener(UnchangedFile(&fs_item, &db_item));
+                    if listener(UnchangedFile(fs_item, &db_item)) {
+                        self.apply_scan_change(ScanChange::UpdateItemNameCase(
+                            metadata.clone(),
+                            fs_item.clone(),
+                        ))?;
+                    }
                     if bitrot {
                         let hash = self.fs_access.calculate_hash(&fs_item.relative_path)?;
                         if metadata.hash != hash {
@@ -1417,10 +5389,10 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
             metadata_db::ItemType::FOLDER { .. } => {
                 if listener(ChangedFolderToFile(&fs_item, &db_item)) {
                     // Delete existing directory db entry ...
-                    self.db_access
-                        .delete_local_data_item(&fs_item.relative_path)?;
+                    self.apply_scan_change(ScanChange::DeleteItem(fs_item.relative_path.clone()))?;
                     // ...replace it with a file entry.
-                    self.update_db_item(&fs_item, "")?;
+                    let hash = self.scan_hash(fs_item, trust_mtime)?;
+                    self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), hash))?;
                 }
             }
             metadata_db::ItemType::DELETION { .. } => {
@@ -1430,16 +5402,113 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
                 {
                     // Do not do anything with ignored files that have no DB entries!
                     listener(IgnoredNewItem(&fs_item));
+                } else if !is_settled {
+                    listener(DeferredUnsettledFile(fs_item));
                 } else {
                     if listener(NewFile(&fs_item)) {
-                        let hash = self.fs_access.calculate_hash(&fs_item.relative_path)?;
-                        self.update_db_item(&fs_item, &hash)?;
+                        let hash = self.scan_hash(fs_item, trust_mtime)?;
+                        self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), hash))?;
                     }
                 }
             }
             metadata_db::ItemType::IGNORED { .. } => {
-                // Mark it as ignored by the DB entry.
-                listener(IgnoredExistingItem(&fs_item));
+                if !self.includes_item(&fs_item.relative_path) {
+                    // Mark it as ignored by the DB entry.
+                    listener(IgnoredExistingItem(fs_item));
+                } else if !is_settled {
+                    listener(DeferredUnsettledFile(fs_item));
+                } else {
+                    if listener(NewFile(fs_item)) {
+                        // Ignored by an ignore file that no longer matches it (see perform_scan).
+                        self.apply_scan_change(ScanChange::ResetItem(
+                            fs_item.relative_path.clone(),
+                        ))?;
+                        let hash = self.scan_hash(fs_item, trust_mtime)?;
+                        self.apply_scan_change(ScanChange::UpdateItem(fs_item.clone(), hash))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_duplicate_names(&self, items: &[DataItem]) -> Vec<DuplicateNames> {
+        let duplicate_keys: HashSet<String> = items
+            .iter()
+            .filter(|item| item.issue == Some(Issue::Duplicate))
+            .map(|item| self.fs_access.name_key(item.relative_path.name()))
+            .collect();
+
+        // Items are listed in canonical order, i.e. duplicates are next to each other.
+        let mut duplicate_names: Vec<DuplicateNames> = Vec::new();
+        let mut last_key = None;
+        for item in items {
+            let name_key = self.fs_access.name_key(item.relative_path.name());
+            if !duplicate_keys.contains(&name_key) {
+                continue;
+            }
+
+            if last_key.as_ref() != Some(&name_key) {
+                duplicate_names.push(DuplicateNames {
+                    paths: vec![],
+                    policy: self.duplicate_policy,
+                    indexed_path: None,
+                });
+                last_key = Some(name_key);
+            }
+            let current_names = duplicate_names.last_mut().unwrap();
+            current_names.paths.push(item.relative_path.clone());
+            if item.issue.is_none() {
+                current_names.indexed_path = Some(item.relative_path.clone());
+            }
+        }
+
+        duplicate_names
+    }
+
+    // Indexes a single item found on disk (and the content of folders that require it).
+    // dir_rules are the ignore file rules of the folder holding the item.
+    fn scan_item<F>(
+        &self,
+        item: &DataItem,
+        dir_rules: &DirIgnoreRules,
+        max_depth: Option<usize>,
+        listener: &mut F,
+    ) -> Result<()>
+    where
+        F: FnMut(ScanEvent) -> bool,
+    {
+        // Items matching ignore files are treated like items ignored by the inclusion rules,
+        // i.e. already indexed ones are marked as ignored in the DB.
+        if dir_rules.is_ignored(&item.relative_path) {
+            let db_item = self
+                .db_access
+                .get_local_data_item(&item.relative_path, false)?;
+            if db_item.is_deletion() {
+                listener(ScanEvent::IgnoredNewItem(item));
+            } else if listener(ScanEvent::IgnoredExistingItem(item)) && !db_item.is_ignored() {
+                self.apply_scan_change(ScanChange::IgnoreItem(item.relative_path.clone()))?;
+            }
+            return Ok(());
+        }
+
+        if let Some(issue) = item.issue.as_ref() {
+            listener(ScanEvent::IssueOther(item, issue));
+            return Ok(());
+        }
+
+        match item.metadata.as_ref().unwrap().file_type() {
+            virtual_fs::FileType::File => {
+                self.index_file(item, false, listener)?;
+            }
+            virtual_fs::FileType::Dir => {
+                if self.index_dir(item, listener)? {
+                    self.perform_scan(item, dir_rules, max_depth, listener)?;
+                }
+            }
+            virtual_fs::FileType::Link => {
+                listener(ScanEvent::IssueSkipLink(item));
             }
         }
 
@@ -1447,50 +5516,108 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     }
 
     #[allow(clippy::collapsible_if)] // We want to explicitly nest the listener hook.
-    fn perform_scan<F>(&self, dir_item: &DataItem, listener: &mut F) -> Result<()>
+    fn perform_scan<F>(
+        &self,
+        dir_item: &DataItem,
+        dir_rules: &DirIgnoreRules,
+        max_depth: Option<usize>,
+        listener: &mut F,
+    ) -> Result<()>
     where
         F: FnMut(ScanEvent) -> bool,
     {
+        // Internal data (e.g. of nested stores) is never indexed.
+        if self.fs_access.is_internal_path(&dir_item.relative_path) {
+            return Ok(());
+        }
+
+        // Directories at the maximum depth are left out completely, i.e. also the 'negative'
+        // part of the scan below does not look at (and delete) their children.
+        if let Some(max_depth) = max_depth {
+            if Self::path_depth(&dir_item.relative_path) >= max_depth {
+                return Ok(());
+            }
+        }
+
+        // Paused sub-trees keep their DB entries as they are (see pause_path).
+        if self.paused_paths.is_paused(&dir_item.relative_path) {
+            return Ok(());
+        }
+
         // First, we index each file present on disk in this directory.
         // This is the 'positive' part of the scan operation, i.e. we add anything that is on
         // disk and not in the DB, as well as anything that has changed on disk.
         let items = self.fs_access.index(&dir_item.relative_path)?;
 
+        // Items whose names only differ in case are treated as set by the duplicate policy,
+        // index already left out the skipped ones (see set_duplicate_policy).
+        for duplicate_names in self.find_duplicate_names(&items) {
+            if self.duplicate_policy == DuplicatePolicy::Fail {
+                return Err(DataStoreError::DuplicateNames {
+                    paths: duplicate_names.paths,
+                });
+            }
+            warn!(
+                "Items only differing in the case of their names in '{}' ({:?}): {}",
+                dir_item.relative_path.get_path_components().join("/"),
+                self.duplicate_policy,
+                duplicate_names
+                    .paths
+                    .iter()
+                    .map(|path| path.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            listener(ScanEvent::IssueDuplicateNames(&duplicate_names));
+        }
+
+        // Rules of an ignore file in this directory apply to its sub-tree.
+        let ignore_file = self
+            .local_inclusion_rules
+            .ignore_file_name()
+            .and_then(|name| {
+                items.iter().find(|item| {
+                    item.relative_path.name() == name
+                        && item.metadata.as_ref().is_some_and(|m| m.is_file())
+                })
+            });
+        let dir_rules = if let Some(ignore_file) = ignore_file {
+            let mut content = String::new();
+            self.fs_access
+                .read_file(&ignore_file.relative_path)?
+                .read_to_string(&mut content)
+                .map_err(fs_interaction::FSInteractionError::from)?;
+            dir_rules.with_ignore_file(&dir_item.relative_path, &content)
+        } else {
+            dir_rules.clone()
+        };
+
         let mut lower_case_names = HashSet::new();
         for item in items {
-            lower_case_names.insert(item.relative_path.name().to_lowercase());
-
-            if item.issue.is_none() {
-                let item_metadata = item.metadata.as_ref().unwrap();
-                match item_metadata.file_type() {
-                    virtual_fs::FileType::File => {
-                        self.index_file(&item, false, listener)?;
-                    }
-                    virtual_fs::FileType::Dir => {
-                        if self.index_dir(&item, listener)? {
-                            self.perform_scan(&item, listener)?;
-                        }
-                    }
-                    virtual_fs::FileType::Link => {
-                        listener(ScanEvent::IssueSkipLink(&item));
-                    }
-                }
-            } else {
-                listener(ScanEvent::IssueOther(&item, &item.issue.as_ref().unwrap()));
+            lower_case_names.insert(self.fs_access.name_key(item.relative_path.name()));
+            if self.paused_paths.is_paused(&item.relative_path) {
+                continue;
             }
+
+            self.scan_item(&item, &dir_rules, max_depth, listener)?;
         }
 
         // Lastly we perform the 'negative' operation of the scan process:
         // We load all known entries of the directory and see if there are any that are
         // no longer present on disk, thus signaling a deletion.
+        // Deletion notices and ignored items have no content on disk to compare against,
+        // they stay as they are.
         let child_items = self
             .db_access
             .get_local_child_items(&dir_item.relative_path, false)?;
         for child_item in child_items.iter() {
-            if !lower_case_names.contains(&child_item.path.name().to_lowercase()) {
+            if !lower_case_names.contains(&self.fs_access.name_key(child_item.path.name()))
+                && !child_item.is_deletion()
+                && !child_item.is_ignored()
+                && !self.paused_paths.is_paused(&child_item.path)
+            {
                 if listener(DeletedItem(child_item)) {
-                    let child_item_path = child_item.path.clone();
-                    self.db_access.delete_local_data_item(&child_item_path)?;
+                    self.apply_scan_change(ScanChange::DeleteItem(child_item.path.clone()))?;
                 }
             }
         }
@@ -1499,5 +5626,13 @@ impl<FS: virtual_fs::FS> DataStore<FS> {
     }
 }
 
+/// Conflict copies (see keep_both_files) are named after the store their version came from.
+fn conflict_copy_suffix(data_store_desc: &str) -> String {
+    format!(".conflict-{}", data_store_desc)
+}
+
 #[cfg(test)]
 mod tests;
+
+#[cfg(test)]
+mod benchmarks;
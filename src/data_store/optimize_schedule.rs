@@ -0,0 +1,68 @@
+use std::time::{Duration, Instant};
+
+/// Source of the current time, e.g. replaced by a manually advanced clock in tests.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+#[derive(Debug, Clone)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Decides when long running processes (e.g. a daemon watching the store) optimize the DB
+/// (see DataStore::optimize_database_if_due).
+/// There is no such process yet (the CLI runs one command and exits), the schedule is the API
+/// for a future watch mode to call on each of its iterations.
+///
+/// Runs are spaced by the interval plus a random jitter of up to max_jitter, so many devices
+/// started at the same time do not all optimize their DBs (and hit shared disks) at once.
+#[derive(Debug, Clone)]
+pub struct OptimizeSchedule<C: Clock> {
+    clock: C,
+    interval: Duration,
+    max_jitter: Duration,
+    next_run: Instant,
+}
+
+impl<C: Clock> OptimizeSchedule<C> {
+    pub fn new(clock: C, interval: Duration, max_jitter: Duration) -> Self {
+        let now = clock.now();
+        let mut schedule = Self {
+            clock,
+            interval,
+            max_jitter,
+            next_run: now,
+        };
+        schedule.schedule_next();
+
+        schedule
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.clock.now() >= self.next_run
+    }
+
+    /// Schedules the next run one interval (plus jitter) from now.
+    pub fn schedule_next(&mut self) {
+        self.next_run = self.clock.now() + self.interval + self.jitter();
+    }
+
+    fn jitter(&self) -> Duration {
+        use ring::rand::{SecureRandom, SystemRandom};
+
+        let max_jitter_millis = self.max_jitter.as_millis() as u64;
+        if max_jitter_millis == 0 {
+            return Duration::from_millis(0);
+        }
+        let mut random_bytes = [0u8; 8];
+        if SystemRandom::new().fill(&mut random_bytes).is_err() {
+            return Duration::from_millis(0);
+        }
+
+        Duration::from_millis(u64::from_le_bytes(random_bytes) % (max_jitter_millis + 1))
+    }
+}
@@ -0,0 +1,70 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// Sub-trees temporarily excluded from scans (see DataStore::pause_path). Unlike ignore rules
+/// they do not change the sync state of items, scans simply leave their DB entries untouched.
+///
+/// Persisted in the store's metadata dir (it is never synced) as one path per line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PausedPaths {
+    paths: Vec<RelativePath>,
+}
+impl PausedPaths {
+    pub fn new() -> Self {
+        Self { paths: vec![] }
+    }
+
+    pub fn paths(&self) -> &Vec<RelativePath> {
+        &self.paths
+    }
+
+    /// Returns false if the path is already paused.
+    pub fn pause(&mut self, path: &RelativePath) -> bool {
+        if self.position(path).is_some() {
+            return false;
+        }
+
+        self.paths.push(path.clone());
+        true
+    }
+
+    /// Returns false if the path was not paused.
+    pub fn resume(&mut self, path: &RelativePath) -> bool {
+        match self.position(path) {
+            Some(index) => {
+                self.paths.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks if the path or one of its parents is paused.
+    pub fn is_paused(&self, path: &RelativePath) -> bool {
+        let path = path.to_lower_case();
+        self.paths.iter().any(|paused_path| {
+            path.get_path_components()
+                .starts_with(paused_path.to_lower_case().get_path_components())
+        })
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut result = String::new();
+        for path in &self.paths {
+            result.push_str(&format!("{}\n", path.to_path_buf().to_str().unwrap()));
+        }
+        result
+    }
+
+    pub fn parse(content: &str) -> Self {
+        Self {
+            paths: content.lines().map(RelativePath::from_path).collect(),
+        }
+    }
+
+    fn position(&self, path: &RelativePath) -> Option<usize> {
+        let path = path.to_lower_case();
+        self.paths
+            .iter()
+            .position(|paused_path| paused_path.to_lower_case() == path)
+    }
+}
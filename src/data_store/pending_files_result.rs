@@ -0,0 +1,19 @@
+#[derive(Debug, PartialEq)]
+pub struct PendingFilesResult {
+    pub pending_files: usize,
+    pub pending_bytes: u64,
+}
+impl Default for PendingFilesResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PendingFilesResult {
+    pub fn new() -> Self {
+        Self {
+            pending_files: 0,
+            pending_bytes: 0,
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+/// Limits how long and how much of the store's trash is kept, i.e. the conflict copies created
+/// when keeping both versions of a conflicting file (see DataStore::clean_retention).
+///
+/// The newest copies are kept first, all copies older than max_age or exceeding max_bytes in
+/// total are permanently removed. No limits are set by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age: Option<Duration>,
+    pub max_bytes: Option<u64>,
+}
+
+impl RetentionPolicy {
+    /// Keeps all conflict copies forever.
+    pub fn unlimited() -> Self {
+        Self::new(None, None)
+    }
+
+    pub fn new(max_age: Option<Duration>, max_bytes: Option<u64>) -> Self {
+        Self { max_age, max_bytes }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age.is_none() && self.max_bytes.is_none()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct RetentionResult {
+    pub removed_files: usize,
+    pub removed_bytes: u64,
+}
+impl Default for RetentionResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RetentionResult {
+    pub fn new() -> Self {
+        Self {
+            removed_files: 0,
+            removed_bytes: 0,
+        }
+    }
+}
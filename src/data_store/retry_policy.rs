@@ -0,0 +1,59 @@
+use super::{DataStoreError, Result};
+use crate::fs_interaction::FSInteractionError;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// Retries file transfers that failed with transient IO errors, e.g. a network share that
+/// is briefly unavailable. Permanent errors (e.g. missing files or permissions) fail directly.
+///
+/// The wait between attempts starts at the backoff and doubles with each further attempt.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Tries every operation exactly once.
+    pub fn none() -> Self {
+        Self::new(1, Duration::from_secs(0))
+    }
+
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        Self {
+            attempts: std::cmp::max(attempts, 1),
+            backoff,
+        }
+    }
+
+    pub fn run<T, F: FnMut() -> Result<T>>(&self, mut operation: F) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match operation() {
+                Err(ref error) if attempt < self.attempts && Self::is_transient(error) => {
+                    warn!(
+                        "Transient error, retrying ({}/{}): {:?}",
+                        attempt,
+                        self.attempts - 1,
+                        error
+                    );
+                    std::thread::sleep(self.backoff * 2u32.pow(attempt - 1));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn is_transient(error: &DataStoreError) -> bool {
+        match error {
+            DataStoreError::FSInteractionError {
+                source: FSInteractionError::IOError { kind, .. },
+            } => matches!(
+                kind,
+                ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+            ),
+            _ => false,
+        }
+    }
+}
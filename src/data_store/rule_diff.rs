@@ -0,0 +1,35 @@
+use metadata_db::DBInclusionRule;
+
+/// Inclusion/ignore rules that only one of two data stores holds. Differing rule sets are a
+/// common reason for data not propagating between stores.
+/// A pattern that is an inclusion rule on one side and an ignore rule on the other side is
+/// listed on both sides.
+#[derive(Debug, PartialEq)]
+pub struct RuleDiff {
+    pub only_local: Vec<DBInclusionRule>,
+    pub only_remote: Vec<DBInclusionRule>,
+}
+impl RuleDiff {
+    pub fn new(local_rules: &[DBInclusionRule], remote_rules: &[DBInclusionRule]) -> Self {
+        let only_local = local_rules
+            .iter()
+            .filter(|rule| !remote_rules.contains(rule))
+            .cloned()
+            .collect();
+        let only_remote = remote_rules
+            .iter()
+            .filter(|rule| !local_rules.contains(rule))
+            .cloned()
+            .collect();
+
+        Self {
+            only_local,
+            only_remote,
+        }
+    }
+
+    /// Both stores use exactly the same rules (the order of the rules is not considered).
+    pub fn is_empty(&self) -> bool {
+        self.only_local.is_empty() && self.only_remote.is_empty()
+    }
+}
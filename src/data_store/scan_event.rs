@@ -1,3 +1,5 @@
+use data_store::DuplicateNames;
+use fs_interaction::relative_path::RelativePath;
 use fs_interaction::{DataItem, Issue};
 use metadata_db::DBItem;
 
@@ -17,6 +19,7 @@ pub enum ScanEvent<'a> {
     DeletedItem(&'a DBItem),
     IgnoredNewItem(&'a DataItem),
     IgnoredExistingItem(&'a DataItem),
+    DeferredUnsettledFile(&'a DataItem),
 
     IssueBitRot {
         fs_item: &'a DataItem,
@@ -25,4 +28,32 @@ pub enum ScanEvent<'a> {
     },
     IssueSkipLink(&'a DataItem),
     IssueOther(&'a DataItem, &'a Issue),
+    /// Items of a folder whose names only differ in case (see DuplicatePolicy).
+    IssueDuplicateNames(&'a DuplicateNames),
+}
+
+impl<'a> ScanEvent<'a> {
+    /// The path of the item the event is about.
+    pub fn path(&self) -> &RelativePath {
+        use self::ScanEvent::*;
+
+        match self {
+            UnchangedFile(fs_item, _)
+            | UnchangedFolder(fs_item, _)
+            | NewFile(fs_item)
+            | NewFolder(fs_item)
+            | ChangedFile(fs_item, _)
+            | ChangedFolder(fs_item, _)
+            | ChangedFolderToFile(fs_item, _)
+            | ChangedFileToFolder(fs_item, _)
+            | IgnoredNewItem(fs_item)
+            | IgnoredExistingItem(fs_item)
+            | DeferredUnsettledFile(fs_item)
+            | IssueBitRot { fs_item, .. }
+            | IssueSkipLink(fs_item)
+            | IssueOther(fs_item, _) => &fs_item.relative_path,
+            DeletedItem(db_item) => &db_item.path,
+            IssueDuplicateNames(duplicate_names) => &duplicate_names.paths[0],
+        }
+    }
 }
@@ -0,0 +1,84 @@
+use super::*;
+
+/// Scans a sub-tree of a data store on a different thread, e.g. while the data store syncs a
+/// different sub-tree (see DataStore::scan_reader).
+///
+/// The scan classifies the items exactly like a regular scan (i.e. it walks the disk and hashes
+/// changed files), but only reads from the store's DB through its own connection. The found
+/// changes are returned as a PendingScan, which the data store it was created from writes into
+/// its DB (see DataStore::apply_scan). This way all writes go through the store's single DB
+/// connection, while the expensive part of the scan runs in parallel.
+pub struct ScanReader<FS: virtual_fs::FS> {
+    data_store: DataStore<FS>,
+    path: RelativePath,
+}
+
+/// The changes found by a ScanReader that are not written into the DB yet.
+/// Keeps the scanned sub-tree locked until it is applied or dropped.
+pub struct PendingScan {
+    pub(super) scan_result: ScanResult,
+    pub(super) changes: Vec<ScanChange>,
+    pub(super) guard: SubtreeGuard,
+}
+
+// A single DB update of a scan (see DataStore::apply_scan_change).
+pub(super) enum ScanChange {
+    UpdateItem(DataItem, String),
+    UpdateItemNameCase(ItemFSMetadata, DataItem),
+    DeleteItem(RelativePath),
+    ResetItem(RelativePath),
+    IgnoreItem(RelativePath),
+}
+
+impl<FS: virtual_fs::FS> ScanReader<FS> {
+    pub fn new(data_store: &DataStore<FS>, path: &RelativePath) -> Result<Self> {
+        Ok(Self {
+            data_store: data_store.scan_worker()?,
+            path: path.clone(),
+        })
+    }
+
+    /// Scans the sub-tree, failing with SubtreeBusy if an overlapping operation is running.
+    pub fn scan(&self) -> Result<PendingScan> {
+        // Items can only be indexed into folders known to the DB, i.e. the scan starts at the
+        // top-most folder that is not indexed yet (see DataStore::reindex_path).
+        let mut scan_path = self.path.clone();
+        while !scan_path.is_root()
+            && !self
+                .data_store
+                .db_access
+                .get_local_data_item(&scan_path.parent(), false)?
+                .is_folder()
+        {
+            scan_path = scan_path.parent();
+        }
+        let guard = self
+            .data_store
+            .subtree_locks
+            .lock(&scan_path)
+            .ok_or_else(|| DataStoreError::SubtreeBusy {
+                path: scan_path.clone(),
+            })?;
+
+        let scan_result = self.data_store.reindex_path_unlocked(&self.path)?;
+        let changes = self
+            .data_store
+            .recorded_scan_changes
+            .as_ref()
+            .unwrap()
+            .replace(vec![]);
+
+        Ok(PendingScan {
+            scan_result,
+            changes,
+            guard,
+        })
+    }
+}
+
+impl PendingScan {
+    /// The result the scan reports once it is applied.
+    pub fn scan_result(&self) -> &ScanResult {
+        &self.scan_result
+    }
+}
@@ -1,9 +1,24 @@
+use data_store::DuplicateNames;
+use fs_interaction::relative_path::RelativePath;
+
 #[derive(Debug, PartialEq)]
 pub struct ScanResult {
     pub indexed_items: usize,
     pub changed_items: usize,
     pub new_items: usize,
     pub deleted_items: usize,
+    /// New or changed items whose reported creation time was bogus and replaced by the
+    /// modification time (see DataStore::sanitize_creation_time).
+    pub unreliable_creation_times: usize,
+    /// Ignored items whose content is still on disk (e.g. after changing the inclusion rules)
+    /// and the total size of their content. Folders are listed without their content.
+    /// They are no longer synced, see DataStore::delete_ignored_on_disk to remove them.
+    pub ignored_on_disk: Vec<RelativePath>,
+    pub ignored_on_disk_bytes: u64,
+    /// Items whose names only differ in case and how they were treated (see DuplicatePolicy).
+    pub duplicate_names: Vec<DuplicateNames>,
+    /// Paths of all changed items, only collected if requested for the scan.
+    pub changes: Option<ScanChanges>,
 }
 impl ScanResult {
     pub fn new() -> Self {
@@ -12,15 +27,69 @@ impl ScanResult {
             changed_items: 0,
             new_items: 0,
             deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     }
 
     pub fn combine(&self, other: &Self) -> Self {
+        let changes = match (&self.changes, &other.changes) {
+            (Some(changes), Some(other_changes)) => Some(changes.combine(other_changes)),
+            (Some(changes), None) | (None, Some(changes)) => Some(changes.clone()),
+            (None, None) => None,
+        };
+
         Self {
             indexed_items: self.indexed_items + other.indexed_items,
             changed_items: self.changed_items + other.changed_items,
             new_items: self.new_items + other.new_items,
             deleted_items: self.deleted_items + other.deleted_items,
+            unreliable_creation_times: self.unreliable_creation_times
+                + other.unreliable_creation_times,
+            ignored_on_disk: [&self.ignored_on_disk[..], &other.ignored_on_disk[..]].concat(),
+            ignored_on_disk_bytes: self.ignored_on_disk_bytes + other.ignored_on_disk_bytes,
+            duplicate_names: [&self.duplicate_names[..], &other.duplicate_names[..]].concat(),
+            changes,
+        }
+    }
+}
+
+/// Lists the paths behind the counts of a ScanResult.
+/// Items changing their type (file to folder or vice versa) are listed as deleted AND new.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScanChanges {
+    pub new_items: Vec<RelativePath>,
+    pub changed_items: Vec<RelativePath>,
+    pub deleted_items: Vec<RelativePath>,
+}
+impl Default for ScanChanges {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScanChanges {
+    pub fn new() -> Self {
+        Self {
+            new_items: Vec::new(),
+            changed_items: Vec::new(),
+            deleted_items: Vec::new(),
         }
     }
+
+    pub fn combine(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        result.new_items.extend(other.new_items.iter().cloned());
+        result
+            .changed_items
+            .extend(other.changed_items.iter().cloned());
+        result
+            .deleted_items
+            .extend(other.deleted_items.iter().cloned());
+
+        result
+    }
 }
@@ -0,0 +1,74 @@
+use fs_interaction::relative_path::RelativePath;
+use std::sync::{Arc, Mutex};
+
+/// Coordinates operations that work on parts of the same data store, e.g. a sync of one
+/// sub-tree and a scan (see ScanReader) of another one running on a different thread.
+///
+/// Each operation holds a lock on its sub-tree, overlapping sub-trees (i.e. one contains the
+/// other) can not be locked at the same time. Clones share their locks.
+#[derive(Debug, Clone)]
+pub struct SubtreeLocks {
+    locked_paths: Arc<Mutex<Vec<RelativePath>>>,
+}
+
+/// Holds the lock on a sub-tree until it is dropped.
+#[derive(Debug)]
+pub struct SubtreeGuard {
+    locks: SubtreeLocks,
+    path: RelativePath,
+}
+
+impl Default for SubtreeLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SubtreeLocks {
+    pub fn new() -> Self {
+        Self {
+            locked_paths: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Locks the sub-tree at the given path. Returns None if an overlapping sub-tree is locked.
+    pub fn lock(&self, path: &RelativePath) -> Option<SubtreeGuard> {
+        let path = path.to_lower_case();
+        let mut locked_paths = self.locked_paths.lock().unwrap();
+        if locked_paths
+            .iter()
+            .any(|locked_path| Self::overlaps(locked_path, &path))
+        {
+            return None;
+        }
+
+        locked_paths.push(path.clone());
+        Some(SubtreeGuard {
+            locks: self.clone(),
+            path,
+        })
+    }
+
+    fn overlaps(path_1: &RelativePath, path_2: &RelativePath) -> bool {
+        let components_1 = path_1.get_path_components();
+        let components_2 = path_2.get_path_components();
+
+        components_1.starts_with(components_2) || components_2.starts_with(components_1)
+    }
+}
+
+impl SubtreeGuard {
+    /// Checks if the guard holds a lock of the given locks (or one of their clones).
+    pub fn belongs_to(&self, locks: &SubtreeLocks) -> bool {
+        Arc::ptr_eq(&self.locks.locked_paths, &locks.locked_paths)
+    }
+}
+
+impl Drop for SubtreeGuard {
+    fn drop(&mut self) {
+        let mut locked_paths = self.locks.locked_paths.lock().unwrap();
+        if let Some(index) = locked_paths.iter().position(|path| *path == self.path) {
+            locked_paths.remove(index);
+        }
+    }
+}
@@ -5,6 +5,16 @@ pub enum SyncConflictResolution {
     ChooseLocalItem,
     ChooseRemoteItem,
     DoNotResolve,
+    /// Keeps the side whose file system modification time is more recent (local on ties).
+    /// An existing item always wins against a deletion, as deletions carry no modification time.
+    /// NOTE: This relies on wall-clock times of possibly different machines, which can be off
+    ///       or manipulated. It does not use the logical version vectors and is only a heuristic.
+    MostRecentMtime,
+    /// Merges both versions of a file using the merge command of the sync options. The tool
+    /// gets the local, the common base and the remote version and its result becomes the new
+    /// local version. If no base was recorded for the file, the remote version is kept next to
+    /// the local one instead. Only applies to file content, other conflicts stay unresolved.
+    ExternalMerge,
 }
 
 pub enum SyncConflictEvent<'a> {
@@ -16,3 +26,16 @@ pub enum SyncConflictEvent<'a> {
     LocalItemRemoteFile(&'a DBItem, &'a IntFileSyncContent),
     LocalItemRemoteDeletion(&'a DBItem, &'a IntDeletionSyncContent),
 }
+
+impl<'a> SyncConflictEvent<'a> {
+    /// The local side of the conflict, its path identifies the conflict.
+    pub fn local_item(&self) -> &'a DBItem {
+        match self {
+            SyncConflictEvent::LocalFileRemoteFolder(local_item, _)
+            | SyncConflictEvent::LocalDeletionRemoteFile(local_item, _)
+            | SyncConflictEvent::LocalDeletionRemoteFolder(local_item, _)
+            | SyncConflictEvent::LocalItemRemoteFile(local_item, _)
+            | SyncConflictEvent::LocalItemRemoteDeletion(local_item, _) => local_item,
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// Options for a single sync run, the defaults perform a regular sync of the complete tree.
+///
+/// Adjust them using the builder methods, e.g.
+/// SyncOptions::new().full_walk(true).skip(vec![RelativePath::from_path("videos")]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncOptions {
+    /// Descend into every sub-folder, even if the remote reports it to be up to date.
+    pub full_walk: bool,
+    /// Sub-trees synced first (in the given order), all others are synced afterwards.
+    pub priority: Vec<RelativePath>,
+    /// Sub-trees left out of this sync run. Unlike ignore rules, this does not change the
+    /// DB state, i.e. a later sync without them picks them up as usual.
+    pub skip: Vec<RelativePath>,
+    /// Limits this sync run to the given sub-trees (all paths if empty).
+    pub only: Vec<RelativePath>,
+    /// Number of files downloaded concurrently (per folder) before applying them one by one.
+    pub parallel_downloads: usize,
+    /// Downloads the next file of a folder in the background while the current item is synced,
+    /// i.e. overlaps transfers with DB updates. Prefetched files are verified against the
+    /// remote's hash and discarded if they are not needed (e.g. on conflicts).
+    pub pipelined_prefetch: bool,
+    /// Replace local folders by remote files (and vice versa). If not allowed (the default, as
+    /// replacing a folder removes all its content), these items are left out of the sync and
+    /// reported as refused type changes.
+    pub allow_type_changes: bool,
+    /// Re-index local items whose disk content changed since the last scan and retry them once,
+    /// instead of failing the sync with DiskOutOfSync.
+    pub reindex_out_of_sync_items: bool,
+    /// Leaves out items more than the given number of levels below the store root (e.g. 1 only
+    /// syncs the root's direct children). Folders with left out children keep their sync times.
+    pub max_depth: Option<usize>,
+    /// Aborts the sync with TooManyConflicts once more than the given number of conflicts were
+    /// left unresolved. Items synced up to then stay in sync.
+    pub max_conflicts: Option<usize>,
+    /// External command (program followed by its leading arguments) used to merge conflicting
+    /// file versions, see SyncConflictResolution::ExternalMerge. If set, the sync also records
+    /// the content of transferred files as merge bases for later conflicts.
+    pub merge_command: Option<Vec<String>>,
+    /// Leaves out items the remote's own inclusion rules ignore, even if it holds them (e.g.
+    /// transfer stores carry items for other stores regardless of their own rules). These are
+    /// handled like skipped items, i.e. they are not recorded as ignored locally and a later
+    /// sync without the option picks them up.
+    pub respect_remote_rules: bool,
+    /// Never deletes local items because of remote deletion notices, e.g. to merge the remote
+    /// into this store for recovery (see DataStore::merge_index_from). Kept items are handled
+    /// like skipped items, i.e. a later sync without the option still applies the deletions.
+    pub keep_local_items: bool,
+}
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SyncOptions {
+    pub fn new() -> Self {
+        Self {
+            full_walk: false,
+            priority: vec![],
+            skip: vec![],
+            only: vec![],
+            parallel_downloads: 1,
+            pipelined_prefetch: false,
+            allow_type_changes: false,
+            reindex_out_of_sync_items: false,
+            max_depth: None,
+            max_conflicts: None,
+            merge_command: None,
+            respect_remote_rules: false,
+            keep_local_items: false,
+        }
+    }
+
+    pub fn full_walk(mut self, full_walk: bool) -> Self {
+        self.full_walk = full_walk;
+        self
+    }
+
+    pub fn priority(mut self, priority: Vec<RelativePath>) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn skip(mut self, skip: Vec<RelativePath>) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    pub fn only(mut self, only: Vec<RelativePath>) -> Self {
+        self.only = only;
+        self
+    }
+
+    pub fn parallel_downloads(mut self, parallel_downloads: usize) -> Self {
+        self.parallel_downloads = std::cmp::max(parallel_downloads, 1);
+        self
+    }
+
+    pub fn pipelined_prefetch(mut self, pipelined_prefetch: bool) -> Self {
+        self.pipelined_prefetch = pipelined_prefetch;
+        self
+    }
+
+    pub fn allow_type_changes(mut self, allow_type_changes: bool) -> Self {
+        self.allow_type_changes = allow_type_changes;
+        self
+    }
+
+    pub fn reindex_out_of_sync_items(mut self, reindex_out_of_sync_items: bool) -> Self {
+        self.reindex_out_of_sync_items = reindex_out_of_sync_items;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn max_conflicts(mut self, max_conflicts: Option<usize>) -> Self {
+        self.max_conflicts = max_conflicts;
+        self
+    }
+
+    pub fn merge_command(mut self, merge_command: Option<Vec<String>>) -> Self {
+        self.merge_command = merge_command;
+        self
+    }
+
+    pub fn respect_remote_rules(mut self, respect_remote_rules: bool) -> Self {
+        self.respect_remote_rules = respect_remote_rules;
+        self
+    }
+
+    pub fn keep_local_items(mut self, keep_local_items: bool) -> Self {
+        self.keep_local_items = keep_local_items;
+        self
+    }
+}
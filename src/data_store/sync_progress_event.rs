@@ -0,0 +1,30 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// Reports the files transferred during a sync run, e.g. to render a progress bar.
+/// The running totals include the finished file.
+pub enum SyncProgressEvent<'a> {
+    FileStarted {
+        path: &'a RelativePath,
+        size: u64,
+    },
+    FileFinished {
+        path: &'a RelativePath,
+        size: u64,
+        transferred_files: usize,
+        transferred_bytes: u64,
+    },
+    /// An item changed on disk and its DB entry is committed, e.g. to re-generate thumbnails
+    /// or notify an indexer. Reported after the DB update, i.e. handlers can not interfere
+    /// with the sync state of the item.
+    ItemSynced {
+        path: &'a RelativePath,
+        action: SyncedItemAction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncedItemAction {
+    Created,
+    Updated,
+    Deleted,
+}
@@ -0,0 +1,138 @@
+use fs_interaction::relative_path::RelativePath;
+use std::collections::HashSet;
+
+#[derive(Debug, PartialEq)]
+pub struct SyncResult {
+    pub visited_items: usize,
+    pub up_to_date_items: usize,
+    /// Files downloaded (or re-created as hard links) and their total size in bytes.
+    /// Content re-used from the local version of a file (appended files) is not counted.
+    pub transferred_files: usize,
+    pub transferred_bytes: u64,
+    /// Local items replaced by a remote item of a different type.
+    pub type_changes: Vec<TypeChange>,
+    /// Type changes that were left out, as the sync options did not allow them.
+    pub refused_type_changes: Vec<TypeChange>,
+    /// Conflicts the resolver decided to not resolve, i.e. the items were left as they are.
+    pub unresolved_conflicts: usize,
+    /// Remote files whose content the remote could not supply (content unavailable), e.g. as
+    /// only their metadata was synced to it. They are left out and picked up by a later sync.
+    pub unavailable_items: Vec<RelativePath>,
+    /// Local files replaced by the result of the external merge command.
+    pub merged_files: Vec<RelativePath>,
+    /// Copies of remote file versions stored next to the conflicting local file, as they could
+    /// not be merged (no merge base available).
+    pub conflict_copies: Vec<RelativePath>,
+    /// Items the remote ignores while we do not, i.e. it can not tell us about their current
+    /// state. They keep their parent folders from taking up the remote's sync time.
+    pub remotely_ignored_items: Vec<RelativePath>,
+    /// Local items deleted by a mirror as the remote does not hold them (see mirror_from).
+    pub mirrored_deletions: Vec<RelativePath>,
+    /// Items kept (or changed) for subtle reasons, e.g. local items surviving a remote deletion
+    /// as they were created independently of it (see SyncExplanation).
+    pub explanations: Vec<SyncExplanation>,
+    /// Set if the syncs from this remote repeatedly do not converge (see StalledSyncWarning).
+    pub stalled_sync_warning: Option<StalledSyncWarning>,
+    /// All items whose DB entry was written by the sync, i.e. items that got created, changed
+    /// or deleted, but also items that only advanced their sync time (e.g. up to date folders).
+    /// Use it to invalidate anything cached about these paths.
+    pub touched_paths: HashSet<RelativePath>,
+}
+impl Default for SyncResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl SyncResult {
+    pub fn new() -> Self {
+        Self {
+            visited_items: 0,
+            up_to_date_items: 0,
+            transferred_files: 0,
+            transferred_bytes: 0,
+            type_changes: vec![],
+            refused_type_changes: vec![],
+            unresolved_conflicts: 0,
+            unavailable_items: vec![],
+            merged_files: vec![],
+            conflict_copies: vec![],
+            remotely_ignored_items: vec![],
+            mirrored_deletions: vec![],
+            explanations: vec![],
+            stalled_sync_warning: None,
+            touched_paths: HashSet::new(),
+        }
+    }
+
+    pub fn combine(&self, other: &Self) -> Self {
+        Self {
+            visited_items: self.visited_items + other.visited_items,
+            up_to_date_items: self.up_to_date_items + other.up_to_date_items,
+            transferred_files: self.transferred_files + other.transferred_files,
+            transferred_bytes: self.transferred_bytes + other.transferred_bytes,
+            type_changes: [&self.type_changes[..], &other.type_changes[..]].concat(),
+            refused_type_changes: [
+                &self.refused_type_changes[..],
+                &other.refused_type_changes[..],
+            ]
+            .concat(),
+            unresolved_conflicts: self.unresolved_conflicts + other.unresolved_conflicts,
+            unavailable_items: [&self.unavailable_items[..], &other.unavailable_items[..]].concat(),
+            merged_files: [&self.merged_files[..], &other.merged_files[..]].concat(),
+            conflict_copies: [&self.conflict_copies[..], &other.conflict_copies[..]].concat(),
+            remotely_ignored_items: [
+                &self.remotely_ignored_items[..],
+                &other.remotely_ignored_items[..],
+            ]
+            .concat(),
+            mirrored_deletions: [&self.mirrored_deletions[..], &other.mirrored_deletions[..]]
+                .concat(),
+            explanations: [&self.explanations[..], &other.explanations[..]].concat(),
+            stalled_sync_warning: other
+                .stalled_sync_warning
+                .clone()
+                .or_else(|| self.stalled_sync_warning.clone()),
+            touched_paths: self
+                .touched_paths
+                .union(&other.touched_paths)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Syncs from the same remote that repeatedly transferred nothing, but still could not take up
+/// the remote's sync time. Each further sync walks the same items again without converging.
+/// This is usually caused by the blocking_paths, i.e. items the remote ignores or can not supply
+/// while we hold them. Syncing them in from a store that holds them resolves the situation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StalledSyncWarning {
+    pub stalled_syncs: i64,
+    pub blocking_paths: Vec<RelativePath>,
+}
+
+/// A human-readable reason for the outcome of an item's sync, reported where the outcome might
+/// be unexpected. For example, a remote deletion only deletes local items created before the
+/// remote last synced with us (by comparing their creation time with the deletion's sync time).
+/// Items created independently of the deletion are kept without any conflict.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncExplanation {
+    pub path: RelativePath,
+    pub reason: &'static str,
+}
+
+/// A local item is replaced by a remote item of a different type during a sync.
+/// These are destructive, e.g. replacing a folder by a file removes all of the folder's content.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeChange {
+    FileBecameFolder(RelativePath),
+    FolderBecameFile(RelativePath),
+}
+impl TypeChange {
+    pub fn path(&self) -> &RelativePath {
+        match self {
+            TypeChange::FileBecameFolder(path) => path,
+            TypeChange::FolderBecameFile(path) => path,
+        }
+    }
+}
@@ -7,50 +7,111 @@ use metadata_db::MetadataDB;
 use std::collections::HashMap;
 
 /// Handshake message before the actual sync procedure starts running.
+/// To stay small for data sets with many stores, it only lists the IDs of all known stores and
+/// a digest over them. Full store records are only included for stores the receiver misses,
+/// i.e. stores knowing the same stores (equal digests) do not exchange any records.
 pub struct SyncHandshake {
     pub data_set_name: String,
+    /// The unique name of the sending data store itself.
+    pub data_store_name: String,
+    /// SHA-256 over the sorted unique names of all known data stores.
+    pub data_stores_digest: String,
+    /// The sender's (DB) IDs of all known data stores and their unique names.
+    pub data_store_ids: Vec<(i64, String)>,
+    /// Full records of the data stores the receiver does not know about.
     pub data_stores: Vec<metadata_db::DataStore>,
 }
+impl SyncHandshake {
+    /// Creates a handshake listing the given stores, without including any full store records.
+    pub fn new(data_set_name: String, data_stores: &[metadata_db::DataStore]) -> Self {
+        use data_encoding::HEXUPPER;
+        use ring::digest::{Context, SHA256};
+
+        let mut unique_names: Vec<&str> = data_stores
+            .iter()
+            .map(|data_store| data_store.unique_name.as_str())
+            .collect();
+        unique_names.sort_unstable();
+        let mut context = Context::new(&SHA256);
+        for unique_name in unique_names {
+            context.update(unique_name.as_bytes());
+            context.update(b"\n");
+        }
+
+        Self {
+            data_set_name,
+            data_store_name: data_stores
+                .iter()
+                .find(|data_store| data_store.is_this_store)
+                .map(|data_store| data_store.unique_name.clone())
+                .unwrap_or_default(),
+            data_stores_digest: HEXUPPER.encode(context.finish().as_ref()),
+            data_store_ids: data_stores
+                .iter()
+                .map(|data_store| (data_store.id, data_store.unique_name.clone()))
+                .collect(),
+            data_stores: vec![],
+        }
+    }
+
+    pub fn knows_data_store(&self, unique_name: &str) -> bool {
+        self.data_store_ids
+            .iter()
+            .any(|(_, known_name)| known_name == unique_name)
+    }
+}
 /// Mapper to translate remote data store IDs into local data store IDs.
 /// This is required to understand the sync and version vectors given by the other store.
+/// Stores unknown locally (see DataStore::set_data_store_approval) are dropped from vectors.
+/// This is only safe for sync times, check other vectors with knows_all before mapping them.
 pub struct DataStoreIDMapper {
     ext_to_int: HashMap<i64, i64>,
 }
 impl DataStoreIDMapper {
     pub fn create_mapper(local_db: &MetadataDB, remote: SyncHandshake) -> super::Result<Self> {
-        let mut ext_to_int = HashMap::with_capacity(remote.data_stores.len());
+        let mut ext_to_int = HashMap::with_capacity(remote.data_store_ids.len());
 
-        for remote_data_store in remote.data_stores {
-            let local_data_store = local_db
-                .get_data_store(&remote_data_store.unique_name)?
-                .unwrap();
-            ext_to_int.insert(remote_data_store.id, local_data_store.id);
+        for (remote_id, unique_name) in remote.data_store_ids {
+            if let Some(local_data_store) = local_db.get_data_store(&unique_name)? {
+                ext_to_int.insert(remote_id, local_data_store.id);
+            }
         }
 
         Ok(Self { ext_to_int })
     }
 
-    pub fn external_to_internal(&self, ext_vector: &VersionVector<i64>) -> VersionVector<i64> {
-        let mut result = VersionVector::new();
-        for (id, time) in ext_vector.iter() {
-            result[&self.ext_to_int[id]] = *time;
-        }
+    /// Checks if all stores of the vector are known locally, i.e. if it is mapped without loss.
+    pub fn knows_all(&self, ext_vector: &VersionVector<i64>) -> bool {
+        ext_vector
+            .iter()
+            .all(|(id, _)| self.ext_to_int.contains_key(id))
+    }
 
-        result
+    pub fn external_to_internal(&self, ext_vector: &VersionVector<i64>) -> VersionVector<i64> {
+        VersionVector::from_pairs(
+            ext_vector
+                .iter()
+                .filter_map(|(id, time)| self.ext_to_int.get(id).map(|int_id| (*int_id, *time))),
+        )
     }
 }
 
 /// Send this request to synchronize an item with a target data store.
 /// It will answer appropriately depending on it's local DB entries, i.e. for a file it only
 /// answers with information on the individual file, for a folder it includes it's contents.
+///
+/// Setting full_walk forces the target to answer with the folder contents even if the folder
+/// is already up to date, i.e. the sync descends into every sub-folder.
 pub struct ExtSyncRequest {
     pub item_path: RelativePath,
     pub item_sync_time: VersionVector<i64>,
+    pub full_walk: bool,
 }
 
 pub struct IntSyncRequest {
     pub item_path: RelativePath,
     pub item_sync_time: VersionVector<i64>,
+    pub full_walk: bool,
 }
 
 /// Response to a SyncRequest.
@@ -141,6 +202,7 @@ impl ExtSyncRequest {
         IntSyncRequest {
             item_path: self.item_path,
             item_sync_time: mapper.external_to_internal(&self.item_sync_time),
+            full_walk: self.full_walk,
         }
     }
 }
@@ -149,6 +211,7 @@ impl IntSyncRequest {
         ExtSyncRequest {
             item_path: self.item_path,
             item_sync_time: self.item_sync_time,
+            full_walk: self.full_walk,
         }
     }
 }
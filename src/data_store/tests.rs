@@ -1,7 +1,8 @@
 use super::*;
-use fs_interaction::virtual_fs::{InMemoryFS, FS};
+use fs_interaction::virtual_fs::{InMemoryFS, Operation, FS};
 use glob::Pattern;
 use std::fs::File;
+use std::io;
 use std::io::Write;
 use std::thread::sleep;
 use std::time::Duration;
@@ -43,6 +44,90 @@ fn re_open_data_store() {
     assert!(DefaultDataStore::open(test_dir.path()).is_err());
 }
 
+#[test]
+fn re_open_data_store_with_external_db() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let db_dir = tempfile::tempdir().unwrap();
+    let db_path = db_dir.path().join("store.sqlite");
+    std::fs::write(test_dir.path().join("file-1"), "content").unwrap();
+
+    let options = DataStoreCreateOptions::new("XYZ").external_db_path(&db_path);
+    let data_store = DefaultDataStore::create_with_options(test_dir.path(), &options).unwrap();
+    data_store.perform_full_scan().unwrap();
+    drop(data_store);
+    assert!(db_path.is_file());
+    assert!(!test_dir
+        .path()
+        .join(".__data_squirrel__/database.sqlite")
+        .exists());
+
+    // The lock still lives in the store's metadata dir.
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert!(DefaultDataStore::open(test_dir.path()).is_err());
+    assert!(!data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap()
+        .is_deletion());
+}
+
+#[test]
+fn open_data_store_read_only() {
+    let test_dir = tempfile::tempdir().unwrap();
+    std::fs::write(test_dir.path().join("file-1"), "content").unwrap();
+    let data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+    data_store.perform_full_scan().unwrap();
+    drop(data_store);
+
+    let data_store = DefaultDataStore::open_read_only(test_dir.path()).unwrap();
+    assert!(data_store.fs_access.is_read_only());
+    let item = data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    assert!(item.is_file());
+
+    // Read-only stores neither lock the store nor accept changes.
+    let _data_store_2 = DefaultDataStore::open_read_only(test_dir.path()).unwrap();
+    std::fs::write(test_dir.path().join("file-2"), "content").unwrap();
+    assert!(data_store.perform_full_scan().is_err());
+    drop(data_store);
+    drop(_data_store_2);
+
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(data_store.interrupted_operation(), None);
+    assert_eq!(data_store.perform_full_scan().unwrap().new_items, 1);
+}
+
+#[test]
+fn detect_interrupted_operation_on_open() {
+    let test_dir = tempfile::tempdir().unwrap();
+
+    let data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+    assert_eq!(data_store.interrupted_operation(), None);
+
+    // Simulate a crash in the middle of an operation (marker is never cleared).
+    data_store
+        .fs_access
+        .begin_operation("update_inclusion_rules")
+        .unwrap();
+    drop(data_store);
+
+    // Re-opening must report the unclean state...
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(
+        data_store.interrupted_operation(),
+        Some("update_inclusion_rules")
+    );
+    drop(data_store);
+
+    // ...and recover from it, i.e. the next open is clean again.
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(data_store.interrupted_operation(), None);
+}
+
 #[test]
 fn scan_data_store_directory() {
     let in_memory_fs = virtual_fs::InMemoryFS::new();
@@ -66,7 +151,12 @@ fn scan_data_store_directory() {
             indexed_items: 6,
             changed_items: 0,
             new_items: 6,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 8);
@@ -84,7 +174,12 @@ fn scan_data_store_directory() {
             indexed_items: 7,
             changed_items: 1,
             new_items: 1,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 10);
@@ -103,6 +198,11 @@ fn scan_data_store_directory() {
             changed_items: 0,
             new_items: 0,
             deleted_items: 2,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 12);
@@ -118,7 +218,12 @@ fn scan_data_store_directory() {
             indexed_items: 6,
             changed_items: 0,
             new_items: 3,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 15);
@@ -135,7 +240,12 @@ fn scan_data_store_directory() {
             indexed_items: 6,
             changed_items: 3,
             new_items: 0,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 18);
@@ -146,7 +256,12 @@ fn scan_data_store_directory() {
             indexed_items: 6,
             changed_items: 0,
             new_items: 0,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
     assert_eq!(data_store_1.local_time().unwrap(), 18);
@@ -177,7 +292,12 @@ fn exclude_ignored_files_during_scan() {
             indexed_items: 6, // Note that we do not even 'see' file-3
             changed_items: 0,
             new_items: 5, // Note that we ignore one of the scanned items
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
 
@@ -203,7 +323,12 @@ fn exclude_ignored_files_during_scan() {
             indexed_items: 7, // We expect to 'see' the ignored file-2, but we do not index it.
             changed_items: 0,
             new_items: 0,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![RelativePath::from_path("file-2")],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
 
@@ -220,10 +345,62 @@ fn exclude_ignored_files_during_scan() {
             changed_items: 0,
             new_items: 2,
             deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
 }
 
+#[test]
+fn root_mod_time_reflects_all_local_changes() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+
+    fs_1.create_dir("sub-1", false).unwrap();
+    fs_1.create_file("sub-1/file-1").unwrap();
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let initial_mod_time = data_store_1.root_mod_time().unwrap();
+
+    // A no-op scan must not change anything.
+    data_store_1.perform_full_scan().unwrap();
+    assert_eq!(data_store_1.root_mod_time().unwrap(), initial_mod_time);
+
+    // Changes in nested items bubble up to the root.
+    fs_1.test_increase_file_mod_time("sub-1/file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let changed_mod_time = data_store_1.root_mod_time().unwrap();
+    assert!(changed_mod_time > initial_mod_time);
+
+    // Deletions bump the root.
+    fs_1.remove_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let deleted_mod_time = data_store_1.root_mod_time().unwrap();
+    assert!(deleted_mod_time > changed_mod_time);
+
+    // Ignoring items bumps the root.
+    data_store_1
+        .add_ignore_rule(Pattern::new("/sub-1").unwrap())
+        .unwrap();
+    let ignored_mod_time = data_store_1.root_mod_time().unwrap();
+    assert!(ignored_mod_time > deleted_mod_time);
+
+    // Un-ignoring (i.e. resetting) items bumps the root.
+    let mut rules = data_store_1.get_inclusion_rules().clone();
+    rules.remove_rule("/sub-1");
+    data_store_1.update_inclusion_rules(rules, false).unwrap();
+    let reset_mod_time = data_store_1.root_mod_time().unwrap();
+    assert!(reset_mod_time > ignored_mod_time);
+
+    data_store_1.perform_full_scan().unwrap();
+    let rescanned_mod_time = data_store_1.root_mod_time().unwrap();
+    assert!(rescanned_mod_time > reset_mod_time);
+    data_store_1.perform_full_scan().unwrap();
+    assert_eq!(data_store_1.root_mod_time().unwrap(), rescanned_mod_time);
+}
+
 /// Regression:
 /// The sync algorithm used 'all_children_synced = all_children_synced && recursive_call()'.
 /// The short circuiting of the && operator did not perform any further calls once the variable
@@ -319,7 +496,12 @@ fn unidirectional_sync() {
             indexed_items: 6,
             changed_items: 0,
             new_items: 0,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
 
@@ -358,8 +540,7 @@ fn unidirectional_sync() {
 }
 
 #[test]
-#[should_panic(expected = "Must not sync if disk content is not correctly indexed in DB")]
-fn panics_when_trying_to_sync_without_index_1() {
+fn fails_when_trying_to_sync_without_index_1() {
     let (fs_1, data_store_1) = create_in_memory_store();
     let (fs_2, data_store_2) = create_in_memory_store();
 
@@ -373,14 +554,16 @@ fn panics_when_trying_to_sync_without_index_1() {
     data_store_2.perform_full_scan().unwrap();
     // Rename item on receiving data store after scan operation
     fs_2.rename("file-1", "FILE-1").unwrap();
-    data_store_2
+    match data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
-        .unwrap();
+    {
+        Err(DataStoreError::DiskOutOfSync { .. }) => (),
+        _ => panic!("Must not sync if disk content is not correctly indexed in DB!"),
+    };
 }
 
 #[test]
-#[should_panic(expected = "Must not sync if disk content is not correctly indexed in DB")]
-fn panics_when_trying_to_sync_without_index_2() {
+fn fails_when_trying_to_sync_without_index_2() {
     let (fs_1, data_store_1) = create_in_memory_store();
     let (fs_2, data_store_2) = create_in_memory_store();
 
@@ -394,14 +577,16 @@ fn panics_when_trying_to_sync_without_index_2() {
     data_store_2.perform_full_scan().unwrap();
     // Delete item on receiving data store after scan operation
     fs_2.remove_file("file-1").unwrap();
-    data_store_2
+    match data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
-        .unwrap();
+    {
+        Err(DataStoreError::DiskOutOfSync { .. }) => (),
+        _ => panic!("Must not sync if disk content is not correctly indexed in DB!"),
+    };
 }
 
 #[test]
-#[should_panic(expected = "Must not sync if disk content is not correctly indexed in DB")]
-fn panics_when_trying_to_sync_without_index_3() {
+fn fails_when_trying_to_sync_without_index_3() {
     let (fs_1, data_store_1) = create_in_memory_store();
     let (fs_2, data_store_2) = create_in_memory_store();
 
@@ -415,14 +600,16 @@ fn panics_when_trying_to_sync_without_index_3() {
     data_store_2.perform_full_scan().unwrap();
     // Delete item on sending data store after scan operation
     fs_1.remove_file("file-1").unwrap();
-    data_store_2
+    match data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
-        .unwrap();
+    {
+        Err(DataStoreError::DiskOutOfSync { .. }) => (),
+        _ => panic!("Must not sync if disk content is not correctly indexed in DB!"),
+    };
 }
 
 #[test]
-#[should_panic(expected = "Must not sync if disk content is not correctly indexed in DB")]
-fn panics_when_trying_to_sync_without_index_4() {
+fn fails_when_trying_to_sync_without_index_4() {
     let (fs_1, data_store_1) = create_in_memory_store();
     let (fs_2, data_store_2) = create_in_memory_store();
 
@@ -436,9 +623,30 @@ fn panics_when_trying_to_sync_without_index_4() {
     data_store_2.perform_full_scan().unwrap();
     // Modify item on sending data store after scan operation
     fs_1.test_set_file_content("file-1", "test", true).unwrap();
-    data_store_2
+    match data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    {
+        Err(DataStoreError::DiskOutOfSync { .. }) => (),
+        _ => panic!("Must not sync if disk content is not correctly indexed in DB!"),
+    };
+}
+
+#[test]
+fn report_fs_errors_when_checking_disk_against_index() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Modify item on sending data store, the receiving store can not read its own copy
+    fs_1.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    fs_2.set_error_on("file-1", Operation::Read, io::ErrorKind::PermissionDenied)
         .unwrap();
+    match data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    {
+        Err(DataStoreError::FSInteractionError { .. }) => (),
+        _ => panic!("Must report FS errors instead of treating the disk as out of sync!"),
+    };
 }
 
 #[test]
@@ -477,7 +685,12 @@ fn metadata_set_correctly_after_sync() {
             indexed_items: 1,
             changed_items: 0,
             new_items: 0,
-            deleted_items: 0
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
         }
     );
 }
@@ -578,7 +791,12 @@ fn sync_changes_in_file_name_case() {
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
     data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &SyncOptions::new().allow_type_changes(true),
+            &mut |_| panic!("No conflicts expected!"),
+        )
         .unwrap();
 
     // We expect the case changes to be propagated
@@ -587,6 +805,37 @@ fn sync_changes_in_file_name_case() {
     dir_should_contain(&fs_2, "sub-1", vec!["FILE-1", "file-2"]);
 }
 
+#[test]
+fn sync_names_differing_in_unicode_normalization() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    assert!(data_store_1.normalizes_unicode().unwrap());
+    let nfc_name = "caf\u{e9}";
+    let nfd_name = "cafe\u{301}";
+
+    fs_1.create_file(nfc_name).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // The second store e.g. lives on a disk writing NFD names, it is still the same item.
+    fs_2.rename(nfc_name, nfd_name).unwrap();
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 0);
+    assert_eq!(changes.deleted_items, 0);
+
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    let root_path = RelativePath::from_path("");
+    let items = data_store_1
+        .db_access
+        .get_local_child_items(&root_path, false)
+        .unwrap();
+    assert_eq!(items.len(), 1);
+}
+
 #[test]
 fn multi_target_sync() {
     let (fs_1, data_store_1) = create_in_memory_store();
@@ -777,6 +1026,73 @@ fn multi_target_sync_with_ignores() {
     //       as a missing entry with a higher sync time creates an implicit deletion.
 }
 
+#[test]
+fn warn_about_syncs_that_never_converge() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    let (fs_2, mut data_store_2) = create_in_memory_store();
+    let (_fs_3, data_store_3) = create_in_memory_store();
+
+    // Same 'sharded' setup as in multi_target_sync_with_ignores.
+    fs_1.create_file("file-1").unwrap();
+    fs_2.create_file("file-3").unwrap();
+    data_store_1
+        .add_ignore_rule(Pattern::new("/file-3").unwrap())
+        .unwrap();
+    data_store_2
+        .add_ignore_rule(Pattern::new("/file-1").unwrap())
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Store 1 learns about a change of 'file-3' that store 3 did not get from store 2.
+    fs_2.test_set_file_content("file-3", "changed", true)
+        .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Store 1 only knows 'file-3' as ignored item, i.e. store 3 never takes up its sync time
+    // (as long as it does not get the change from store 2).
+    // Each further sync from store 1 transfers nothing, but does not converge either.
+    for _ in 1..STALLED_SYNCS_WARNING_THRESHOLD {
+        let result = data_store_3
+            .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+            .unwrap();
+        assert_eq!(result.transferred_files, 0);
+        assert_eq!(result.stalled_sync_warning, None);
+    }
+    let result = data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        result.stalled_sync_warning,
+        Some(StalledSyncWarning {
+            stalled_syncs: STALLED_SYNCS_WARNING_THRESHOLD,
+            blocking_paths: vec![RelativePath::from_path("file-3")],
+        })
+    );
+
+    // Syncs that make progress reset the counter.
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let result = data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(result.transferred_files, 1);
+    assert_eq!(result.stalled_sync_warning, None);
+}
+
 #[test]
 fn multi_target_sync_with_transfer_store() {
     let (fs_1, data_store_1) = create_in_memory_store();
@@ -894,719 +1210,4785 @@ fn multi_target_sync_with_transfer_store() {
 }
 
 #[test]
-fn detect_ignore_status_changes() {
-    let (fs_1, mut data_store_1) = create_in_memory_store();
+fn respect_remote_inclusion_rules_on_pull() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_3, data_store_3) = create_in_memory_store();
+    let (fs_transfer, mut transfer_store) = create_in_memory_store();
+    transfer_store.mark_as_transfer_store().unwrap();
+    // The transfer store carries items for other stores, regardless of its own rules.
+    transfer_store
+        .add_ignore_rule(Pattern::new("/private").unwrap())
+        .unwrap();
 
-    // Two stores have a 'split' data set, i.e. they contain exclusive files (think sharding data).
-    fs_1.create_file("file-1").unwrap();
-    fs_1.create_file("file-2").unwrap();
-    fs_1.create_dir("sub", false).unwrap();
-    fs_1.create_file("sub/file-1").unwrap();
-    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.create_dir("private", false).unwrap();
+    fs_1.create_file("private/file-1").unwrap();
+    fs_1.create_dir("public", false).unwrap();
+    fs_1.create_file("public/file-2").unwrap();
     data_store_1.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
 
-    // We indexed everything to be included expect **/file-3, try some other ignore patterns.
-    let mut rules_1 = data_store_1.get_inclusion_rules().clone();
-    rules_1.add_ignore_rule(glob::Pattern::new("**/file-1").unwrap());
-    let rules_1_copy = rules_1.clone();
-
-    let (new_items, removed_items) = data_store_1.update_inclusion_rules(rules_1, false).unwrap();
-    assert_eq!(new_items.len(), 0);
-    assert_eq!(removed_items.len(), 2);
-    assert!(removed_items
-        .iter()
-        .all(|item| item.path.name() == "file-1"));
+    transfer_store
+        .get_significant_sync_times_from_other(&data_store_1)
+        .unwrap();
+    transfer_store
+        .get_significant_sync_times_from_other(&data_store_3)
+        .unwrap();
+    transfer_store
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_transfer, "", vec!["private", "public"]);
 
-    let mut rules_2 = data_store_1.get_inclusion_rules().clone();
-    rules_2.add_ignore_rule(glob::Pattern::new("**/sub").unwrap());
-    let (new_items, removed_items) = data_store_1.update_inclusion_rules(rules_2, false).unwrap();
-    assert_eq!(new_items.len(), 0);
-    assert_eq!(removed_items.len(), 2);
-    assert!(removed_items
-        .iter()
-        .all(|item| item.path.name() == "file-2" || item.path.name() == "sub"));
+    // Respecting the transfer store's rules leaves out the items it ignores itself...
+    data_store_3
+        .sync_from_other_store_with_options(
+            &transfer_store,
+            &RelativePath::from_path(""),
+            &SyncOptions::new().respect_remote_rules(true),
+            &mut |_conflict| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+    dir_should_contain(&fs_3, "", vec!["public"]);
+    dir_should_contain(&fs_3, "public", vec!["file-2"]);
+    dir_should_not_contain(&fs_3, "", vec!["private"]);
+    // ...without recording them as ignored.
+    assert!(data_store_3.ignored_items().unwrap().is_empty());
 
-    // Lets see the effect of 'un-ignoring' files.
-    let (new_items, removed_items) = data_store_1
-        .update_inclusion_rules(rules_1_copy, false)
+    // A regular sync picks them up later on.
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&transfer_store, &RelativePath::from_path(""))
         .unwrap();
-    assert_eq!(new_items.len(), 1);
-    assert_eq!(new_items.first().unwrap().path.name(), "sub");
-    assert_eq!(removed_items.len(), 0);
-    // The scan should pick-up the 'un-ignored' items as new items.
-    let changes = data_store_1.perform_full_scan().unwrap();
-    assert_eq!(
-        changes,
-        ScanResult {
-            indexed_items: 5,
-            changed_items: 0,
-            new_items: 2,
-            deleted_items: 0
-        }
-    );
+    dir_should_contain(&fs_3, "", vec!["private", "public"]);
+    dir_should_contain(&fs_3, "private", vec!["file-1"]);
 }
 
 #[test]
-fn multi_target_transfer_significant_times() {
+fn encrypt_contents_carried_by_transfer_store() {
     let (fs_1, data_store_1) = create_in_memory_store();
-    let (fs_2, data_store_2) = create_in_memory_store();
-    let (_fs_3, data_store_3) = create_in_memory_store();
+    let (fs_3, data_store_3) = create_in_memory_store();
+    let (fs_transfer, mut transfer_store) = create_in_memory_store();
+    transfer_store.mark_as_transfer_store().unwrap();
+    transfer_store
+        .enable_encryption("secret passphrase")
+        .unwrap();
+    assert!(transfer_store.is_encrypted());
 
-    // Initial Data Set
     fs_1.create_dir("sub-1", false).unwrap();
     fs_1.create_file("sub-1/file-1").unwrap();
-    fs_2.create_dir("sub-2", false).unwrap();
-    fs_2.create_file("sub-2/file-1").unwrap();
-
-    // Index all
+    fs_1.test_set_file_content("sub-1/file-1", "private content", true)
+        .unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
     data_store_3.perform_full_scan().unwrap();
 
-    // Sync from 1 to 3 partially only sub-1.
-    // This should leave this should leave the store with 'partial' sync times of 3.
-    data_store_3
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path("sub-1"))
+    transfer_store
+        .get_significant_sync_times_from_other(&data_store_1)
         .unwrap();
-
-    let significant_items = data_store_1
+    transfer_store
         .get_significant_sync_times_from_other(&data_store_3)
         .unwrap();
-    assert_eq!(significant_items, 2); // The root '/' and 'sub-1' should hold times.
-
-    // Should not re-transfer.
-    let significant_items = data_store_1
-        .get_significant_sync_times_from_other(&data_store_3)
+    transfer_store
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    assert_eq!(significant_items, 0);
+    dir_should_contain(&fs_transfer, "sub-1", vec!["file-1"]);
 
-    // Now pass the knowledge from store 1 to 2.
-    let significant_items = data_store_2
-        .get_significant_sync_times_from_other(&data_store_1)
+    // The transfer store must only hold the encrypted content.
+    let mut carried_content = vec![];
+    fs_transfer
+        .read_file("sub-1/file-1")
+        .unwrap()
+        .read_to_end(&mut carried_content)
         .unwrap();
-    assert_eq!(significant_items, 3); // The root '/' of store_1 + root '/' and 'sub-1' of store_3.
+    assert_ne!(carried_content, b"private content".to_vec());
 
-    // Now pass the knowledge back from 2 to 3.
-    let significant_items = data_store_3
-        .get_significant_sync_times_from_other(&data_store_2)
+    // ...but delivers the plain content to other stores.
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&transfer_store, &RelativePath::from_path(""))
         .unwrap();
-    assert_eq!(significant_items, 2); // The root '/' of store_1  and root '/' of store_2.
-                                      // All information on store 3 is already in store 3.
+    assert_eq!(
+        fs_3.test_get_file_content("sub-1/file-1").unwrap(),
+        "private content"
+    );
+
+    // Only clean transfer stores can be encrypted and only with the right passphrase unlocked.
+    match transfer_store.enable_encryption("other passphrase") {
+        Err(DataStoreError::OnlyCleanTransferStoresCanBeEncrypted) => (),
+        _ => panic!("Must not encrypt stores that already carry data!"),
+    }
+    match transfer_store.unlock_encryption("wrong passphrase") {
+        Err(DataStoreError::FSInteractionError {
+            source: fs_interaction::FSInteractionError::WrongPassphrase,
+        }) => (),
+        _ => panic!("Must not unlock encrypted stores with wrong passphrases!"),
+    }
 }
 
-fn create_synced_base_state() -> (
-    (InMemoryFS, DataStore<InMemoryFS>),
-    (InMemoryFS, DataStore<InMemoryFS>),
-) {
-    let (fs_1, data_store_1) = create_in_memory_store();
-    let (fs_2, data_store_2) = create_in_memory_store();
-
-    fs_1.create_file("file-1").unwrap();
-    fs_1.test_set_file_content("file-1", "start", true).unwrap();
+#[test]
+fn toggle_transfer_store() {
+    let test_dir = tempfile::tempdir().unwrap();
+    {
+        let data_store =
+            DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+        data_store.mark_as_transfer_store().unwrap();
+        assert!(data_store.is_transfer_store().unwrap());
+    }
+    {
+        let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+        assert!(data_store.is_transfer_store().unwrap());
+        assert!(matches!(
+            data_store.perform_full_scan(),
+            Err(DataStoreError::MustNotScanTransferStores)
+        ));
+
+        data_store.unmark_as_transfer_store().unwrap();
+        assert!(!data_store.is_transfer_store().unwrap());
+        data_store.perform_full_scan().unwrap();
+    }
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert!(!data_store.is_transfer_store().unwrap());
+}
 
+#[test]
+fn unmarked_transfer_store_receives_all_data() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_transfer, transfer_store) = create_in_memory_store();
+    transfer_store.mark_as_transfer_store().unwrap();
+    fs_1.create_dir("sub-1", false).unwrap();
+    fs_1.create_file("sub-1/file-1").unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
 
-    data_store_2
+    // No other store needs the data, i.e. the transfer store does not carry it...
+    transfer_store
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
+    dir_should_not_contain(&fs_transfer, "", vec!["sub-1"]);
 
-    ((fs_1, data_store_1), (fs_2, data_store_2))
+    // ...but as a regular store it wants all of it.
+    transfer_store.unmark_as_transfer_store().unwrap();
+    transfer_store.perform_full_scan().unwrap();
+    transfer_store
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_transfer, "", vec!["sub-1"]);
+    dir_should_contain(&fs_transfer, "sub-1", vec!["file-1"]);
 }
 
-fn create_synced_base_state_folders() -> (
-    (InMemoryFS, DataStore<InMemoryFS>),
-    (InMemoryFS, DataStore<InMemoryFS>),
-) {
+#[test]
+fn resume_interrupted_carry_of_transfer_store() {
     let (fs_1, data_store_1) = create_in_memory_store();
-    let (fs_2, data_store_2) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+    let (fs_transfer, transfer_store) = create_in_memory_store();
+    transfer_store.mark_as_transfer_store().unwrap();
 
-    fs_1.create_dir("sub", true).unwrap();
-    fs_1.create_file("sub/file-1").unwrap();
-    fs_1.test_set_file_content("sub/file-1", "start", true)
+    fs_1.create_dir("sub-1", false).unwrap();
+    for path in ["file-1", "file-2", "sub-1/file-3"] {
+        fs_1.create_file(path).unwrap();
+        fs_1.test_set_file_content(path, path, true).unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    transfer_store
+        .get_significant_sync_times_from_other(&data_store_2)
         .unwrap();
 
+    // The carry is interrupted while fetching file-2.
+    fs_1.set_error_on_next(
+        "file-2",
+        Operation::Read,
+        io::ErrorKind::PermissionDenied,
+        1,
+    )
+    .unwrap();
+    assert!(transfer_store
+        .carry_from_other_store(&data_store_1, &mut |_| panic!("No conflicts expected!"))
+        .is_err());
+    let fetched_before: Vec<_> = vec!["file-1", "sub-1/file-3"]
+        .into_iter()
+        .filter(|path| fs_transfer.test_get_file_content(path).is_ok())
+        .collect();
+    assert!(fs_transfer.test_get_file_content("file-2").is_err());
+
+    // Changes after the interruption are not part of the persisted plan.
+    fs_1.create_file("file-4").unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
 
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    // Resuming fetches exactly the remaining files of the plan...
+    let resumed_plan = transfer_store
+        .carry_from_other_store(&data_store_1, &mut |_| panic!("No conflicts expected!"))
         .unwrap();
+    let resumed_paths = resumed_plan.paths();
+    assert_eq!(resumed_paths.len(), 3 - fetched_before.len());
+    assert!(resumed_paths.contains(&RelativePath::from_path("file-2")));
+    for path in fetched_before {
+        assert!(!resumed_paths.contains(&RelativePath::from_path(path)));
+    }
+    for path in ["file-1", "file-2", "sub-1/file-3"] {
+        assert_eq!(fs_transfer.test_get_file_content(path).unwrap(), path);
+    }
+    dir_should_not_contain(&fs_transfer, "", vec!["file-4"]);
 
-    ((fs_1, data_store_1), (fs_2, data_store_2))
+    // ...and the next carry plans from scratch.
+    let next_plan = transfer_store
+        .carry_from_other_store(&data_store_1, &mut |_| panic!("No conflicts expected!"))
+        .unwrap();
+    assert_eq!(next_plan.paths(), vec![RelativePath::from_path("file-4")]);
+    dir_should_contain(&fs_transfer, "", vec!["file-4"]);
 }
 
-// CASE 1: Two different, concurrently changed file versions on both data stores.
-//         Choose the remote item.
 #[test]
-fn sync_with_conflicts_01() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+fn report_stores_missing_an_item() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+    let (_fs_3, data_store_3) = create_in_memory_store();
 
-    // Two independent changes -> this should result in conflict on sync
-    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
 
-    // Sync from 1 -> 2. No resolution, nothing should happen.
-    let mut conflict_happened = false;
+    // Only store 2 gets the file.
     data_store_2
-        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::DoNotResolve
-        })
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_2");
-
-    // Sync from 1 -> 2. Resolution, we should choose the remote item now.
-    let mut conflict_happened = false;
-    data_store_2
-        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
+    data_store_1
+        .get_significant_sync_times_from_other(&data_store_2)
+        .unwrap();
+    data_store_1
+        .get_significant_sync_times_from_other(&data_store_3)
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
 
-    // A second sync SHOULD NOT be a conflict.
-    data_store_2
+    let path = RelativePath::from_path("sub/file-1");
+    assert_eq!(
+        data_store_1.stores_missing(&path).unwrap(),
+        vec![data_store_3.local_data_store_name().unwrap()]
+    );
+
+    // Store 3 catches up, but we only know after learning its new status.
+    data_store_3
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-
-    // Sync from 2 -> 1 should result in 2 propagating the its choice of keeping the fs_1 version.
+    assert_eq!(data_store_1.stores_missing(&path).unwrap().len(), 1);
     data_store_1
-        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .get_significant_sync_times_from_other(&data_store_3)
         .unwrap();
-    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert!(data_store_1.stores_missing(&path).unwrap().is_empty());
 }
 
-// CASE 2: Two different, concurrently changed file versions on both data stores.
-//         Choose the local item.
 #[test]
-fn sync_with_conflicts_02() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
-
-    // Two independent changes -> this should result in conflict on sync.
-    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
-    data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
+fn report_store_that_last_modified_an_item() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
 
-    // Sync from 1 -> 2. Choose the local item.
-    let mut conflict_happened = false;
-    data_store_2
-        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
-        .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_2");
+    let path = RelativePath::from_path("sub/file-1");
+    let last_modification = data_store_1.last_modified_by(&path).unwrap();
+    assert_eq!(
+        last_modification.data_store_name,
+        data_store_1.local_data_store_name().unwrap()
+    );
 
-    // We chose our local file. Make a further change and move the data back to store 1.
-    // This should work, as we know about all changes in 1 and then deliberately change it.
-    fs_2.test_set_file_content("file-1", "change_after_resolution", true)
+    // Modify the file on store 2 and sync it back to store 1.
+    fs_2.test_set_file_content("sub/file-1", "changed", true)
         .unwrap();
     data_store_2.perform_full_scan().unwrap();
-
-    // Sync from 2 -> 1. Should work without conflict.
     data_store_1
         .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
         .unwrap();
-    assert_eq!(
-        fs_1.test_get_file_content("file-1").unwrap(),
-        "change_after_resolution"
-    );
+    assert_eq!(fs_1.test_get_file_content("sub/file-1").unwrap(), "changed");
+
+    let remote_store = data_store_1
+        .db_access
+        .get_data_store(&data_store_2.local_data_store_name().unwrap())
+        .unwrap()
+        .unwrap();
+    for item_path in ["sub/file-1", "sub"] {
+        let last_modification = data_store_1
+            .last_modified_by(&RelativePath::from_path(item_path))
+            .unwrap();
+        assert_eq!(last_modification.path, path);
+        assert_eq!(last_modification.data_store_name, remote_store.unique_name);
+        assert_eq!(
+            last_modification.data_store_human_name,
+            remote_store.human_name
+        );
+        assert_eq!(
+            last_modification.data_store_time,
+            data_store_2
+                .last_modified_by(&path)
+                .unwrap()
+                .data_store_time
+        );
+    }
 }
 
-// CASE 3: The local store deletes a file, the remote concurrently modifies it.
-//         Choose the remote item.
 #[test]
-fn sync_with_conflicts_03() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+fn detect_ignore_status_changes() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
 
-    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
-    fs_1.remove_file("file-1").unwrap();
-    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    // Two stores have a 'split' data set, i.e. they contain exclusive files (think sharding data).
+    fs_1.create_file("file-1").unwrap();
+    fs_1.create_file("file-2").unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1. We choose the remote item on fs_2.
-    // Resolution, we should choose the remote item on fs_2.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFile(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
-        .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_2");
+    // We indexed everything to be included expect **/file-3, try some other ignore patterns.
+    let mut rules_1 = data_store_1.get_inclusion_rules().clone();
+    rules_1.add_ignore_rule(glob::Pattern::new("**/file-1").unwrap());
+    let rules_1_copy = rules_1.clone();
 
-    // Further syncs should just work fine.
-    data_store_1
-        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
-        .unwrap();
+    let (new_items, removed_items) = data_store_1.update_inclusion_rules(rules_1, false).unwrap();
+    assert_eq!(new_items.len(), 0);
+    assert_eq!(removed_items.len(), 2);
+    assert!(removed_items
+        .iter()
+        .all(|item| item.path.name() == "file-1"));
 
-    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    data_store_1.perform_full_scan().unwrap();
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    let mut rules_2 = data_store_1.get_inclusion_rules().clone();
+    rules_2.add_ignore_rule(glob::Pattern::new("**/sub").unwrap());
+    let (new_items, removed_items) = data_store_1.update_inclusion_rules(rules_2, false).unwrap();
+    assert_eq!(new_items.len(), 0);
+    assert_eq!(removed_items.len(), 2);
+    assert!(removed_items
+        .iter()
+        .all(|item| item.path.name() == "file-2" || item.path.name() == "sub"));
+
+    // Lets see the effect of 'un-ignoring' files.
+    let (new_items, removed_items) = data_store_1
+        .update_inclusion_rules(rules_1_copy, false)
         .unwrap();
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert_eq!(new_items.len(), 1);
+    assert_eq!(new_items.first().unwrap().path.name(), "sub");
+    assert_eq!(removed_items.len(), 0);
+    // The scan should pick-up the 'un-ignored' items as new items.
+    let changes = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(
+        changes,
+        ScanResult {
+            indexed_items: 5,
+            changed_items: 0,
+            new_items: 2,
+            deleted_items: 0,
+            unreliable_creation_times: 0,
+            ignored_on_disk: vec![RelativePath::from_path("file-1")],
+            ignored_on_disk_bytes: 0,
+            duplicate_names: vec![],
+            changes: None,
+        }
+    );
 }
 
-// CASE 4: The local store deletes a file, the remote concurrently modifies it.
-//         Choose the local item.
 #[test]
-fn sync_with_conflicts_04() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
-
-    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
-    fs_1.remove_file("file-1").unwrap();
-    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+fn ignore_explicit_list_of_paths() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.create_file("file-[2]").unwrap();
+    fs_1.create_file("file-3").unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the local deletion on fs_1.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFile(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
+    let is_ignored = |data_store: &DataStore<InMemoryFS>, path: &str| {
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap()
+            .is_ignored()
+    };
+
+    // Names are taken literally (no glob), folders are ignored with all their content.
+    let (no_longer_ignored, newly_ignored) = data_store_1
+        .ignore_paths(&[
+            RelativePath::from_path("file-1"),
+            RelativePath::from_path("file-[2]"),
+            RelativePath::from_path("sub"),
+        ])
         .unwrap();
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    assert!(no_longer_ignored.is_empty());
+    assert_eq!(newly_ignored.len(), 4);
+    for path in ["file-1", "file-[2]", "sub", "sub/file-1"] {
+        assert!(is_ignored(&data_store_1, path));
+    }
+    assert!(!is_ignored(&data_store_1, "file-3"));
+
+    let (no_longer_ignored, newly_ignored) = data_store_1
+        .unignore_paths(&[
+            RelativePath::from_path("file-[2]"),
+            RelativePath::from_path("sub"),
+        ])
         .unwrap();
-    assert!(conflict_happened);
-    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
-    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+    assert_eq!(no_longer_ignored.len(), 2);
+    assert!(newly_ignored.is_empty());
+    assert!(is_ignored(&data_store_1, "file-1"));
+    assert!(!is_ignored(&data_store_1, "file-[2]"));
+    assert!(!is_ignored(&data_store_1, "sub"));
 }
 
-// CASE 5: The local store modifies a file, the remote concurrently deletes it.
-//         Choose the local item.
 #[test]
-fn sync_with_conflicts_05() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
-
-    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
-    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.remove_file("file-1").unwrap();
+fn plan_disk_impact_of_inclusion_rules() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "12345", true).unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.test_set_file_content("sub/file-1", "123", true)
+        .unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.test_set_file_content("sub/file-2", "1234567", true)
+        .unwrap();
     data_store_1.perform_full_scan().unwrap();
-    data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the local change on fs_1.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteDeletion(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
-        .unwrap();
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    let mut rules = data_store_1.get_inclusion_rules().clone();
+    rules.add_ignore_rule(glob::Pattern::new("/sub").unwrap());
+    let plan = data_store_1.plan_inclusion_rules(&rules).unwrap();
+    assert!(plan.no_longer_ignored.is_empty());
+    assert_eq!(plan.newly_ignored.len(), 3);
+    assert_eq!(plan.disk_cleanup, vec![RelativePath::from_path("sub")]);
+    assert_eq!(plan.disk_cleanup_bytes, 10);
+
+    // Planning does not change anything.
+    let (_, newly_ignored) = data_store_1
+        .update_inclusion_rules(rules.clone(), true)
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert_eq!(newly_ignored.len(), 3);
 }
 
-// CASE 6: The local store modifies a file, the remote concurrently deletes it.
-//         Choose the remote item.
 #[test]
-fn sync_with_conflicts_06() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+fn delete_content_of_newly_ignored_items() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
 
-    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
-    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.remove_file("file-1").unwrap();
+    let mut rules = data_store_1.get_inclusion_rules().clone();
+    rules.add_ignore_rule(glob::Pattern::new("**/file-1").unwrap());
+    let plan = data_store_1.plan_inclusion_rules(&rules).unwrap();
+    assert_eq!(plan.disk_cleanup.len(), 2);
+
+    // Content must only be deleted once it is actually ignored.
+    assert!(data_store_1.delete_ignored_content(&plan).is_err());
+    dir_should_contain(&fs_1, "", vec!["file-1", "sub"]);
+
+    data_store_1.update_inclusion_rules(rules, false).unwrap();
+    data_store_1.delete_ignored_content(&plan).unwrap();
+    dir_should_contain(&fs_1, "", vec!["sub"]);
+    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
+    dir_should_contain(&fs_1, "sub", vec!["file-2"]);
+    dir_should_not_contain(&fs_1, "sub", vec!["file-1"]);
+
+    // The store stays consistent with the disk content.
+    let changes = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(
+        changes.changed_items + changes.new_items + changes.deleted_items,
+        0
+    );
+}
+
+#[test]
+fn multi_target_transfer_significant_times() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    let (_fs_3, data_store_3) = create_in_memory_store();
+
+    // Initial Data Set
+    fs_1.create_dir("sub-1", false).unwrap();
+    fs_1.create_file("sub-1/file-1").unwrap();
+    fs_2.create_dir("sub-2", false).unwrap();
+    fs_2.create_file("sub-2/file-1").unwrap();
+
+    // Index all
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the local change on fs_1.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteDeletion(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
+    // Sync from 1 to 3 partially only sub-1.
+    // This should leave this should leave the store with 'partial' sync times of 3.
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path("sub-1"))
+        .unwrap();
+
+    let significant_items = data_store_1
+        .get_significant_sync_times_from_other(&data_store_3)
+        .unwrap();
+    assert_eq!(significant_items, 2); // The root '/' and 'sub-1' should hold times.
+
+    // Should not re-transfer.
+    let significant_items = data_store_1
+        .get_significant_sync_times_from_other(&data_store_3)
+        .unwrap();
+    assert_eq!(significant_items, 0);
+
+    // Now pass the knowledge from store 1 to 2.
+    let significant_items = data_store_2
+        .get_significant_sync_times_from_other(&data_store_1)
+        .unwrap();
+    assert_eq!(significant_items, 3); // The root '/' of store_1 + root '/' and 'sub-1' of store_3.
+
+    // Now pass the knowledge back from 2 to 3.
+    let significant_items = data_store_3
+        .get_significant_sync_times_from_other(&data_store_2)
         .unwrap();
+    assert_eq!(significant_items, 2); // The root '/' of store_1  and root '/' of store_2.
+                                      // All information on store 3 is already in store 3.
+}
+
+fn create_synced_base_state() -> (
+    (InMemoryFS, DataStore<InMemoryFS>),
+    (InMemoryFS, DataStore<InMemoryFS>),
+) {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "start", true).unwrap();
+
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
     data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    assert!(conflict_happened);
-    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
-    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+
+    ((fs_1, data_store_1), (fs_2, data_store_2))
 }
 
-// CASE 7: The local store deletes a folder, the remote concurrently modifies it.
-//         Choose the local item.
-#[test]
-fn sync_with_conflicts_07() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+fn create_synced_base_state_folders() -> (
+    (InMemoryFS, DataStore<InMemoryFS>),
+    (InMemoryFS, DataStore<InMemoryFS>),
+) {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
 
-    // Now lets remove the folder on one store 1 and modify it on store 2. This should conflict.
-    fs_1.remove_dir_recursive("sub").unwrap();
-    fs_2.test_set_file_content("sub/file-1", "fs_2", true)
+    fs_1.create_dir("sub", true).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.test_set_file_content("sub/file-1", "start", true)
         .unwrap();
-    fs_2.create_file("sub/file-2").unwrap();
+
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the local deletion on fs_1.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFolder(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
-        .unwrap();
     data_store_2
         .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    assert!(conflict_happened);
-    dir_should_not_contain(&fs_1, "", vec!["sub-1"]);
-    dir_should_not_contain(&fs_2, "", vec!["sub-1"]);
+
+    ((fs_1, data_store_1), (fs_2, data_store_2))
 }
 
-// CASE 8: The local store modifies a folder, the remote concurrently deletes it.
-//         Choose the remote item.
 #[test]
-fn sync_with_conflicts_08() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
-
-    // Now lets remove the folder on one store 1 and modify it on store 2. This should conflict.
-    fs_1.remove_dir_recursive("sub").unwrap();
-    fs_2.test_set_file_content("sub/file-1", "fs_2", true)
+fn abort_sync_on_too_many_conflicts() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    for i in 0..5 {
+        fs_1.create_file(format!("file-{}", i)).unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    fs_2.create_file("sub/file-2").unwrap();
+
+    // Independent changes to all files on both stores.
+    for i in 0..5 {
+        let path = format!("file-{}", i);
+        fs_1.test_set_file_content(&path, "fs_1", true).unwrap();
+        fs_2.test_set_file_content(&path, "fs_2", true).unwrap();
+    }
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the remote change on fs_2.
-    let mut conflict_happened = false;
+    // The first conflict is resolved, the sync stops at the third unresolved one.
+    let mut conflicts = 0;
+    let mut resolved_path = None;
+    let result = data_store_2.sync_from_other_store_with_options(
+        &data_store_1,
+        &RelativePath::from_path(""),
+        &SyncOptions::new().max_conflicts(Some(2)),
+        &mut |event| {
+            conflicts += 1;
+            if let SyncConflictEvent::LocalItemRemoteFile(local_item, _) = event {
+                if resolved_path.is_none() {
+                    resolved_path = Some(local_item.path.clone());
+                    return SyncConflictResolution::ChooseRemoteItem;
+                }
+            }
+            SyncConflictResolution::DoNotResolve
+        },
+    );
+    match result {
+        Err(DataStoreError::TooManyConflicts { count }) => assert_eq!(count, 3),
+        _ => panic!("Must abort the sync once too many conflicts are left unresolved!"),
+    }
+    assert_eq!(conflicts, 4);
+
+    // The item resolved before the abort stays in sync.
+    let resolved_path = resolved_path.unwrap();
+    assert_eq!(
+        fs_2.test_get_file_content(resolved_path.to_path_buf())
+            .unwrap(),
+        "fs_1"
+    );
     data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened |=
-                matches!(event, SyncConflictEvent::LocalDeletionRemoteFolder(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
+        .sync_from_other_store_panic_conflicts(&data_store_2, &resolved_path)
         .unwrap();
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
-        .unwrap();
-    assert!(conflict_happened);
-    dir_should_contain(&fs_1, "", vec!["sub"]);
-    dir_should_contain(&fs_2, "", vec!["sub"]);
-    dir_should_contain(&fs_1, "sub", vec!["file-1", "file-2"]);
-    dir_should_contain(&fs_2, "sub", vec!["file-1", "file-2"]);
-    assert_eq!(fs_1.test_get_file_content("sub/file-1").unwrap(), "fs_2");
-    assert_eq!(fs_2.test_get_file_content("sub/file-1").unwrap(), "fs_2");
+    assert_eq!(
+        fs_1.test_get_file_content(resolved_path.to_path_buf())
+            .unwrap(),
+        "fs_1"
+    );
 }
 
-// CASE 9: The local store has a file, the remote concurrently modifies it to be a folder.
-//         Choose the local item.
 #[test]
-fn sync_with_conflicts_09() {
-    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+fn resolve_conflicts_by_most_recent_mtime() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    for i in 1..4 {
+        let path = format!("file-{}", i);
+        fs_1.create_file(&path).unwrap();
+        fs_1.test_set_file_content(&path, "start", false).unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
 
-    // Now lets modify the item on one store 1 and  change it to a folder on store 2. This should conflict.
+    // Each change moves the file's mod time one second ahead.
+    // file-1 is newer on store 2, file-2 is newer on store 1.
     fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.remove_file("file-1").unwrap();
-    fs_2.create_dir("file-1", false).unwrap();
-    fs_2.create_file("file-1/file-2").unwrap();
+    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    fs_2.test_increase_file_mod_time("file-1").unwrap();
+    fs_1.test_set_file_content("file-2", "fs_1", true).unwrap();
+    fs_1.test_increase_file_mod_time("file-2").unwrap();
+    fs_2.test_set_file_content("file-2", "fs_2", true).unwrap();
+    // file-3 is modified on store 1 and deleted on store 2.
+    fs_1.test_set_file_content("file-3", "fs_1", true).unwrap();
+    fs_2.remove_file("file-3").unwrap();
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the local deletion on fs_1.
-    let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalFileRemoteFolder(_, _));
-            SyncConflictResolution::ChooseLocalItem
+    // Sync from 1 -> 2, keeping the newer side of each conflict.
+    let mut conflicts = 0;
+    let result = data_store_2
+        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |_| {
+            conflicts += 1;
+            SyncConflictResolution::MostRecentMtime
         })
         .unwrap();
-    data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    assert_eq!(conflicts, 3);
+    assert_eq!(result.unresolved_conflicts, 0);
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_2");
+    assert_eq!(fs_2.test_get_file_content("file-2").unwrap(), "fs_1");
+    // The existing item wins over the deletion.
+    assert_eq!(fs_2.test_get_file_content("file-3").unwrap(), "fs_1");
+
+    // Both stores converge to the same choice.
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |_| {
+            SyncConflictResolution::MostRecentMtime
+        })
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
-    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_2");
+    assert_eq!(fs_1.test_get_file_content("file-2").unwrap(), "fs_1");
+    assert_eq!(fs_1.test_get_file_content("file-3").unwrap(), "fs_1");
 }
 
-// CASE 10: The local store has a file, the remote concurrently modifies it to be a folder.
+// CASE 1: Two different, concurrently changed file versions on both data stores.
 //         Choose the remote item.
 #[test]
-fn sync_with_conflicts_10() {
+fn sync_with_conflicts_01() {
     let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
 
-    // Now lets modify the item on one store 1 and  change it to a folder on store 2. This should conflict.
+    // Two independent changes -> this should result in conflict on sync
     fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
-    fs_2.remove_file("file-1").unwrap();
-    fs_2.create_dir("file-1", false).unwrap();
-    fs_2.create_file("file-1/file-2").unwrap();
+    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
     data_store_1.perform_full_scan().unwrap();
     data_store_2.perform_full_scan().unwrap();
 
-    // Sync form 2 -> 1, should have a conflict.
-    // Resolution, we should choose the remote change on fs_2.
+    // Sync from 1 -> 2. No resolution, nothing should happen.
     let mut conflict_happened = false;
-    data_store_1
-        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalFileRemoteFolder(_, _));
-            SyncConflictResolution::ChooseRemoteItem
+    data_store_2
+        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::DoNotResolve
         })
         .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_2");
+
+    // Sync from 1 -> 2. Resolution, we should choose the remote item now.
+    let mut conflict_happened = false;
     data_store_2
-        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
         .unwrap();
     assert!(conflict_happened);
-    dir_should_contain(&fs_1, "", vec!["file-1"]);
-    dir_should_contain(&fs_1, "file-1", vec!["file-2"]);
-    dir_should_contain(&fs_2, "", vec!["file-1"]);
-    dir_should_contain(&fs_2, "file-1", vec!["file-2"]);
-}
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
 
-// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
-// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
-// subsequent conflicts if possible.
-// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
-// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
-// Case 1: Same as figure (b)
+    // A second sync SHOULD NOT be a conflict.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Sync from 2 -> 1 should result in 2 propagating the its choice of keeping the fs_1 version.
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
+}
+
+// CASE 2: Two different, concurrently changed file versions on both data stores.
+//         Choose the local item.
 #[test]
-fn sync_conflict_forwarding_multiple_1() {
-    let (fs_a, data_store_a) = create_in_memory_store();
-    let (fs_b, data_store_b) = create_in_memory_store();
-    let (_fs_c, data_store_c) = create_in_memory_store();
+fn sync_with_conflicts_02() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
 
-    // Time 1
-    fs_b.create_file("file-1").unwrap();
-    data_store_b.perform_full_scan().unwrap();
-    // Time 2
-    data_store_a
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+    // Two independent changes -> this should result in conflict on sync.
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync from 1 -> 2. Choose the local item.
+    let mut conflict_happened = false;
+    data_store_2
+        .sync_from_other_store(&data_store_1, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
         .unwrap();
-    // Time 3
-    data_store_c
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+    assert!(conflict_happened);
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_2");
+
+    // We chose our local file. Make a further change and move the data back to store 1.
+    // This should work, as we know about all changes in 1 and then deliberately change it.
+    fs_2.test_set_file_content("file-1", "change_after_resolution", true)
         .unwrap();
+    data_store_2.perform_full_scan().unwrap();
 
-    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
-    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
-    data_store_b.perform_full_scan().unwrap();
+    // Sync from 2 -> 1. Should work without conflict.
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        fs_1.test_get_file_content("file-1").unwrap(),
+        "change_after_resolution"
+    );
+}
 
-    // Time 4
+// CASE 3: The local store deletes a file, the remote concurrently modifies it.
+//         Choose the remote item.
+#[test]
+fn sync_with_conflicts_03() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
+    fs_1.remove_file("file-1").unwrap();
+    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1. We choose the remote item on fs_2.
+    // Resolution, we should choose the remote item on fs_2.
     let mut conflict_happened = false;
-    data_store_b
-        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFile(_, _));
             SyncConflictResolution::ChooseRemoteItem
         })
         .unwrap();
     assert!(conflict_happened);
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a");
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_2");
 
-    // Time 5
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+    // Further syncs should just work fine.
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
         .unwrap();
-    // Time 6
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a");
-    // Time 7
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+}
+
+// CASE 4: The local store deletes a file, the remote concurrently modifies it.
+//         Choose the local item.
+#[test]
+fn sync_with_conflicts_04() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
+    fs_1.remove_file("file-1").unwrap();
+    fs_2.test_set_file_content("file-1", "fs_2", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the local deletion on fs_1.
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFile(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(conflict_happened);
+    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+}
+
+// CASE 5: The local store modifies a file, the remote concurrently deletes it.
+//         Choose the local item.
+#[test]
+fn sync_with_conflicts_05() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    fs_2.remove_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the local change on fs_1.
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteDeletion(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+}
+
+// CASE 6: The local store modifies a file, the remote concurrently deletes it.
+//         Choose the remote item.
+#[test]
+fn sync_with_conflicts_06() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets remove the file on one store 1 and modify it on store 2. This should conflict.
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    fs_2.remove_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the local change on fs_1.
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteDeletion(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(conflict_happened);
+    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+}
+
+// CASE 7: The local store deletes a folder, the remote concurrently modifies it.
+//         Choose the local item.
+#[test]
+fn sync_with_conflicts_07() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+
+    // Now lets remove the folder on one store 1 and modify it on store 2. This should conflict.
+    fs_1.remove_dir_recursive("sub").unwrap();
+    fs_2.test_set_file_content("sub/file-1", "fs_2", true)
+        .unwrap();
+    fs_2.create_file("sub/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the local deletion on fs_1.
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalDeletionRemoteFolder(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(conflict_happened);
+    dir_should_not_contain(&fs_1, "", vec!["sub-1"]);
+    dir_should_not_contain(&fs_2, "", vec!["sub-1"]);
+}
+
+// CASE 8: The local store modifies a folder, the remote concurrently deletes it.
+//         Choose the remote item.
+#[test]
+fn sync_with_conflicts_08() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+
+    // Now lets remove the folder on one store 1 and modify it on store 2. This should conflict.
+    fs_1.remove_dir_recursive("sub").unwrap();
+    fs_2.test_set_file_content("sub/file-1", "fs_2", true)
+        .unwrap();
+    fs_2.create_file("sub/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the remote change on fs_2.
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store(&data_store_2, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened |=
+                matches!(event, SyncConflictEvent::LocalDeletionRemoteFolder(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(conflict_happened);
+    dir_should_contain(&fs_1, "", vec!["sub"]);
+    dir_should_contain(&fs_2, "", vec!["sub"]);
+    dir_should_contain(&fs_1, "sub", vec!["file-1", "file-2"]);
+    dir_should_contain(&fs_2, "sub", vec!["file-1", "file-2"]);
+    assert_eq!(fs_1.test_get_file_content("sub/file-1").unwrap(), "fs_2");
+    assert_eq!(fs_2.test_get_file_content("sub/file-1").unwrap(), "fs_2");
+}
+
+// CASE 9: The local store has a file, the remote concurrently modifies it to be a folder.
+//         Choose the local item.
+#[test]
+fn sync_with_conflicts_09() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets modify the item on one store 1 and  change it to a folder on store 2. This should conflict.
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    fs_2.remove_file("file-1").unwrap();
+    fs_2.create_dir("file-1", false).unwrap();
+    fs_2.create_file("file-1/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the local deletion on fs_1.
+    // Replacing the file by the folder (or vice versa) must be allowed explicitly.
+    let options = SyncOptions::new().allow_type_changes(true);
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store_with_options(
+            &data_store_2,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |event| {
+                conflict_happened = matches!(event, SyncConflictEvent::LocalFileRemoteFolder(_, _));
+                SyncConflictResolution::ChooseLocalItem
+            },
+        )
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "fs_1");
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "fs_1");
+}
+
+// CASE 10: The local store has a file, the remote concurrently modifies it to be a folder.
+//         Choose the remote item.
+#[test]
+fn sync_with_conflicts_10() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Now lets modify the item on one store 1 and  change it to a folder on store 2. This should conflict.
+    fs_1.test_set_file_content("file-1", "fs_1", true).unwrap();
+    fs_2.remove_file("file-1").unwrap();
+    fs_2.create_dir("file-1", false).unwrap();
+    fs_2.create_file("file-1/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Sync form 2 -> 1, should have a conflict.
+    // Resolution, we should choose the remote change on fs_2.
+    // Replacing the file by the folder (or vice versa) must be allowed explicitly.
+    let options = SyncOptions::new().allow_type_changes(true);
+    let mut conflict_happened = false;
+    data_store_1
+        .sync_from_other_store_with_options(
+            &data_store_2,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |event| {
+                conflict_happened = matches!(event, SyncConflictEvent::LocalFileRemoteFolder(_, _));
+                SyncConflictResolution::ChooseRemoteItem
+            },
+        )
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+    assert!(conflict_happened);
+    dir_should_contain(&fs_1, "", vec!["file-1"]);
+    dir_should_contain(&fs_1, "file-1", vec!["file-2"]);
+    dir_should_contain(&fs_2, "", vec!["file-1"]);
+    dir_should_contain(&fs_2, "file-1", vec!["file-2"]);
+}
+
+// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
+// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
+// subsequent conflicts if possible.
+// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
+// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
+// Case 1: Same as figure (b)
+#[test]
+fn sync_conflict_forwarding_multiple_1() {
+    let (fs_a, data_store_a) = create_in_memory_store();
+    let (fs_b, data_store_b) = create_in_memory_store();
+    let (_fs_c, data_store_c) = create_in_memory_store();
+
+    // Time 1
+    fs_b.create_file("file-1").unwrap();
+    data_store_b.perform_full_scan().unwrap();
+    // Time 2
+    data_store_a
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 3
+    data_store_c
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+
+    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
+    data_store_b.perform_full_scan().unwrap();
+
+    // Time 4
+    let mut conflict_happened = false;
+    data_store_b
+        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a");
+
+    // Time 5
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 6
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+        .unwrap();
+    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a");
+    // Time 7
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+}
+
+// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
+// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
+// subsequent conflicts if possible.
+// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
+// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
+// Case 2: Same as figure (c)
+#[test]
+fn sync_conflict_forwarding_multiple_2() {
+    let (fs_a, data_store_a) = create_in_memory_store();
+    let (fs_b, data_store_b) = create_in_memory_store();
+    let (_fs_c, data_store_c) = create_in_memory_store();
+
+    // Time 1
+    fs_b.create_file("file-1").unwrap();
+    data_store_b.perform_full_scan().unwrap();
+    // Time 2
+    data_store_a
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 3
+    data_store_c
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+
+    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
+    data_store_b.perform_full_scan().unwrap();
+
+    // Time 4
+    let mut conflict_happened = false;
+    data_store_b
+        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
+
+    // Time 5
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 6
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+        .unwrap();
+    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
+    // Time 7
+    let mut conflict_happened = false;
+    data_store_b
+        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+}
+
+// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
+// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
+// subsequent conflicts if possible.
+// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
+// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
+// Case 3: Same as figure (d)
+#[test]
+fn sync_conflict_forwarding_multiple_3() {
+    let (fs_a, data_store_a) = create_in_memory_store();
+    let (fs_b, data_store_b) = create_in_memory_store();
+    let (_fs_c, data_store_c) = create_in_memory_store();
+
+    // Time 1
+    fs_b.create_file("file-1").unwrap();
+    data_store_b.perform_full_scan().unwrap();
+    // Time 2
+    data_store_a
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 3
+    data_store_c
+        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+        .unwrap();
+
+    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
+    data_store_b.perform_full_scan().unwrap();
+
+    // Time 4
+    let mut conflict_happened = false;
+    data_store_b
+        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseLocalItem
+        })
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
+    // EMULATING MERGING THE TWO FILES.
+    // In our implementation this is ALWAYS a two action operation.
+    // We keep one of the two files and place the second one as a 'file-1-conflict' besides it.
+    // The user then merges them manually and re-indexes.
+    fs_b.test_set_file_content("file-1", "fs_b_and_fs_a", true)
+        .unwrap();
+    data_store_b.perform_full_scan().unwrap();
+
+    // Time 5
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+        .unwrap();
+    // Time 6
+    data_store_b
+        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+        .unwrap();
+    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
+    data_store_a.perform_full_scan().unwrap();
+    assert_eq!(
+        fs_b.test_get_file_content("file-1").unwrap(),
+        "fs_b_and_fs_a"
+    );
+    // Time 7
+    let mut conflict_happened = false;
+    data_store_b
+        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
+            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
+            SyncConflictResolution::ChooseRemoteItem
+        })
+        .unwrap();
+    assert!(conflict_happened);
+    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+}
+
+#[test]
+fn convert_from_and_to_external_version_vectors() {
+    let (_fs_1, data_store_1) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+
+    let data_store_1_name = data_store_1
+        .db_access
+        .get_local_data_store()
+        .unwrap()
+        .unique_name;
+
+    let (mapper_1, mapper_2) = data_store_1.sync_data_store_lists(&data_store_2).unwrap();
+
+    // Create a vector local to store 1
+    let data_store_1_id = data_store_1
+        .db_access
+        .get_data_store(&data_store_1_name)
+        .unwrap()
+        .unwrap()
+        .id;
+    let mut vector_on_store_1 = VersionVector::new();
+    vector_on_store_1[&data_store_1_id] = 42;
+
+    // Simulate the 'externalize and internalize' procedure to transfer it to store 2.
+    let internalized_vector_on_store_2 = mapper_2.external_to_internal(&vector_on_store_1);
+
+    // Check it...
+    let data_store_2_id = data_store_2
+        .db_access
+        .get_data_store(&data_store_1_name)
+        .unwrap()
+        .unwrap()
+        .id;
+    assert_eq!(internalized_vector_on_store_2[&data_store_2_id], 42);
+
+    // Transfer it back
+    let internalized_vector_on_store_1 =
+        mapper_1.external_to_internal(&internalized_vector_on_store_2);
+    assert_eq!(internalized_vector_on_store_1[&data_store_1_id], 42);
+}
+
+#[test]
+fn sync_skips_unchanged_sub_trees() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+
+    fs_1.create_dir("big", false).unwrap();
+    for i in 0..20 {
+        fs_1.create_file(format!("big/file-{}", i)).unwrap();
+    }
+    fs_1.create_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Only change a file outside of the big folder.
+    fs_1.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // The big folder is up to date and must not be walked child-by-child.
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        result,
+        SyncResult {
+            visited_items: 3, // root, big and file-1
+            up_to_date_items: 1,
+            transferred_files: 1,
+            transferred_bytes: 7,
+            type_changes: vec![],
+            refused_type_changes: vec![],
+            unresolved_conflicts: 0,
+            unavailable_items: vec![],
+            merged_files: vec![],
+            conflict_copies: vec![],
+            remotely_ignored_items: vec![],
+            mirrored_deletions: vec![],
+            explanations: vec![],
+            stalled_sync_warning: None,
+            touched_paths: ["", "big", "file-1"]
+                .iter()
+                .map(RelativePath::from_path)
+                .collect(),
+        }
+    );
+
+    // Forcing a full walk descends into every folder.
+    let result = data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &SyncOptions::new().full_walk(true),
+            &mut |_| panic!("Must not have conflicts on a full walk of synced stores!"),
+        )
+        .unwrap();
+    assert_eq!(
+        result,
+        SyncResult {
+            visited_items: 23,
+            up_to_date_items: 21,
+            transferred_files: 0,
+            transferred_bytes: 0,
+            type_changes: vec![],
+            refused_type_changes: vec![],
+            unresolved_conflicts: 0,
+            unavailable_items: vec![],
+            merged_files: vec![],
+            conflict_copies: vec![],
+            remotely_ignored_items: vec![],
+            mirrored_deletions: vec![],
+            explanations: vec![],
+            stalled_sync_warning: None,
+            touched_paths: (0..20)
+                .map(|i| format!("big/file-{}", i))
+                .chain(vec![
+                    String::from(""),
+                    String::from("big"),
+                    String::from("file-1")
+                ])
+                .map(RelativePath::from_path)
+                .collect(),
+        }
+    );
+}
+
+#[test]
+fn scan_hashes_files_replacing_folders() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    fs_1.create_dir("item", false).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    fs_1.remove_dir_recursive("item").unwrap();
+    fs_1.create_file("item").unwrap();
+    fs_1.test_set_file_content("item", "content", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let db_item = data_store_1
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("item"), false)
+        .unwrap();
+    let expected_hash = data_store_1
+        .fs_access
+        .calculate_hash(&RelativePath::from_path("item"))
+        .unwrap();
+    assert!(db_item.is_file());
+    assert_eq!(db_item.metadata().hash, expected_hash);
+}
+
+#[test]
+fn report_touched_paths() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Change a file and delete another one, the sub folder stays as it is.
+    fs_1.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    fs_1.remove_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "changed");
+    assert!(fs_2.test_get_file_content("file-2").is_err());
+
+    // The sub folder only advanced its sync time, but still must be reported as touched.
+    assert!(result
+        .touched_paths
+        .contains(&RelativePath::from_path("sub")));
+    assert!(!result
+        .touched_paths
+        .contains(&RelativePath::from_path("sub/file-1")));
+    assert!(result
+        .touched_paths
+        .contains(&RelativePath::from_path("file-1")));
+    assert!(result
+        .touched_paths
+        .contains(&RelativePath::from_path("file-2")));
+    assert!(result.touched_paths.contains(&RelativePath::from_path("")));
+    assert_eq!(result.touched_paths.len(), 4);
+}
+
+#[test]
+fn trust_mtime_skips_hashing_on_scan() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    data_store_1.set_trust_mtime(true).unwrap();
+    assert!(data_store_1.trusts_mtime().unwrap());
+
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "content", true)
+        .unwrap();
+    // The file body is not read during the scan, only a placeholder hash is stored.
+    fs_1.set_error_on_all(Operation::Read, io::ErrorKind::PermissionDenied);
+    data_store_1.perform_full_scan().unwrap();
+    fs_1.clear_errors();
+
+    let db_item = data_store_1
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    assert_eq!(db_item.metadata().hash, "");
+
+    // Bit-rot checks are refused, as we have no hashes to compare to.
+    let fs_items = data_store_1
+        .fs_access
+        .index(&RelativePath::from_path(""))
+        .unwrap();
+    assert!(matches!(
+        data_store_1.index_file(&fs_items[0], true, &mut |_| true),
+        Err(DataStoreError::BitRotCheckRequiresHashes)
+    ));
+
+    // Syncing the file lazily calculates its hash.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "content");
+    let db_item = data_store_1
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    let expected_hash = data_store_1
+        .fs_access
+        .calculate_hash(&RelativePath::from_path("file-1"))
+        .unwrap();
+    assert_eq!(db_item.metadata().hash, expected_hash);
+    let db_item = data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    assert_eq!(db_item.metadata().hash, expected_hash);
+}
+
+#[test]
+fn trust_mtime_hashes_placeholders_on_demand() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    data_store_1.set_trust_mtime(true).unwrap();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "content", true)
+        .unwrap();
+    fs_1.create_file("file-2").unwrap();
+    fs_1.test_set_file_content("file-2", "content", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // Duplicates are found by their content, not by their placeholder hashes.
+    let duplicates = data_store_1.find_duplicates().unwrap();
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].1.len(), 2);
+    assert!(!duplicates[0].0.is_empty());
+
+    // Manifests list all files with their actual hash.
+    fs_1.create_file("file-3").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let mut manifest = Vec::new();
+    assert_eq!(data_store_1.export_manifest(&mut manifest).unwrap(), 3);
+    let expected_hash = data_store_1
+        .fs_access
+        .calculate_hash(&RelativePath::from_path("file-3"))
+        .unwrap();
+    assert!(String::from_utf8(manifest)
+        .unwrap()
+        .contains(&format!("{}  file-3", expected_hash.to_lowercase())));
+
+    // Stores holding only part of their content can not hash, no incomplete manifest is written.
+    fs_1.create_file("file-4").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_1.set_partial_content(true).unwrap();
+    let mut manifest = Vec::new();
+    match data_store_1.export_manifest(&mut manifest) {
+        Err(DataStoreError::ContentUnavailable { path }) => {
+            assert_eq!(path, RelativePath::from_path("file-4"))
+        }
+        _ => panic!("Must not export manifests missing files!"),
+    }
+}
+
+#[test]
+fn create_data_store_with_options() {
+    let in_memory_fs = virtual_fs::InMemoryFS::new();
+    let options = DataStoreCreateOptions::new("XYZ-123")
+        .data_set_human_name("XYZ")
+        .data_store_name("transfer-disk")
+        .location_note("in the drawer")
+        .transfer_store(true)
+        .trust_mtime(true);
+    let data_store = DataStore::create_with_options_and_fs("", &options, in_memory_fs).unwrap();
+
+    assert_eq!(data_store.data_set_name().unwrap(), "XYZ-123");
+    assert_eq!(data_store.local_data_store_desc().unwrap(), "transfer-disk");
+    assert!(data_store
+        .local_data_store_name()
+        .unwrap()
+        .starts_with("transfer-disk-"));
+    assert!(data_store.trusts_mtime().unwrap());
+
+    let local_store = data_store.db_access.get_local_data_store().unwrap();
+    assert!(local_store.is_transfer_store);
+    assert_eq!(local_store.location_note, "in the drawer");
+
+    // The plain constructor keeps using the defaults.
+    let (_fs, default_store) = create_in_memory_store();
+    assert!(!default_store.trusts_mtime().unwrap());
+    assert!(
+        !default_store
+            .db_access
+            .get_local_data_store()
+            .unwrap()
+            .is_transfer_store
+    );
+}
+
+#[test]
+fn create_data_store_with_explicit_id() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let options = DataStoreCreateOptions::new("XYZ")
+        .data_store_name("laptop")
+        .data_store_id("laptop-restored-id");
+    let data_store = DefaultDataStore::create_with_options(test_dir.path(), &options).unwrap();
+    assert_eq!(
+        data_store.local_data_store_name().unwrap(),
+        "laptop-restored-id"
+    );
+    assert_eq!(data_store.local_data_store_desc().unwrap(), "laptop");
+    drop(data_store);
+
+    // The identity is persisted.
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(
+        data_store.local_data_store_name().unwrap(),
+        "laptop-restored-id"
+    );
+    assert_eq!(data_store.local_data_store_desc().unwrap(), "laptop");
+
+    // Empty ids are rejected.
+    let options = DataStoreCreateOptions::new("XYZ").data_store_id(" ");
+    let result = DataStore::create_with_options_and_fs("", &options, InMemoryFS::new());
+    match result {
+        Err(DataStoreError::InvalidDataStoreId { .. }) => (),
+        _ => panic!("Must not create stores with empty ids!"),
+    }
+}
+
+#[test]
+fn refuse_sync_partners_with_the_same_id() {
+    let options = DataStoreCreateOptions::new("XYZ").data_store_id("laptop-id");
+    let data_store_1 =
+        DataStore::create_with_options_and_fs("", &options, InMemoryFS::new()).unwrap();
+    let data_store_2 =
+        DataStore::create_with_options_and_fs("", &options, InMemoryFS::new()).unwrap();
+    let (_, other_store) = create_in_memory_store();
+
+    // The collision is detected during the handshake of the sync protocol.
+    let (handshake_1, _) = data_store_1.sync_handshakes(&data_store_2).unwrap();
+    match data_store_2.sync_data_store_list(handshake_1) {
+        Err(DataStoreError::InvalidDataStoreId { data_store_id, .. }) => {
+            assert_eq!(data_store_id, "laptop-id")
+        }
+        _ => panic!("Must not sync with stores using the same id!"),
+    }
+
+    // Stores with distinct ids are fine.
+    let (handshake_1, _) = data_store_1.sync_handshakes(&other_store).unwrap();
+    other_store.sync_data_store_list(handshake_1).unwrap();
+}
+
+#[test]
+fn rename_data_set() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ-123", "XYZ", "local-data-store").unwrap();
+    assert_eq!(data_store.data_set_human_name().unwrap(), "XYZ");
+    let time_before = data_store.local_time().unwrap();
+
+    // Only the human name changes, the local time is bumped to record the change.
+    data_store.set_data_set_human_name("Family Photos").unwrap();
+    assert_eq!(data_store.data_set_human_name().unwrap(), "Family Photos");
+    assert_eq!(data_store.data_set_name().unwrap(), "XYZ-123");
+    assert_eq!(data_store.local_time().unwrap(), time_before + 1);
+    drop(data_store);
+
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(data_store.data_set_human_name().unwrap(), "Family Photos");
+}
+
+#[test]
+fn undelete_file_from_partner() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    let (fs_3, data_store_3) = create_in_memory_store();
+    data_store_3.perform_full_scan().unwrap();
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Accidentally delete the file and sync the deletion out to store 3.
+    fs_1.remove_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_not_contain(&fs_3, "", vec!["file-1"]);
+
+    // A normal sync from store 2 does not bring it back (store 2 is outdated)...
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_not_contain(&fs_1, "", vec!["file-1"]);
+
+    // ...but un-deleting does.
+    data_store_1
+        .undelete(&data_store_2, &RelativePath::from_path("file-1"))
+        .unwrap();
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "start");
+
+    // Un-deleting a live item is refused.
+    assert!(matches!(
+        data_store_1.undelete(&data_store_2, &RelativePath::from_path("file-1")),
+        Err(DataStoreError::NotDeleted { .. })
+    ));
+
+    // The restored file propagates to stores that saw the deletion, without any conflicts.
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_3.test_get_file_content("file-1").unwrap(), "start");
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "start");
+}
+
+#[test]
+fn clean_orphaned_pending_files() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_file("file-1").unwrap();
+    data_store.perform_full_scan().unwrap();
+
+    // Seed a junk file, as if a download crashed mid-way.
+    let pending_dir = data_store.fs_access.pending_files_dir();
+    fs.create_file(pending_dir.join("ABC123")).unwrap();
+    fs.test_set_file_content(pending_dir.join("ABC123"), "junk", false)
+        .unwrap();
+
+    // Dry runs only report the file...
+    let result = data_store.clean_pending(true).unwrap();
+    assert_eq!(
+        result,
+        PendingFilesResult {
+            pending_files: 1,
+            pending_bytes: 4,
+        }
+    );
+    assert_eq!(fs.list_dir(&pending_dir).unwrap().len(), 1);
+
+    // ...actual runs remove it, keeping the regular data untouched.
+    let result = data_store.clean_pending(false).unwrap();
+    assert_eq!(result.pending_files, 1);
+    assert_eq!(fs.list_dir(&pending_dir).unwrap().len(), 0);
+    assert_eq!(
+        data_store.clean_pending(false).unwrap(),
+        PendingFilesResult::new()
+    );
+    dir_should_contain(&fs, "", vec!["file-1"]);
+}
+
+#[test]
+fn track_last_completed_sync_per_store() {
+    let (_fs_1, data_store_1) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let store_1_name = data_store_1.local_data_store_name().unwrap();
+    let last_sync_from_store_1 = |data_store: &DataStore<InMemoryFS>| {
+        data_store
+            .get_data_stores()
+            .unwrap()
+            .into_iter()
+            .find(|store| store.unique_name == store_1_name)
+            .unwrap()
+            .last_sync_completed
+    };
+
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    let first_sync = last_sync_from_store_1(&data_store_2).unwrap();
+
+    sleep(Duration::from_millis(10));
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    let second_sync = last_sync_from_store_1(&data_store_2).unwrap();
+    assert!(first_sync < second_sync);
+
+    // The store we did not sync from has no record.
+    data_store_1
+        .get_data_stores()
+        .unwrap()
+        .into_iter()
+        .filter(|store| !store.is_this_store)
+        .for_each(|store| assert_eq!(store.last_sync_completed, None));
+}
+
+#[test]
+fn defer_unsettled_files_during_scan() {
+    let (fs, mut data_store) = create_in_memory_store();
+    data_store
+        .set_min_file_age(Some(Duration::from_secs(60)))
+        .unwrap();
+
+    fs.create_file("old-file").unwrap();
+    let an_hour_ago = FileTime::from_unix_time(FileTime::now().unix_seconds() - 3600, 0);
+    fs.update_metadata("old-file", an_hour_ago, false).unwrap();
+    fs.create_file("new-file").unwrap();
+
+    // Only the old file is indexed, the new one is deferred.
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.indexed_items, 2);
+    assert_eq!(changes.new_items, 1);
+
+    let root_path = RelativePath::from_path("");
+    let indexed_items = data_store
+        .db_access
+        .get_local_child_items(&root_path, false)
+        .unwrap();
+    assert_eq!(indexed_items.len(), 1);
+    assert_eq!(indexed_items[0].path.name(), "old-file");
+
+    // Once the file settled, it is indexed normally.
+    data_store.set_min_file_age(None).unwrap();
+    data_store.perform_full_scan().unwrap();
+    let indexed_items = data_store
+        .db_access
+        .get_local_child_items(&root_path, false)
+        .unwrap();
+    assert_eq!(indexed_items.len(), 2);
+}
+
+#[test]
+fn persist_min_file_age() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let mut data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+    data_store
+        .set_min_file_age(Some(Duration::from_secs(60)))
+        .unwrap();
+    drop(data_store);
+
+    // Later scans keep deferring recently modified files.
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(data_store.min_file_age(), Some(Duration::from_secs(60)));
+    std::fs::write(test_dir.path().join("new-file"), "content").unwrap();
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn sync_hard_links_as_linked_files() {
+    use std::os::unix::fs::MetadataExt;
+
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    let data_store_2 =
+        DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+
+    std::fs::write(test_dir_1.path().join("file-a"), "linked content").unwrap();
+    std::fs::hard_link(
+        test_dir_1.path().join("file-a"),
+        test_dir_1.path().join("file-b"),
+    )
+    .unwrap();
+    std::fs::write(test_dir_1.path().join("file-c"), "linked content").unwrap();
+
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Both links are synced as hard links, the independent file with equal content is not.
+    let inode = |name: &str| {
+        std::fs::metadata(test_dir_2.path().join(name))
+            .unwrap()
+            .ino()
+    };
+    assert_eq!(inode("file-a"), inode("file-b"));
+    assert_ne!(inode("file-a"), inode("file-c"));
+    assert_eq!(
+        std::fs::read_to_string(test_dir_2.path().join("file-b")).unwrap(),
+        "linked content"
+    );
+
+    // The synced store is consistent with its disk content.
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.new_items, 0);
+}
+
+#[cfg(unix)]
+#[test]
+fn merge_conflicting_files_with_external_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let script_dir = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    let data_store_2 =
+        DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+
+    // A trivial 'merge' appending the remote to the local version (if it got the right base).
+    let merge_script = script_dir.path().join("merge.sh");
+    std::fs::write(
+        &merge_script,
+        "#!/bin/sh\n[ \"$(cat \"$2\")\" = \"base\" ] || exit 1\ncat \"$3\" >> \"$1\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&merge_script, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let options =
+        SyncOptions::new().merge_command(Some(vec![merge_script.to_str().unwrap().to_string()]));
+
+    // Syncs with a merge command record the base versions of transferred files.
+    std::fs::write(test_dir_1.path().join("notes.txt"), "base").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+
+    // Concurrent changes, one file has a base version and one is new on both stores.
+    std::fs::write(test_dir_1.path().join("notes.txt"), "remote change\n").unwrap();
+    std::fs::write(test_dir_2.path().join("notes.txt"), "local change\n").unwrap();
+    std::fs::write(test_dir_1.path().join("new.txt"), "remote").unwrap();
+    std::fs::write(test_dir_2.path().join("new.txt"), "local").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let result = data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| SyncConflictResolution::ExternalMerge,
+        )
+        .unwrap();
+    assert_eq!(
+        result.merged_files,
+        vec![RelativePath::from_path("notes.txt")]
+    );
+    assert_eq!(
+        result.conflict_copies,
+        vec![RelativePath::from_path(
+            "new.txt.conflict-source-data-store"
+        )]
+    );
+    assert_eq!(
+        std::fs::read_to_string(test_dir_2.path().join("notes.txt")).unwrap(),
+        "local change\nremote change\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(test_dir_2.path().join("new.txt")).unwrap(),
+        "local"
+    );
+    assert_eq!(
+        std::fs::read_to_string(test_dir_2.path().join("new.txt.conflict-source-data-store"))
+            .unwrap(),
+        "remote"
+    );
+
+    // The merged versions dominate both sides, i.e. they sync back without conflicts.
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(test_dir_1.path().join("notes.txt")).unwrap(),
+        "local change\nremote change\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(test_dir_1.path().join("new.txt")).unwrap(),
+        "local"
+    );
+
+    // The remote version is checked against the remote's index before merging it.
+    std::fs::write(test_dir_1.path().join("notes.txt"), "remote change\n").unwrap();
+    std::fs::write(test_dir_2.path().join("notes.txt"), "local change\n").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    // Same size and modification time, i.e. only the content tells the change apart.
+    let notes_1 = test_dir_1.path().join("notes.txt");
+    let mod_time = FileTime::from_last_modification_time(&std::fs::metadata(&notes_1).unwrap());
+    std::fs::write(&notes_1, "remote CHANGE\n").unwrap();
+    filetime::set_file_mtime(&notes_1, mod_time).unwrap();
+    match data_store_2.sync_from_other_store_with_options(
+        &data_store_1,
+        &RelativePath::from_path(""),
+        &options,
+        &mut |_| SyncConflictResolution::ExternalMerge,
+    ) {
+        Err(DataStoreError::DiskOutOfSync { path }) => {
+            assert_eq!(path, RelativePath::from_path("notes.txt"))
+        }
+        _ => panic!("The merge must fail on items out of sync with the remote's index!"),
+    }
+    assert_eq!(
+        std::fs::read_to_string(test_dir_2.path().join("notes.txt")).unwrap(),
+        "local change\n"
+    );
+}
+
+#[test]
+fn export_every_item_of_the_index_once() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_dir("sub/nested", false).unwrap();
+    fs.create_file("file-1").unwrap();
+    fs.create_file("sub/file-2").unwrap();
+    fs.create_file("sub/nested/file-3").unwrap();
+    fs.create_file("deleted-file").unwrap();
+    data_store.perform_full_scan().unwrap();
+    fs.remove_file("deleted-file").unwrap();
+    data_store.perform_full_scan().unwrap();
+
+    let mut lines = Vec::new();
+    let exported_items = data_store
+        .export_index(|line| lines.push(line.to_string()))
+        .unwrap();
+    assert_eq!(exported_items, 5);
+    assert_eq!(lines.len(), 5);
+
+    let store_name = data_store.local_data_store_name().unwrap();
+    for (path, item_type) in [
+        ("sub", "folder"),
+        ("sub/nested", "folder"),
+        ("file-1", "file"),
+        ("sub/file-2", "file"),
+        ("sub/nested/file-3", "file"),
+    ] {
+        let matching_lines: Vec<serde_json::Value> = lines
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|item| item["path"] == path)
+            .collect();
+        assert_eq!(matching_lines.len(), 1);
+        assert_eq!(matching_lines[0]["type"], item_type);
+        // Version vectors are rendered with store names, not internal IDs.
+        assert!(matching_lines[0]["sync_time"][&store_name].is_u64());
+    }
+}
+
+#[test]
+fn refuse_sync_between_different_data_sets() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let fs_2 = virtual_fs::InMemoryFS::new();
+    let data_store_2 =
+        DataStore::create_with_fs("", "ABC", "ABC", "other-data-store", fs_2.clone()).unwrap();
+    fs_1.create_file("file").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let error = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .err()
+        .unwrap();
+    match &error {
+        DataStoreError::DataSetMismatch {
+            local_data_set,
+            remote_data_set,
+        } => {
+            assert_eq!(local_data_set, "ABC");
+            assert_eq!(remote_data_set, "XYZ");
+        }
+        _ => panic!("Must report mismatching data sets!"),
+    }
+    let error_text = format!("{:?}", error);
+    assert!(error_text.contains("ABC"));
+    assert!(error_text.contains("XYZ"));
+
+    // Nothing was synced and the foreign store is not known locally.
+    dir_should_not_contain(&fs_2, "", vec!["file"]);
+    assert_eq!(data_store_2.get_data_stores().unwrap().len(), 1);
+}
+
+#[test]
+fn sync_prioritized_sub_trees_first() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_dir("a-media", false).unwrap();
+    fs_1.create_file("a-media/movie").unwrap();
+    fs_1.create_dir("z-documents", false).unwrap();
+    fs_1.create_file("z-documents/letter").unwrap();
+    fs_1.create_file("z-documents/invoice").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Interrupt the sync on the low priority file, everything before it is transferred.
+    fs_2.set_error_on("a-media/movie", Operation::Rename, io::ErrorKind::Other)
+        .unwrap();
+    let options = SyncOptions::new().priority(vec![RelativePath::from_path("z-documents")]);
+    assert!(data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("Must not have conflicts!"),
+        )
+        .is_err());
+    dir_should_contain(&fs_2, "z-documents", vec!["letter", "invoice"]);
+    dir_should_not_contain(&fs_2, "a-media", vec!["movie"]);
+
+    // Lower priority items are still synced once possible.
+    fs_2.clear_errors();
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("Must not have conflicts!"),
+        )
+        .unwrap();
+    dir_should_contain(&fs_2, "a-media", vec!["movie"]);
+}
+
+#[test]
+fn refuse_sync_exceeding_free_space() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "0123456789", false)
+        .unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.test_set_file_content("sub/file-2", "01234", false)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    assert_eq!(data_store_2.estimate_sync_size(&data_store_1).unwrap(), 15);
+
+    // The sync does not fit, nothing must be transferred.
+    fs_2.set_capacity(Some(14));
+    match data_store_2.check_free_space(&data_store_1, 0) {
+        Err(DataStoreError::InsufficientSpace {
+            required,
+            available,
+        }) => {
+            assert_eq!(required, 15);
+            assert!(available <= 14);
+        }
+        _ => panic!("Must refuse syncs that do not fit on the disk!"),
+    }
+    dir_should_not_contain(&fs_2, "", vec!["file-1", "sub"]);
+
+    // Reserved headroom is taken into account (the store's metadata also uses some space).
+    fs_2.set_capacity(Some(1000));
+    let used_space = 1000 - fs_2.available_space("").unwrap();
+    fs_2.set_capacity(Some(used_space + 20));
+    assert!(data_store_2.check_free_space(&data_store_1, 10).is_err());
+    assert_eq!(data_store_2.check_free_space(&data_store_1, 5).unwrap(), 15);
+
+    // Once synced, nothing has to be downloaded anymore.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(data_store_2.estimate_sync_size(&data_store_1).unwrap(), 0);
+}
+
+#[test]
+fn report_divergence_per_data_store() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    let store_1_name = data_store_1.local_data_store_name().unwrap();
+    let store_2_name = data_store_2.local_data_store_name().unwrap();
+    assert!(data_store_1
+        .divergence(&data_store_2)
+        .unwrap()
+        .is_converged());
+
+    // Each store has changes the other does not know about.
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    fs_2.create_file("file-3").unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let report = data_store_1.divergence(&data_store_2).unwrap();
+    assert!(!report.is_converged());
+    assert_eq!(report.entries.len(), 2);
+
+    let store_1_entry = report.entry(&store_1_name).unwrap();
+    assert!(store_1_entry.is_local_ahead());
+    assert!(store_1_entry.local_mod_time > store_1_entry.remote_mod_time);
+    let store_2_entry = report.entry(&store_2_name).unwrap();
+    assert!(store_2_entry.is_remote_ahead());
+    assert!(store_2_entry.remote_mod_time > store_2_entry.local_mod_time);
+}
+
+#[test]
+fn compare_overlapping_inclusion_rules() {
+    let ((_fs_1, mut data_store_1), (_fs_2, mut data_store_2)) = create_synced_base_state();
+    assert!(data_store_1
+        .compare_inclusion_rules(&data_store_2)
+        .unwrap()
+        .is_empty());
+
+    // Shared rules are not reported...
+    data_store_1
+        .add_ignore_rule(Pattern::new("**/*.bak").unwrap())
+        .unwrap();
+    data_store_2
+        .add_ignore_rule(Pattern::new("**/*.bak").unwrap())
+        .unwrap();
+    // ...rules of only one side are...
+    data_store_1
+        .add_ignore_rule(Pattern::new("/local-only").unwrap())
+        .unwrap();
+    data_store_2
+        .add_ignore_rule(Pattern::new("/remote-only").unwrap())
+        .unwrap();
+    // ...and patterns used differently are reported on both sides.
+    data_store_1
+        .add_ignore_rule(Pattern::new("/videos").unwrap())
+        .unwrap();
+    data_store_2
+        .add_inclusion_rule(Pattern::new("/videos").unwrap())
+        .unwrap();
+
+    let rule_diff = data_store_1.compare_inclusion_rules(&data_store_2).unwrap();
+    assert_eq!(
+        rule_diff,
+        RuleDiff {
+            only_local: vec![
+                metadata_db::DBInclusionRule {
+                    include: false,
+                    rule: Pattern::new("/local-only").unwrap(),
+                },
+                metadata_db::DBInclusionRule {
+                    include: false,
+                    rule: Pattern::new("/videos").unwrap(),
+                },
+            ],
+            only_remote: vec![
+                metadata_db::DBInclusionRule {
+                    include: false,
+                    rule: Pattern::new("/remote-only").unwrap(),
+                },
+                metadata_db::DBInclusionRule {
+                    include: true,
+                    rule: Pattern::new("/videos").unwrap(),
+                },
+            ],
+        }
+    );
+}
+
+#[test]
+fn reidentify_cloned_store() {
+    let ((fs_1, data_store_1), (fs_2, mut data_store_2)) = create_synced_base_state();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+
+    let old_name = data_store_2.local_data_store_name().unwrap();
+    let new_name = data_store_2.reidentify().unwrap();
+    assert_ne!(old_name, new_name);
+    assert_eq!(data_store_2.local_data_store_name().unwrap(), new_name);
+    assert_eq!(data_store_2.local_time().unwrap(), 0);
+    // The old identity is kept as an external store.
+    let old_store = data_store_2
+        .get_data_stores()
+        .unwrap()
+        .into_iter()
+        .find(|store| store.unique_name == old_name)
+        .unwrap();
+    assert!(!old_store.is_this_store);
+
+    // The store is still in sync, nothing is transferred.
+    let result = data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(result.visited_items, result.up_to_date_items);
+
+    // Changes of the new identity are synced as changes of a brand-new store.
+    fs_2.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "changed");
+    assert!(data_store_1
+        .get_data_stores()
+        .unwrap()
+        .iter()
+        .any(|store| store.unique_name == new_name));
+
+    // ...and changes of other stores still reach the re-identified store without conflicts.
+    fs_1.test_set_file_content("file-1", "changed again", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        fs_2.test_get_file_content("file-1").unwrap(),
+        "changed again"
+    );
+}
+
+#[test]
+fn detect_moved_store() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let original_path = test_dir.path().join("original");
+    let moved_path = test_dir.path().join("moved");
+    std::fs::create_dir(&original_path).unwrap();
+
+    let data_store = DefaultDataStore::create(&original_path, "XYZ", "XYZ", "local").unwrap();
+    assert!(!data_store.is_possibly_cloned());
+    drop(data_store);
+
+    std::fs::rename(&original_path, &moved_path).unwrap();
+    let mut data_store = DefaultDataStore::open(&moved_path).unwrap();
+    assert!(data_store.is_possibly_cloned());
+    data_store.confirm_location().unwrap();
+    drop(data_store);
+
+    let data_store = DefaultDataStore::open(&moved_path).unwrap();
+    assert!(!data_store.is_possibly_cloned());
+}
+
+#[test]
+fn export_manifest_matching_file_contents() {
+    use data_encoding::HEXLOWER;
+    use ring::digest::{digest, SHA256};
+
+    let test_dir = tempfile::tempdir().unwrap();
+    let data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+
+    std::fs::create_dir_all(test_dir.path().join("sub").join("nested")).unwrap();
+    for (path, content) in [
+        ("file-1", "hello!"),
+        ("sub/file-2", "world!"),
+        ("sub/nested/file-3", ""),
+    ] {
+        File::create(test_dir.path().join(path))
+            .unwrap()
+            .write_all(content.as_bytes())
+            .unwrap();
+    }
+    data_store.perform_full_scan().unwrap();
+
+    let mut manifest = Vec::new();
+    let exported_files = data_store.export_manifest(&mut manifest).unwrap();
+    assert_eq!(exported_files, 3);
+
+    // Every line is a plain checksum entry (as required by 'sha256sum --strict -c').
+    let manifest = String::from_utf8(manifest).unwrap();
+    let mut verified_paths = Vec::new();
+    for line in manifest.lines() {
+        let (hash, path) = line.split_once("  ").unwrap();
+
+        let content = std::fs::read(test_dir.path().join(path)).unwrap();
+        assert_eq!(hash, HEXLOWER.encode(digest(&SHA256, &content).as_ref()));
+        verified_paths.push(path.to_string());
+    }
+    verified_paths.sort();
+    assert_eq!(
+        verified_paths,
+        vec!["file-1", "sub/file-2", "sub/nested/file-3"]
+    );
+}
+
+#[test]
+fn fall_back_to_mod_time_for_bogus_creation_times() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "start", true).unwrap();
+    let mod_time = fs_1.metadata("file-1").unwrap().last_mod_time();
+    let bogus_creation_time = FileTime::from_unix_time(mod_time.unix_seconds() + 3600, 0);
+    fs_1.test_set_creation_time("file-1", bogus_creation_time)
+        .unwrap();
+
+    let changes = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 1);
+    assert_eq!(changes.unreliable_creation_times, 1);
+    let db_item = data_store_1
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    assert_eq!(
+        db_item.metadata().creation_time,
+        db_item.metadata().mod_time
+    );
+
+    // Syncing back and forth must not detect any conflicts.
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "start");
+
+    fs_2.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "changed");
+
+    let changes = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.new_items, 0);
+}
+
+#[test]
+fn sync_with_store_without_creation_times() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_2.set_creation_times_supported(false);
+
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "start", true).unwrap();
+    let mod_time = fs_1.metadata("file-1").unwrap().last_mod_time();
+    let creation_time = FileTime::from_unix_time(mod_time.unix_seconds() - 3600, 0);
+    fs_1.test_set_creation_time("file-1", creation_time)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let db_creation_time = |data_store: &DataStore<InMemoryFS>, path: &str| {
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap()
+            .metadata()
+            .creation_time
+    };
+
+    // The synced file keeps the creation time reported by the first store...
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    let file_1_creation_time = db_creation_time(&data_store_1, "file-1");
+    assert_eq!(
+        db_creation_time(&data_store_2, "file-1"),
+        file_1_creation_time
+    );
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+
+    // ...also when it is changed on the store without creation times.
+    fs_2.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    fs_2.create_file("file-2").unwrap();
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 1);
+    assert_eq!(changes.new_items, 1);
+    assert_eq!(changes.unreliable_creation_times, 2);
+    assert_eq!(
+        db_creation_time(&data_store_2, "file-1"),
+        file_1_creation_time
+    );
+    let file_2_item = data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-2"), false)
+        .unwrap();
+    assert_eq!(
+        file_2_item.metadata().creation_time,
+        file_2_item.metadata().mod_time
+    );
+
+    // Both stores agree on all creation times and sync without conflicts.
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "changed");
+    assert_eq!(
+        db_creation_time(&data_store_1, "file-1"),
+        file_1_creation_time
+    );
+    assert_eq!(
+        db_creation_time(&data_store_1, "file-2"),
+        db_creation_time(&data_store_2, "file-2")
+    );
+
+    let changes = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.new_items, 0);
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.new_items, 0);
+}
+
+#[test]
+fn list_changed_paths_during_scan() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-1").unwrap();
+    fs.create_file("file-2").unwrap();
+    fs.create_file("file-3").unwrap();
+
+    // Changes are only collected on request.
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 4);
+    assert_eq!(changes.changes, None);
+
+    fs.test_set_file_content("sub/file-1", "changed", true)
+        .unwrap();
+    fs.remove_file("file-2").unwrap();
+    fs.create_file("sub/file-4").unwrap();
+
+    let result = data_store.perform_full_scan_listing_changes().unwrap();
+    let changes = result.changes.unwrap();
+    assert_eq!(
+        changes.new_items,
+        vec![RelativePath::from_path("sub/file-4")]
+    );
+    assert_eq!(
+        changes.changed_items,
+        vec![RelativePath::from_path("sub/file-1")]
+    );
+    assert_eq!(
+        changes.deleted_items,
+        vec![RelativePath::from_path("file-2")]
+    );
+    assert_eq!(changes.new_items.len(), result.new_items);
+    assert_eq!(changes.changed_items.len(), result.changed_items);
+    assert_eq!(changes.deleted_items.len(), result.deleted_items);
+}
+
+#[test]
+fn retry_transient_errors_during_sync() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, mut data_store_2) = create_in_memory_store();
+    fs_1.create_file("file-1").unwrap();
+    fs_1.test_set_file_content("file-1", "content", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Without retries, a single transient error aborts the sync.
+    fs_1.set_error_on_next("file-1", Operation::Read, io::ErrorKind::Interrupted, 1)
+        .unwrap();
+    assert!(data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .is_err());
+    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+
+    // Permanent errors are not retried.
+    data_store_2.set_retry_policy(RetryPolicy::new(3, Duration::from_millis(1)));
+    fs_1.set_error_on_next(
+        "file-1",
+        Operation::Read,
+        io::ErrorKind::PermissionDenied,
+        1,
+    )
+    .unwrap();
+    assert!(data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .is_err());
+    dir_should_not_contain(&fs_2, "", vec!["file-1"]);
+
+    // Transient errors are retried and the sync completes.
+    fs_1.set_error_on_next("file-1", Operation::Read, io::ErrorKind::Interrupted, 1)
+        .unwrap();
+    fs_2.set_error_on_next("file-1", Operation::Rename, io::ErrorKind::TimedOut, 1)
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "", vec!["file-1"]);
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "content");
+}
+
+#[test]
+fn skip_files_without_content_on_the_remote() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.test_set_file_content("sub/file-1", "content-1", true)
+        .unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.test_set_file_content("sub/file-2", "content-2", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // The remote knows about the file, but can not supply its content.
+    data_store_1.set_partial_content(true).unwrap();
+    assert!(data_store_1.has_partial_content().unwrap());
+    fs_1.set_error_on("sub/file-1", Operation::Read, io::ErrorKind::NotFound)
+        .unwrap();
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        result.unavailable_items,
+        vec![RelativePath::from_path("sub/file-1")]
+    );
+    dir_should_contain(&fs_2, "sub", vec!["file-2"]);
+    dir_should_not_contain(&fs_2, "sub", vec!["file-1"]);
+    assert_eq!(
+        fs_2.test_get_file_content("sub/file-2").unwrap(),
+        "content-2"
+    );
+
+    // The parents did not take up the remote's sync time, i.e. the next sync gets the file.
+    fs_1.clear_error_on("sub/file-1", Operation::Read).unwrap();
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(result.unavailable_items.is_empty());
+    assert_eq!(result.transferred_files, 1);
+    assert_eq!(
+        fs_2.test_get_file_content("sub/file-1").unwrap(),
+        "content-1"
+    );
+}
+
+#[test]
+fn report_folder_replaced_by_file() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+    fs_1.remove_dir_recursive("sub").unwrap();
+    fs_1.create_file("sub").unwrap();
+    fs_1.test_set_file_content("sub", "now a file", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // Type changes are refused by default, i.e. they are left as they are...
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        result.refused_type_changes,
+        vec![TypeChange::FolderBecameFile(RelativePath::from_path("sub"))]
+    );
+    assert!(result.type_changes.is_empty());
+    dir_should_contain(&fs_2, "sub", vec!["file-1"]);
+
+    // ...and are applied (and reported) once they are allowed.
+    let options = SyncOptions::new().allow_type_changes(true);
+    let result = data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+    assert_eq!(
+        result.type_changes,
+        vec![TypeChange::FolderBecameFile(RelativePath::from_path("sub"))]
+    );
+    assert!(result.refused_type_changes.is_empty());
+    assert_eq!(fs_2.test_get_file_content("sub").unwrap(), "now a file");
+}
+
+#[test]
+fn skip_sub_trees_for_a_single_sync() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_dir("documents", false).unwrap();
+    fs_1.create_file("documents/file-1").unwrap();
+    fs_1.create_dir("videos", false).unwrap();
+    fs_1.create_file("videos/movie").unwrap();
+    fs_1.test_set_file_content("videos/movie", "movie", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let options = SyncOptions::new().skip(vec![RelativePath::from_path("Videos")]);
+    let result = data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("No conflicts expected!"),
+        )
+        .unwrap();
+    dir_should_contain(&fs_2, "", vec!["documents"]);
+    dir_should_contain(&fs_2, "documents", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "", vec!["videos"]);
+
+    // Nothing of the skipped sub-tree is transferred or recorded...
+    assert_eq!(result.transferred_files, 1);
+    for path in &["videos", "videos/movie"] {
+        let path = RelativePath::from_path(path);
+        assert!(!result.touched_paths.contains(&path));
+        let skipped_item = data_store_2
+            .db_access
+            .get_local_data_item(&path, false)
+            .unwrap();
+        assert!(skipped_item.is_deletion());
+        // ...and it is neither marked as ignored nor excluded by the inclusion rules.
+        assert!(!skipped_item.is_ignored());
+        assert!(data_store_2.get_inclusion_rules().is_included(&path));
+    }
+
+    // A later, regular sync transfers exactly the skipped sub-tree.
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(result.transferred_files, 1);
+    assert!(result
+        .touched_paths
+        .contains(&RelativePath::from_path("videos/movie")));
+    assert!(!result
+        .touched_paths
+        .contains(&RelativePath::from_path("documents/file-1")));
+    dir_should_contain(&fs_2, "", vec!["documents", "videos"]);
+    assert_eq!(fs_2.test_get_file_content("videos/movie").unwrap(), "movie");
+    assert!(data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("videos/movie"), false)
+        .unwrap()
+        .is_file());
+}
+
+#[test]
+fn sync_with_parallel_downloads() {
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    let data_store_2 =
+        DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+
+    std::fs::create_dir(test_dir_1.path().join("sub")).unwrap();
+    let files = vec![
+        "file-1",
+        "file-2",
+        "file-3",
+        "file-4",
+        "sub/file-5",
+        "sub/file-6",
+    ];
+    for file in &files {
+        File::create(test_dir_1.path().join(file))
+            .unwrap()
+            .write_all(format!("content of {}", file).as_bytes())
+            .unwrap();
+    }
+    // A concurrent local change, its prefetched remote version must be thrown away.
+    File::create(test_dir_2.path().join("file-3"))
+        .unwrap()
+        .write_all(b"local content")
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let options = SyncOptions::new().parallel_downloads(3);
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| SyncConflictResolution::ChooseLocalItem,
+        )
+        .unwrap();
+
+    for file in &files {
+        let expected_content = if *file == "file-3" {
+            "local content".to_string()
+        } else {
+            format!("content of {}", file)
+        };
+        assert_eq!(
+            std::fs::read_to_string(test_dir_2.path().join(file)).unwrap(),
+            expected_content
+        );
+    }
+    assert_eq!(data_store_2.clean_pending(true).unwrap().pending_files, 0);
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
+}
+
+#[test]
+fn clean_prefetched_files_of_failed_syncs() {
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    let data_store_2 =
+        DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+
+    let files = vec!["file-1", "file-2", "file-3", "file-4"];
+    for file in &files {
+        std::fs::write(test_dir_1.path().join(file), format!("content of {}", file)).unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    // The remote's disk is out of sync with its index, the sync fails at file-3...
+    std::fs::write(test_dir_1.path().join("file-3"), "changed content").unwrap();
+
+    let options = SyncOptions::new().parallel_downloads(4);
+    match data_store_2.sync_from_other_store_with_options(
+        &data_store_1,
+        &RelativePath::from_path(""),
+        &options,
+        &mut |_| panic!("No conflicts expected!"),
+    ) {
+        Err(DataStoreError::DiskOutOfSync { path }) => {
+            assert_eq!(path, RelativePath::from_path("file-3"))
+        }
+        _ => panic!("The sync must fail on items out of sync with the remote's index!"),
+    }
+
+    // ...after file-4 was prefetched, which must not be left behind.
+    assert!(!test_dir_2.path().join("file-4").exists());
+    assert_eq!(data_store_2.clean_pending(true).unwrap().pending_files, 0);
+}
+
+fn sync_with_options_on_disk(options: &SyncOptions) -> (SyncResult, Vec<(String, String)>) {
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    let data_store_2 =
+        DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+
+    std::fs::create_dir(test_dir_1.path().join("sub")).unwrap();
+    let files = vec!["file-1", "file-2", "file-3", "sub/file-4", "sub/file-5"];
+    for file in &files {
+        File::create(test_dir_1.path().join(file))
+            .unwrap()
+            .write_all(format!("content of {}", file).as_bytes())
+            .unwrap();
+    }
+    // A concurrent local change, its prefetched remote version must be thrown away.
+    File::create(test_dir_2.path().join("file-2"))
+        .unwrap()
+        .write_all(b"local content")
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let result = data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            options,
+            &mut |_| SyncConflictResolution::ChooseLocalItem,
+        )
+        .unwrap();
+    assert_eq!(data_store_2.clean_pending(true).unwrap().pending_files, 0);
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
+
+    let contents = files
+        .iter()
+        .map(|file| {
+            (
+                file.to_string(),
+                std::fs::read_to_string(test_dir_2.path().join(file)).unwrap(),
+            )
+        })
+        .collect();
+    (result, contents)
+}
+
+#[test]
+fn sync_with_pipelined_prefetch() {
+    let (result, contents) = sync_with_options_on_disk(&SyncOptions::new());
+    let (prefetch_result, prefetch_contents) =
+        sync_with_options_on_disk(&SyncOptions::new().pipelined_prefetch(true));
+    assert_eq!(prefetch_result, result);
+    assert_eq!(prefetch_contents, contents);
+    assert_eq!(
+        prefetch_contents[1],
+        ("file-2".to_string(), "local content".to_string())
+    );
+
+    // Also combined with batches of parallel downloads.
+    let (prefetch_result, prefetch_contents) = sync_with_options_on_disk(
+        &SyncOptions::new()
+            .pipelined_prefetch(true)
+            .parallel_downloads(2),
+    );
+    assert_eq!(prefetch_result, result);
+    assert_eq!(prefetch_contents, contents);
+}
+
+#[test]
+fn apply_per_directory_ignore_files_during_scan() {
+    let (fs, mut data_store) = create_in_memory_store();
+    data_store
+        .set_ignore_file_name(Some(".squirrelignore".to_string()))
+        .unwrap();
+
+    fs.create_file(".squirrelignore").unwrap();
+    fs.test_set_file_content(".squirrelignore", "*.tmp\n!important.tmp\n", false)
+        .unwrap();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/.squirrelignore").unwrap();
+    fs.test_set_file_content("sub/.squirrelignore", "important.tmp\n*.log", false)
+        .unwrap();
+    fs.create_file("file.tmp").unwrap();
+    fs.create_file("important.tmp").unwrap();
+    fs.create_file("file.log").unwrap();
+    fs.create_file("sub/important.tmp").unwrap();
+    fs.create_file("sub/file.log").unwrap();
+    fs.create_file("sub/file.txt").unwrap();
+
+    let changes = data_store.perform_full_scan_listing_changes().unwrap();
+    let mut new_items: Vec<String> = changes
+        .changes
+        .unwrap()
+        .new_items
+        .iter()
+        .map(|path| path.to_path_buf().to_str().unwrap().to_string())
+        .collect();
+    new_items.sort();
+    // The nested ignore file excludes 'sub/important.tmp', which the root scope includes.
+    assert_eq!(
+        new_items,
+        vec![
+            ".squirrelignore",
+            "file.log",
+            "important.tmp",
+            "sub",
+            "sub/.squirrelignore",
+            "sub/file.txt",
+        ]
+    );
+
+    // Without the ignore files, the items are picked up by the next scan.
+    data_store.set_ignore_file_name(None).unwrap();
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 3);
+
+    // Re-enabling them marks the already indexed items as ignored, their content stays on disk.
+    data_store
+        .set_ignore_file_name(Some(".squirrelignore".to_string()))
+        .unwrap();
+    let changes = data_store.perform_full_scan().unwrap();
+    let mut ignored_on_disk: Vec<String> = changes
+        .ignored_on_disk
+        .iter()
+        .map(|path| path.to_path_buf().to_str().unwrap().to_string())
+        .collect();
+    ignored_on_disk.sort();
+    assert_eq!(
+        ignored_on_disk,
+        vec!["file.tmp", "sub/file.log", "sub/important.tmp"]
+    );
+    for path in &ignored_on_disk {
+        let db_item = data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap();
+        assert!(db_item.is_ignored());
+    }
+
+    // Items no longer matching the ignore files are indexed again.
+    fs.test_set_file_content("sub/.squirrelignore", "*.log", false)
+        .unwrap();
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 1);
+    let db_item = data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("sub/important.tmp"), false)
+        .unwrap();
+    assert!(db_item.is_file());
+}
+
+#[test]
+fn persist_ignore_file_name_and_skip_ignored_items_on_sync() {
+    let ((fs_1, data_store_1), (fs_2, mut data_store_2)) = create_synced_base_state();
+    fs_1.create_dir("videos", false).unwrap();
+    fs_1.create_file("videos/movie.tmp").unwrap();
+    fs_1.create_file("videos/movie.mp4").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    data_store_2
+        .set_ignore_file_name(Some(".squirrelignore".to_string()))
+        .unwrap();
+    fs_2.create_file(".squirrelignore").unwrap();
+    fs_2.test_set_file_content(".squirrelignore", "*.tmp", false)
+        .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Synced items matching the local ignore files are recorded as ignored, not fetched.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(fs_2.test_get_file_content("videos/movie.mp4").is_ok());
+    assert!(fs_2.test_get_file_content("videos/movie.tmp").is_err());
+    let db_item = data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("videos/movie.tmp"), false)
+        .unwrap();
+    assert!(db_item.is_ignored());
+
+    // The setting is persisted with the store.
+    let test_dir = tempfile::tempdir().unwrap();
+    let mut data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+    data_store
+        .set_ignore_file_name(Some(".squirrelignore".to_string()))
+        .unwrap();
+    drop(data_store);
+    let data_store = DefaultDataStore::open(test_dir.path()).unwrap();
+    assert_eq!(data_store.ignore_file_name(), Some(".squirrelignore"));
+}
+
+#[test]
+fn stage_downloads_on_a_different_volume() {
+    let ((fs_1, data_store_1), _) = create_synced_base_state();
+    let fs_2 = virtual_fs::InMemoryFS::new();
+    fs_2.create_dir("store", false).unwrap();
+    let mut data_store_2 =
+        DataStore::create_with_fs("store", "XYZ", "XYZ", "staged-data-store", fs_2.clone())
+            .unwrap();
+    data_store_2.set_staging_dir(Some("staging")).unwrap();
+    // Another store's pending file in the shared staging dir.
+    fs_2.create_dir("staging/other-data-store", false).unwrap();
+    fs_2.create_file("staging/other-data-store/pending")
+        .unwrap();
+
+    // Renames out of the staging dir fail, as they would between different disks.
+    fs_2.set_error_on_next(
+        "store/file-1",
+        Operation::Rename,
+        io::ErrorKind::CrossesDevices,
+        1,
+    )
+    .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // The file lands in the store (keeping its metadata) and nothing is left behind.
+    assert_eq!(fs_2.test_get_file_content("store/file-1").unwrap(), "start");
+    assert_eq!(
+        fs_2.metadata("store/file-1").unwrap().last_mod_time(),
+        fs_1.metadata("file-1").unwrap().last_mod_time()
+    );
+    let pending_dir = data_store_2.fs_access.pending_files_dir();
+    assert!(pending_dir.starts_with(fs_2.canonicalize("staging").unwrap()));
+    assert!(fs_2.list_dir(pending_dir).unwrap().is_empty());
+    assert!(!fs_2
+        .list_dir("store")
+        .unwrap()
+        .iter()
+        .any(|entry| entry.file_name.to_string_lossy().ends_with(".tmp")));
+    assert!(fs_2.metadata("staging/other-data-store/pending").is_ok());
+}
+
+#[test]
+fn work_on_a_local_copy_of_the_db() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let fs = virtual_fs::WrapperFS::with_tmp_copy_db();
+    let data_store =
+        DataStore::create_with_fs(test_dir.path(), "XYZ", "XYZ", "tmp-copy-store", fs).unwrap();
+    File::create(test_dir.path().join("file-1")).unwrap();
+    data_store.perform_full_scan().unwrap();
+    drop(data_store);
+
+    // The changes are written back into the store when it is closed.
+    let metadata_files: Vec<_> = std::fs::read_dir(test_dir.path().join(".__data_squirrel__"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(metadata_files.contains(&"database.sqlite".to_string()));
+    assert!(!metadata_files
+        .iter()
+        .any(|name| name.ends_with(".tmp") || name.ends_with(".old")));
+
+    let data_store =
+        DataStore::open_with_fs(test_dir.path(), virtual_fs::WrapperFS::with_tmp_copy_db())
+            .unwrap();
+    let db_item = data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-1"), false)
+        .unwrap();
+    assert!(db_item.is_file());
+}
+
+#[test]
+fn never_index_or_sync_internal_paths() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, mut data_store_2) = create_in_memory_store();
+    data_store_2.set_staging_dir(Some("tmp")).unwrap();
+
+    // Store 1 contains a nested store and a regular folder named like store 2's staging dir.
+    fs_1.create_dir("inner", false).unwrap();
+    let inner_store =
+        DataStore::create_with_fs("inner", "XYZ", "XYZ", "inner-data-store", fs_1.clone()).unwrap();
+    fs_1.create_file("inner/file-1").unwrap();
+    inner_store.perform_full_scan().unwrap();
+    fs_1.create_dir("tmp", false).unwrap();
+    fs_1.create_file("tmp/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let is_indexed = |data_store: &DataStore<InMemoryFS>, path: &str| {
+        !data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap()
+            .is_deletion()
+    };
+    assert!(is_indexed(&data_store_1, "inner/file-1"));
+    assert!(is_indexed(&data_store_1, "tmp/file-2"));
+    assert!(!is_indexed(&data_store_1, "inner/.__data_squirrel__"));
+
+    // Neither the nested store's metadata nor anything in store 2's staging dir is synced.
+    data_store_2.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "inner", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "inner", vec![".__data_squirrel__"]);
+    dir_should_not_contain(&fs_2, "tmp", vec!["file-2"]);
+    let pending_dir = data_store_2.fs_access.pending_files_dir();
+    assert!(fs_2.list_dir(pending_dir).unwrap().is_empty());
+    assert!(!is_indexed(&data_store_2, "tmp"));
+    assert!(!is_indexed(&data_store_2, "inner/.__data_squirrel__"));
+
+    // Scans keep ignoring them as well.
+    data_store_2.perform_full_scan().unwrap();
+    assert!(!is_indexed(&data_store_2, "tmp"));
+}
+
+#[test]
+fn sync_metadata_only_changes_without_transfer() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Only the read-only bit changes on store 1.
+    let mod_time = fs_1.metadata("file-1").unwrap().last_mod_time();
+    fs_1.update_metadata("file-1", mod_time, true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // The content must not be read again.
+    fs_1.set_error_on_all(Operation::Read, io::ErrorKind::PermissionDenied);
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(result.transferred_files, 0);
+    assert_eq!(result.transferred_bytes, 0);
+    assert!(fs_2.metadata("file-1").unwrap().read_only());
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "start");
+
+    // The store is consistent with its disk content.
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+}
+
+#[test]
+fn refuse_downloads_not_matching_the_remote_index() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    fs_1.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // The content changes again without touching the modification time.
+    fs_1.test_set_file_content("file-1", "unscanned", false)
+        .unwrap();
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""));
+    assert!(matches!(result, Err(DataStoreError::DiskOutOfSync { .. })));
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "start");
+    assert_eq!(data_store_2.clean_pending(false).unwrap().pending_files, 0);
+}
+
+#[test]
+fn report_progress_per_transferred_file() {
+    let ((fs_1, data_store_1), (_fs_2, data_store_2)) = create_synced_base_state_folders();
+    fs_1.test_set_file_content("sub/file-1", "changed", true)
+        .unwrap();
+    fs_1.create_file("file-2").unwrap();
+    fs_1.test_set_file_content("file-2", "new", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let mut started = Vec::new();
+    let mut finished = Vec::new();
+    let result = data_store_2
+        .sync_from_other_store_with_progress(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &SyncOptions::new(),
+            &mut |_| panic!("No conflicts expected!"),
+            &mut |event| match event {
+                SyncProgressEvent::FileStarted { path, size } => started.push((path.clone(), size)),
+                SyncProgressEvent::FileFinished {
+                    path,
+                    transferred_files,
+                    transferred_bytes,
+                    ..
+                } => finished.push((path.clone(), transferred_files, transferred_bytes)),
+                SyncProgressEvent::ItemSynced { .. } => (),
+            },
+        )
+        .unwrap();
+
+    started.sort_by_key(|(path, _)| path.to_path_buf());
+    assert_eq!(
+        started,
+        vec![
+            (RelativePath::from_path("file-2"), 3),
+            (RelativePath::from_path("sub/file-1"), 7),
+        ]
+    );
+    assert_eq!(finished.len(), 2);
+    assert_eq!(finished[1].1, 2);
+    assert_eq!(finished[1].2, 10);
+    assert_eq!(result.transferred_files, 2);
+    assert_eq!(result.transferred_bytes, 10);
+}
+
+#[test]
+fn report_synced_items_after_db_commit() {
+    let ((fs_1, data_store_1), (_fs_2, data_store_2)) = create_synced_base_state();
+    fs_1.create_file("file-2").unwrap();
+    fs_1.test_set_file_content("file-2", "new", true).unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    let mut synced_items = Vec::new();
+    data_store_2
+        .sync_from_other_store_with_progress(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &SyncOptions::new(),
+            &mut |_| panic!("No conflicts expected!"),
+            &mut |event| {
+                if let SyncProgressEvent::ItemSynced { path, action } = event {
+                    // The DB already holds the synced item.
+                    assert!(data_store_2
+                        .db_access
+                        .get_local_data_item(path, false)
+                        .unwrap()
+                        .is_file());
+                    synced_items.push((path.clone(), action));
+                }
+            },
+        )
+        .unwrap();
+
+    assert_eq!(
+        synced_items,
+        vec![(RelativePath::from_path("file-2"), SyncedItemAction::Created)]
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn refuse_to_sync_a_store_with_itself() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let store_path = test_dir.path().join("store");
+    let clone_path = test_dir.path().join("clone");
+    let link_path = test_dir.path().join("link");
+    std::fs::create_dir(&store_path).unwrap();
+    let data_store = DefaultDataStore::create(&store_path, "XYZ", "XYZ", "laptop").unwrap();
+
+    let assert_refused = |result: Result<SyncResult>| match result {
+        Err(DataStoreError::SameDataStore) => (),
+        _ => panic!("Must not sync a store with itself!"),
+    };
+    assert_refused(data_store.sync_from_other_store(
+        &data_store,
+        &RelativePath::from_path(""),
+        &mut |_| panic!("No conflicts expected!"),
+    ));
+
+    // A second path to the same store can not lock it again...
+    std::os::unix::fs::symlink(&store_path, &link_path).unwrap();
+    assert!(DefaultDataStore::open(&link_path).is_err());
+
+    // ...but it can be opened for reading next to the locked handle.
+    drop(data_store);
+    let data_store = DefaultDataStore::open_shared(&store_path).unwrap();
+    for second_path in &[link_path.clone(), store_path.join("..").join("store")] {
+        let second_handle = DefaultDataStore::open_read_only(second_path).unwrap();
+        assert_refused(data_store.sync_from_other_store(
+            &second_handle,
+            &RelativePath::from_path(""),
+            &mut |_| panic!("No conflicts expected!"),
+        ));
+    }
+
+    // A copy of the store shares its identity until it is re-identified.
+    drop(data_store);
+    copy_dir_recursive(&store_path, &clone_path);
+    let data_store = DefaultDataStore::open(&link_path).unwrap();
+    let clone = DefaultDataStore::open(&clone_path).unwrap();
+    assert_refused(clone.sync_from_other_store(
+        &data_store,
+        &RelativePath::from_path(""),
+        &mut |_| panic!("No conflicts expected!"),
+    ));
+}
+
+fn copy_dir_recursive(from: &std::path::Path, to: &std::path::Path) {
+    std::fs::create_dir(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir_recursive(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            std::fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+#[test]
+fn scan_sub_tree_while_syncing_another() {
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 =
+        DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "source-data-store").unwrap();
+    DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "target-data-store").unwrap();
+    // Scan readers require a store that is not locked exclusively.
+    let data_store_2 = DefaultDataStore::open_shared(test_dir_2.path()).unwrap();
+
+    for dir in &["a", "b"] {
+        std::fs::create_dir(test_dir_1.path().join(dir)).unwrap();
+        std::fs::write(test_dir_1.path().join(dir).join("file"), b"start").unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // New data in both sub-trees, one on each of the stores.
+    sleep(Duration::from_millis(10));
+    std::fs::write(test_dir_2.path().join("a").join("new"), b"new").unwrap();
+    std::fs::write(test_dir_1.path().join("b").join("file"), b"changed").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // Scan 'a' on a different thread while 'b' is synced, i.e. the whole scan runs while the
+    // sync is in the middle of transferring its file...
+    let barrier = std::sync::Arc::new(std::sync::Barrier::new(2));
+    let scan_reader = data_store_2
+        .scan_reader(&RelativePath::from_path("a"))
+        .unwrap();
+    let scan_barrier = barrier.clone();
+    let scan_thread = std::thread::spawn(move || {
+        scan_barrier.wait();
+        let pending_scan = scan_reader.scan().unwrap();
+        scan_barrier.wait();
+        pending_scan
+    });
+    let sync_result = data_store_2
+        .sync_from_other_store_with_progress(
+            &data_store_1,
+            &RelativePath::from_path("b"),
+            &SyncOptions::new(),
+            &mut |_| panic!("No conflicts expected!"),
+            &mut |event| {
+                if let SyncProgressEvent::FileStarted { .. } = event {
+                    barrier.wait();
+                    barrier.wait();
+                }
+            },
+        )
+        .unwrap();
+    let pending_scan = scan_thread.join().unwrap();
+    assert_eq!(sync_result.transferred_files, 1);
+    assert_eq!(
+        std::fs::read(test_dir_2.path().join("b").join("file")).unwrap(),
+        b"changed"
+    );
+
+    // ...and its changes are written by the store afterwards.
+    assert!(data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("a/new"), false)
+        .unwrap()
+        .is_deletion());
+    let scan_result = data_store_2.apply_scan(pending_scan).unwrap();
+    assert_eq!(scan_result.new_items, 1);
+    // The folder 'a' got modified by adding the file.
+    assert_eq!(scan_result.changed_items, 1);
+    assert!(data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("a/new"), false)
+        .unwrap()
+        .is_file());
+    let scan_result = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(scan_result.new_items + scan_result.changed_items, 0);
+
+    // Overlapping operations are refused.
+    let _guard = data_store_2
+        .subtree_locks
+        .lock(&RelativePath::from_path("a"))
+        .unwrap();
+    match data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    {
+        Err(DataStoreError::SubtreeBusy { .. }) => (),
+        _ => panic!("Must not sync a sub-tree that is in use!"),
+    }
+}
+
+#[test]
+fn list_ignored_items() {
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-1").unwrap();
+    fs.create_file("file-2").unwrap();
+    data_store.perform_full_scan().unwrap();
+    assert!(data_store.ignored_items().unwrap().is_empty());
+
+    data_store
+        .add_ignore_rule(Pattern::new("/sub/file-1").unwrap())
+        .unwrap();
+    let ignored_paths: Vec<_> = data_store
+        .ignored_items()
+        .unwrap()
+        .into_iter()
+        .map(|item| item.path)
+        .collect();
+    assert_eq!(ignored_paths, vec![RelativePath::from_path("sub/file-1")]);
+}
+
+#[test]
+fn reindex_items_changed_between_scan_and_sync() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    fs_1.test_set_file_content("file-1", "remote", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    // Changed after the last scan of the local store.
+    fs_2.test_set_file_content("file-1", "local", true).unwrap();
+
+    match data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    {
+        Err(DataStoreError::DiskOutOfSync { path }) => {
+            assert_eq!(path, RelativePath::from_path("file-1"))
+        }
+        _ => panic!("Must not sync if disk content is not correctly indexed in DB!"),
+    };
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "local");
+
+    // The re-indexed local change conflicts with the remote change.
+    let options = SyncOptions::new().reindex_out_of_sync_items(true);
+    let mut conflicts = 0;
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| {
+                conflicts += 1;
+                SyncConflictResolution::ChooseRemoteItem
+            },
+        )
+        .unwrap();
+    assert_eq!(conflicts, 1);
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "remote");
+}
+
+#[test]
+fn reindex_only_the_requested_path() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_file("file-1").unwrap();
+    fs.create_file("file-2").unwrap();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-3").unwrap();
+    data_store.perform_full_scan().unwrap();
+
+    fs.test_set_file_content("file-1", "changed", true).unwrap();
+    fs.test_set_file_content("file-2", "changed", true).unwrap();
+    fs.create_file("sub/file-4").unwrap();
+    // Neither the siblings of the path nor their parent folder are looked at.
+    fs.set_error_on("file-2", Operation::Read, io::ErrorKind::Other)
+        .unwrap();
+    fs.set_error_on("", Operation::ListDir, io::ErrorKind::Other)
+        .unwrap();
+
+    let scan_result = data_store
+        .reindex_path(&RelativePath::from_path("file-1"))
+        .unwrap();
+    assert_eq!(scan_result.changed_items, 1);
+    let scan_result = data_store
+        .reindex_path(&RelativePath::from_path("sub"))
+        .unwrap();
+    assert_eq!(scan_result.new_items, 1);
+    fs.remove_file("sub/file-3").unwrap();
+    let scan_result = data_store
+        .reindex_path(&RelativePath::from_path("sub/file-3"))
+        .unwrap();
+    assert_eq!(scan_result.deleted_items, 1);
+
+    fs.clear_errors();
+    let scan_result = data_store.perform_full_scan().unwrap();
+    assert_eq!(scan_result.changed_items, 1);
+    assert_eq!(scan_result.new_items + scan_result.deleted_items, 0);
+}
+
+#[test]
+fn rebuild_index_keeps_history_of_unchanged_files() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    for name in &["sub/file-1", "sub/file-2", "sub/file-3", "file-4"] {
+        fs.create_file(name).unwrap();
+        fs.test_set_file_content(name, "start", true).unwrap();
+    }
+    data_store.perform_full_scan().unwrap();
+    let mod_time = |path: &str| {
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), true)
+            .unwrap()
+            .mod_time()
+            .clone()
+    };
+    let initial_mod_times: Vec<_> = ["sub/file-1", "sub/file-2", "sub/file-3", "file-4"]
+        .iter()
+        .map(|path| mod_time(path))
+        .collect();
+
+    // The index drifted from the disk: a touched but unchanged file, a file changed without
+    // touching it (not noticed by a normal scan) and a new and a deleted file.
+    fs.test_set_file_content("sub/file-1", "start", true)
+        .unwrap();
+    fs.test_set_file_content("sub/file-2", "other", false)
+        .unwrap();
+    fs.remove_file("sub/file-3").unwrap();
+    fs.create_file("sub/file-5").unwrap();
+    fs.test_set_file_content("file-4", "other", false).unwrap();
+
+    let result = data_store
+        .rebuild_index(&RelativePath::from_path("sub"))
+        .unwrap();
+    assert_eq!(result.changed_items, 1);
+    assert_eq!(result.new_items, 1);
+    assert_eq!(result.deleted_items, 1);
+
+    assert_eq!(mod_time("sub/file-1"), initial_mod_times[0]);
+    assert_ne!(mod_time("sub/file-2"), initial_mod_times[1]);
+    assert!(data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("sub/file-3"), false)
+        .unwrap()
+        .is_deletion());
+    assert!(data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("sub/file-5"), false)
+        .unwrap()
+        .is_file());
+    // Items outside of the sub-tree are left as they are.
+    assert_eq!(mod_time("file-4"), initial_mod_times[3]);
+
+    // The refreshed index matches the disk, i.e. a scan finds nothing to do.
+    let result = data_store.perform_full_scan().unwrap();
+    assert_eq!(result.changed_items, 0);
+    assert_eq!(result.new_items, 0);
+    assert_eq!(result.deleted_items, 0);
+}
+
+#[test]
+fn limit_scan_and_sync_depth() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_dir("sub/deep", false).unwrap();
+    fs_1.create_file("sub/deep/file-1").unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.create_file("file-3").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let is_file = |data_store: &DataStore<InMemoryFS>, path: &str| {
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap()
+            .is_file()
+    };
+
+    // Items below the depth limit must not be seen as deleted.
+    fs_1.remove_file("sub/deep/file-1").unwrap();
+    fs_1.remove_file("sub/file-2").unwrap();
+    fs_1.create_file("file-4").unwrap();
+    data_store_1.set_max_scan_depth(Some(1));
+    let result = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(result.new_items, 1);
+    assert_eq!(result.deleted_items, 0);
+    assert!(is_file(&data_store_1, "sub/file-2"));
+    assert!(is_file(&data_store_1, "sub/deep/file-1"));
+
+    data_store_1.set_max_scan_depth(Some(2));
+    let result = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(result.deleted_items, 1);
+    assert!(!is_file(&data_store_1, "sub/file-2"));
+    assert!(is_file(&data_store_1, "sub/deep/file-1"));
+
+    // Depth limited syncs leave out deeper items, a later sync picks them up.
+    data_store_2.perform_full_scan().unwrap();
+    let options = SyncOptions::new().max_depth(Some(1));
+    data_store_2
+        .sync_from_other_store_with_options(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            &options,
+            &mut |_| panic!("Must not have conflicts!"),
+        )
+        .unwrap();
+    dir_should_contain(&fs_2, "", vec!["sub", "file-3", "file-4"]);
+    dir_should_not_contain(&fs_2, "sub", vec!["deep"]);
+
+    data_store_1.set_max_scan_depth(None);
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "sub", vec!["deep"]);
+}
+
+#[test]
+fn find_duplicate_files() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    for (path, content) in &[
+        ("sub/Photo-1", "photo"),
+        ("photo-2", "photo"),
+        ("other", "other"),
+        ("small-1", "a"),
+        ("small-2", "a"),
+        ("small-3", "a"),
+    ] {
+        fs.create_file(path).unwrap();
+        fs.test_set_file_content(path, content, true).unwrap();
+    }
+    data_store.perform_full_scan().unwrap();
+
+    let duplicates = data_store.find_duplicates().unwrap();
+    let paths: Vec<Vec<RelativePath>> = duplicates.iter().map(|(_, paths)| paths.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            vec![
+                RelativePath::from_path("photo-2"),
+                RelativePath::from_path("sub/Photo-1"),
+            ],
+            vec![
+                RelativePath::from_path("small-1"),
+                RelativePath::from_path("small-2"),
+                RelativePath::from_path("small-3"),
+            ],
+        ]
+    );
+    assert_eq!(
+        duplicates[0].0,
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path("photo-2"), false)
+            .unwrap()
+            .metadata()
+            .hash
+    );
+
+    // The in memory FS does not support hard links, the duplicates must stay intact.
+    assert!(data_store.hard_link_duplicates().is_err());
+    assert_eq!(fs.test_get_file_content("photo-2").unwrap(), "photo");
+    assert_eq!(fs.test_get_file_content("sub/Photo-1").unwrap(), "photo");
+}
+
+#[test]
+fn summarize_sub_trees() {
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-1").unwrap();
+    fs.test_set_file_content("sub/file-1", "12345", true)
+        .unwrap();
+    fs.create_file("sub/File-2").unwrap();
+    fs.test_set_file_content("sub/File-2", "1234567890", true)
+        .unwrap();
+    fs.create_file("sub/ignored").unwrap();
+    fs.test_set_file_content("sub/ignored", "12", true).unwrap();
+    fs.create_file("file-3").unwrap();
+    fs.test_set_file_content("file-3", "123", true).unwrap();
+    fs.create_file("deleted").unwrap();
+    data_store.perform_full_scan().unwrap();
+    fs.remove_file("deleted").unwrap();
+    data_store.perform_full_scan().unwrap();
+    data_store
+        .add_ignore_rule(Pattern::new("/sub/ignored").unwrap())
+        .unwrap();
+
+    let summary = data_store
+        .tree_summary(&RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(summary.files, 3);
+    assert_eq!(summary.directories, 1);
+    assert_eq!(summary.total_bytes, 18);
+    assert_eq!(
+        summary.largest_files,
+        vec![
+            (RelativePath::from_path("sub/File-2"), 10),
+            (RelativePath::from_path("sub/file-1"), 5),
+            (RelativePath::from_path("file-3"), 3),
+        ]
+    );
+
+    let summary = data_store
+        .tree_summary(&RelativePath::from_path("sub"))
+        .unwrap();
+    assert_eq!(summary.files, 2);
+    assert_eq!(summary.directories, 1);
+    assert_eq!(summary.total_bytes, 15);
+}
+
+#[test]
+fn keep_local_name_case_if_not_propagating_case_changes() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+    data_store_2.set_propagate_case_changes(false).unwrap();
+    assert!(!data_store_2.propagates_case_changes().unwrap());
+
+    // Case-only remote changes do not rename local items...
+    fs_1.rename("sub", "SUB").unwrap();
+    fs_1.rename("SUB/file-1", "SUB/File-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "", vec!["sub"]);
+    dir_should_not_contain(&fs_2, "", vec!["SUB"]);
+    dir_should_contain(&fs_2, "sub", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "sub", vec!["File-1"]);
+
+    // ...but content changes are still synced (keeping the local name).
+    fs_1.test_set_file_content("SUB/File-1", "changed", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "sub", vec!["file-1"]);
+    assert_eq!(fs_2.test_get_file_content("sub/file-1").unwrap(), "changed");
+
+    // Case-only local changes are no modifications, i.e. they are not synced to other stores.
+    fs_2.rename("sub/file-1", "sub/FILE-1").unwrap();
+    let scan_result = data_store_2.perform_full_scan_listing_changes().unwrap();
+    assert!(!scan_result
+        .changes
+        .unwrap()
+        .changed_items
+        .iter()
+        .any(|path| path.to_lower_case() == RelativePath::from_path("sub/file-1")));
+    // The item still matches the index, i.e. it can be synced.
+    fs_1.test_set_file_content("SUB/File-1", "changed again", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        fs_2.test_get_file_content("sub/FILE-1").unwrap(),
+        "changed again"
+    );
+}
+
+#[test]
+fn rehash_all_files_with_new_algorithm() {
+    use fs_interaction::HashAlgorithm;
+
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-1").unwrap();
+    fs.test_set_file_content("sub/file-1", "hello", true)
+        .unwrap();
+    fs.create_file("file-2").unwrap();
+    fs.test_set_file_content("file-2", "world", true).unwrap();
+    data_store.perform_full_scan().unwrap();
+    let mod_time_before = data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-2"), true)
+        .unwrap()
+        .mod_time()
+        .clone();
+
+    // Nothing to do with the algorithm the hashes are stored in.
+    assert_eq!(data_store.hash_algorithm(), HashAlgorithm::SHA256);
+    assert_eq!(data_store.rehash_all().unwrap(), 0);
+
+    data_store.set_hash_algorithm(HashAlgorithm::SHA512);
+    assert_eq!(data_store.rehash_all().unwrap(), 2);
+    assert_eq!(data_store.db_access.get_hash_algorithm().unwrap(), "SHA512");
+    for path in &["sub/file-1", "file-2"] {
+        let path = RelativePath::from_path(path);
+        let file_item = data_store
+            .db_access
+            .get_local_data_item(&path, false)
+            .unwrap();
+        assert_eq!(
+            file_item.metadata().hash,
+            data_store.fs_access.calculate_hash(&path).unwrap()
+        );
+        assert_eq!(file_item.metadata().hash.len(), 128);
+    }
+    // The contents did not change, i.e. no modifications are recorded...
+    let file_item = data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-2"), true)
+        .unwrap();
+    assert_eq!(file_item.mod_time(), &mod_time_before);
+    // ...and a scan reports no changes.
+    let scan_result = data_store.perform_full_scan_listing_changes().unwrap();
+    assert_eq!(scan_result.changed_items, 0);
+    assert_eq!(scan_result.new_items, 0);
+    assert_eq!(scan_result.deleted_items, 0);
+    assert_eq!(data_store.rehash_all().unwrap(), 0);
+}
+
+#[test]
+fn optimize_database_periodically() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Instant;
+
+    #[derive(Clone)]
+    struct ManualClock(Rc<Cell<Instant>>);
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            self.0.get()
+        }
+    }
+    let clock = ManualClock(Rc::new(Cell::new(Instant::now())));
+    let advance = |seconds| clock.0.set(clock.0.get() + Duration::from_secs(seconds));
+
+    let (_fs, data_store) = create_in_memory_store();
+    let mut schedule = OptimizeSchedule::new(
+        clock.clone(),
+        Duration::from_secs(60),
+        Duration::from_secs(0),
+    );
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_none());
+    advance(59);
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_none());
+    advance(1);
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_some());
+
+    // The next run is one interval after the last one...
+    advance(30);
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_none());
+    advance(30);
+    // ...but it is skipped while the store is busy.
+    let guard = data_store
+        .subtree_locks
+        .lock(&RelativePath::from_path(""))
+        .unwrap();
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_none());
+    drop(guard);
+    assert!(data_store
+        .optimize_database_if_due(&mut schedule)
+        .unwrap()
+        .is_some());
+
+    // Jitter delays runs by at most max_jitter.
+    let schedule = OptimizeSchedule::new(
+        clock.clone(),
+        Duration::from_secs(60),
+        Duration::from_secs(10),
+    );
+    advance(59);
+    assert!(!schedule.is_due());
+    advance(11);
+    assert!(schedule.is_due());
+}
+
+#[test]
+fn only_transfer_appended_content() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Only the appended tail is transferred...
+    fs_1.test_set_file_content("file-1", "start and more", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        fs_2.test_get_file_content("file-1").unwrap(),
+        "start and more"
+    );
+    assert_eq!(result.transferred_files, 1);
+    assert_eq!(result.transferred_bytes, 9);
+
+    // ...while other changes transfer the whole file.
+    fs_1.test_set_file_content("file-1", "START and more!", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(
+        fs_2.test_get_file_content("file-1").unwrap(),
+        "START and more!"
+    );
+    assert_eq!(result.transferred_bytes, 15);
+}
+
+#[test]
+fn report_metadata_footprint() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let data_store =
+        DefaultDataStore::create(test_dir.path(), "XYZ", "XYZ", "local-data-store").unwrap();
+
+    let footprint = data_store.metadata_footprint().unwrap();
+    assert!(footprint.db_bytes > 0);
+    assert_eq!(footprint.pending_bytes, 0);
+    assert_eq!(
+        footprint.total_bytes(),
+        footprint.db_bytes + footprint.snapshot_bytes
+    );
+}
+
+#[test]
+fn apply_exported_changes_like_a_live_sync() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    let (fs_3, data_store_3) = create_in_memory_store();
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Export the changes after the point both other stores are synced to.
+    let since = data_store_1.root_mod_time().unwrap();
+    fs_1.test_set_file_content("file-1", "changed", true)
+        .unwrap();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-2").unwrap();
+    fs_1.test_set_file_content("sub/file-2", "new", true)
+        .unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    let mut bundle = Vec::new();
+    let bundled_files = data_store_1.export_changes(&since, &mut bundle).unwrap();
+    assert_eq!(bundled_files, 2);
+
+    // Applying the bundle reaches the same state as a live sync.
+    let applied = data_store_2
+        .apply_changes(&mut bundle.as_slice(), &mut |_| {
+            panic!("No conflicts expected!")
+        })
+        .unwrap();
+    let synced = data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(applied.transferred_files, synced.transferred_files);
+    assert!(applied.unavailable_items.is_empty());
+    for path in &["file-1", "sub/file-2"] {
+        assert_eq!(
+            fs_2.test_get_file_content(path).unwrap(),
+            fs_3.test_get_file_content(path).unwrap()
+        );
+    }
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(result.transferred_files, 0);
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
+
+    // Truncated bundles are refused.
+    match data_store_2.apply_changes(&mut &bundle[..bundle.len() - 1], &mut |_| {
+        panic!("No conflicts expected!")
+    }) {
+        Err(DataStoreError::InvalidChangeBundle { .. }) => (),
+        _ => panic!("Must not apply truncated change bundles!"),
+    };
+
+    // So are entries claiming more content than the bundle holds.
+    let entry = b"file 7 file-1\n";
+    let position = bundle
+        .windows(entry.len())
+        .position(|window| window == entry)
+        .unwrap();
+    let mut oversized = bundle[..position].to_vec();
+    oversized.extend_from_slice(b"file 99999999999999 file-1\n");
+    oversized.extend_from_slice(&bundle[position + entry.len()..]);
+    match data_store_2.apply_changes(&mut oversized.as_slice(), &mut |_| {
+        panic!("No conflicts expected!")
+    }) {
+        Err(DataStoreError::InvalidChangeBundle { .. }) => (),
+        _ => panic!("Must not apply change bundles with oversized entries!"),
+    };
+}
+
+#[test]
+fn paused_paths_are_not_indexed_until_resumed() {
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_dir("vm", false).unwrap();
+    fs.create_file("vm/image").unwrap();
+    fs.create_file("vm/config").unwrap();
+    data_store.perform_full_scan().unwrap();
+    let image_path = RelativePath::from_path("vm/image");
+    let image_before = data_store
+        .db_access
+        .get_local_data_item(&image_path, false)
+        .unwrap();
+
+    assert!(data_store.pause_path(&image_path).unwrap());
+    assert!(!data_store.pause_path(&image_path).unwrap());
+    fs.test_set_file_content("vm/image", "churn", true).unwrap();
+    fs.remove_file("vm/config").unwrap();
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.deleted_items, 1);
+    let image_after = data_store
+        .db_access
+        .get_local_data_item(&image_path, false)
+        .unwrap();
+    assert_eq!(image_after.metadata().hash, image_before.metadata().hash);
+    assert_eq!(image_after.mod_time(), image_before.mod_time());
+
+    // Paused paths are persisted next to the DB (they survive re-opening the store).
+    assert_eq!(data_store.paused_paths(), vec![image_path.clone()]);
+    assert_eq!(
+        data_store.fs_access.read_paused_paths().unwrap(),
+        Some("vm/image\n".to_string())
+    );
+
+    assert!(data_store.resume_path(&image_path).unwrap());
+    let changes = data_store.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 1);
+}
+
+#[test]
+fn merge_index_from_partial_backups() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    // Each backup holds a different subset, the second one lost file-2 on the way.
+    fs_1.create_file("only-1").unwrap();
+    fs_2.create_file("only-2").unwrap();
+    fs_2.test_set_file_content("file-1", "newer", true).unwrap();
+    fs_2.remove_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    data_store_1.merge_index_from(&data_store_2).unwrap();
+    dir_should_contain(&fs_1, "", vec!["file-1", "file-2", "only-1", "only-2"]);
+    assert_eq!(fs_1.test_get_file_content("file-1").unwrap(), "newer");
+
+    // The merge leaves the other store as it is.
+    dir_should_not_contain(&fs_2, "", vec!["file-2", "only-1"]);
+}
+
+#[test]
+fn verify_tree_hash_after_sync() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_dir("sub", false).unwrap();
+    fs_1.create_file("sub/file-1").unwrap();
+    fs_1.test_set_file_content("sub/file-1", "hello", true)
+        .unwrap();
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    let root_path = RelativePath::from_path("");
+    data_store_2
+        .verify_tree_hash(&data_store_1, &root_path)
+        .unwrap();
+    assert_eq!(
+        data_store_1.compute_tree_hash(&root_path).unwrap(),
+        data_store_2.compute_tree_hash(&root_path).unwrap()
+    );
+
+    // Corrupt the target on disk (the index does not know about it).
+    fs_2.test_set_file_content("sub/file-1", "hellx", false)
+        .unwrap();
+    match data_store_2.verify_tree_hash(&data_store_1, &root_path) {
+        Err(DataStoreError::TreeHashMismatch { .. }) => (),
+        _ => panic!("Must detect corrupted content in the tree hash!"),
+    };
+}
+
+#[test]
+fn scan_only_listed_paths() {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_file("file-1").unwrap();
+    fs.create_file("file-2").unwrap();
+    fs.create_file("file-3").unwrap();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-4").unwrap();
+    fs.create_file("sub/file-5").unwrap();
+    data_store.perform_full_scan().unwrap();
+    let mod_time_of = |path: &str| {
+        data_store
+            .db_access
+            .get_local_data_item(&RelativePath::from_path(path), false)
+            .unwrap()
+            .mod_time()
+            .clone()
+    };
+    let file_1_before = mod_time_of("file-1");
+    let file_2_before = mod_time_of("file-2");
+
+    fs.test_set_file_content("file-1", "changed", true).unwrap();
+    fs.test_set_file_content("file-2", "not listed", true)
+        .unwrap();
+    fs.remove_file("file-3").unwrap();
+    fs.test_set_file_content("sub/file-4", "changed", true)
+        .unwrap();
+    fs.remove_file("sub/file-5").unwrap();
+    fs.create_file("sub/file-6").unwrap();
+
+    let paths = vec![
+        RelativePath::from_path("file-1"),
+        RelativePath::from_path("file-3"),
+        RelativePath::from_path("sub"),
+        RelativePath::from_path("sub/file-4"),
+        RelativePath::from_path("file-1"),
+    ];
+    let changes = data_store.scan_paths(&paths).unwrap();
+    assert_eq!(changes.new_items, 1);
+    assert_eq!(changes.deleted_items, 2);
+
+    assert_ne!(mod_time_of("file-1"), file_1_before);
+    assert_eq!(mod_time_of("file-2"), file_2_before);
+    assert!(data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("file-3"), false)
+        .unwrap()
+        .is_deletion());
+    assert!(!data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("sub/file-6"), false)
+        .unwrap()
+        .is_deletion());
+
+    // The unlisted change is still picked up by the next full scan.
+    data_store.perform_full_scan().unwrap();
+    assert_ne!(mod_time_of("file-2"), file_2_before);
+}
+
+#[test]
+fn sync_zero_byte_files() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    fs_1.create_file("empty-1").unwrap();
+    fs_1.create_file("empty-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    dir_should_contain(&fs_2, "", vec!["empty-1", "empty-2"]);
+    assert_eq!(fs_2.test_get_file_content("empty-1").unwrap(), "");
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.new_items, 0);
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.deleted_items, 0);
+}
+
+#[test]
+fn sync_sparse_files() {
+    use std::io::{Seek, Write};
+
+    let test_dir_1 = tempfile::tempdir().unwrap();
+    let test_dir_2 = tempfile::tempdir().unwrap();
+    let data_store_1 = DefaultDataStore::create(test_dir_1.path(), "XYZ", "XYZ", "1").unwrap();
+    let data_store_2 = DefaultDataStore::create(test_dir_2.path(), "XYZ", "XYZ", "2").unwrap();
+
+    // A mostly empty file with some content in the middle and a trailing hole.
+    let sparse_length = 4 * 1024 * 1024;
+    let mut sparse_file = std::fs::File::create(test_dir_1.path().join("sparse")).unwrap();
+    sparse_file.set_len(sparse_length).unwrap();
+    sparse_file
+        .seek(std::io::SeekFrom::Start(1024 * 1024))
+        .unwrap();
+    sparse_file.write_all(b"data in the middle").unwrap();
+    drop(sparse_file);
+
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+
+    let synced_path = test_dir_2.path().join("sparse");
+    assert_eq!(
+        std::fs::metadata(&synced_path).unwrap().len(),
+        sparse_length
+    );
+    assert_eq!(
+        std::fs::read(&synced_path).unwrap(),
+        std::fs::read(test_dir_1.path().join("sparse")).unwrap()
+    );
+}
+
+#[test]
+fn reject_learning_about_third_data_store() {
+    let (fs_1, mut data_store_1) = create_in_memory_store();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    let (fs_3, data_store_3) = create_in_memory_store();
+    let store_3_name = data_store_3
+        .db_access
+        .get_local_data_store()
+        .unwrap()
+        .unique_name;
+
+    fs_2.create_file("file-2").unwrap();
+    fs_3.create_file("file-3").unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_3, &RelativePath::from_path(""))
+        .unwrap();
+
+    let rejected_name = store_3_name.clone();
+    data_store_1.set_data_store_approval(Some(Box::new(move |data_store| {
+        data_store.unique_name != rejected_name
+    })));
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+
+    // The partner is learned, the rejected store is not. Its file can not be tracked.
+    assert!(data_store_1
+        .db_access
+        .get_data_store(&store_3_name)
+        .unwrap()
+        .is_none());
+    dir_should_contain(&fs_1, "", vec!["file-2"]);
+    dir_should_not_contain(&fs_1, "", vec!["file-3"]);
+
+    // Approving the store picks up its changes.
+    data_store_1.set_data_store_approval(None);
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(data_store_1
+        .db_access
+        .get_data_store(&store_3_name)
+        .unwrap()
+        .is_some());
+    dir_should_contain(&fs_1, "", vec!["file-2", "file-3"]);
+}
+
+#[test]
+fn reject_remote_names_escaping_the_root() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state_folders();
+    fs_1.create_file("sub/file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // The root folder has no name, syncing it is fine.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "sub", vec!["file-1", "file-2"]);
+
+    fs_1.create_file("sub/file-3").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+
+    // A buggy or malicious remote announcing a child named '..' in 'sub'.
+    data_store_1
+        .db_access
+        .update_local_data_item_name(&RelativePath::from_path("sub/file-1"), "..")
+        .unwrap();
+
+    match data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+    {
+        Err(DataStoreError::InvalidItemName { name }) => assert_eq!(name, ".."),
+        _ => panic!("Must reject item names escaping their parent folder!"),
+    };
+    dir_should_contain(&fs_2, "sub", vec!["file-1"]);
+    dir_should_not_contain(&fs_2, "", vec!["file-3"]);
+}
+
+#[test]
+fn clean_retention_purges_old_conflict_copies() {
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_file("file-1").unwrap();
+    fs.create_file("file-1.conflict-source-data-store").unwrap();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-2.conflict-source-data-store")
+        .unwrap();
+    let two_days_ago = FileTime::from_unix_time(FileTime::now().unix_seconds() - 2 * 86400, 0);
+    fs.test_set_creation_time("file-1", two_days_ago).unwrap();
+    fs.test_set_creation_time("file-1.conflict-source-data-store", two_days_ago)
+        .unwrap();
+    data_store.perform_full_scan().unwrap();
+
+    // Without limits all copies are kept.
+    assert_eq!(data_store.clean_retention().unwrap().removed_files, 0);
+
+    data_store.set_retention_policy(RetentionPolicy::new(Some(Duration::from_secs(86400)), None));
+    let result = data_store.clean_retention().unwrap();
+    assert_eq!(result.removed_files, 1);
+    dir_should_contain(&fs, "", vec!["file-1"]);
+    dir_should_not_contain(&fs, "", vec!["file-1.conflict-source-data-store"]);
+    dir_should_contain(&fs, "sub", vec!["file-2.conflict-source-data-store"]);
+    assert!(data_store
+        .db_access
+        .get_local_data_item(
+            &RelativePath::from_path("file-1.conflict-source-data-store"),
+            false
+        )
+        .unwrap()
+        .is_deletion());
+}
+
+#[test]
+fn report_item_replication_state() {
+    let (fs_1, data_store_1) = create_in_memory_store();
+    let (_fs_2, data_store_2) = create_in_memory_store();
+    let (_fs_3, data_store_3) = create_in_memory_store();
+
+    fs_1.create_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    data_store_3.perform_full_scan().unwrap();
+
+    // Only store 2 gets the file.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    data_store_1
+        .get_significant_sync_times_from_other(&data_store_2)
+        .unwrap();
+    data_store_1
+        .get_significant_sync_times_from_other(&data_store_3)
+        .unwrap();
+
+    let state = data_store_1
+        .item_replication_state(&RelativePath::from_path("file-1"))
         .unwrap();
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+    let store_1_name = data_store_1.local_data_store_name().unwrap();
+    let mod_time_stores: Vec<String> = state
+        .mod_time
+        .to_pairs()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    assert_eq!(mod_time_stores, vec![store_1_name.clone()]);
+    assert_eq!(state.stores.len(), 3);
+    let has_current_version = |name: String| {
+        state
+            .stores
+            .iter()
+            .find(|store| store.data_store_name == name)
+            .unwrap()
+            .has_current_version
+    };
+    assert!(has_current_version(store_1_name));
+    assert!(has_current_version(
+        data_store_2.local_data_store_name().unwrap()
+    ));
+    assert!(!has_current_version(
+        data_store_3.local_data_store_name().unwrap()
+    ));
 }
 
-// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
-// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
-// subsequent conflicts if possible.
-// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
-// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
-// Case 2: Same as figure (c)
 #[test]
-fn sync_conflict_forwarding_multiple_2() {
-    let (fs_a, data_store_a) = create_in_memory_store();
-    let (fs_b, data_store_b) = create_in_memory_store();
-    let (_fs_c, data_store_c) = create_in_memory_store();
-
-    // Time 1
-    fs_b.create_file("file-1").unwrap();
-    data_store_b.perform_full_scan().unwrap();
-    // Time 2
-    data_store_a
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+fn apply_conflict_resolutions_as_a_unit() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    fs_1.create_file("file-2").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    // Time 3
-    data_store_c
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+
+    // Independent changes to both files on both stores.
+    for path in ["file-1", "file-2"] {
+        fs_1.test_set_file_content(path, "remote", true).unwrap();
+        fs_2.test_set_file_content(path, "local", true).unwrap();
+    }
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    let mut conflicts = data_store_2
+        .find_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
+    conflicts.sort_by_key(|path| path.name().to_string());
+    assert_eq!(
+        conflicts,
+        vec![
+            RelativePath::from_path("file-1"),
+            RelativePath::from_path("file-2")
+        ]
+    );
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "local");
+
+    // An incomplete set of resolutions fails, the resolutions applied so far are rolled back.
+    match data_store_2.apply_resolutions(
+        &data_store_1,
+        &RelativePath::from_path(""),
+        vec![(
+            RelativePath::from_path("file-1"),
+            SyncConflictResolution::ChooseRemoteItem,
+        )],
+    ) {
+        Err(DataStoreError::MissingConflictResolution { .. }) => (),
+        _ => panic!("Must fail on conflicts without resolution!"),
+    };
+    for path in ["file-1", "file-2"] {
+        assert_eq!(fs_2.test_get_file_content(path).unwrap(), "local");
+    }
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
 
-    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
-    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
-    data_store_b.perform_full_scan().unwrap();
+    // A failing file system step rolls back the resolutions applied before it, too.
+    fs_2.set_error_on_next("file-2", Operation::Rename, io::ErrorKind::Other, 1)
+        .unwrap();
+    assert!(data_store_2
+        .apply_resolutions(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            vec![
+                (
+                    RelativePath::from_path("file-1"),
+                    SyncConflictResolution::ChooseRemoteItem,
+                ),
+                (
+                    RelativePath::from_path("file-2"),
+                    SyncConflictResolution::ChooseRemoteItem,
+                ),
+            ],
+        )
+        .is_err());
+    fs_2.clear_errors();
+    for path in ["file-1", "file-2"] {
+        assert_eq!(fs_2.test_get_file_content(path).unwrap(), "local");
+    }
+    let changes = data_store_2.perform_full_scan().unwrap();
+    assert_eq!(changes.changed_items, 0);
+    assert_eq!(changes.new_items, 0);
 
-    // Time 4
-    let mut conflict_happened = false;
-    data_store_b
-        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
+    // A complete set applies all of them.
+    data_store_2
+        .apply_resolutions(
+            &data_store_1,
+            &RelativePath::from_path(""),
+            vec![
+                (
+                    RelativePath::from_path("file-1"),
+                    SyncConflictResolution::ChooseRemoteItem,
+                ),
+                (
+                    RelativePath::from_path("file-2"),
+                    SyncConflictResolution::ChooseLocalItem,
+                ),
+            ],
+        )
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "remote");
+    assert_eq!(fs_2.test_get_file_content("file-2").unwrap(), "local");
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+}
 
-    // Time 5
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+#[test]
+fn report_ignored_items_left_on_disk() {
+    let (fs, mut data_store) = create_in_memory_store();
+    fs.create_file("file-1").unwrap();
+    fs.test_set_file_content("file-1", "12345", true).unwrap();
+    fs.create_dir("sub", false).unwrap();
+    fs.create_file("sub/file-2").unwrap();
+    fs.test_set_file_content("sub/file-2", "123", true).unwrap();
+    fs.create_file("file-3").unwrap();
+    data_store.perform_full_scan().unwrap();
+
+    data_store
+        .add_ignore_rule(Pattern::new("/file-1").unwrap())
         .unwrap();
-    // Time 6
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+    data_store
+        .add_ignore_rule(Pattern::new("/sub").unwrap())
         .unwrap();
-    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
-    // Time 7
-    let mut conflict_happened = false;
-    data_store_b
-        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
+    let result = data_store.perform_full_scan().unwrap();
+    assert_eq!(
+        result.ignored_on_disk,
+        vec![
+            RelativePath::from_path("file-1"),
+            RelativePath::from_path("sub")
+        ]
+    );
+    assert_eq!(result.ignored_on_disk_bytes, 8);
+
+    data_store
+        .delete_ignored_on_disk(&result.ignored_on_disk)
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+    dir_should_not_contain(&fs, "", vec!["file-1", "sub"]);
+    dir_should_contain(&fs, "", vec!["file-3"]);
+    assert!(data_store
+        .perform_full_scan()
+        .unwrap()
+        .ignored_on_disk
+        .is_empty());
 }
 
-// Testing the ability of version vectors to 'forward' a sync decision to further syncs.
-// The goal is for a conflict resolution to be 'remembered' for further syncs, not causing
-// subsequent conflicts if possible.
-// The three cases here are the same as in 'File Synchronization with Vector Time Pairs'
-// by Russ Cox and William Josephson, page 4, figure 3 (b) to (d).
-// Case 3: Same as figure (d)
 #[test]
-fn sync_conflict_forwarding_multiple_3() {
-    let (fs_a, data_store_a) = create_in_memory_store();
-    let (fs_b, data_store_b) = create_in_memory_store();
-    let (_fs_c, data_store_c) = create_in_memory_store();
+fn scans_visit_items_in_canonical_order() {
+    let create_tree = |names: Vec<&str>| {
+        let (fs, data_store) = create_in_memory_store();
+        for name in names {
+            if name.ends_with("/") {
+                fs.create_dir(name.trim_end_matches('/'), false).unwrap();
+            } else {
+                fs.create_file(name).unwrap();
+            }
+        }
+        let result = data_store.perform_full_scan_listing_changes().unwrap();
+        result.changes.unwrap().new_items
+    };
+
+    // The same tree created in a different order is visited in the same (canonical) order.
+    let first_run = create_tree(vec!["b", "C", "A", "B-sub/", "B-sub/y", "B-sub/X", "a-2"]);
+    let second_run = create_tree(vec!["a-2", "B-sub/", "B-sub/X", "B-sub/y", "C", "A", "b"]);
+    assert_eq!(first_run, second_run);
+    assert_eq!(
+        first_run,
+        vec!["A", "a-2", "b", "B-sub", "B-sub/X", "B-sub/y", "C"]
+            .into_iter()
+            .map(RelativePath::from_path)
+            .collect::<Vec<_>>()
+    );
+}
 
-    // Time 1
-    fs_b.create_file("file-1").unwrap();
-    data_store_b.perform_full_scan().unwrap();
-    // Time 2
-    data_store_a
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+#[test]
+fn set_read_only_through_the_store() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+    let file_1 = RelativePath::from_path("file-1");
+    let local_time = data_store_1.local_time().unwrap();
+
+    assert!(!data_store_1.is_read_only(&file_1).unwrap());
+    data_store_1.set_read_only(&file_1, true).unwrap();
+    assert!(fs_1.metadata("file-1").unwrap().read_only());
+    assert!(data_store_1.is_read_only(&file_1).unwrap());
+    // The change is recorded as a local modification...
+    assert!(data_store_1.local_time().unwrap() > local_time);
+
+    // ...i.e. the next scan does not pick it up again...
+    let scan_result = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(scan_result.changed_items, 0);
+
+    // ...but it propagates to other stores.
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    // Time 3
-    data_store_c
-        .sync_from_other_store_panic_conflicts(&data_store_b, &RelativePath::from_path(""))
+    assert!(fs_2.metadata("file-1").unwrap().read_only());
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "start");
+
+    // Clearing the bit works the same way.
+    data_store_2.set_read_only(&file_1, false).unwrap();
+    assert!(!fs_2.metadata("file-1").unwrap().read_only());
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
         .unwrap();
+    assert!(!fs_1.metadata("file-1").unwrap().read_only());
+}
 
-    fs_a.test_set_file_content("file-1", "fs_a", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
-    fs_b.test_set_file_content("file-1", "fs_b", true).unwrap();
-    data_store_b.perform_full_scan().unwrap();
+#[test]
+fn mirror_deletes_items_missing_on_the_remote() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
 
-    // Time 4
-    let mut conflict_happened = false;
-    data_store_b
-        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseLocalItem
-        })
+    // Independent changes on the mirror side, the remote deletes nothing.
+    fs_2.create_file("local-only").unwrap();
+    fs_2.create_dir("local-dir", false).unwrap();
+    fs_2.create_file("local-dir/file").unwrap();
+    fs_1.create_file("remote-only").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // The preview lists folders without their content and changes nothing.
+    let planned_paths: Vec<RelativePath> = data_store_2
+        .plan_mirror(&data_store_1, &RelativePath::from_path(""))
+        .unwrap()
+        .into_iter()
+        .map(|item| item.path)
+        .collect();
+    assert_eq!(
+        planned_paths,
+        vec![
+            RelativePath::from_path("local-dir"),
+            RelativePath::from_path("local-only")
+        ]
+    );
+    dir_should_contain(&fs_2, "", vec!["file-1", "local-dir", "local-only"]);
+
+    let result = data_store_2
+        .mirror_from(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_b");
-    // EMULATING MERGING THE TWO FILES.
-    // In our implementation this is ALWAYS a two action operation.
-    // We keep one of the two files and place the second one as a 'file-1-conflict' besides it.
-    // The user then merges them manually and re-indexes.
-    fs_b.test_set_file_content("file-1", "fs_b_and_fs_a", true)
+    assert_eq!(result.mirrored_deletions, planned_paths);
+    dir_should_contain(&fs_2, "", vec!["file-1", "remote-only"]);
+    dir_should_not_contain(&fs_2, "", vec!["local-dir", "local-only"]);
+    assert!(data_store_2
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("local-only"), false)
+        .unwrap()
+        .is_deletion());
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
+
+    // Mirroring again has nothing left to delete.
+    let result = data_store_2
+        .mirror_from(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    data_store_b.perform_full_scan().unwrap();
+    assert!(result.mirrored_deletions.is_empty());
+}
 
-    // Time 5
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_a, &RelativePath::from_path(""))
+#[test]
+fn mirror_checks_and_keeps_the_content_of_local_only_folders() {
+    let ((_fs_1, data_store_1), (fs_2, mut data_store_2)) = create_synced_base_state();
+    data_store_2
+        .add_ignore_rule(Pattern::new("**/*.tmp").unwrap())
         .unwrap();
-    // Time 6
-    data_store_b
-        .sync_from_other_store_panic_conflicts(&data_store_c, &RelativePath::from_path(""))
+
+    fs_2.create_dir("local-dir", false).unwrap();
+    fs_2.create_dir("local-dir/kept", false).unwrap();
+    fs_2.create_file("local-dir/kept/file").unwrap();
+    fs_2.create_file("local-dir/kept/ignored.tmp").unwrap();
+    fs_2.create_dir("local-dir/deleted", false).unwrap();
+    fs_2.create_file("local-dir/deleted/file").unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // Content unknown to the DB deep down a local-only folder blocks all deletions.
+    fs_2.create_file("local-dir/deleted/unscanned").unwrap();
+    match data_store_2.mirror_from(&data_store_1, &RelativePath::from_path("")) {
+        Err(DataStoreError::DiskOutOfSync { path }) => {
+            assert_eq!(path, RelativePath::from_path("local-dir/deleted/unscanned"))
+        }
+        _ => panic!("Must not delete folders holding content that is not indexed in the DB!"),
+    };
+    dir_should_contain(&fs_2, "local-dir", vec!["deleted", "kept"]);
+    dir_should_contain(&fs_2, "local-dir/kept", vec!["file", "ignored.tmp"]);
+
+    // Ignored items (and the folders holding them) are kept, everything else is deleted.
+    fs_2.remove_file("local-dir/deleted/unscanned").unwrap();
+    let result = data_store_2
+        .mirror_from(&data_store_1, &RelativePath::from_path(""))
         .unwrap();
-    fs_a.test_set_file_content("file-1", "fs_a'", true).unwrap();
-    data_store_a.perform_full_scan().unwrap();
     assert_eq!(
-        fs_b.test_get_file_content("file-1").unwrap(),
-        "fs_b_and_fs_a"
+        result.mirrored_deletions,
+        vec![
+            RelativePath::from_path("local-dir/deleted"),
+            RelativePath::from_path("local-dir/kept/file")
+        ]
     );
-    // Time 7
-    let mut conflict_happened = false;
-    data_store_b
-        .sync_from_other_store(&data_store_a, &RelativePath::from_path(""), &mut |event| {
-            conflict_happened = matches!(event, SyncConflictEvent::LocalItemRemoteFile(_, _));
-            SyncConflictResolution::ChooseRemoteItem
-        })
-        .unwrap();
-    assert!(conflict_happened);
-    assert_eq!(fs_b.test_get_file_content("file-1").unwrap(), "fs_a'");
+    dir_should_contain(&fs_2, "local-dir", vec!["kept"]);
+    dir_should_not_contain(&fs_2, "local-dir", vec!["deleted"]);
+    dir_should_contain(&fs_2, "local-dir/kept", vec!["ignored.tmp"]);
+    dir_should_not_contain(&fs_2, "local-dir/kept", vec!["file"]);
+    assert_eq!(data_store_2.perform_full_scan().unwrap().changed_items, 0);
 }
 
 #[test]
-fn convert_from_and_to_external_version_vectors() {
-    let (_fs_1, data_store_1) = create_in_memory_store();
-    let (_fs_2, data_store_2) = create_in_memory_store();
+fn sync_handshake_only_exchanges_missing_stores() {
+    let ((_fs_1, data_store_1), (_fs_2, data_store_2)) = create_synced_base_state();
 
-    let data_store_1_name = data_store_1
-        .db_access
-        .get_local_data_store()
-        .unwrap()
-        .unique_name;
+    // Both stores know each other, i.e. no store records need to be exchanged.
+    let (local_handshake, remote_handshake) = data_store_2.sync_handshakes(&data_store_1).unwrap();
+    assert_eq!(
+        local_handshake.data_stores_digest,
+        remote_handshake.data_stores_digest
+    );
+    assert_eq!(local_handshake.data_store_ids.len(), 2);
+    assert!(local_handshake.data_stores.is_empty());
+    assert!(remote_handshake.data_stores.is_empty());
 
-    let (mapper_1, mapper_2) = data_store_1.sync_data_store_lists(&data_store_2).unwrap();
+    // A new store only receives the records it misses.
+    let (fs_3, data_store_3) = create_in_memory_store();
+    fs_3.create_file("file-3").unwrap();
+    data_store_3.perform_full_scan().unwrap();
+    let (local_handshake, remote_handshake) = data_store_3.sync_handshakes(&data_store_1).unwrap();
+    assert_ne!(
+        local_handshake.data_stores_digest,
+        remote_handshake.data_stores_digest
+    );
+    assert_eq!(local_handshake.data_stores.len(), 1);
+    assert_eq!(remote_handshake.data_stores.len(), 2);
 
-    // Create a vector local to store 1
-    let data_store_1_id = data_store_1
+    // The ID mappers stay correct, i.e. versions of all stores are understood.
+    data_store_3
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_3, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(data_store_2.get_data_stores().unwrap().len(), 3);
+    let (local_handshake, remote_handshake) = data_store_2.sync_handshakes(&data_store_3).unwrap();
+    assert!(local_handshake.data_stores.is_empty());
+    assert!(remote_handshake.data_stores.is_empty());
+    data_store_1
+        .sync_from_other_store_panic_conflicts(&data_store_2, &RelativePath::from_path(""))
+        .unwrap();
+    assert!(data_store_1
         .db_access
-        .get_data_store(&data_store_1_name)
+        .get_local_data_item(&RelativePath::from_path("file-3"), false)
         .unwrap()
-        .unwrap()
-        .id;
-    let mut vector_on_store_1 = VersionVector::new();
-    vector_on_store_1[&data_store_1_id] = 42;
+        .is_file());
+}
 
-    // Simulate the 'externalize and internalize' procedure to transfer it to store 2.
-    let internalized_vector_on_store_2 = mapper_2.external_to_internal(&vector_on_store_1);
+fn create_store_with_case_duplicates() -> (InMemoryFS, DataStore<InMemoryFS>) {
+    let (fs, data_store) = create_in_memory_store();
+    fs.create_file("file-a").unwrap();
+    fs.test_set_file_content("file-a", "lower", true).unwrap();
+    fs.create_file("File-A").unwrap();
+    fs.test_set_file_content("File-A", "upper", true).unwrap();
+    fs.create_file("other").unwrap();
 
-    // Check it...
-    let data_store_2_id = data_store_2
+    (fs, data_store)
+}
+
+#[test]
+fn duplicate_names_are_reported_and_skipped_by_default() {
+    let (_fs, data_store) = create_store_with_case_duplicates();
+
+    let result = data_store.perform_full_scan().unwrap();
+    assert_eq!(
+        result.duplicate_names,
+        vec![DuplicateNames {
+            paths: vec![
+                RelativePath::from_path("File-A"),
+                RelativePath::from_path("file-a")
+            ],
+            policy: DuplicatePolicy::ReportAndSkip,
+            indexed_path: None,
+        }]
+    );
+    assert!(data_store
         .db_access
-        .get_data_store(&data_store_1_name)
+        .get_local_data_item(&RelativePath::from_path("file-a"), false)
         .unwrap()
+        .is_deletion());
+    assert!(data_store
+        .db_access
+        .get_local_data_item(&RelativePath::from_path("other"), false)
         .unwrap()
-        .id;
-    assert_eq!(internalized_vector_on_store_2[&data_store_2_id], 42);
+        .is_file());
+}
 
-    // Transfer it back
-    let internalized_vector_on_store_1 =
-        mapper_1.external_to_internal(&internalized_vector_on_store_2);
-    assert_eq!(internalized_vector_on_store_1[&data_store_1_id], 42);
+#[test]
+fn duplicate_names_sync_the_first_item() {
+    let (_fs_1, mut data_store_1) = create_store_with_case_duplicates();
+    let (fs_2, data_store_2) = create_in_memory_store();
+    data_store_1.set_duplicate_policy(DuplicatePolicy::SyncFirst);
+
+    let result = data_store_1.perform_full_scan().unwrap();
+    assert_eq!(result.duplicate_names.len(), 1);
+    assert_eq!(result.duplicate_names[0].policy, DuplicatePolicy::SyncFirst);
+    assert_eq!(
+        result.duplicate_names[0].indexed_path,
+        Some(RelativePath::from_path("File-A"))
+    );
+
+    // Only the lexicographically first item is synced, also on a re-scan.
+    assert_eq!(data_store_1.perform_full_scan().unwrap().changed_items, 0);
+    data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    dir_should_contain(&fs_2, "", vec!["File-A", "other"]);
+    dir_should_not_contain(&fs_2, "", vec!["file-a"]);
+    assert_eq!(fs_2.test_get_file_content("File-A").unwrap(), "upper");
+}
+
+#[test]
+fn duplicate_names_can_fail_the_scan() {
+    let (_fs, mut data_store) = create_store_with_case_duplicates();
+    data_store.set_duplicate_policy(DuplicatePolicy::Fail);
+
+    match data_store.perform_full_scan() {
+        Err(DataStoreError::DuplicateNames { paths }) => assert_eq!(
+            paths,
+            vec![
+                RelativePath::from_path("File-A"),
+                RelativePath::from_path("file-a")
+            ]
+        ),
+        _ => panic!("Must fail the scan on duplicate names!"),
+    }
+}
+
+#[test]
+fn explain_items_kept_despite_remote_deletions() {
+    let ((fs_1, data_store_1), (fs_2, data_store_2)) = create_synced_base_state();
+
+    // Store 1 deletes the file, store 2 re-creates it without knowing about this deletion.
+    fs_1.remove_file("file-1").unwrap();
+    data_store_1.perform_full_scan().unwrap();
+    fs_2.remove_file("file-1").unwrap();
+    data_store_2.perform_full_scan().unwrap();
+    fs_2.create_file("file-1").unwrap();
+    fs_2.test_set_file_content("file-1", "re-created", true)
+        .unwrap();
+    data_store_2.perform_full_scan().unwrap();
+
+    // The remote deletion does not target the re-created file, it is kept (no conflict)...
+    let result = data_store_2
+        .sync_from_other_store_panic_conflicts(&data_store_1, &RelativePath::from_path(""))
+        .unwrap();
+    assert_eq!(fs_2.test_get_file_content("file-1").unwrap(), "re-created");
+
+    // ...and the sync result tells why.
+    assert_eq!(
+        result.explanations,
+        vec![SyncExplanation {
+            path: RelativePath::from_path("file-1"),
+            reason: "kept because your copy was created independently of the remote deletion",
+        }]
+    );
 }
@@ -0,0 +1,91 @@
+use fs_interaction::relative_path::RelativePath;
+
+/// The files a transfer store fetches from a source store to carry them to its destinations.
+/// It is persisted while carrying, i.e. an interrupted carry resumes with the remaining files.
+///
+/// Serialized as JSON, paths are given as lists of their components, i.e. names containing
+/// any special characters (e.g. tabs or line breaks) are kept as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferPlan {
+    pub source_store: String,
+    pub files: Vec<(RelativePath, String)>,
+}
+impl TransferPlan {
+    pub fn new(source_store: &str) -> Self {
+        Self {
+            source_store: source_store.to_string(),
+            files: vec![],
+        }
+    }
+
+    pub fn paths(&self) -> Vec<RelativePath> {
+        self.files
+            .iter()
+            .map(|(path, _hash)| path.clone())
+            .collect()
+    }
+
+    pub fn serialize(&self) -> String {
+        let files: Vec<serde_json::Value> = self
+            .files
+            .iter()
+            .map(|(path, hash)| {
+                json!({
+                    "path": path.get_path_components()[1..],
+                    "hash": hash,
+                })
+            })
+            .collect();
+
+        json!({
+            "source_store": self.source_store,
+            "files": files,
+        })
+        .to_string()
+    }
+
+    /// Returns None for malformed plans (e.g. partially written ones).
+    pub fn parse(content: &str) -> Option<Self> {
+        let content: serde_json::Value = serde_json::from_str(content).ok()?;
+        let mut result = Self::new(content.get("source_store")?.as_str()?);
+        for file in content.get("files")?.as_array()? {
+            let mut path_components = vec!["".to_string()];
+            for component in file.get("path")?.as_array()? {
+                path_components.push(component.as_str()?.to_string());
+            }
+            let hash = file.get("hash")?.as_str()?;
+
+            result
+                .files
+                .push((RelativePath::from_vec(path_components), hash.to_string()));
+        }
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_through_serialization() {
+        let mut plan = TransferPlan::new("laptop-1234");
+        plan.files
+            .push((RelativePath::from_path("file 1"), "AB12".to_string()));
+        plan.files
+            .push((RelativePath::from_path("sub/file-2"), "".to_string()));
+        plan.files.push((
+            RelativePath::from_path("tab\tand\nline break"),
+            "CD34".to_string(),
+        ));
+
+        assert_eq!(TransferPlan::parse(&plan.serialize()), Some(plan.clone()));
+        assert_eq!(TransferPlan::parse(""), None);
+        let serialized = plan.serialize();
+        assert_eq!(
+            TransferPlan::parse(&serialized[..serialized.len() / 2]),
+            None
+        );
+    }
+}
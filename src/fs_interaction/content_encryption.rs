@@ -0,0 +1,249 @@
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{constant_time, pbkdf2};
+use std::fmt;
+use std::io;
+use std::num::NonZeroU32;
+
+// File contents are encrypted in independent chunks, i.e. we never hold a complete file in
+// memory. Each chunk is authenticated, including the information if it is the last one
+// (truncated files are detected).
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+// Each file starts with a random nonce prefix, the remaining nonce bytes count the chunks.
+const NONCE_PREFIX_LEN: usize = NONCE_LEN - 4;
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Symmetric key (AES-256-GCM) derived from a passphrase, used to encrypt file contents.
+#[derive(Clone)]
+pub struct ContentKey {
+    key: [u8; KEY_LEN],
+}
+impl fmt::Debug for ContentKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContentKey(..)")
+    }
+}
+impl ContentKey {
+    /// Derives a key from the passphrase using a new, random salt.
+    /// Returns the key, the salt and a verifier that allows to check later passphrases.
+    pub fn create(passphrase: &str) -> io::Result<(Self, Vec<u8>, Vec<u8>)> {
+        let mut salt = vec![0; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| io::Error::other("No random numbers available!"))?;
+        let (key, verifier) = Self::derive(passphrase, &salt);
+
+        Ok((key, salt, verifier))
+    }
+
+    /// Derives the key from the passphrase, if it matches the verifier created with the salt.
+    pub fn unlock(passphrase: &str, salt: &[u8], verifier: &[u8]) -> Option<Self> {
+        let (key, expected_verifier) = Self::derive(passphrase, salt);
+        if constant_time::verify_slices_are_equal(&expected_verifier, verifier).is_ok() {
+            Some(key)
+        } else {
+            None
+        }
+    }
+
+    fn derive(passphrase: &str, salt: &[u8]) -> (Self, Vec<u8>) {
+        let mut derived = [0; 2 * KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            salt,
+            passphrase.as_bytes(),
+            &mut derived,
+        );
+
+        let mut key = [0; KEY_LEN];
+        key.copy_from_slice(&derived[..KEY_LEN]);
+        (Self { key }, derived[KEY_LEN..].to_vec())
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.key).unwrap())
+    }
+}
+
+fn chunk_nonce(prefix: &[u8; NONCE_PREFIX_LEN], counter: u32) -> Nonce {
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn chunk_aad(is_last: bool) -> Aad<[u8; 1]> {
+    Aad::from([is_last as u8])
+}
+
+fn too_many_chunks() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "File too large to encrypt!")
+}
+
+// Reads until the buffer is full or the reader is exhausted.
+fn read_full<R: io::Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut count = 0;
+    while count < buffer.len() {
+        match reader.read(&mut buffer[count..]) {
+            Ok(0) => break,
+            Ok(read) => count += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(count)
+}
+
+/// Encrypts the plaintext of the wrapped reader while it is read.
+pub struct EncryptingReader<R: io::Read> {
+    inner: R,
+    key: LessSafeKey,
+    nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    chunk_counter: u32,
+    output: Vec<u8>,
+    output_pos: usize,
+    finished: bool,
+}
+impl<R: io::Read> EncryptingReader<R> {
+    pub fn new(inner: R, key: &ContentKey) -> io::Result<Self> {
+        let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce_prefix)
+            .map_err(|_| io::Error::other("No random numbers available!"))?;
+
+        Ok(Self {
+            inner,
+            key: key.aead_key(),
+            nonce_prefix,
+            chunk_counter: 0,
+            output: nonce_prefix.to_vec(),
+            output_pos: 0,
+            finished: false,
+        })
+    }
+
+    fn encrypt_next_chunk(&mut self) -> io::Result<()> {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let count = read_full(&mut self.inner, &mut chunk)?;
+        chunk.truncate(count);
+        let is_last = count < CHUNK_SIZE;
+
+        let nonce = chunk_nonce(&self.nonce_prefix, self.chunk_counter);
+        self.key
+            .seal_in_place_append_tag(nonce, chunk_aad(is_last), &mut chunk)
+            .map_err(|_| io::Error::other("Encryption failed!"))?;
+        self.chunk_counter = self
+            .chunk_counter
+            .checked_add(1)
+            .ok_or_else(too_many_chunks)?;
+
+        self.output = chunk;
+        self.output_pos = 0;
+        self.finished = is_last;
+        Ok(())
+    }
+}
+impl<R: io::Read> io::Read for EncryptingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.output_pos == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.encrypt_next_chunk()?;
+        }
+
+        let count = std::cmp::min(buf.len(), self.output.len() - self.output_pos);
+        buf[..count].copy_from_slice(&self.output[self.output_pos..self.output_pos + count]);
+        self.output_pos += count;
+        Ok(count)
+    }
+}
+
+/// Decrypts the content of the wrapped reader while it is read.
+/// Fails with InvalidData if the content was not encrypted with the key or got corrupted.
+pub struct DecryptingReader<R: io::Read> {
+    inner: R,
+    key: LessSafeKey,
+    nonce_prefix: Option<[u8; NONCE_PREFIX_LEN]>,
+    chunk_counter: u32,
+    output: Vec<u8>,
+    output_pos: usize,
+    finished: bool,
+}
+impl<R: io::Read> DecryptingReader<R> {
+    pub fn new(inner: R, key: &ContentKey) -> Self {
+        Self {
+            inner,
+            key: key.aead_key(),
+            nonce_prefix: None,
+            chunk_counter: 0,
+            output: vec![],
+            output_pos: 0,
+            finished: false,
+        }
+    }
+
+    fn decrypt_next_chunk(&mut self) -> io::Result<()> {
+        let nonce_prefix = match self.nonce_prefix {
+            Some(nonce_prefix) => nonce_prefix,
+            None => {
+                let mut nonce_prefix = [0; NONCE_PREFIX_LEN];
+                if read_full(&mut self.inner, &mut nonce_prefix)? < NONCE_PREFIX_LEN {
+                    return Err(Self::invalid_content());
+                }
+                self.nonce_prefix = Some(nonce_prefix);
+                nonce_prefix
+            }
+        };
+
+        let mut chunk = vec![0; CHUNK_SIZE + TAG_LEN];
+        let count = read_full(&mut self.inner, &mut chunk)?;
+        chunk.truncate(count);
+        let is_last = count < CHUNK_SIZE + TAG_LEN;
+
+        let nonce = chunk_nonce(&nonce_prefix, self.chunk_counter);
+        let plaintext_len = self
+            .key
+            .open_in_place(nonce, chunk_aad(is_last), &mut chunk)
+            .map_err(|_| Self::invalid_content())?
+            .len();
+        chunk.truncate(plaintext_len);
+        self.chunk_counter = self
+            .chunk_counter
+            .checked_add(1)
+            .ok_or_else(too_many_chunks)?;
+
+        self.output = chunk;
+        self.output_pos = 0;
+        self.finished = is_last;
+        Ok(())
+    }
+
+    fn invalid_content() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Can not decrypt file content (wrong key or corrupted file)!",
+        )
+    }
+}
+impl<R: io::Read> io::Read for DecryptingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            // The last chunk might be empty, i.e. keep going until we have data or are done.
+            self.decrypt_next_chunk()?;
+        }
+
+        let count = std::cmp::min(buf.len(), self.output.len() - self.output_pos);
+        buf[..count].copy_from_slice(&self.output[self.output_pos..self.output_pos + count]);
+        self.output_pos += count;
+        Ok(count)
+    }
+}
@@ -6,7 +6,19 @@ use std::fmt;
 pub enum FSInteractionError {
     MetadataDirAlreadyExists,
     MetadataDirAlreadyOpened,
+    MetadataDirNotLocked,
+    // The lock might still be held by a live process (pid is None for locks without a PID).
+    LockStillHeld {
+        pid: Option<u32>,
+    },
     SoftLinksForbidden,
+    // The store was opened read-only (see FSInteraction::open_read_only).
+    ReadOnlyStore,
+    // Encryption was already enabled on the store.
+    AlreadyEncrypted,
+    // The store encrypts its file contents, but was not unlocked (see unlock_encryption).
+    EncryptionLocked,
+    WrongPassphrase,
     // IOError is simply our 'catch all' error type for 'non-special' issues
     IOError {
         source: io::Error,
@@ -47,7 +59,13 @@ impl Error for FSInteractionError {
             Self::IOError { ref source, .. } => Some(source),
             Self::MetadataDirAlreadyExists => None,
             Self::SoftLinksForbidden => None,
+            Self::ReadOnlyStore => None,
+            Self::AlreadyEncrypted => None,
+            Self::EncryptionLocked => None,
+            Self::WrongPassphrase => None,
             Self::MetadataDirAlreadyOpened => None,
+            Self::MetadataDirNotLocked => None,
+            Self::LockStillHeld { .. } => None,
         }
     }
 }
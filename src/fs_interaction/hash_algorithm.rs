@@ -0,0 +1,34 @@
+use ring::digest;
+
+/// Hash algorithms available for file contents (see FSInteraction::calculate_hash).
+/// The name of an algorithm is stored along with the hashes of a data store.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    SHA256,
+    SHA512,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::SHA256 => "SHA256",
+            HashAlgorithm::SHA512 => "SHA512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SHA256" => Some(HashAlgorithm::SHA256),
+            "SHA512" => Some(HashAlgorithm::SHA512),
+            _ => None,
+        }
+    }
+
+    pub fn digest_algorithm(&self) -> &'static digest::Algorithm {
+        match self {
+            HashAlgorithm::SHA256 => &digest::SHA256,
+            HashAlgorithm::SHA512 => &digest::SHA512,
+        }
+    }
+}
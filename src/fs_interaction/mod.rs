@@ -3,28 +3,62 @@ pub mod relative_path;
 pub mod virtual_fs;
 use self::relative_path::*;
 
+mod content_encryption;
+use self::content_encryption::{ContentKey, DecryptingReader, EncryptingReader};
 mod errors;
 pub use self::errors::*;
+mod hash_algorithm;
+pub use self::hash_algorithm::HashAlgorithm;
 
 use filetime::FileTime;
-use ring::digest::{Context, SHA256};
+use ring::digest::Context;
+use std::collections::HashMap;
 use std::io;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 const METADATA_DIR: &str = ".__data_squirrel__";
 const METADATA_DB_FILE: &str = "database.sqlite";
+const DB_LOCATION_FILE: &str = "database_location";
 const LOCK_FILE: &str = "lock";
+const OPERATION_MARKER_FILE: &str = "operation_in_progress";
+const TRANSFER_PLAN_FILE: &str = "transfer_plan";
+const PAUSED_PATHS_FILE: &str = "paused_paths";
 const PENDING_FILES_DIR: &str = "pending_files";
 const SNAPSHOT_DIR: &str = "snapshots";
+const MERGE_BASES_DIR: &str = "merge_bases";
+const ENCRYPTION_FILE: &str = "encryption";
+const CONFIG_FILE: &str = "config.toml";
 
 const DS_STORE: &str = ".DS_Store";
 
+// How often we check for a lock to be released when waiting for it.
+const LOCK_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Debug)]
 pub struct FSInteraction<FS: virtual_fs::FS> {
     fs: FS,
     root_path: PathBuf,
     locked: bool,
+    read_only: bool,
+    // Pending files are placed in a sub-directory per store of the staging dir (see
+    // set_staging_dir), i.e. several stores can share one staging dir.
+    staging_dir: Option<PathBuf>,
+    staging_sub_dir: String,
+    hash_algorithm: HashAlgorithm,
+    // Names only differing in their unicode normalization refer to the same item (see name_key).
+    normalize_unicode: bool,
+    // Key of stores encrypting their file contents (see enable_encryption).
+    content_key: Option<ContentKey>,
+    // DB placed outside of the metadata dir (see set_external_db_path).
+    external_db_path: Option<PathBuf>,
+    // Duplicate names still list their first item without an issue (see index).
+    index_first_duplicate: bool,
+    // Local copy of the DB the store works with if the FS requires it (see
+    // DBAccessType::TmpCopy). Only the instance owning it writes it back and removes it.
+    db_working_copy: Option<PathBuf>,
+    owns_db_working_copy: bool,
 
     ignore_rules: Vec<glob::Pattern>,
 }
@@ -43,26 +77,186 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
 
     /// Same as open, but uses an explicit instance of the virtual FS abstraction.
     pub fn open_with_fs<P: AsRef<Path>>(data_store_root: P, virtual_fs: FS) -> Result<Self> {
+        Self::open_with_fs_and_lock_timeout(data_store_root, virtual_fs, Duration::from_secs(0))
+    }
+
+    /// Same as open, but waits up to the given timeout for a lock held by a different
+    /// application to be released (e.g. a concurrent short operation to finish).
+    ///
+    /// # Errors
+    /// If the lock is still held after the timeout, MetadataDirAlreadyOpened is returned.
+    pub fn open_with_lock_timeout<P: AsRef<Path>>(
+        data_store_root: P,
+        timeout: Duration,
+    ) -> Result<Self> {
+        Self::open_with_fs_and_lock_timeout(data_store_root, FS::default(), timeout)
+    }
+
+    /// Same as open_with_lock_timeout, but uses an explicit instance of the virtual FS.
+    pub fn open_with_fs_and_lock_timeout<P: AsRef<Path>>(
+        data_store_root: P,
+        virtual_fs: FS,
+        timeout: Duration,
+    ) -> Result<Self> {
         let data_store_root = virtual_fs.canonicalize(data_store_root)?;
         let mut result = FSInteraction {
             fs: virtual_fs,
             root_path: data_store_root,
             locked: false,
+            read_only: false,
+            staging_dir: None,
+            staging_sub_dir: String::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            normalize_unicode: false,
+            content_key: None,
+            external_db_path: None,
+            index_first_duplicate: false,
+            db_working_copy: None,
+            owns_db_working_copy: true,
             ignore_rules: vec![],
         };
-        result.acquire_exclusive_lock()?;
+        result.acquire_exclusive_lock_with_timeout(timeout)?;
         result.ensure_metadata_dirs_exist()?;
+        result.external_db_path = result.read_external_db_path()?;
+        result.db_working_copy = result.create_db_working_copy()?;
 
         Ok(result)
     }
 
+    /// Opens a data_store for queries only, e.g. on a read-only mount (a snapshot or CD image).
+    /// Neither the lock nor any metadata directory is created. Any attempt to change the
+    /// data_store's content fails with ReadOnlyStore.
+    ///
+    /// # Errors
+    /// If the directory does not contain a metadata folder an error is returned.
+    pub fn open_read_only<P: AsRef<Path>>(data_store_root: P) -> Result<Self> {
+        Self::open_read_only_with_fs(data_store_root, FS::default())
+    }
+
+    /// Same as open_read_only, but uses an explicit instance of the virtual FS abstraction.
+    pub fn open_read_only_with_fs<P: AsRef<Path>>(
+        data_store_root: P,
+        virtual_fs: FS,
+    ) -> Result<Self> {
+        let data_store_root = virtual_fs.canonicalize(data_store_root)?;
+        let mut result = FSInteraction {
+            fs: virtual_fs,
+            root_path: data_store_root,
+            locked: false,
+            read_only: true,
+            staging_dir: None,
+            staging_sub_dir: String::new(),
+            hash_algorithm: HashAlgorithm::default(),
+            normalize_unicode: false,
+            content_key: None,
+            external_db_path: None,
+            index_first_duplicate: false,
+            db_working_copy: None,
+            owns_db_working_copy: true,
+            ignore_rules: vec![],
+        };
+        if !result.fs.metadata(result.metadata_path())?.is_dir() {
+            return Err(io::Error::from(io::ErrorKind::NotFound).into());
+        }
+        result.external_db_path = result.read_external_db_path()?;
+        result.db_working_copy = result.create_db_working_copy()?;
+
+        Ok(result)
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns a second handle to the same data_store that does NOT hold the lock, e.g. for
+    /// read-only operations running on a different thread next to this instance.
+    pub fn unlocked_view(&self) -> Self {
+        FSInteraction {
+            fs: self.fs.clone(),
+            root_path: self.root_path.clone(),
+            locked: false,
+            read_only: self.read_only,
+            staging_dir: self.staging_dir.clone(),
+            staging_sub_dir: self.staging_sub_dir.clone(),
+            hash_algorithm: self.hash_algorithm,
+            normalize_unicode: self.normalize_unicode,
+            content_key: self.content_key.clone(),
+            external_db_path: self.external_db_path.clone(),
+            index_first_duplicate: self.index_first_duplicate,
+            db_working_copy: self.db_working_copy.clone(),
+            owns_db_working_copy: false,
+            ignore_rules: self.ignore_rules.clone(),
+        }
+    }
+
     /// Explicitly closes the data_store by releasing the lock (deleting the dot-file).
     /// Can be useful to catch potential errors in the operation instead of crashing
     /// the whole application when the struct is dropped.
     pub fn close(mut self) -> Result<()> {
+        self.write_back_db_working_copy()?;
         self.release_exclusive_lock()
     }
 
+    /// Removes the lock of a data_store that was not properly closed, e.g. because the process
+    /// holding it crashed. Returns the PID of the process that held the lock (if known).
+    ///
+    /// # Errors
+    /// If the lock might still be held by a live process, it is only removed when forced.
+    pub fn unlock<P: AsRef<Path>>(data_store_root: P, force: bool) -> Result<Option<u32>> {
+        Self::unlock_with_fs(data_store_root, FS::default(), force)
+    }
+
+    /// Same as unlock, but uses an explicit instance of the virtual FS abstraction.
+    pub fn unlock_with_fs<P: AsRef<Path>>(
+        data_store_root: P,
+        virtual_fs: FS,
+        force: bool,
+    ) -> Result<Option<u32>> {
+        let lock_path = virtual_fs
+            .canonicalize(data_store_root)?
+            .join(METADATA_DIR)
+            .join(LOCK_FILE);
+
+        let mut lock_content = String::new();
+        match virtual_fs.read_file(&lock_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(FSInteractionError::MetadataDirNotLocked);
+            }
+            Err(e) => return Err(e.into()),
+            Ok(mut reader) => reader.read_to_string(&mut lock_content)?,
+        };
+
+        let holder_pid = lock_content.trim().parse::<u32>().ok();
+        let holder_alive = holder_pid.is_none_or(is_process_alive);
+        if holder_alive && !force {
+            return Err(FSInteractionError::LockStillHeld { pid: holder_pid });
+        }
+
+        virtual_fs.remove_file(&lock_path)?;
+        Ok(holder_pid)
+    }
+
+    /// Reads the (optional) config file in the metadata dir of the data_store at the given path.
+    /// Returns None if the store has no config file. Works without opening (locking) the store.
+    pub fn read_config_with_fs<P: AsRef<Path>>(
+        data_store_root: P,
+        virtual_fs: FS,
+    ) -> Result<Option<String>> {
+        let config_path = match virtual_fs.canonicalize(data_store_root) {
+            Ok(data_store_root) => data_store_root.join(METADATA_DIR).join(CONFIG_FILE),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut config = String::new();
+        match virtual_fs.read_file(&config_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+            Ok(mut reader) => reader.read_to_string(&mut config)?,
+        };
+        Ok(Some(config))
+    }
+
     /// Turns a directory into a new data_store and opens it.
     /// This is done by creating the required metadata directory.
     ///
@@ -98,7 +292,7 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
     /// Returns a list off all relevant metadata of the entries found on disk.
     pub fn index(&self, relative_path: &RelativePath) -> Result<Vec<DataItem>> {
         // We do not follow soft-links in our sync procedure.
-        let indexed_dir = self.root_path.join(&relative_path.to_path_buf());
+        let indexed_dir = self.absolute_path(relative_path);
         if indexed_dir != self.fs.canonicalize(&indexed_dir)? {
             return Err(FSInteractionError::SoftLinksForbidden);
         }
@@ -107,39 +301,46 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         let mut entries: Vec<DataItem> = Vec::new();
         let mut dir_entries = self.fs.list_dir(&indexed_dir)?;
 
-        // We want to detect duplicates during this pass. To do so, we sort the vector and
-        // keep the last file name around.
-        let mut last_filename_lowercase = String::new();
-        dir_entries.sort_by(|a, b| a.file_name.partial_cmp(&b.file_name).unwrap());
+        // We want to detect duplicates during this pass. To do so, we keep the name key
+        // of all entries seen so far around.
+        let mut entries_by_name_key: HashMap<String, usize> = HashMap::new();
+        dir_entries.sort_by(|a, b| {
+            self.cmp_names(
+                &a.file_name.to_string_lossy(),
+                &b.file_name.to_string_lossy(),
+            )
+        });
         for dir_entry in dir_entries {
             let file_name = dir_entry
                 .file_name
                 .to_str()
                 .expect("TODO: we currently only support UTF-8 compatible file names!");
 
-            // Skip reserved entries, we simply do not list them.
-            if self.is_reserved_name(file_name) {
+            // Skip reserved and internal entries, we simply do not list them.
+            let relative_path = relative_path.join(file_name.to_string());
+            if self.is_reserved_name(file_name) || self.is_internal_path(&relative_path) {
                 continue;
             }
 
             // Create basic data_item for remaining, valid entries.
-            let relative_path = relative_path.join(file_name.to_string());
             let mut data_item = DataItem {
                 relative_path: relative_path,
                 metadata: None,
                 issue: None,
             };
 
-            // Check if item is a duplicate (when ignoring case in names).
+            // Check if item is a duplicate (when ignoring case/normalization in names).
+            // Entries are sorted, i.e. the first one of duplicates has the smallest name.
             if data_item.issue.is_none() {
-                let filename_lowercase = file_name.to_lowercase();
-                if filename_lowercase == last_filename_lowercase {
+                let name_key = self.name_key(file_name);
+                if let Some(&duplicate_index) = entries_by_name_key.get(&name_key) {
                     data_item.issue = Some(Issue::Duplicate);
-                    if entries.last().unwrap().issue.is_none() {
-                        entries.last_mut().unwrap().issue = Some(Issue::Duplicate);
+                    if entries[duplicate_index].issue.is_none() && !self.index_first_duplicate {
+                        entries[duplicate_index].issue = Some(Issue::Duplicate);
                     }
+                } else {
+                    entries_by_name_key.insert(name_key, entries.len());
                 }
-                last_filename_lowercase = filename_lowercase;
             }
 
             // Try to load metadata for the item and detect possible issues.
@@ -154,11 +355,26 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
     }
 
     pub fn calculate_hash(&self, relative_path: &RelativePath) -> Result<String> {
-        let absolute_path = self.root_path.join(relative_path.to_path_buf());
-        let reader = self.fs.read_file(&absolute_path)?;
+        let reader = self.read_file(relative_path)?;
+
+        self.hash_reader(reader)
+    }
+
+    /// Same as calculate_hash, but only hashes the first length bytes of the file.
+    pub fn calculate_prefix_hash(
+        &self,
+        relative_path: &RelativePath,
+        length: u64,
+    ) -> Result<String> {
+        let reader = self.read_file(relative_path)?;
+
+        self.hash_reader(reader.take(length))
+    }
+
+    fn hash_reader<R: io::Read>(&self, reader: R) -> Result<String> {
         let mut buffered_reader = io::BufReader::new(reader);
 
-        let mut context = Context::new(&SHA256);
+        let mut context = Context::new(self.hash_algorithm.digest_algorithm());
         let mut buffer = [0; 1024];
 
         loop {
@@ -176,22 +392,165 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         Ok(hash)
     }
 
+    /// Items whose names only differ in case (or normalization) are listed with
+    /// Issue::Duplicate by index. If set, the first of them (smallest name) is listed without
+    /// the issue, i.e. it is treated like any other item.
+    pub fn set_index_first_duplicate(&mut self, index_first_duplicate: bool) {
+        self.index_first_duplicate = index_first_duplicate;
+    }
+
+    /// The algorithm used by calculate_hash.
+    pub fn set_hash_algorithm(&mut self, hash_algorithm: HashAlgorithm) {
+        self.hash_algorithm = hash_algorithm;
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// Whether names only differing in their unicode normalization are the same item.
+    pub fn set_normalize_unicode(&mut self, normalize_unicode: bool) {
+        self.normalize_unicode = normalize_unicode;
+    }
+
+    /// Key identifying an item name within its directory (see relative_path::name_lookup_key).
+    /// Orders names canonically, see relative_path::cmp_names.
+    pub fn cmp_names(&self, a: &str, b: &str) -> std::cmp::Ordering {
+        relative_path::cmp_names(a, b, self.normalize_unicode)
+    }
+
+    pub fn name_key(&self, name: &str) -> String {
+        relative_path::name_lookup_key(name, self.normalize_unicode)
+    }
+
     pub fn root_path(&self) -> PathBuf {
         self.root_path.clone()
     }
 
+    /// Checks if both instances point to the same directory (their root paths are canonical).
+    pub fn is_same_location(&self, other: &Self) -> bool {
+        self.fs.shares_file_system(&other.fs) && self.root_path == other.root_path
+    }
+
     pub fn metadata_db_path(&self) -> PathBuf {
         match self.fs.db_access_type() {
-            virtual_fs::DBAccessType::InPlace => {
-                self.root_path.join(METADATA_DIR).join(METADATA_DB_FILE)
-            }
+            virtual_fs::DBAccessType::InPlace => self.store_db_path(),
             virtual_fs::DBAccessType::InMemory => PathBuf::from(":memory:"),
-            virtual_fs::DBAccessType::TmpCopy => panic!("Not implemented!"),
+            virtual_fs::DBAccessType::TmpCopy => self
+                .db_working_copy
+                .as_ref()
+                .expect("DB working copies are created when opening the store!")
+                .join(METADATA_DB_FILE),
+        }
+    }
+
+    // Location of the DB file within the store (or the external DB, see set_external_db_path).
+    fn store_db_path(&self) -> PathBuf {
+        match &self.external_db_path {
+            Some(external_db_path) => external_db_path.clone(),
+            None => self.metadata_path().join(METADATA_DB_FILE),
         }
     }
 
+    // Copies the store's DB into a new local temp dir if the FS requires it, SQLite does not
+    // work reliably on e.g. network shares (see DBAccessType::TmpCopy). New stores have no DB
+    // yet, it is created in the working copy.
+    fn create_db_working_copy(&self) -> Result<Option<PathBuf>> {
+        match self.fs.db_access_type() {
+            virtual_fs::DBAccessType::TmpCopy => (),
+            _ => return Ok(None),
+        }
+
+        let working_dir = tempfile::Builder::new()
+            .prefix("data_squirrel_db_")
+            .tempdir()?
+            .keep();
+        match self.fs.read_file(self.store_db_path()) {
+            Ok(mut reader) => {
+                let mut writer = std::fs::File::create(working_dir.join(METADATA_DB_FILE))?;
+                io::copy(&mut reader, &mut writer)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+
+        Ok(Some(working_dir))
+    }
+
+    // Writes the local working copy of the DB back into the store and removes it.
+    // Must only be called once the DB is closed. The new DB is fully written before it replaces
+    // the old one, i.e. an interruption never leaves a partially written DB behind.
+    fn write_back_db_working_copy(&mut self) -> Result<()> {
+        if !self.owns_db_working_copy {
+            return Ok(());
+        }
+        let working_dir = match &self.db_working_copy {
+            Some(working_dir) => working_dir.clone(),
+            None => return Ok(()),
+        };
+
+        if self.locked && !self.read_only {
+            let store_db_path = self.store_db_path();
+            let with_suffix = |suffix: &str| {
+                let mut path = store_db_path.clone().into_os_string();
+                path.push(suffix);
+                PathBuf::from(path)
+            };
+            let (tmp_db_path, old_db_path) = (with_suffix(".tmp"), with_suffix(".old"));
+
+            let reader = std::fs::File::open(working_dir.join(METADATA_DB_FILE))?;
+            self.write_new_file(&tmp_db_path, Box::new(reader))?;
+            // Renames must not replace existing files, move the old DB out of the way first.
+            if self.fs.metadata(&store_db_path).is_ok() {
+                self.fs.rename(&store_db_path, &old_db_path)?;
+            }
+            self.fs.rename(&tmp_db_path, &store_db_path)?;
+            self.fs.remove_file(&old_db_path).ok();
+        }
+        std::fs::remove_dir_all(&working_dir)?;
+        self.db_working_copy = None;
+
+        Ok(())
+    }
+
+    /// Places pending files (e.g. downloads in progress) into the given directory instead of
+    /// the store's metadata dir, e.g. to stage them on a faster or larger disk.
+    /// None switches back to the metadata dir. The directory is created if it is missing.
+    ///
+    /// Each store uses its own sub-directory (named after the given store name), i.e. several
+    /// stores can share a staging dir without cleaning up each others pending files.
+    pub fn set_staging_dir<P: AsRef<Path>>(
+        &mut self,
+        staging_dir: Option<P>,
+        store_name: &str,
+    ) -> Result<()> {
+        self.staging_dir = match staging_dir {
+            Some(staging_dir) => {
+                self.fs.create_dir(staging_dir.as_ref(), true)?;
+                Some(self.fs.canonicalize(staging_dir)?)
+            }
+            None => None,
+        };
+        // Store names are chosen freely, keep them from addressing other directories.
+        self.staging_sub_dir = store_name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if self.staging_dir.is_some() {
+            self.fs.create_dir(self.pending_files_dir(), true)?;
+        }
+
+        Ok(())
+    }
+
     pub fn metadata(&self, relative_path: &RelativePath) -> Result<virtual_fs::Metadata> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        let absolute_path = self.absolute_path(relative_path);
         let result = self.fs.metadata(&absolute_path)?;
         Ok(result)
     }
@@ -202,7 +561,8 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         mod_time: FileTime,
         read_only: bool,
     ) -> Result<()> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
         self.fs
             .update_metadata(&absolute_path, mod_time, read_only)?;
 
@@ -212,7 +572,7 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
     fn load_metadata(&self, data_item: &mut DataItem) {
         // Loading metadata from the os can fail, however, we do not see this as failing
         // to provide the data_item. We simply mark any conflicts we encounter.
-        let absolute_path = self.root_path.join(&data_item.relative_path.to_path_buf());
+        let absolute_path = self.absolute_path(&data_item.relative_path);
         let metadata = self.fs.metadata(&absolute_path);
 
         if let Ok(metadata) = metadata {
@@ -233,26 +593,30 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
     }
 
     pub fn create_file(&self, relative_path: &RelativePath) -> Result<()> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
         self.fs.create_file(&absolute_path)?;
 
         Ok(())
     }
     pub fn delete_file(&self, relative_path: &RelativePath) -> Result<()> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
         self.fs.remove_file(&absolute_path)?;
 
         Ok(())
     }
 
     pub fn create_dir(&self, relative_path: &RelativePath) -> Result<()> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
         self.fs.create_dir(&absolute_path, false)?;
 
         Ok(())
     }
     pub fn delete_directory(&self, relative_path: &RelativePath) -> Result<()> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
         self.fs.remove_dir_recursive(&absolute_path)?;
 
         Ok(())
@@ -263,40 +627,554 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         source_path: &RelativePath,
         dest_path: &RelativePath,
     ) -> Result<()> {
-        let absolute_source_path = self.root_path.join(&source_path.to_path_buf());
-        let absolute_dest_path = self.root_path.join(&dest_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_source_path = self.absolute_path(source_path);
+        let absolute_dest_path = self.absolute_path(dest_path);
+
+        match self.fs.rename(&absolute_source_path, &absolute_dest_path) {
+            Err(ref e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                self.move_file_across_devices(&absolute_source_path, &absolute_dest_path)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    // Renames can not move files between volumes, e.g. out of a staging dir on a different
+    // disk. We copy the file (keeping its metadata) next to the destination and rename it into
+    // place, i.e. the destination never holds a partial copy. The original is removed last.
+    fn move_file_across_devices(&self, source_path: &Path, dest_path: &Path) -> Result<()> {
+        let metadata = self.fs.metadata(source_path)?;
+        if !metadata.is_file() {
+            return Err(io::Error::from(io::ErrorKind::CrossesDevices).into());
+        }
+
+        let dest_name = dest_path.file_name().unwrap().to_string_lossy();
+        let tmp_path = dest_path.with_file_name(format!(".{}.{}.tmp", dest_name, METADATA_DIR));
+        let copy_result = self
+            .write_new_file(&tmp_path, self.fs.read_file(source_path)?)
+            .and_then(|_| {
+                self.fs
+                    .update_metadata(&tmp_path, metadata.last_mod_time(), metadata.read_only())
+            })
+            .and_then(|_| self.fs.rename(&tmp_path, dest_path));
+        if let Err(e) = copy_result {
+            // Best effort, the partial copy must not stay around as a regular file.
+            self.fs.remove_file(&tmp_path).ok();
+            return Err(e.into());
+        }
+        self.fs.remove_file(source_path)?;
 
-        self.fs.rename(&absolute_source_path, &absolute_dest_path)?;
+        Ok(())
+    }
+
+    // (Re-)creates the file at the given path with the given content, e.g. a temporary file
+    // left over by an interrupted operation is replaced.
+    fn write_new_file(&self, path: &Path, data: Box<dyn io::Read>) -> io::Result<usize> {
+        match self.fs.remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        self.fs.create_file(path)?;
+        self.fs.overwrite_file(path, data)
+    }
+
+    pub fn create_hard_link(
+        &self,
+        source_path: &RelativePath,
+        dest_path: &RelativePath,
+    ) -> Result<()> {
+        self.check_writable()?;
+        let absolute_source_path = self.absolute_path(source_path);
+        let absolute_dest_path = self.absolute_path(dest_path);
+        self.fs
+            .hard_link(&absolute_source_path, &absolute_dest_path)?;
 
         Ok(())
     }
 
     pub fn read_file(&self, relative_path: &RelativePath) -> Result<Box<dyn io::Read>> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        let absolute_path = self.absolute_path(relative_path);
+        let reader = self.fs.read_file(&absolute_path)?;
 
-        Ok(self.fs.read_file(&absolute_path)?)
+        match self.content_key()? {
+            Some(content_key) => Ok(Box::new(DecryptingReader::new(reader, &content_key))),
+            None => Ok(reader),
+        }
     }
 
-    pub fn write_file(
+    /// Reads the file starting at the given offset.
+    // TODO: The FS abstraction can not seek yet, i.e. we read over the skipped content.
+    //       A streaming implementation would only transfer the remaining content.
+    pub fn read_file_from(
         &self,
         relative_path: &RelativePath,
-        data: Box<dyn io::Read>,
+        offset: u64,
+    ) -> Result<Box<dyn io::Read>> {
+        let mut reader = self.read_file(relative_path)?;
+        io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+
+        Ok(reader)
+    }
+
+    pub fn write_file<'a>(
+        &self,
+        relative_path: &RelativePath,
+        data: Box<dyn io::Read + 'a>,
     ) -> Result<usize> {
-        let absolute_path = self.root_path.join(&relative_path.to_path_buf());
+        self.check_writable()?;
+        let absolute_path = self.absolute_path(relative_path);
+        let data: Box<dyn io::Read + 'a> = match self.content_key()? {
+            Some(content_key) => Box::new(EncryptingReader::new(data, &content_key)?),
+            None => data,
+        };
 
         Ok(self.fs.overwrite_file(&absolute_path, data)?)
     }
 
+    /// Copies the (source, destination) files of the jobs from the other store into this
+    /// store, running up to max_parallel copies at once if the FS supports it.
+    /// Returns the result of each job in job order.
+    pub fn copy_files_from(
+        &self,
+        other: &Self,
+        jobs: &[(RelativePath, RelativePath)],
+        max_parallel: usize,
+    ) -> Vec<Result<u64>> {
+        if let Some(copy) = self.start_copy_files_from(other, jobs, max_parallel) {
+            return copy.join().into_iter().map(|result| Ok(result?)).collect();
+        }
+        if self.read_only {
+            return jobs
+                .iter()
+                .map(|_| Err(FSInteractionError::ReadOnlyStore))
+                .collect();
+        }
+
+        // Raw copies would mix up encrypted and plain content, re-encode the content instead.
+        jobs.iter()
+            .map(|(source_path, dest_path)| {
+                let content = other.read_file(source_path)?;
+                match self.create_file(dest_path) {
+                    Err(error) if !error.is_io_already_exists() => return Err(error),
+                    _ => (),
+                }
+                Ok(self.write_file(dest_path, content)? as u64)
+            })
+            .collect()
+    }
+
+    /// Same as copy_files_from, but the copies run in the background (see
+    /// FS::start_copy_files_from). Returns None if the FS can not copy the files, i.e. for
+    /// read-only stores and if any of the stores encrypts its content.
+    pub fn start_copy_files_from(
+        &self,
+        other: &Self,
+        jobs: &[(RelativePath, RelativePath)],
+        max_parallel: usize,
+    ) -> Option<virtual_fs::BackgroundCopy> {
+        if self.read_only || self.is_encrypted() || other.is_encrypted() {
+            return None;
+        }
+        let absolute_jobs: Vec<(PathBuf, PathBuf)> = jobs
+            .iter()
+            .map(|(source_path, dest_path)| {
+                (
+                    other.absolute_path(source_path),
+                    self.absolute_path(dest_path),
+                )
+            })
+            .collect();
+
+        Some(
+            self.fs
+                .start_copy_files_from(&other.fs, absolute_jobs, max_parallel),
+        )
+    }
+
+    /// Returns the number of bytes that can still be written to the volume holding the store.
+    pub fn available_space(&self) -> Result<u64> {
+        Ok(self.fs.available_space(&self.root_path)?)
+    }
+
+    /// Size of the metadata DB file including its WAL and shared memory files in bytes
+    /// (zero for DBs not stored on disk).
+    pub fn metadata_db_size(&self) -> Result<u64> {
+        if let virtual_fs::DBAccessType::InMemory = self.fs.db_access_type() {
+            return Ok(0);
+        }
+
+        let db_path = self.metadata_db_path();
+        let mut size = 0;
+        for suffix in &["", "-wal", "-shm"] {
+            let mut file_path = db_path.clone().into_os_string();
+            file_path.push(suffix);
+            size += self.tree_size(&PathBuf::from(file_path))?;
+        }
+
+        Ok(size)
+    }
+
+    /// Total size of all files in the pending files directory in bytes.
+    pub fn pending_files_size(&self) -> Result<u64> {
+        self.tree_size(&self.pending_files_dir())
+    }
+
+    /// Total size of all files in the snapshot directory in bytes.
+    pub fn snapshots_size(&self) -> Result<u64> {
+        self.tree_size(&self.snapshot_dir())
+    }
+
+    /// Total size of the file or all files in the folder at the given path in bytes.
+    pub fn item_size(&self, relative_path: &RelativePath) -> Result<u64> {
+        self.tree_size(&self.absolute_path(relative_path))
+    }
+
+    // Sums up the sizes of all files in the given tree (zero if it does not exist).
+    fn tree_size(&self, path: &Path) -> Result<u64> {
+        let metadata = match self.fs.metadata(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+            Ok(metadata) => metadata,
+        };
+        if !metadata.is_dir() {
+            return Ok(metadata.size());
+        }
+
+        let mut size = 0;
+        for dir_entry in self.fs.list_dir(path)? {
+            size += self.tree_size(&path.join(dir_entry.file_name))?;
+        }
+        Ok(size)
+    }
+
+    /// Encrypts all file contents written from now on with a key derived from the passphrase.
+    /// The salt and a verifier of the passphrase are stored in the metadata directory, i.e.
+    /// later instances must be unlocked with the same passphrase to access any file content.
+    pub fn enable_encryption(&mut self, passphrase: &str) -> Result<()> {
+        self.check_writable()?;
+        if self.is_encrypted() {
+            return Err(FSInteractionError::AlreadyEncrypted);
+        }
+
+        use data_encoding::HEXUPPER;
+        let (content_key, salt, verifier) = ContentKey::create(passphrase)?;
+        let content = format!(
+            "{}\n{}\n",
+            HEXUPPER.encode(&salt),
+            HEXUPPER.encode(&verifier)
+        );
+        self.fs.create_file(self.encryption_path())?;
+        self.fs
+            .overwrite_file(self.encryption_path(), Box::new(io::Cursor::new(content)))?;
+
+        self.content_key = Some(content_key);
+        Ok(())
+    }
+
+    /// Unlocks the file contents of an encrypted store.
+    ///
+    /// # Errors
+    /// WrongPassphrase if the passphrase does not match the one the store was encrypted with.
+    pub fn unlock_encryption(&mut self, passphrase: &str) -> Result<()> {
+        use data_encoding::HEXUPPER;
+
+        let mut content = String::new();
+        self.fs
+            .read_file(self.encryption_path())?
+            .read_to_string(&mut content)?;
+        let mut lines = content.lines().map(|line| HEXUPPER.decode(line.as_bytes()));
+        let (salt, verifier) = match (lines.next(), lines.next()) {
+            (Some(Ok(salt)), Some(Ok(verifier))) => (salt, verifier),
+            _ => return Err(io::Error::from(io::ErrorKind::InvalidData).into()),
+        };
+
+        match ContentKey::unlock(passphrase, &salt, &verifier) {
+            Some(content_key) => {
+                self.content_key = Some(content_key);
+                Ok(())
+            }
+            None => Err(FSInteractionError::WrongPassphrase),
+        }
+    }
+
+    /// Checks if the store encrypts its file contents (see enable_encryption).
+    pub fn is_encrypted(&self) -> bool {
+        self.content_key.is_some() || self.fs.metadata(self.encryption_path()).is_ok()
+    }
+
+    /// Fails with EncryptionLocked if the store's file contents can not be accessed, as it is
+    /// encrypted but was not unlocked yet.
+    pub fn check_content_access(&self) -> Result<()> {
+        self.content_key().map(|_| ())
+    }
+
+    // Key used for file contents, fails if the store is encrypted but was not unlocked yet.
+    fn content_key(&self) -> Result<Option<ContentKey>> {
+        if self.content_key.is_none() && self.is_encrypted() {
+            return Err(FSInteractionError::EncryptionLocked);
+        }
+
+        Ok(self.content_key.clone())
+    }
+
+    /// Runs the external merge command as `command local base remote` on the given files.
+    /// The command must write the merged result into the local file and report a clean merge
+    /// by its exit code (e.g. `git merge-file`). Returns if the merge succeeded.
+    pub fn run_merge_command(
+        &self,
+        command: &[String],
+        local: &RelativePath,
+        base: &RelativePath,
+        remote: &RelativePath,
+    ) -> Result<bool> {
+        self.check_writable()?;
+        let (program, args) = match command.split_first() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+
+        let status = std::process::Command::new(program)
+            .args(args)
+            .arg(self.absolute_path(local))
+            .arg(self.absolute_path(base))
+            .arg(self.absolute_path(remote))
+            .status()?;
+        Ok(status.success())
+    }
+
+    /// Lists all files in the pending files directory, i.e. downloaded files that were not yet
+    /// moved to their final location. These are left behind if a sync crashes mid-download.
+    pub fn list_pending_files(&self) -> Result<Vec<DataItem>> {
+        let pending_files_relative = self.pending_files_relative();
+
+        let mut pending_files = Vec::new();
+        for dir_entry in self.fs.list_dir(self.pending_files_dir())? {
+            let file_name = dir_entry
+                .file_name
+                .to_str()
+                .expect("TODO: we currently only support UTF-8 compatible file names!");
+
+            let mut data_item = DataItem {
+                relative_path: pending_files_relative.join(file_name.to_string()),
+                metadata: None,
+                issue: None,
+            };
+            self.load_metadata(&mut data_item);
+            pending_files.push(data_item);
+        }
+
+        Ok(pending_files)
+    }
+
+    /// Marks that a destructive, multi-step operation is in progress by writing a marker file.
+    /// The marker survives crashes and must be cleared with finish_operation on success.
+    pub fn begin_operation(&self, operation: &str) -> Result<()> {
+        self.check_writable()?;
+        match self.fs.create_file(self.operation_marker_path()) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e.into()),
+            Ok(()) => (),
+        };
+        self.fs.overwrite_file(
+            self.operation_marker_path(),
+            Box::new(io::Cursor::new(operation.as_bytes().to_vec())),
+        )?;
+
+        Ok(())
+    }
+
+    /// Clears the marker written by begin_operation.
+    pub fn finish_operation(&self) -> Result<()> {
+        self.check_writable()?;
+        match self.fs.remove_file(self.operation_marker_path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Returns the name of an operation that was started but never finished, e.g. because the
+    /// process crashed in the middle of it.
+    pub fn interrupted_operation(&self) -> Result<Option<String>> {
+        let mut reader = match self.fs.read_file(self.operation_marker_path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+            Ok(reader) => reader,
+        };
+
+        let mut operation = String::new();
+        reader.read_to_string(&mut operation)?;
+        Ok(Some(operation))
+    }
+
+    /// Persists the plan of a transfer store's carry, it survives crashes and must be cleared
+    /// with clear_transfer_plan once the carry is complete.
+    pub fn write_transfer_plan(&self, plan: &str) -> Result<()> {
+        self.check_writable()?;
+        match self.fs.create_file(self.transfer_plan_path()) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e.into()),
+            Ok(()) => (),
+        };
+        self.fs.overwrite_file(
+            self.transfer_plan_path(),
+            Box::new(io::Cursor::new(plan.as_bytes().to_vec())),
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the plan written by write_transfer_plan (if it was not cleared).
+    pub fn read_transfer_plan(&self) -> Result<Option<String>> {
+        let mut reader = match self.fs.read_file(self.transfer_plan_path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+            Ok(reader) => reader,
+        };
+
+        let mut plan = String::new();
+        reader.read_to_string(&mut plan)?;
+        Ok(Some(plan))
+    }
+
+    /// Clears the plan written by write_transfer_plan.
+    pub fn clear_transfer_plan(&self) -> Result<()> {
+        self.check_writable()?;
+        match self.fs.remove_file(self.transfer_plan_path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+            Ok(()) => Ok(()),
+        }
+    }
+
+    /// Places the metadata DB at the given file path instead of the metadata dir, e.g. on a fast
+    /// local disk while the data lives on a slow network volume. The location is recorded in
+    /// the metadata dir, i.e. later opens find the DB again. The lock stays in the metadata dir
+    /// and is always acquired before the DB is accessed.
+    /// Only call this before the DB is created, the path must not hold any file yet.
+    pub fn set_external_db_path<P: AsRef<Path>>(&mut self, db_path: P) -> Result<()> {
+        self.check_writable()?;
+        let db_path = db_path.as_ref();
+        let db_dir = self
+            .fs
+            .canonicalize(db_path.parent().unwrap_or_else(|| Path::new(".")))?;
+        let db_path = db_dir.join(
+            db_path
+                .file_name()
+                .unwrap_or_else(|| METADATA_DB_FILE.as_ref()),
+        );
+        if self.fs.metadata(&db_path).is_ok() {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+        }
+
+        let location_path = self.metadata_path().join(DB_LOCATION_FILE);
+        self.fs.create_file(&location_path)?;
+        self.fs.overwrite_file(
+            &location_path,
+            Box::new(io::Cursor::new(
+                db_path.to_str().unwrap().as_bytes().to_vec(),
+            )),
+        )?;
+        self.external_db_path = Some(db_path);
+
+        Ok(())
+    }
+
+    fn read_external_db_path(&self) -> Result<Option<PathBuf>> {
+        let location_path = self.metadata_path().join(DB_LOCATION_FILE);
+        let mut reader = match self.fs.read_file(&location_path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+            Ok(reader) => reader,
+        };
+
+        let mut db_path = String::new();
+        reader.read_to_string(&mut db_path)?;
+        Ok(Some(PathBuf::from(db_path.trim())))
+    }
+
+    /// Persists the sub-trees currently excluded from scans (see DataStore::pause_path).
+    pub fn write_paused_paths(&self, paused_paths: &str) -> Result<()> {
+        self.check_writable()?;
+        match self.fs.create_file(self.paused_paths_path()) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e.into()),
+            Ok(()) => (),
+        };
+        self.fs.overwrite_file(
+            self.paused_paths_path(),
+            Box::new(io::Cursor::new(paused_paths.as_bytes().to_vec())),
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the paths written by write_paused_paths (None if none were ever written).
+    pub fn read_paused_paths(&self) -> Result<Option<String>> {
+        let mut reader = match self.fs.read_file(self.paused_paths_path()) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+            Ok(reader) => reader,
+        };
+
+        let mut paused_paths = String::new();
+        reader.read_to_string(&mut paused_paths)?;
+        Ok(Some(paused_paths))
+    }
+
     fn is_reserved_name(&self, file_name: &str) -> bool {
-        // Currently we only skip the metadata dir, however,
-        // we might want to add special marker files later on.
-        file_name.eq(METADATA_DIR) || file_name.eq(DS_STORE)
+        // OS specific marker files that never carry user data.
+        file_name.eq(DS_STORE)
+    }
+
+    /// Checks if the path points into data used internally by data stores, i.e. the metadata
+    /// directory (DB files, pending files, snapshots) of this or any nested store, an external
+    /// DB or the staging dir if they are placed inside the store.
+    /// Internal paths must never be indexed or synced.
+    pub fn is_internal_path(&self, relative_path: &RelativePath) -> bool {
+        if relative_path
+            .get_path_components()
+            .iter()
+            .any(|component| component.eq(METADATA_DIR))
+        {
+            return true;
+        }
+        if let Some(external_db_path) = &self.external_db_path {
+            let absolute_path = self.root_path.join(relative_path.to_path_buf());
+            let is_db_file = ["", "-wal", "-shm"].iter().any(|suffix| {
+                let mut db_file_path = external_db_path.clone().into_os_string();
+                db_file_path.push(suffix);
+                absolute_path == db_file_path
+            });
+            if is_db_file {
+                return true;
+            }
+        }
+
+        match &self.staging_dir {
+            Some(staging_dir) => self
+                .root_path
+                .join(relative_path.to_path_buf())
+                .starts_with(staging_dir),
+            None => false,
+        }
+    }
+
+    // Stores opened read-only must not change anything on disk.
+    fn check_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(FSInteractionError::ReadOnlyStore);
+        }
+
+        Ok(())
     }
 
     // Ensures all metadata directories exist.
     fn ensure_metadata_dirs_exist(&self) -> Result<()> {
         self.fs.create_dir(self.pending_files_dir(), true)?;
         self.fs.create_dir(self.snapshot_dir(), true)?;
+        self.fs
+            .create_dir(self.metadata_path().join(MERGE_BASES_DIR), true)?;
 
         Ok(())
     }
@@ -319,11 +1197,30 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
             }
             Ok(file) => file,
         };
+        // Remember who holds the lock, this allows to detect stale locks of crashed processes.
+        self.fs.overwrite_file(
+            self.lock_path(),
+            Box::new(io::Cursor::new(std::process::id().to_string().into_bytes())),
+        )?;
 
         self.locked = true;
         Ok(())
     }
 
+    // Polls for the lock dot-file until we can create it or the timeout passed.
+    fn acquire_exclusive_lock_with_timeout(&mut self, timeout: Duration) -> Result<()> {
+        let start = Instant::now();
+        loop {
+            match self.acquire_exclusive_lock() {
+                Err(FSInteractionError::MetadataDirAlreadyOpened) if start.elapsed() < timeout => {
+                    let remaining = timeout - start.elapsed();
+                    std::thread::sleep(std::cmp::min(remaining, LOCK_POLL_INTERVAL));
+                }
+                result => return result,
+            }
+        }
+    }
+
     // Deletes the lock dot-file.
     fn release_exclusive_lock(&mut self) -> Result<()> {
         if !self.locked {
@@ -336,6 +1233,24 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         Ok(())
     }
 
+    // Resolves a path relative to the store root, redirecting pending files into the
+    // staging dir if one is set.
+    fn absolute_path(&self, relative_path: &RelativePath) -> PathBuf {
+        let components = relative_path.get_path_components();
+        let pending_components = self.pending_files_relative();
+        let pending_components = pending_components.get_path_components();
+
+        if self.staging_dir.is_some() && components.starts_with(pending_components) {
+            components[pending_components.len()..]
+                .iter()
+                .fold(self.pending_files_dir(), |path, component| {
+                    path.join(component)
+                })
+        } else {
+            self.root_path.join(relative_path.to_path_buf())
+        }
+    }
+
     // Helpers for common path and file names
     fn metadata_path(&self) -> PathBuf {
         self.root_path.join(METADATA_DIR)
@@ -345,8 +1260,27 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
         self.metadata_path().join(LOCK_FILE)
     }
 
+    fn operation_marker_path(&self) -> PathBuf {
+        self.metadata_path().join(OPERATION_MARKER_FILE)
+    }
+
+    fn transfer_plan_path(&self) -> PathBuf {
+        self.metadata_path().join(TRANSFER_PLAN_FILE)
+    }
+
+    fn paused_paths_path(&self) -> PathBuf {
+        self.metadata_path().join(PAUSED_PATHS_FILE)
+    }
+
+    fn encryption_path(&self) -> PathBuf {
+        self.metadata_path().join(ENCRYPTION_FILE)
+    }
+
     pub fn pending_files_dir(&self) -> PathBuf {
-        self.metadata_path().join(PENDING_FILES_DIR)
+        match &self.staging_dir {
+            Some(staging_dir) => staging_dir.join(&self.staging_sub_dir),
+            None => self.metadata_path().join(PENDING_FILES_DIR),
+        }
     }
 
     pub fn snapshot_dir(&self) -> PathBuf {
@@ -364,23 +1298,59 @@ impl<FS: virtual_fs::FS> FSInteraction<FS> {
             .join(METADATA_DIR.to_string())
             .join(SNAPSHOT_DIR.to_string())
     }
+
+    pub fn merge_bases_relative(&self) -> RelativePath {
+        RelativePath::from_path("")
+            .join(METADATA_DIR.to_string())
+            .join(MERGE_BASES_DIR.to_string())
+    }
+}
+
+// Checks if a process with the given PID is still running.
+// If we can not tell on the current platform, we conservatively assume it is.
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    if pid == std::process::id() {
+        return true;
+    }
+    // Zero and negative PIDs address process groups, no single process has them.
+    if pid == 0 || pid > libc::pid_t::MAX as u32 {
+        return false;
+    }
+
+    // Signal 0 performs all permission checks without actually sending a signal.
+    // Only a missing process tells it is gone, e.g. processes of other users deny the signal.
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+#[cfg(not(unix))]
+fn is_process_alive(_pid: u32) -> bool {
+    true
 }
 
 impl<FS: virtual_fs::FS> Drop for FSInteraction<FS> {
     fn drop(&mut self) {
+        // Keep the working copy around on failures, it holds the only up to date DB.
+        if let Err(e) = self.write_back_db_working_copy() {
+            error!(
+                "Could not write back the DB working copy in '{}': {:?}",
+                self.db_working_copy.as_ref().unwrap().display(),
+                e
+            );
+        }
         // This is kind of a fatal fail...we can not release the lock?!
         self.release_exclusive_lock().unwrap();
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DataItem {
     pub relative_path: RelativePath,
     pub metadata: Option<virtual_fs::Metadata>,
     pub issue: Option<Issue>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum Issue {
     Duplicate,
     CanNotReadMetadata,
@@ -1,4 +1,33 @@
+use std::cmp::Ordering;
 use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Key identifying an item name independent of its case and (optionally) its unicode
+/// normalization form, e.g. 'café' written as NFC or NFD (common on macOS) is the same name.
+pub fn name_lookup_key(name: &str, normalize_unicode: bool) -> String {
+    if normalize_unicode {
+        name.nfc().collect::<String>().to_lowercase()
+    } else {
+        name.to_lowercase()
+    }
+}
+
+/// The canonical order of the items within a folder: by their lookup key (see name_lookup_key),
+/// names with equal keys by their exact characters.
+/// Scans, syncs and the index all list folder contents in this order, i.e. the same tree is
+/// always visited in the same order (independent of the file system's listing order).
+pub fn cmp_names(a: &str, b: &str, normalize_unicode: bool) -> Ordering {
+    name_lookup_key(a, normalize_unicode)
+        .cmp(&name_lookup_key(b, normalize_unicode))
+        .then_with(|| a.cmp(b))
+}
+
+/// Checks that a name can be used as a single path component, i.e. it can not address anything
+/// outside of its parent directory. Names received from other stores MUST be checked before
+/// joining them to a path, as e.g. '..' would escape the store's root once written to disk.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name != "." && name != ".." && !name.contains(['/', '\\', '\0'])
+}
 
 /// Represents a simplified, relative path within a data_store.
 ///
@@ -93,6 +122,18 @@ impl RelativePath {
         }
     }
 
+    /// Like to_lower_case, but using name_lookup_key on all components.
+    pub fn to_lookup_key(&self, normalize_unicode: bool) -> RelativePath {
+        let lookup_path = self
+            .path_components
+            .iter()
+            .map(|component| name_lookup_key(component, normalize_unicode))
+            .collect();
+        Self {
+            path_components: lookup_path,
+        }
+    }
+
     pub fn name(&self) -> &str {
         &self.path_components.last().unwrap()
     }
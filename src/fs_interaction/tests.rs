@@ -1,4 +1,4 @@
-use self::virtual_fs::{InMemoryFS, FS};
+use self::virtual_fs::{InMemoryFS, Operation, FS};
 use super::*;
 use filetime::FileTime;
 use std::fs;
@@ -52,6 +52,55 @@ fn data_store_creates_and_releases_locks() {
     );
 }
 
+#[test]
+fn unlock_removes_stale_locks() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let lock_path = test_dir.path().join(METADATA_DIR).join(LOCK_FILE);
+
+    // Locks held by live processes (us) are only removed when forced.
+    let data_store = DefaultFSInteraction::create(test_dir.path()).unwrap();
+    assert_eq!(
+        fs::read_to_string(&lock_path).unwrap(),
+        std::process::id().to_string()
+    );
+    assert!(matches!(
+        DefaultFSInteraction::unlock(test_dir.path(), false),
+        Err(FSInteractionError::LockStillHeld { pid: Some(_) })
+    ));
+    std::mem::forget(data_store);
+
+    // Processes we may not signal (e.g. init owned by root) are still alive.
+    #[cfg(unix)]
+    {
+        fs::write(&lock_path, "1").unwrap();
+        assert!(matches!(
+            DefaultFSInteraction::unlock(test_dir.path(), false),
+            Err(FSInteractionError::LockStillHeld { pid: Some(1) })
+        ));
+    }
+
+    // Simulate a crashed process holding the lock.
+    fs::write(&lock_path, "999999999").unwrap();
+    assert!(DefaultFSInteraction::open(test_dir.path()).is_err());
+    assert_eq!(
+        DefaultFSInteraction::unlock(test_dir.path(), false).unwrap(),
+        Some(999999999)
+    );
+    assert!(matches!(
+        DefaultFSInteraction::unlock(test_dir.path(), false),
+        Err(FSInteractionError::MetadataDirNotLocked)
+    ));
+    DefaultFSInteraction::open(test_dir.path()).unwrap();
+
+    // Legacy locks without a PID require force.
+    fs::write(&lock_path, "").unwrap();
+    assert!(DefaultFSInteraction::unlock(test_dir.path(), false).is_err());
+    assert_eq!(
+        DefaultFSInteraction::unlock(test_dir.path(), true).unwrap(),
+        None
+    );
+}
+
 #[test]
 fn can_not_open_data_store_multiple_times() {
     let test_dir = tempfile::tempdir().unwrap();
@@ -70,6 +119,61 @@ fn can_not_open_data_store_multiple_times() {
     };
 }
 
+#[test]
+fn wait_for_lock_to_be_released() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let data_store_1 = DefaultFSInteraction::create(test_dir.path()).unwrap();
+
+    // The lock is not released in time.
+    match DefaultFSInteraction::open_with_lock_timeout(test_dir.path(), Duration::from_millis(100))
+    {
+        Err(FSInteractionError::MetadataDirAlreadyOpened) => (),
+        _ => panic!("Must report error that data_store is in use."),
+    };
+
+    // The lock is released during the wait window.
+    let releasing_thread = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        data_store_1.close().unwrap();
+    });
+    let _data_store_2 =
+        DefaultFSInteraction::open_with_lock_timeout(test_dir.path(), Duration::from_secs(10))
+            .unwrap();
+    releasing_thread.join().unwrap();
+}
+
+#[test]
+fn open_data_store_on_read_only_mount() {
+    let fs = InMemoryFS::new();
+    let data_store = FSInteraction::create_with_fs("", fs.clone()).unwrap();
+    fs.create_file("file-1").unwrap();
+    drop(data_store);
+
+    fs.set_read_only_mount();
+    assert!(FSInteraction::open_with_fs("", fs.clone()).is_err());
+
+    // Queries work without taking the lock, changes are refused before touching the FS.
+    let data_store = FSInteraction::open_read_only_with_fs("", fs.clone()).unwrap();
+    assert!(data_store.is_read_only());
+    assert!(has_data_item(
+        &data_store.index(&RelativePath::from_path("")).unwrap(),
+        "file-1"
+    ));
+    assert!(fs.metadata(data_store.lock_path()).is_err());
+    match data_store.delete_file(&RelativePath::from_path("file-1")) {
+        Err(FSInteractionError::ReadOnlyStore) => (),
+        _ => panic!("Must not change read-only data stores!"),
+    }
+    match data_store.begin_operation("scan") {
+        Err(FSInteractionError::ReadOnlyStore) => (),
+        _ => panic!("Must not change read-only data stores!"),
+    }
+    drop(data_store);
+
+    // Data stores that do not exist can not be opened.
+    assert!(FSInteraction::open_read_only_with_fs("sub", fs.clone()).is_err());
+}
+
 fn has_data_item(items: &Vec<DataItem>, name: &str) -> bool {
     items
         .iter()
@@ -115,6 +219,42 @@ fn can_index_sub_directory() {
     assert!(has_data_item(&content, "sub/a"));
 }
 
+#[test]
+fn never_index_internal_paths() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let mut data_store = DefaultFSInteraction::create(test_dir.path()).unwrap();
+    data_store
+        .set_staging_dir(Some(test_dir.path().join("staging")), "store")
+        .unwrap();
+
+    // A store nested inside this store's tree.
+    fs::create_dir(test_dir.path().join("inner")).unwrap();
+    let _inner_store = DefaultFSInteraction::create(test_dir.path().join("inner")).unwrap();
+    fs::File::create(test_dir.path().join("inner/a.txt")).unwrap();
+    fs::File::create(test_dir.path().join("staging/b.txt")).unwrap();
+
+    let content = data_store.index(&RelativePath::from_path("")).unwrap();
+    assert!(has_data_item(&content, "inner"));
+    assert!(!has_data_item(&content, METADATA_DIR));
+    assert!(!has_data_item(&content, "staging"));
+
+    let content = data_store.index(&RelativePath::from_path("inner")).unwrap();
+    assert!(has_data_item(&content, "inner/a.txt"));
+    assert!(!has_data_item(&content, &format!("inner/{}", METADATA_DIR)));
+
+    for internal_path in &[
+        METADATA_DIR.to_string(),
+        format!("{}/{}", METADATA_DIR, METADATA_DB_FILE),
+        format!("{}/{}", METADATA_DIR, PENDING_FILES_DIR),
+        format!("{}/{}", METADATA_DIR, SNAPSHOT_DIR),
+        format!("inner/{}/{}", METADATA_DIR, METADATA_DB_FILE),
+        "staging/b.txt".to_string(),
+    ] {
+        assert!(data_store.is_internal_path(&RelativePath::from_path(internal_path)));
+    }
+    assert!(!data_store.is_internal_path(&RelativePath::from_path("inner/a.txt")));
+}
+
 #[test]
 fn detects_duplicates() {
     // Create some test content
@@ -252,3 +392,232 @@ fn moves_data_correctly<FS: virtual_fs::FS>(root_dir: &Path) {
     assert!(root_entries.iter().any(|item| item.file_name == "new-dir"));
     assert!(root_entries.iter().any(|item| item.file_name == "file"));
 }
+
+#[test]
+fn injected_errors_surface_per_path() {
+    let test_fs = InMemoryFS::default();
+    test_fs.create_dir("/sub", false).unwrap();
+    test_fs.create_file("/sub/file-1").unwrap();
+    test_fs.create_file("/sub/file-2").unwrap();
+
+    let data_store =
+        FSInteraction::<InMemoryFS>::create_with_fs(PathBuf::from("/"), test_fs.clone()).unwrap();
+
+    test_fs
+        .set_error_on(
+            "sub/file-1",
+            Operation::Read,
+            io::ErrorKind::PermissionDenied,
+        )
+        .unwrap();
+    let result = data_store.read_file(&RelativePath::from_path("sub/file-1"));
+    assert!(matches!(
+        result,
+        Err(FSInteractionError::IOError {
+            kind: io::ErrorKind::PermissionDenied,
+            ..
+        })
+    ));
+    assert!(data_store
+        .calculate_hash(&RelativePath::from_path("sub/file-1"))
+        .is_err());
+
+    // Other paths and operations are not affected...
+    assert!(data_store
+        .read_file(&RelativePath::from_path("sub/file-2"))
+        .is_ok());
+    assert!(data_store
+        .metadata(&RelativePath::from_path("sub/file-1"))
+        .is_ok());
+
+    // ...and clearing the error restores normal operation.
+    test_fs
+        .clear_error_on("sub/file-1", Operation::Read)
+        .unwrap();
+    assert!(data_store
+        .read_file(&RelativePath::from_path("sub/file-1"))
+        .is_ok());
+}
+
+#[test]
+fn injected_errors_surface_per_operation() {
+    let test_fs = InMemoryFS::default();
+    test_fs.create_file("/file-1").unwrap();
+
+    let data_store =
+        FSInteraction::<InMemoryFS>::create_with_fs(PathBuf::from("/"), test_fs.clone()).unwrap();
+
+    test_fs.set_error_on_all(Operation::Rename, io::ErrorKind::Other);
+    test_fs.set_error_on_all(Operation::CreateFile, io::ErrorKind::PermissionDenied);
+    assert!(data_store
+        .rename_file_or_directory(
+            &RelativePath::from_path("file-1"),
+            &RelativePath::from_path("file-2"),
+        )
+        .is_err());
+    let result = data_store.create_file(&RelativePath::from_path("file-3"));
+    assert!(matches!(
+        result,
+        Err(FSInteractionError::IOError {
+            kind: io::ErrorKind::PermissionDenied,
+            ..
+        })
+    ));
+    dir_has_entries(&test_fs, vec!["file-1"]);
+
+    test_fs.clear_errors();
+    data_store
+        .rename_file_or_directory(
+            &RelativePath::from_path("file-1"),
+            &RelativePath::from_path("file-2"),
+        )
+        .unwrap();
+    data_store
+        .create_file(&RelativePath::from_path("file-3"))
+        .unwrap();
+    dir_has_entries(&test_fs, vec!["file-2", "file-3"]);
+}
+
+#[test]
+fn round_trip_in_memory_snapshots() {
+    let test_fs = InMemoryFS::new();
+    test_fs.create_dir("sub", false).unwrap();
+    test_fs.create_file("sub/file-1").unwrap();
+    test_fs
+        .test_set_file_content("sub/file-1", "content", true)
+        .unwrap();
+    test_fs.create_file("file-2").unwrap();
+    test_fs
+        .update_metadata("file-2", FileTime::from_unix_time(42, 0), true)
+        .unwrap();
+
+    // Restored file systems are equal, including all time stamps...
+    let snapshot = test_fs.snapshot();
+    let restored_fs = InMemoryFS::from_snapshot(&snapshot);
+    assert_eq!(restored_fs.snapshot(), snapshot);
+    assert_eq!(
+        restored_fs.test_get_file_content("sub/file-1").unwrap(),
+        "content"
+    );
+    let metadata = restored_fs.metadata("file-2").unwrap();
+    assert_eq!(metadata.last_mod_time(), FileTime::from_unix_time(42, 0));
+    assert!(metadata.read_only());
+    // ...but independent of the original.
+    restored_fs.remove_file("file-2").unwrap();
+    assert!(test_fs.metadata("file-2").is_ok());
+
+    // Declarative fixtures compare equal if the trees match (ignoring time stamps).
+    let mut expected = virtual_fs::FsSnapshot::new()
+        .dir("sub")
+        .file("sub/file-1", "content")
+        .file("file-2", "");
+    expected
+        .items
+        .get_mut(Path::new("file-2"))
+        .unwrap()
+        .read_only = true;
+    virtual_fs::assert_tree_eq(&test_fs.snapshot(), &expected);
+    assert_eq!(
+        restored_fs.snapshot().tree_differences(&expected),
+        vec![PathBuf::from("file-2")]
+    );
+    assert!(!restored_fs
+        .snapshot()
+        .tree_eq(&expected.without("file-2").without("sub/file-1")));
+}
+
+fn dir_has_entries(test_fs: &InMemoryFS, expected_entries: Vec<&str>) {
+    let entries = test_fs.list_dir("").unwrap();
+    for expected_entry in expected_entries {
+        assert!(entries.iter().any(|item| item.file_name == expected_entry));
+    }
+}
+
+#[test]
+fn reject_names_addressing_other_folders() {
+    assert!(relative_path::is_valid_name("file-1"));
+    assert!(relative_path::is_valid_name(".hidden"));
+    assert!(relative_path::is_valid_name("..."));
+
+    assert!(!relative_path::is_valid_name(""));
+    assert!(!relative_path::is_valid_name("."));
+    assert!(!relative_path::is_valid_name(".."));
+    assert!(!relative_path::is_valid_name("../../etc"));
+    assert!(!relative_path::is_valid_name("sub/file"));
+    assert!(!relative_path::is_valid_name("..\\file"));
+}
+
+#[test]
+fn report_available_space_of_the_volume() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let data_store = DefaultFSInteraction::create(test_dir.path()).unwrap();
+    assert!(data_store.available_space().unwrap() > 0);
+
+    let missing_dir = test_dir.path().join("missing");
+    assert!(virtual_fs::WrapperFS::default()
+        .available_space(&missing_dir)
+        .is_err());
+}
+
+#[test]
+fn discard_unfinished_copies_in_memory() {
+    discard_unfinished_copies::<virtual_fs::InMemoryFS>(&PathBuf::new());
+}
+
+#[test]
+fn discard_unfinished_copies_wrapper() {
+    let test_dir = tempfile::tempdir().unwrap();
+    discard_unfinished_copies::<virtual_fs::WrapperFS>(test_dir.path());
+}
+
+fn discard_unfinished_copies<FS: virtual_fs::FS>(root_dir: &Path) {
+    let test_fs = FS::default();
+    for name in &["file-1", "file-2"] {
+        test_fs.create_file(root_dir.join(name)).unwrap();
+        test_fs
+            .overwrite_file(root_dir.join(name), Box::new(name.as_bytes()))
+            .unwrap();
+    }
+    let jobs = vec![
+        (root_dir.join("file-1"), root_dir.join("copy-1")),
+        (root_dir.join("file-2"), root_dir.join("copy-2")),
+    ];
+
+    // Joined copies are kept...
+    let results = test_fs
+        .start_copy_files_from(&test_fs, jobs.clone(), 2)
+        .join();
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert_eq!(test_fs.list_dir(root_dir).unwrap().len(), 4);
+
+    // ...copies dropped without joining them are discarded.
+    drop(test_fs.start_copy_files_from(&test_fs, jobs, 2));
+    let mut entries: Vec<_> = test_fs
+        .list_dir(root_dir)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.file_name)
+        .collect();
+    entries.sort();
+    assert_eq!(entries, vec!["file-1", "file-2"]);
+}
+
+#[test]
+fn remove_destinations_of_failed_copies() {
+    let test_fs = InMemoryFS::default();
+    test_fs.create_file("/file-1").unwrap();
+    test_fs.create_file("/file-2").unwrap();
+    test_fs
+        .set_error_on("copy-2", Operation::Write, io::ErrorKind::Other)
+        .unwrap();
+
+    let jobs = vec![
+        (PathBuf::from("/file-1"), PathBuf::from("/copy-1")),
+        (PathBuf::from("/file-2"), PathBuf::from("/copy-2")),
+    ];
+    let results = test_fs.start_copy_files_from(&test_fs, jobs, 1).join();
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(test_fs.metadata("/copy-1").is_ok());
+    assert!(test_fs.metadata("/copy-2").is_err());
+}
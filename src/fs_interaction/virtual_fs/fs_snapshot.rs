@@ -0,0 +1,126 @@
+use super::FileType;
+use filetime::FileTime;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Declarative view on the complete content of an InMemoryFS, i.e. a map of all paths
+/// (relative to the FS root, the root itself is the empty path) to their type and content.
+///
+/// Snapshots are taken using InMemoryFS::snapshot and turned into a new FS using
+/// InMemoryFS::from_snapshot. Test fixtures can also be written down directly, e.g.
+/// FsSnapshot::new().dir("sub").file("sub/file-1", "content").
+#[derive(Debug, Clone, PartialEq)]
+pub struct FsSnapshot {
+    pub items: BTreeMap<PathBuf, SnapshotItem>,
+}
+
+impl Default for FsSnapshot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotItem {
+    pub file_type: FileType,
+    pub content: Vec<u8>,
+    pub read_only: bool,
+    pub mod_time: FileTime,
+    pub creation_time: Option<FileTime>,
+}
+
+impl FsSnapshot {
+    /// Snapshot of an empty FS (only holding the root directory).
+    pub fn new() -> Self {
+        let mut items = BTreeMap::new();
+        items.insert(PathBuf::from(""), SnapshotItem::new(FileType::Dir, vec![]));
+
+        Self { items }
+    }
+
+    pub fn dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.items.insert(
+            path.as_ref().to_path_buf(),
+            SnapshotItem::new(FileType::Dir, vec![]),
+        );
+        self
+    }
+
+    pub fn file<P: AsRef<Path>>(mut self, path: P, content: &str) -> Self {
+        self.items.insert(
+            path.as_ref().to_path_buf(),
+            SnapshotItem::new(FileType::File, Vec::from(content)),
+        );
+        self
+    }
+
+    /// Leaves out the given sub-tree, e.g. the metadata dir of a data store.
+    pub fn without<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.items
+            .retain(|item_path, _| !item_path.starts_with(path.as_ref()));
+        self
+    }
+
+    /// Same as comparing the snapshots, but ignores the time stamps of all items.
+    pub fn tree_eq(&self, other: &Self) -> bool {
+        self.tree_differences(other).is_empty()
+    }
+
+    /// Lists all paths that are missing in one of the snapshots or differ in type, content or
+    /// read only flag (time stamps are ignored).
+    pub fn tree_differences(&self, other: &Self) -> Vec<PathBuf> {
+        let mut differences: Vec<_> = self
+            .items
+            .iter()
+            .filter(|(path, item)| {
+                other
+                    .items
+                    .get(*path)
+                    .is_none_or(|other_item| !item.tree_eq(other_item))
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        differences.extend(
+            other
+                .items
+                .keys()
+                .filter(|path| !self.items.contains_key(*path))
+                .cloned(),
+        );
+        differences.sort();
+
+        differences
+    }
+}
+
+impl SnapshotItem {
+    fn new(file_type: FileType, content: Vec<u8>) -> Self {
+        let time_now = FileTime::now();
+        Self {
+            file_type,
+            content,
+            read_only: false,
+            mod_time: time_now,
+            creation_time: Some(time_now),
+        }
+    }
+
+    fn tree_eq(&self, other: &Self) -> bool {
+        self.file_type == other.file_type
+            && self.content == other.content
+            && self.read_only == other.read_only
+    }
+}
+
+/// Asserts that both snapshots hold the same tree (ignoring time stamps), listing all
+/// differing paths otherwise.
+pub fn assert_tree_eq(actual: &FsSnapshot, expected: &FsSnapshot) {
+    let differences = actual.tree_differences(expected);
+    assert!(
+        differences.is_empty(),
+        "File trees differ at {:?}\nactual: {:?}\nexpected: {:?}",
+        differences,
+        actual.items.keys().collect::<Vec<_>>(),
+        expected.items.keys().collect::<Vec<_>>()
+    );
+}
@@ -16,6 +16,31 @@ pub struct InMemoryFS {
     // it and should be immutable to the outside, as all its actions/changes manifest in side
     // effects on the disk, similar to e.g. a database connection being non mut).
     items: Rc<RefCell<HashMap<PathBuf, InMemoryItem>>>,
+    // Errors injected by tests, keyed by the path (None for any path) and affected operation.
+    // Errors can be limited to a number of failures (None to fail until they are cleared).
+    injected_errors: Rc<RefCell<InjectedErrors>>,
+    // Simulated size of the volume in bytes (None for unlimited space).
+    capacity: Rc<RefCell<Option<u64>>>,
+    // Simulates a file system that does not report creation times if set to false.
+    creation_times_supported: Rc<RefCell<bool>>,
+}
+type InjectedErrors = HashMap<(Option<PathBuf>, Operation), InjectedError>;
+type InjectedError = (io::ErrorKind, Option<usize>);
+
+/// The operations of the InMemoryFS that errors can be injected into (see set_error_on).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Operation {
+    Metadata,
+    UpdateMetadata,
+    CreateDir,
+    RemoveDir,
+    ListDir,
+    CreateFile,
+    RemoveFile,
+    Rename,
+    HardLink,
+    Read,
+    Write,
 }
 
 impl InMemoryFS {
@@ -28,9 +53,131 @@ impl InMemoryFS {
 
         InMemoryFS {
             items: Rc::new(RefCell::new(initial_items)),
+            injected_errors: Rc::new(RefCell::new(HashMap::new())),
+            capacity: Rc::new(RefCell::new(None)),
+            creation_times_supported: Rc::new(RefCell::new(true)),
         }
     }
 
+    /// Makes all future calls of the given operation on the given path fail with an IO error
+    /// of the given kind (until the error is cleared again).
+    /// For renames, both the source and destination path are checked.
+    pub fn set_error_on<P: AsRef<Path>>(
+        &self,
+        path: P,
+        operation: Operation,
+        error_kind: io::ErrorKind,
+    ) -> io::Result<()> {
+        let path = self.canonicalize(path)?;
+        self.injected_errors
+            .borrow_mut()
+            .insert((Some(path), operation), (error_kind, None));
+
+        Ok(())
+    }
+    /// Same as set_error_on, but only fails the next given number of calls, e.g. to simulate
+    /// transient errors that are gone when retrying the operation.
+    pub fn set_error_on_next<P: AsRef<Path>>(
+        &self,
+        path: P,
+        operation: Operation,
+        error_kind: io::ErrorKind,
+        failures: usize,
+    ) -> io::Result<()> {
+        let path = self.canonicalize(path)?;
+        if failures == 0 {
+            return Ok(());
+        }
+        self.injected_errors
+            .borrow_mut()
+            .insert((Some(path), operation), (error_kind, Some(failures)));
+
+        Ok(())
+    }
+    /// Same as set_error_on, but fails the operation on ANY path.
+    pub fn set_error_on_all(&self, operation: Operation, error_kind: io::ErrorKind) {
+        self.injected_errors
+            .borrow_mut()
+            .insert((None, operation), (error_kind, None));
+    }
+    /// Simulates a read-only mount, i.e. all operations changing the FS fail (until the errors
+    /// are cleared again).
+    pub fn set_read_only_mount(&self) {
+        for operation in &[
+            Operation::UpdateMetadata,
+            Operation::CreateDir,
+            Operation::RemoveDir,
+            Operation::CreateFile,
+            Operation::RemoveFile,
+            Operation::Rename,
+            Operation::HardLink,
+            Operation::Write,
+        ] {
+            self.set_error_on_all(*operation, io::ErrorKind::PermissionDenied);
+        }
+    }
+    pub fn clear_error_on<P: AsRef<Path>>(&self, path: P, operation: Operation) -> io::Result<()> {
+        let path = self.canonicalize(path)?;
+        self.injected_errors
+            .borrow_mut()
+            .remove(&(Some(path), operation));
+
+        Ok(())
+    }
+    pub fn clear_errors(&self) {
+        self.injected_errors.borrow_mut().clear();
+    }
+
+    /// Simulates a volume of the given size in bytes, i.e. available_space reports the capacity
+    /// minus the size of all file contents. Writes are NOT limited by it.
+    pub fn set_capacity(&self, capacity: Option<u64>) {
+        *self.capacity.borrow_mut() = capacity;
+    }
+
+    /// Simulates a file system without creation times (e.g. ext4 without crtime), i.e. the
+    /// metadata of all items reports no creation time if set to false.
+    pub fn set_creation_times_supported(&self, supported: bool) {
+        *self.creation_times_supported.borrow_mut() = supported;
+    }
+
+    /// Captures all items of the FS (see FsSnapshot), e.g. to compare trees in tests.
+    pub fn snapshot(&self) -> FsSnapshot {
+        let items = RefCell::borrow(&self.items)
+            .iter()
+            .map(|(path, item)| {
+                let snapshot_item = SnapshotItem {
+                    file_type: item.metadata.file_type,
+                    content: item.data.clone(),
+                    read_only: item.metadata.read_only,
+                    mod_time: item.metadata.last_mod_time,
+                    creation_time: item.metadata.creation_time,
+                };
+                (path.clone(), snapshot_item)
+            })
+            .collect();
+
+        FsSnapshot { items }
+    }
+
+    /// Creates a new FS holding exactly the items of the snapshot (without injected errors).
+    pub fn from_snapshot(snapshot: &FsSnapshot) -> InMemoryFS {
+        let fs = Self::new();
+        {
+            let mut items = fs.items.borrow_mut();
+            for (path, snapshot_item) in &snapshot.items {
+                let mut item = InMemoryItem::new(path.clone(), snapshot_item.file_type);
+                item.metadata.read_only = snapshot_item.read_only;
+                item.metadata.last_acc_time = snapshot_item.mod_time;
+                item.metadata.last_mod_time = snapshot_item.mod_time;
+                item.metadata.creation_time = snapshot_item.creation_time;
+                item.data = snapshot_item.content.clone();
+                items.insert(path.clone(), item);
+            }
+        }
+
+        fs
+    }
+
     pub fn test_set_file_content<P: AsRef<Path>>(
         &self,
         path: P,
@@ -73,6 +220,50 @@ impl InMemoryFS {
         }
     }
 
+    pub fn test_set_creation_time<P: AsRef<Path>>(
+        &self,
+        path: P,
+        creation_time: FileTime,
+    ) -> io::Result<()> {
+        let path = self.canonicalize(path)?;
+
+        if let Some(item) = self.items.borrow_mut().get_mut(&path) {
+            item.metadata.creation_time = Some(creation_time);
+            Ok(())
+        } else {
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        }
+    }
+
+    fn check_injected_error<P: AsRef<Path>>(
+        &self,
+        path: P,
+        operation: Operation,
+    ) -> io::Result<()> {
+        let mut injected_errors = self.injected_errors.borrow_mut();
+        let path_key = (Some(path.as_ref().to_path_buf()), operation);
+        let key = if injected_errors.contains_key(&path_key) {
+            path_key
+        } else {
+            (None, operation)
+        };
+
+        let (error_kind, remaining_failures) = match injected_errors.get_mut(&key) {
+            Some((error_kind, remaining_failures)) => {
+                if let Some(remaining_failures) = remaining_failures.as_mut() {
+                    *remaining_failures -= 1;
+                }
+                (*error_kind, *remaining_failures)
+            }
+            None => return Ok(()),
+        };
+        if remaining_failures == Some(0) {
+            injected_errors.remove(&key);
+        }
+
+        Err(io::Error::from(error_kind))
+    }
+
     fn is_root<P: AsRef<Path>>(&self, path: P) -> bool {
         path.as_ref().as_os_str() == "/"
     }
@@ -106,6 +297,9 @@ impl Clone for InMemoryFS {
     fn clone(&self) -> Self {
         Self {
             items: Rc::clone(&self.items),
+            injected_errors: Rc::clone(&self.injected_errors),
+            capacity: Rc::clone(&self.capacity),
+            creation_times_supported: Rc::clone(&self.creation_times_supported),
         }
     }
 }
@@ -126,9 +320,15 @@ impl FS for InMemoryFS {
     }
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::Metadata)?;
 
         if let Some(item) = self.items.borrow_mut().deref().get(&path) {
-            Ok(item.metadata.clone())
+            let mut metadata = item.metadata.clone();
+            metadata.size = item.data.len() as u64;
+            if !*RefCell::borrow(&self.creation_times_supported) {
+                metadata.creation_time = None;
+            }
+            Ok(metadata)
         } else {
             Err(io::Error::from(io::ErrorKind::NotFound))
         }
@@ -140,6 +340,7 @@ impl FS for InMemoryFS {
         read_only: bool,
     ) -> io::Result<()> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::UpdateMetadata)?;
 
         if let Some(item) = self.items.borrow_mut().deref_mut().get_mut(&path) {
             item.metadata.last_mod_time = mod_time;
@@ -152,6 +353,7 @@ impl FS for InMemoryFS {
 
     fn create_dir<P: AsRef<Path>>(&self, path: P, ignore_existing: bool) -> io::Result<()> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::CreateDir)?;
 
         if self.is_root(&path) || self.parent_exists(&path) {
             if self.items.borrow_mut().deref().contains_key(&path) {
@@ -174,6 +376,7 @@ impl FS for InMemoryFS {
     }
     fn remove_dir_recursive<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::RemoveDir)?;
 
         if self.is_root(&path) {
             Err(io::Error::from(io::ErrorKind::PermissionDenied))
@@ -196,6 +399,7 @@ impl FS for InMemoryFS {
     }
     fn list_dir<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<DirEntry>> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::ListDir)?;
         let items = self.items.borrow_mut();
 
         let dir_item = items.deref().get(&path);
@@ -227,6 +431,7 @@ impl FS for InMemoryFS {
 
     fn create_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::CreateFile)?;
 
         if self.is_root(&path) || self.parent_exists(&path) {
             if self.items.borrow_mut().deref().contains_key(&path) {
@@ -244,6 +449,7 @@ impl FS for InMemoryFS {
     }
     fn remove_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::RemoveFile)?;
 
         if self.is_root(&path) || self.children_exist(&path) {
             return Err(io::Error::from(io::ErrorKind::PermissionDenied));
@@ -261,6 +467,8 @@ impl FS for InMemoryFS {
     ) -> io::Result<()> {
         let source_path = self.canonicalize(source_path)?;
         let dest_path = self.canonicalize(dest_path)?;
+        self.check_injected_error(&source_path, Operation::Rename)?;
+        self.check_injected_error(&dest_path, Operation::Rename)?;
 
         let source_parent_exists = self.is_root(&source_path) || self.parent_exists(&source_path);
         let dest_parent_exists = self.is_root(&dest_path) || self.parent_exists(&dest_path);
@@ -299,8 +507,25 @@ impl FS for InMemoryFS {
         Ok(())
     }
 
+    fn hard_link<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        source_path: P1,
+        dest_path: P2,
+    ) -> io::Result<()> {
+        let source_path = self.canonicalize(source_path)?;
+        let dest_path = self.canonicalize(dest_path)?;
+        self.check_injected_error(&source_path, Operation::HardLink)?;
+        self.check_injected_error(&dest_path, Operation::HardLink)?;
+
+        // We do not model shared data between items, i.e. we behave like a FS without support.
+        Err(io::Error::other(
+            "hard links are not supported by the in memory FS",
+        ))
+    }
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Box<dyn io::Read>> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::Read)?;
 
         if let Some(item) = self.items.borrow_mut().get(&path) {
             Ok(Box::new(std::io::Cursor::new(item.data.clone())))
@@ -314,6 +539,7 @@ impl FS for InMemoryFS {
         mut data: Box<dyn io::Read + 'a>,
     ) -> io::Result<usize> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::Write)?;
 
         if let Some(item) = self.items.borrow_mut().get_mut(&path) {
             item.data.clear();
@@ -330,6 +556,7 @@ impl FS for InMemoryFS {
         mut data: Box<dyn io::Read + 'a>,
     ) -> io::Result<usize> {
         let path = self.canonicalize(path)?;
+        self.check_injected_error(&path, Operation::Write)?;
 
         if let Some(item) = self.items.borrow_mut().get_mut(&path) {
             let bytes_written = data.read_to_end(&mut item.data)?;
@@ -340,6 +567,45 @@ impl FS for InMemoryFS {
         }
     }
 
+    fn start_copy_files_from(
+        &self,
+        source: &Self,
+        jobs: Vec<(PathBuf, PathBuf)>,
+        _max_parallel: usize,
+    ) -> BackgroundCopy {
+        // The in memory FS is not thread safe, copy one file after the other.
+        let results = jobs
+            .iter()
+            .map(|(source_path, dest_path)| {
+                let data = source.read_file(source_path)?;
+                if let Err(error) = self.create_file(dest_path) {
+                    if error.kind() != io::ErrorKind::AlreadyExists {
+                        return Err(error);
+                    }
+                }
+                Ok(self.overwrite_file(dest_path, data)? as u64)
+            })
+            .collect();
+
+        BackgroundCopy::finished(self, &jobs, results)
+    }
+
+    fn available_space<P: AsRef<Path>>(&self, _path: P) -> io::Result<u64> {
+        if let Some(capacity) = *RefCell::borrow(&self.capacity) {
+            let used_space: u64 = RefCell::borrow(&self.items)
+                .values()
+                .map(|item| item.data.len() as u64)
+                .sum();
+            Ok(capacity.saturating_sub(used_space))
+        } else {
+            Ok(u64::MAX)
+        }
+    }
+
+    fn shares_file_system(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.items, &other.items)
+    }
+
     fn db_access_type(&self) -> DBAccessType {
         DBAccessType::InMemory
     }
@@ -361,7 +627,10 @@ impl InMemoryItem {
                 file_type: file_type,
                 last_acc_time: time_now.clone(),
                 last_mod_time: time_now.clone(),
-                creation_time: time_now.clone(),
+                creation_time: Some(time_now),
+                size: 0,
+                inode: None,
+                link_count: 1,
             },
             path: item_path,
             data: Vec::new(),
@@ -1,6 +1,8 @@
 use filetime::FileTime;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 /// Virtual abstraction layer above the actual FS implementation and API.
 ///
@@ -10,7 +12,7 @@ use std::path::{Path, PathBuf};
 ///
 /// We only wrap/implement functions we actually require in our code. This can be less or sometimes
 /// more than the std::fs module provides (e.g. we would like to be able to set times on files).
-pub trait FS: Clone {
+pub trait FS: Clone + 'static {
     fn default() -> Self;
 
     fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf>;
@@ -36,6 +38,14 @@ pub trait FS: Clone {
         dest_path: P2,
     ) -> io::Result<()>;
 
+    /// Creates a hard link at dest_path pointing to the file at source_path.
+    /// Fails if the FS does not support hard links.
+    fn hard_link<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        source_path: P1,
+        dest_path: P2,
+    ) -> io::Result<()>;
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Box<dyn io::Read>>;
     fn overwrite_file<'a, P: AsRef<Path>>(
         &self,
@@ -47,6 +57,27 @@ pub trait FS: Clone {
         path: P,
         data: Box<dyn io::Read + 'a>,
     ) -> io::Result<usize>;
+
+    /// Copies the (source, destination) files of the jobs from the source FS into this FS in the
+    /// background, running up to max_parallel copies at once. Destinations are created or
+    /// truncated. Holes of sparse files should be kept where the platform supports it.
+    /// Join the returned copy to wait for the results in job order (see BackgroundCopy).
+    /// FS implementations that are not thread safe copy the files before returning.
+    fn start_copy_files_from(
+        &self,
+        source: &Self,
+        jobs: Vec<(PathBuf, PathBuf)>,
+        max_parallel: usize,
+    ) -> BackgroundCopy;
+
+    /// Returns the number of bytes that can still be written to the volume holding path.
+    /// Returns u64::MAX if the available space can not be determined on the platform.
+    fn available_space<P: AsRef<Path>>(&self, path: P) -> io::Result<u64>;
+
+    /// Checks if both instances work on the same underlying file system, i.e. if equal paths
+    /// refer to the same files in both of them.
+    fn shares_file_system(&self, other: &Self) -> bool;
+
     fn db_access_type(&self) -> DBAccessType;
 }
 
@@ -68,7 +99,12 @@ pub struct Metadata {
     read_only: bool,
     last_acc_time: FileTime,
     last_mod_time: FileTime,
-    creation_time: FileTime,
+    // None if the file system does not report creation times (e.g. ext4 without crtime).
+    creation_time: Option<FileTime>,
+    size: u64,
+    // (device, inode) pair identifying the file on disk, None if not supported on the platform.
+    inode: Option<(u64, u64)>,
+    link_count: u64,
 }
 impl Metadata {
     pub fn file_type(&self) -> FileType {
@@ -90,9 +126,25 @@ impl Metadata {
     pub fn last_mod_time(&self) -> FileTime {
         self.last_mod_time
     }
-    pub fn creation_time(&self) -> FileTime {
+    /// The creation time of the item, None if the file system does not support it.
+    pub fn creation_time(&self) -> Option<FileTime> {
         self.creation_time
     }
+    /// The creation time of the item, falling back to its modification time if the
+    /// file system does not report creation times.
+    pub fn creation_or_mod_time(&self) -> FileTime {
+        self.creation_time.unwrap_or(self.last_mod_time)
+    }
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn inode(&self) -> Option<(u64, u64)> {
+        self.inode
+    }
+    /// Number of hard links pointing to the item (1 for regular files).
+    pub fn link_count(&self) -> u64 {
+        self.link_count
+    }
 
     pub fn set_read_only(&mut self, read_only: bool) {
         self.read_only = read_only;
@@ -101,6 +153,136 @@ impl Metadata {
         self.last_mod_time = last_mod_time;
     }
 }
+// Removes the destination files of discarded copies on the FS that copied them.
+type RemoveFile = Box<dyn Fn(&Path) -> io::Result<()>>;
+
+/// File copies started by FS::start_copy_files_from, some of them might still be running.
+///
+/// Destinations of failed copies are removed when joining, i.e. no partially written files are
+/// left behind. Dropping the copy without joining it waits for all copies and discards them,
+/// i.e. removes all destination files (e.g. if the operation that started them fails).
+pub struct BackgroundCopy {
+    destinations: Vec<PathBuf>,
+    workers: Vec<std::thread::JoinHandle<()>>,
+    results: Arc<Mutex<Vec<Option<io::Result<u64>>>>>,
+    remove_file: RemoveFile,
+}
+impl BackgroundCopy {
+    /// Runs the jobs on up to max_parallel threads, each copying one job after the other.
+    pub fn running<F, C>(
+        fs: &F,
+        jobs: Vec<(PathBuf, PathBuf)>,
+        max_parallel: usize,
+        copy_file: C,
+    ) -> Self
+    where
+        F: FS,
+        C: Fn(&Path, &Path) -> io::Result<u64> + Send + Sync + 'static,
+    {
+        let destinations = jobs
+            .iter()
+            .map(|(_, dest_path)| dest_path.clone())
+            .collect();
+        let results: Vec<Option<io::Result<u64>>> = jobs.iter().map(|_| None).collect();
+        let results = Arc::new(Mutex::new(results));
+        let jobs = Arc::new(jobs);
+        let next_job = Arc::new(AtomicUsize::new(0));
+        let copy_file = Arc::new(copy_file);
+
+        let workers = (0..std::cmp::min(std::cmp::max(max_parallel, 1), jobs.len()))
+            .map(|_| {
+                let (jobs, results) = (jobs.clone(), results.clone());
+                let (next_job, copy_file) = (next_job.clone(), copy_file.clone());
+                std::thread::spawn(move || loop {
+                    let job_index = next_job.fetch_add(1, Ordering::SeqCst);
+                    if job_index >= jobs.len() {
+                        break;
+                    }
+                    let (source_path, dest_path) = &jobs[job_index];
+                    let result = copy_file(source_path, dest_path);
+                    results.lock().unwrap()[job_index] = Some(result);
+                })
+            })
+            .collect();
+
+        Self {
+            destinations,
+            workers,
+            results,
+            remove_file: Self::remove_file_on(fs),
+        }
+    }
+
+    /// Wraps copies that already ran, e.g. on FS implementations that are not thread safe.
+    pub fn finished<F: FS>(
+        fs: &F,
+        jobs: &[(PathBuf, PathBuf)],
+        results: Vec<io::Result<u64>>,
+    ) -> Self {
+        Self {
+            destinations: jobs
+                .iter()
+                .map(|(_, dest_path)| dest_path.clone())
+                .collect(),
+            workers: vec![],
+            results: Arc::new(Mutex::new(results.into_iter().map(Some).collect())),
+            remove_file: Self::remove_file_on(fs),
+        }
+    }
+
+    /// Waits for all copies to finish and returns their results in job order.
+    pub fn join(mut self) -> Vec<io::Result<u64>> {
+        let results = self.wait();
+        let destinations = std::mem::take(&mut self.destinations);
+        for (dest_path, result) in destinations.iter().zip(&results) {
+            if result.is_err() {
+                self.remove_destination(dest_path);
+            }
+        }
+
+        results
+    }
+
+    fn wait(&mut self) -> Vec<io::Result<u64>> {
+        for worker in self.workers.drain(..) {
+            // Panicked copies leave their result empty, these are reported as errors below.
+            let _ = worker.join();
+        }
+        self.results
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|result| result.unwrap_or_else(|| Err(io::Error::other("copy thread panicked"))))
+            .collect()
+    }
+
+    // Best effort cleanup, the destination might not even be created yet.
+    fn remove_destination(&self, dest_path: &Path) {
+        if let Err(error) = (self.remove_file)(dest_path) {
+            if error.kind() != io::ErrorKind::NotFound {
+                warn!(
+                    "Could not remove the unfinished copy {}: {:?}",
+                    dest_path.display(),
+                    error
+                );
+            }
+        }
+    }
+
+    fn remove_file_on<F: FS>(fs: &F) -> RemoveFile {
+        let fs = fs.clone();
+        Box::new(move |path| fs.remove_file(path))
+    }
+}
+impl Drop for BackgroundCopy {
+    fn drop(&mut self) {
+        self.wait();
+        for dest_path in std::mem::take(&mut self.destinations) {
+            self.remove_destination(&dest_path);
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum FileType {
     File,
@@ -126,6 +308,9 @@ mod wrapper_fs;
 pub use self::wrapper_fs::WrapperFS;
 
 mod in_memory_fs;
-pub use self::in_memory_fs::InMemoryFS;
+pub use self::in_memory_fs::{InMemoryFS, Operation};
+
+mod fs_snapshot;
+pub use self::fs_snapshot::{assert_tree_eq, FsSnapshot, SnapshotItem};
 use std::ffi::OsString;
 use std::io::BufReader;
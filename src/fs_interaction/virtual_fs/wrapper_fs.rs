@@ -1,11 +1,77 @@
 use super::*;
 use std::fs;
 
+// Granularity in which sparse files are checked for holes when copying them.
+const SPARSE_BLOCK_SIZE: usize = 4096;
+
 #[derive(Clone)]
-pub struct WrapperFS {}
+pub struct WrapperFS {
+    // Work on a local copy of the metadata DB (see DBAccessType::TmpCopy).
+    tmp_copy_db: bool,
+}
+impl WrapperFS {
+    #[allow(clippy::new_without_default)] // FS::default() would clash with Default::default().
+    pub fn new() -> Self {
+        Self { tmp_copy_db: false }
+    }
+
+    /// Accesses the disk like new(), but keeps the store's metadata DB in a local working
+    /// copy that is written back when the store is closed (for volumes SQLite can not run on).
+    pub fn with_tmp_copy_db() -> Self {
+        Self { tmp_copy_db: true }
+    }
+
+    /// Copies the content of the source file into the (created or truncated) destination.
+    /// Sparse source files are copied sparse-aware: blocks of zeros are seeked over instead of
+    /// written, which keeps them as holes on file systems supporting them. Falls back to a
+    /// dense copy if the platform does not report sparse files.
+    fn copy_file_content(source_path: &Path, dest_path: &Path) -> io::Result<u64> {
+        use std::io::{Read, Seek, Write};
+
+        let mut reader = fs::File::open(source_path)?;
+        let mut writer = fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(dest_path)?;
+        if !Self::is_sparse(&reader.metadata()?) {
+            return io::copy(&mut reader, &mut writer);
+        }
+
+        let mut buffer = vec![0u8; SPARSE_BLOCK_SIZE];
+        let mut copied = 0;
+        loop {
+            let count = reader.read(&mut buffer)?;
+            if count == 0 {
+                break;
+            }
+            if buffer[..count].iter().all(|byte| *byte == 0) {
+                writer.seek(io::SeekFrom::Current(count as i64))?;
+            } else {
+                writer.write_all(&buffer[..count])?;
+            }
+            copied += count as u64;
+        }
+        // Trailing holes are only seeked over, the length makes them part of the file.
+        writer.set_len(copied)?;
+
+        Ok(copied)
+    }
+
+    #[cfg(unix)]
+    fn is_sparse(metadata: &fs::Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        // Blocks are always counted in units of 512 bytes.
+        metadata.blocks() * 512 < metadata.len()
+    }
+    #[cfg(not(unix))]
+    fn is_sparse(_metadata: &fs::Metadata) -> bool {
+        false
+    }
+}
 impl FS for WrapperFS {
     fn default() -> Self {
-        Self {}
+        Self::new()
     }
 
     fn canonicalize<P: AsRef<Path>>(&self, path: P) -> io::Result<PathBuf> {
@@ -13,6 +79,16 @@ impl FS for WrapperFS {
     }
     fn metadata<P: AsRef<Path>>(&self, path: P) -> io::Result<Metadata> {
         let native_metadata = fs::symlink_metadata(path)?;
+        #[cfg(unix)]
+        let (inode, link_count) = {
+            use std::os::unix::fs::MetadataExt;
+            (
+                Some((native_metadata.dev(), native_metadata.ino())),
+                native_metadata.nlink(),
+            )
+        };
+        #[cfg(not(unix))]
+        let (inode, link_count) = (None, 1);
 
         Ok(Metadata {
             read_only: native_metadata.permissions().readonly(),
@@ -24,9 +100,10 @@ impl FS for WrapperFS {
             },
             last_acc_time: FileTime::from_last_access_time(&native_metadata),
             last_mod_time: FileTime::from_last_modification_time(&native_metadata),
-            creation_time: FileTime::from_creation_time(&native_metadata)
-                .or_else(|| Some(FileTime::zero()))
-                .unwrap(),
+            creation_time: FileTime::from_creation_time(&native_metadata),
+            size: native_metadata.len(),
+            inode,
+            link_count,
         })
     }
     fn update_metadata<P: AsRef<Path>>(
@@ -96,6 +173,14 @@ impl FS for WrapperFS {
         }
     }
 
+    fn hard_link<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        source_path: P1,
+        dest_path: P2,
+    ) -> io::Result<()> {
+        fs::hard_link(source_path, dest_path)
+    }
+
     fn read_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Box<dyn io::Read>> {
         let reader = fs::OpenOptions::new()
             .create(false)
@@ -140,7 +225,42 @@ impl FS for WrapperFS {
         Ok(bytes_written as usize)
     }
 
+    fn start_copy_files_from(
+        &self,
+        _source: &Self,
+        jobs: Vec<(PathBuf, PathBuf)>,
+        max_parallel: usize,
+    ) -> BackgroundCopy {
+        BackgroundCopy::running(self, jobs, max_parallel, Self::copy_file_content)
+    }
+
+    #[cfg(unix)]
+    fn available_space<P: AsRef<Path>>(&self, path: P) -> io::Result<u64> {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::ffi::CString::new(path.as_ref().as_os_str().as_bytes())?;
+        let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stats) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Blocks available to unprivileged users, counted in fragments of f_frsize bytes.
+        Ok((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+    }
+    #[cfg(not(unix))]
+    fn available_space<P: AsRef<Path>>(&self, _path: P) -> io::Result<u64> {
+        Ok(u64::MAX)
+    }
+
+    fn shares_file_system(&self, _other: &Self) -> bool {
+        true
+    }
+
     fn db_access_type(&self) -> DBAccessType {
-        DBAccessType::InPlace
+        if self.tmp_copy_db {
+            DBAccessType::TmpCopy
+        } else {
+            DBAccessType::InPlace
+        }
     }
 }
@@ -1,11 +1,18 @@
 extern crate chrono;
 extern crate data_encoding;
 extern crate glob;
+#[cfg(unix)]
+extern crate libc;
+#[macro_use]
+extern crate log;
 #[macro_use]
 extern crate diesel;
 extern crate filetime;
 extern crate ring;
+#[macro_use]
+extern crate serde_json;
 extern crate tempfile;
+extern crate unicode_normalization;
 extern crate uuid;
 
 pub mod data_store;
@@ -81,6 +81,12 @@ pub struct ItemFSMetadata {
     pub hash: String,
 
     pub is_read_only: bool,
+
+    // Shared by all files that are hard links of each other (None for regular files).
+    pub hard_link_group: Option<String>,
+
+    // Size of the file content in bytes (0 for folders).
+    pub size: u64,
 }
 
 impl DBItem {
@@ -157,6 +163,10 @@ impl DBItem {
             hash: metadata.hash,
 
             is_read_only: metadata.is_read_only,
+
+            hard_link_group: metadata.hard_link_group,
+
+            size: metadata.size as u64,
         }
     }
 
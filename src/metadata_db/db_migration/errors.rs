@@ -4,9 +4,23 @@ use std::fmt;
 
 #[derive(Debug)]
 pub enum MigrationError {
-    ReadWriteDBVersion { source: diesel::result::Error },
-    UnknownDBVersion { version: DBVersion },
-    SQLError { source: diesel::result::Error },
+    ReadWriteDBVersion {
+        source: diesel::result::Error,
+    },
+    UnknownDBVersion {
+        version: DBVersion,
+    },
+    IncompatibleDBVersion {
+        version: DBVersion,
+    },
+    /// The DB was created or upgraded by a newer build, its schema is unknown to this build.
+    DBVersionTooNew {
+        found: DBVersion,
+        supported: DBVersion,
+    },
+    SQLError {
+        source: diesel::result::Error,
+    },
 }
 pub type Result<T> = std::result::Result<T, MigrationError>;
 
@@ -26,6 +40,8 @@ impl Error for MigrationError {
         match self {
             Self::ReadWriteDBVersion { ref source } => Some(source),
             Self::UnknownDBVersion { .. } => None,
+            Self::IncompatibleDBVersion { .. } => None,
+            Self::DBVersionTooNew { .. } => None,
             Self::SQLError { ref source } => Some(source),
         }
     }
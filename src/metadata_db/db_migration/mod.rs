@@ -4,6 +4,19 @@
 /// upgrade_db(&connection); // upgrades to latest DB version
 mod version_001;
 mod version_002;
+mod version_003;
+mod version_004;
+mod version_005;
+mod version_006;
+mod version_007;
+mod version_008;
+mod version_009;
+mod version_010;
+mod version_011;
+mod version_012;
+mod version_013;
+mod version_014;
+mod version_015;
 
 mod errors;
 pub use self::errors::*;
@@ -13,7 +26,7 @@ use diesel::sql_query;
 use diesel::sqlite::SqliteConnection;
 
 pub type DBVersion = i32;
-const REQUIRED_DB_VERSION: DBVersion = 2;
+const REQUIRED_DB_VERSION: DBVersion = 15;
 
 /// Upgrades the given database connection to the REQUIRED_DB_VERSION of the
 /// current application build.
@@ -22,7 +35,9 @@ const REQUIRED_DB_VERSION: DBVersion = 2;
 /// used to step-by-step keep database files up to date with the application.
 ///
 /// MUST be run before any other action on the database to make sure it's compatible.
+/// Databases of a newer version are refused, as this build does not know their schema.
 pub fn upgrade_db(conn: &SqliteConnection) -> Result<DBVersion> {
+    check_db_not_too_new(read_db_version(conn)?)?;
     loop {
         let current_version = read_db_version(&conn)?;
         if current_version < REQUIRED_DB_VERSION {
@@ -33,6 +48,31 @@ pub fn upgrade_db(conn: &SqliteConnection) -> Result<DBVersion> {
     }
 }
 
+/// Checks that the given database connection is at the REQUIRED_DB_VERSION without
+/// modifying it. Used for secondary connections that must not run migrations themselves.
+pub fn check_db_version(conn: &SqliteConnection) -> Result<DBVersion> {
+    let current_version = read_db_version(conn)?;
+    check_db_not_too_new(current_version)?;
+    if current_version == REQUIRED_DB_VERSION {
+        Ok(current_version)
+    } else {
+        Err(MigrationError::IncompatibleDBVersion {
+            version: current_version,
+        })
+    }
+}
+
+fn check_db_not_too_new(version: DBVersion) -> Result<()> {
+    if version > REQUIRED_DB_VERSION {
+        return Err(MigrationError::DBVersionTooNew {
+            found: version,
+            supported: REQUIRED_DB_VERSION,
+        });
+    }
+
+    Ok(())
+}
+
 /// Migrates the given database connection from the DBVersion version to (version + 1).
 /// Expects the database to be in the given version and updates the user_version pragma
 /// to the new (version + 1) value if successful.
@@ -44,6 +84,19 @@ fn migrate_up_from(conn: &SqliteConnection, version: DBVersion) -> Result<()> {
         // Just run the know migration steps as a regular functions.
         0 => version_001::migrate(&conn)?,
         1 => version_002::migrate(&conn)?,
+        2 => version_003::migrate(conn)?,
+        3 => version_004::migrate(conn)?,
+        4 => version_005::migrate(conn)?,
+        5 => version_006::migrate(conn)?,
+        6 => version_007::migrate(conn)?,
+        7 => version_008::migrate(conn)?,
+        8 => version_009::migrate(conn)?,
+        9 => version_010::migrate(conn)?,
+        10 => version_011::migrate(conn)?,
+        11 => version_012::migrate(conn)?,
+        12 => version_013::migrate(conn)?,
+        13 => version_014::migrate(conn)?,
+        14 => version_015::migrate(conn)?,
         // We do not know how to handle this migration.
         _ => return Err(MigrationError::UnknownDBVersion { version }),
     };
@@ -60,6 +60,275 @@ fn properly_upgrade_to_version_2() {
     assert_eq!(read_db_version(&conn).unwrap(), 2);
 }
 
+#[test]
+fn properly_upgrade_to_version_3() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 3);
+}
+
+#[test]
+fn properly_upgrade_to_version_4() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 4);
+}
+
+#[test]
+fn properly_upgrade_to_version_5() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 5);
+}
+
+#[test]
+fn properly_upgrade_to_version_6() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 6);
+}
+
+#[test]
+fn properly_upgrade_to_version_7() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    // Any matching ignore rule used to exclude an item, independent of the rule order.
+    sql_query(
+        "INSERT INTO inclusion_rules(id, data_store_id, rule_glob, include)
+         VALUES (1, 1, '/build/**', 0), (2, 1, '**', 1)",
+    )
+    .execute(&conn)
+    .unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 7);
+
+    // Ignore rules are positioned last, their ids stay the same.
+    use diesel::sql_types::BigInt;
+    #[derive(Debug, QueryableByName)]
+    struct Rule {
+        #[sql_type = "BigInt"]
+        id: i64,
+        #[sql_type = "BigInt"]
+        position: i64,
+    }
+    let rules: Vec<Rule> = sql_query("SELECT id, position FROM inclusion_rules ORDER BY position")
+        .load(&conn)
+        .unwrap();
+    let rules: Vec<_> = rules.iter().map(|rule| (rule.id, rule.position)).collect();
+    assert_eq!(rules, vec![(2, 2), (1, 3)]);
+}
+
+#[test]
+fn properly_upgrade_to_version_8() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 8);
+}
+
+#[test]
+fn properly_upgrade_to_version_9() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 9);
+}
+
+#[test]
+fn properly_upgrade_to_version_10() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 10);
+}
+
+#[test]
+fn properly_upgrade_to_version_11() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+    migrate_up_from(&conn, 10).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 11);
+}
+
+#[test]
+fn properly_upgrade_to_version_12() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+    migrate_up_from(&conn, 10).unwrap();
+    migrate_up_from(&conn, 11).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 12);
+}
+
+#[test]
+fn properly_upgrade_to_version_13() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+    migrate_up_from(&conn, 10).unwrap();
+    migrate_up_from(&conn, 11).unwrap();
+    migrate_up_from(&conn, 12).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 13);
+}
+
+#[test]
+fn properly_upgrade_to_version_14() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+    migrate_up_from(&conn, 10).unwrap();
+    migrate_up_from(&conn, 11).unwrap();
+    migrate_up_from(&conn, 12).unwrap();
+    migrate_up_from(&conn, 13).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 14);
+}
+
+#[test]
+fn properly_upgrade_to_version_15() {
+    let conn = open_connection();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 0);
+
+    migrate_up_from(&conn, 0).unwrap();
+    migrate_up_from(&conn, 1).unwrap();
+    migrate_up_from(&conn, 2).unwrap();
+    migrate_up_from(&conn, 3).unwrap();
+    migrate_up_from(&conn, 4).unwrap();
+    migrate_up_from(&conn, 5).unwrap();
+    migrate_up_from(&conn, 6).unwrap();
+    migrate_up_from(&conn, 7).unwrap();
+    migrate_up_from(&conn, 8).unwrap();
+    migrate_up_from(&conn, 9).unwrap();
+    migrate_up_from(&conn, 10).unwrap();
+    migrate_up_from(&conn, 11).unwrap();
+    migrate_up_from(&conn, 12).unwrap();
+    migrate_up_from(&conn, 13).unwrap();
+    migrate_up_from(&conn, 14).unwrap();
+
+    assert_eq!(read_db_version(&conn).unwrap(), 15);
+}
+
 #[test]
 fn properly_upgrade_to_required_version() {
     let conn = open_connection();
@@ -67,3 +336,20 @@ fn properly_upgrade_to_required_version() {
     upgrade_db(&conn).unwrap();
     assert_eq!(read_db_version(&conn).unwrap(), REQUIRED_DB_VERSION);
 }
+
+#[test]
+fn refuse_db_versions_newer_than_required() {
+    let conn = open_connection();
+    upgrade_db(&conn).unwrap();
+    write_db_version(&conn, REQUIRED_DB_VERSION + 1).unwrap();
+
+    match upgrade_db(&conn) {
+        Err(MigrationError::DBVersionTooNew { found, supported }) => {
+            assert_eq!(found, REQUIRED_DB_VERSION + 1);
+            assert_eq!(supported, REQUIRED_DB_VERSION);
+        }
+        _ => panic!("Must not operate on DBs of unknown (newer) versions!"),
+    }
+    assert!(check_db_version(&conn).is_err());
+    assert_eq!(read_db_version(&conn).unwrap(), REQUIRED_DB_VERSION + 1);
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_trust_mtime(conn)?;
+
+    Ok(())
+}
+
+// Stores that trust file modification times skip hashing during scans.
+// Hashes are then only calculated lazily when the file content is actually synced.
+fn add_column_trust_mtime(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN trust_mtime INTEGER NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_last_sync_completed(conn)?;
+
+    Ok(())
+}
+
+// Wall-clock time of the last completed sync from the given store (NULL if never synced).
+// This is purely informative for users and independent of the logical 'time' counter.
+fn add_column_last_sync_completed(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN last_sync_completed TEXT DEFAULT NULL")
+        .execute(conn)?;
+    Ok(())
+}
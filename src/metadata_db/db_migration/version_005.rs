@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_hard_link_group(conn)?;
+
+    Ok(())
+}
+
+// Files that are hard links of each other share the same hard_link_group identifier.
+// This allows to re-create them as hard links on other stores (NULL for regular files).
+fn add_column_hard_link_group(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE file_system_metadatas ADD COLUMN hard_link_group TEXT DEFAULT NULL")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_size(conn)?;
+
+    Ok(())
+}
+
+// The size of files in bytes, e.g. to estimate the space required by a sync before running it.
+// Items indexed before this version report a size of 0 until they are re-indexed.
+fn add_column_size(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE file_system_metadatas ADD COLUMN size BIGINT NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_inclusion_rule_positions(conn)?;
+
+    Ok(())
+}
+
+// Inclusion rules are evaluated by their position, the last matching rule wins.
+// Before, any matching ignore rule excluded an item. Positioning all ignore rules behind the
+// inclusion rules keeps the meaning of existing rule sets.
+fn add_inclusion_rule_positions(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE inclusion_rules ADD COLUMN position INTEGER NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    sql_query(
+        "UPDATE inclusion_rules
+         SET position = CASE
+             WHEN include = 0 THEN id + (SELECT MAX(id) FROM inclusion_rules)
+             ELSE id
+         END",
+    )
+    .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_propagate_case_changes(conn)?;
+
+    Ok(())
+}
+
+// Stores not propagating case changes treat case-only differences of names as non-changes,
+// i.e. they neither rename items on disk nor record case-only renames as modifications.
+fn add_column_propagate_case_changes(conn: &SqliteConnection) -> Result<()> {
+    sql_query(
+        "ALTER TABLE data_stores ADD COLUMN propagate_case_changes INTEGER NOT NULL DEFAULT 1",
+    )
+    .execute(conn)?;
+    Ok(())
+}
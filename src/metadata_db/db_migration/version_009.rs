@@ -0,0 +1,16 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_columns_hash_algorithm(conn)?;
+
+    Ok(())
+}
+
+// Content hashes are tagged with the algorithm they were computed with (all existing ones
+// are SHA256). While re-hashing a store, rehash_progress holds the last re-hashed item id.
+fn add_columns_hash_algorithm(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN hash_algorithm TEXT NOT NULL DEFAULT 'SHA256'")
+        .execute(conn)?;
+    sql_query("ALTER TABLE data_stores ADD COLUMN rehash_progress BIGINT").execute(conn)?;
+    Ok(())
+}
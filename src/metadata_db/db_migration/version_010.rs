@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_partial_content(conn)?;
+
+    Ok(())
+}
+
+// Stores only holding the content of some of their indexed files answer syncs from their
+// index alone, the files they lack are reported to the syncing store as unavailable.
+fn add_column_partial_content(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN partial_content INTEGER NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_stalled_syncs(conn)?;
+
+    Ok(())
+}
+
+// Number of consecutive syncs from a store that transferred nothing, but still could not take
+// up the store's sync time (i.e. syncs from it never converge).
+fn add_column_stalled_syncs(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN stalled_syncs BIGINT NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_normalize_unicode(conn)?;
+
+    Ok(())
+}
+
+// Stores normalizing unicode key the names of their items in NFC (e.g. macOS writes NFD names),
+// i.e. names only differing in their normalization refer to the same item.
+// Existing stores keep their (not normalized) keys, only new stores normalize names by default.
+fn add_column_normalize_unicode(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN normalize_unicode INTEGER NOT NULL DEFAULT 0")
+        .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    create_index_last_mod(conn)?;
+
+    Ok(())
+}
+
+// Creates an index to search for items by their last modification, e.g. to list all items the
+// local store changed since a given local time (see MetadataDB::items_changed_since).
+fn create_index_last_mod(conn: &SqliteConnection) -> Result<()> {
+    sql_query(
+        "CREATE INDEX mod_metadatas_last_mod_idx ON mod_metadatas(last_mod_store_id, last_mod_store_time)",
+    )
+    .execute(conn)?;
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_min_file_age(conn)?;
+
+    Ok(())
+}
+
+// Seconds a file must stay unmodified before scans index it, NULL to index files right away.
+fn add_column_min_file_age(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN min_file_age BIGINT").execute(conn)?;
+    Ok(())
+}
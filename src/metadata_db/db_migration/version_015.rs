@@ -0,0 +1,13 @@
+use super::*;
+
+pub fn migrate(conn: &SqliteConnection) -> Result<()> {
+    add_column_ignore_file_name(conn)?;
+
+    Ok(())
+}
+
+// Name of the per-directory ignore files read by scans, NULL if they are disabled.
+fn add_column_ignore_file_name(conn: &SqliteConnection) -> Result<()> {
+    sql_query("ALTER TABLE data_stores ADD COLUMN ignore_file_name TEXT").execute(conn)?;
+    Ok(())
+}
@@ -15,6 +15,23 @@ pub struct DataStore {
     pub is_transfer_store: bool,
 
     pub time: i64,
+
+    pub trust_mtime: bool,
+    // Wall-clock time of the last completed sync from this store (not the logical time above).
+    pub last_sync_completed: Option<chrono::NaiveDateTime>,
+    pub propagate_case_changes: bool,
+    // Algorithm of the stored content hashes, the last re-hashed item id while changing it.
+    pub hash_algorithm: String,
+    pub rehash_progress: Option<i64>,
+    pub partial_content: bool,
+    // Consecutive syncs from this store that transferred nothing but did not converge.
+    pub stalled_syncs: i64,
+    // Names are keyed in unicode normalization form C (see RelativePath::to_lookup_key).
+    pub normalize_unicode: bool,
+    // Seconds a file must stay unmodified before scans index it (see InclusionRules::min_age).
+    pub min_file_age: Option<i64>,
+    // Name of the per-directory ignore files read by scans (see DirIgnoreRules).
+    pub ignore_file_name: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -11,6 +11,10 @@ pub struct FileSystemMetadata {
     pub hash: String,
 
     pub is_read_only: bool,
+
+    pub hard_link_group: Option<String>,
+
+    pub size: i64,
 }
 
 #[derive(Insertable)]
@@ -24,6 +28,10 @@ pub struct InsertFull<'a> {
     pub hash: &'a str,
 
     pub is_read_only: bool,
+
+    pub hard_link_group: Option<&'a str>,
+
+    pub size: i64,
 }
 
 #[derive(AsChangeset)]
@@ -8,6 +8,8 @@ pub struct InclusionRule {
 
     pub rule_glob: String,
     pub include: bool,
+    // Rules are evaluated in ascending order of their positions.
+    pub position: i64,
 }
 
 #[derive(Insertable)]
@@ -17,4 +19,5 @@ pub struct InsertFull {
 
     pub rule_glob: String,
     pub include: bool,
+    pub position: i64,
 }
@@ -17,6 +17,9 @@ pub enum MetadataDBError {
     ViolatesDBConsistency {
         message: &'static str,
     },
+    ReaderUnavailable {
+        message: &'static str,
+    },
 }
 pub type Result<T> = std::result::Result<T, MetadataDBError>;
 
@@ -52,6 +55,7 @@ impl Error for MetadataDBError {
             Self::GenericSQLError { ref source } => Some(source),
             Self::ViolatesDBConsistency { .. } => None,
             Self::NotFound => None,
+            Self::ReaderUnavailable { .. } => None,
         }
     }
 }
@@ -10,12 +10,14 @@ mod db_item;
 pub use self::db_item::*;
 mod db_inclusion_rule;
 pub use self::db_inclusion_rule::*;
+mod tree_summary;
+pub use self::tree_summary::TreeSummary;
 // Error boilerplate
 mod errors;
 pub use self::errors::*;
 mod db_migration;
 
-use crate::fs_interaction::relative_path::RelativePath;
+use crate::fs_interaction::relative_path::{self, RelativePath};
 use crate::version_vector::VersionVector;
 
 use diesel::prelude::*;
@@ -23,11 +25,16 @@ use diesel::sql_query;
 use diesel::sqlite::SqliteConnection;
 use std::cell::RefCell;
 use std::cmp::max;
+use std::collections::HashSet;
 
 const UPDATES_UNTIL_OPTIMIZATION: usize = 10_000;
 
 pub struct MetadataDB {
     conn: SqliteConnection,
+    // Location of the DB file, used to open additional reader connections
+    path: String,
+    // Only connections without an exclusive lock allow additional reader connections
+    allows_readers: bool,
     // Caching local data store
     local_datastore: RefCell<Option<DataStore>>,
     // Optimize the DB after a big number of inserts
@@ -40,8 +47,20 @@ impl MetadataDB {
     /// Opens the metadata db file located at the given path and performs data migrations to
     /// the current application version if required.
     pub fn open(path: &str) -> Result<MetadataDB> {
+        Self::open_with_locking(path, false)
+    }
+
+    /// Same as open, but allows additional reader connections to the DB (see open_reader).
+    /// The DB file is not locked exclusively, i.e. other processes could also access it.
+    pub fn open_shared(path: &str) -> Result<MetadataDB> {
+        Self::open_with_locking(path, true)
+    }
+
+    fn open_with_locking(path: &str, allows_readers: bool) -> Result<MetadataDB> {
         let result = MetadataDB {
             conn: SqliteConnection::establish(path)?,
+            path: path.to_string(),
+            allows_readers,
 
             local_datastore: RefCell::new(None),
             updates_since_optimization: RefCell::new(0),
@@ -55,15 +74,96 @@ impl MetadataDB {
         Ok(result)
     }
 
+    /// Opens the metadata db file located at the given path for queries only, e.g. on a read-only
+    /// mount. The file is expected to not change while it is open (no locks are taken and no
+    /// WAL index is created). The DB must already be at the current application version.
+    pub fn open_read_only(path: &str) -> Result<MetadataDB> {
+        let uri_path = path
+            .replace('%', "%25")
+            .replace('?', "%3f")
+            .replace('#', "%23");
+        let result = MetadataDB {
+            conn: SqliteConnection::establish(&format!("file:{}?immutable=1", uri_path))?,
+            path: path.to_string(),
+            allows_readers: false,
+
+            local_datastore: RefCell::new(None),
+            updates_since_optimization: RefCell::new(0),
+
+            is_bundled: RefCell::new(false),
+        };
+
+        result.reader_db_settings()?;
+        db_migration::check_db_version(&result.conn)?;
+
+        Ok(result)
+    }
+
+    /// Opens an additional, read-only connection to the same metadata db file.
+    ///
+    /// The returned MetadataDB can be moved to a worker thread and used for read queries
+    /// (e.g. get_local_data_item or get_local_child_items) while this instance keeps writing.
+    /// The DB runs in WAL mode, i.e. the reader sees the state of the last committed transaction
+    /// and never blocks the writer. Any attempt to write through the reader fails.
+    /// Only available for DBs opened with open_shared.
+    pub fn open_reader(&self) -> Result<MetadataDB> {
+        if self.path == ":memory:" {
+            return Err(MetadataDBError::ReaderUnavailable {
+                message: "In-memory databases can not be shared between connections!",
+            });
+        }
+        if !self.allows_readers {
+            return Err(MetadataDBError::ReaderUnavailable {
+                message: "The database is locked exclusively, open it with open_shared!",
+            });
+        }
+
+        let result = MetadataDB {
+            conn: SqliteConnection::establish(&self.path)?,
+            path: self.path.clone(),
+            allows_readers: false,
+
+            local_datastore: RefCell::new(None),
+            updates_since_optimization: RefCell::new(0),
+
+            is_bundled: RefCell::new(false),
+        };
+
+        result.reader_db_settings()?;
+        db_migration::check_db_version(&result.conn)?;
+
+        Ok(result)
+    }
+
     /// Performs a clean-up operation on the local database, removing any redundant information.
     /// Also re-builds the DB to shrink the file size and analyze it for future queries.
     /// Should be run from time to time to decrease the DB size on disk.
-    pub fn optimize_db(&self) -> Result<()> {
+    ///
+    /// Returns the number of orphaned metadata rows removed (see clean_orphaned_metadata).
+    pub fn optimize_db(&self) -> Result<usize> {
         self.clean_up_db()?;
+        let orphaned_rows = self.clean_orphaned_metadata()?;
         diesel::sql_query("ANALYZE").execute(&self.conn)?;
         diesel::sql_query("VACUUM").execute(&self.conn)?;
 
-        Ok(())
+        Ok(orphaned_rows)
+    }
+
+    /// Deletes metadata and time stamp rows that belong to no existing item (fsck-style).
+    /// Foreign keys prevent these in regular operation, but crashes or manual edits of the DB
+    /// can leave them behind. Returns the number of removed rows.
+    pub fn clean_orphaned_metadata(&self) -> Result<usize> {
+        self.run_transaction(|| {
+            let mut removed_rows = 0;
+            removed_rows += diesel::sql_query("DELETE FROM file_system_metadatas WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = file_system_metadatas.id)").execute(&self.conn)?;
+            // Must run before cleaning mod_metadatas, as these would otherwise be removed by the
+            // cascading delete without being counted.
+            removed_rows += diesel::sql_query("DELETE FROM mod_times WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = mod_times.mod_metadata_id)").execute(&self.conn)?;
+            removed_rows += diesel::sql_query("DELETE FROM mod_metadatas WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = mod_metadatas.id)").execute(&self.conn)?;
+            removed_rows += diesel::sql_query("DELETE FROM sync_times WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = sync_times.item_id)").execute(&self.conn)?;
+
+            Ok(removed_rows)
+        })
     }
 
     /// Performs a clean-up operation on the local database, removing any redundant information.
@@ -167,6 +267,19 @@ impl MetadataDB {
         Ok(())
     }
 
+    /// Renames the data set of an existing store (see update_data_set_name).
+    pub fn rename_data_set(&self, human_name: &str) -> Result<()> {
+        self.run_transaction(|| {
+            self.update_data_set_name(human_name)?;
+
+            // Same as for inclusion rules, others can notice that our database changed
+            // even without a new file modification event.
+            self.increase_local_time()?;
+
+            Ok(())
+        })
+    }
+
     /// List all data stores managed by the open MetadataDB.
     /// At most one of them must be the local data set (marked with 'is_this_data_store == true').
     pub fn get_data_stores(&self) -> Result<Vec<DataStore>> {
@@ -190,6 +303,7 @@ impl MetadataDB {
     pub fn get_inclusion_rules(&self, data_store: &DataStore) -> Result<Vec<DBInclusionRule>> {
         let result = inclusion_rules::table
             .filter(inclusion_rules::data_store_id.eq(data_store.id))
+            .order(inclusion_rules::position.asc())
             .load::<InclusionRule>(&self.conn)?
             .into_iter()
             .map(|db_entry| DBInclusionRule {
@@ -215,10 +329,12 @@ impl MetadataDB {
 
             let new_rules: Vec<_> = rules
                 .iter()
-                .map(|rule| inclusion_rule::InsertFull {
+                .enumerate()
+                .map(|(position, rule)| inclusion_rule::InsertFull {
                     data_store_id: data_store.id,
                     rule_glob: rule.rule.to_string(),
                     include: rule.include,
+                    position: position as i64,
                 })
                 .collect();
             diesel::insert_into(inclusion_rules::table)
@@ -293,6 +409,30 @@ impl MetadataDB {
         Ok(())
     }
 
+    /// Records the wall-clock time at which the last sync from the given data store completed.
+    /// This is independent of the logical time counter of the store.
+    pub fn update_last_sync_completed(
+        &self,
+        unique_name: &str,
+        sync_completed: chrono::NaiveDateTime,
+    ) -> Result<()> {
+        diesel::update(data_stores::table.filter(data_stores::unique_name.eq(unique_name)))
+            .set(data_stores::last_sync_completed.eq(sync_completed))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Records the number of consecutive syncs from the given data store that transferred
+    /// nothing, but still could not take up the store's sync time (0 resets the counter).
+    pub fn update_stalled_syncs(&self, unique_name: &str, stalled_syncs: i64) -> Result<()> {
+        diesel::update(data_stores::table.filter(data_stores::unique_name.eq(unique_name)))
+            .set(data_stores::stalled_syncs.eq(stalled_syncs))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
     /// Marks the local data store to be a transfer store, i.e. it does not index its own
     /// items but its purpose is to propagate changes to other stores.
     pub fn mark_as_transfer_store(&self) -> Result<()> {
@@ -305,11 +445,275 @@ impl MetadataDB {
         Ok(())
     }
 
+    /// Turns the local data store back into a regular store indexing its own items.
+    pub fn unmark_as_transfer_store(&self) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::is_transfer_store.eq(false))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Records the location of the local data store on the device.
+    pub fn update_path_on_device(&self, path_on_device: &str) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::path_on_device.eq(path_on_device))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Gives the local data store a new identity (unique_name), e.g. after it was cloned.
+    ///
+    /// All local items are handed over to a new local data store starting at time 0.
+    /// The old identity is kept as a regular, external data store, i.e. all existing versions
+    /// are still attributed to it and the local store still knows about all changes of it up to
+    /// the time of re-identification. Partners see the new identity as a brand-new store.
+    pub fn reidentify_local_data_store(
+        &self,
+        new_unique_name: &str,
+        path_on_device: &str,
+    ) -> Result<DataStore> {
+        self.run_transaction(|| {
+            let old_store = self.get_local_data_store()?;
+            let root_item = self
+                .load_data_items_on_path(&old_store, &RelativePath::from_path(""), false)?
+                .pop()
+                .unwrap();
+
+            diesel::insert_into(data_stores::table)
+                .values(data_store::InsertFull {
+                    data_set_id: old_store.data_set_id,
+
+                    unique_name: new_unique_name,
+                    human_name: &old_store.human_name,
+                    creation_date: &chrono::Utc::now().naive_local(),
+                    path_on_device,
+                    location_note: &old_store.location_note,
+
+                    is_this_store: false,
+                    is_transfer_store: old_store.is_transfer_store,
+
+                    time: 0,
+                })
+                .execute(&self.conn)?;
+            let new_store = data_stores::table
+                .filter(data_stores::unique_name.eq(new_unique_name))
+                .first::<DataStore>(&self.conn)?;
+
+            // Swap the local store, the old identity becomes an external store.
+            diesel::update(data_stores::table.find(old_store.id))
+                .set(data_stores::is_this_store.eq(false))
+                .execute(&self.conn)?;
+            diesel::update(data_stores::table.find(new_store.id))
+                .set((
+                    data_stores::is_this_store.eq(true),
+                    data_stores::trust_mtime.eq(old_store.trust_mtime),
+                    data_stores::propagate_case_changes.eq(old_store.propagate_case_changes),
+                    data_stores::normalize_unicode.eq(old_store.normalize_unicode),
+                    data_stores::hash_algorithm.eq(&old_store.hash_algorithm),
+                    data_stores::rehash_progress.eq(old_store.rehash_progress),
+                    data_stores::partial_content.eq(old_store.partial_content),
+                    data_stores::min_file_age.eq(old_store.min_file_age),
+                    data_stores::ignore_file_name.eq(&old_store.ignore_file_name),
+                ))
+                .execute(&self.conn)?;
+
+            // Hand over all local items and rules to the new identity.
+            diesel::update(items::table.filter(items::data_store_id.eq(old_store.id)))
+                .set(items::data_store_id.eq(new_store.id))
+                .execute(&self.conn)?;
+            diesel::update(
+                inclusion_rules::table.filter(inclusion_rules::data_store_id.eq(old_store.id)),
+            )
+            .set(inclusion_rules::data_store_id.eq(new_store.id))
+            .execute(&self.conn)?;
+
+            // The local store's own sync time component is implicit (its current time).
+            // Make it explicit for the old identity, we know everything it did up to now.
+            diesel::delete(
+                sync_times::table
+                    .filter(sync_times::item_id.eq(root_item.item.id))
+                    .filter(sync_times::data_store_id.eq(old_store.id)),
+            )
+            .execute(&self.conn)?;
+            diesel::insert_into(sync_times::table)
+                .values(sync_time::InsertFull {
+                    item_id: root_item.item.id,
+                    data_store_id: old_store.id,
+                    time: old_store.time,
+                })
+                .execute(&self.conn)?;
+
+            *self.local_datastore.borrow_mut() = None;
+            self.get_local_data_store()
+        })
+    }
+
     /// Queries if the local store is a transfer store, i.e. it carries data to other stores.
     pub fn is_transfer_store(&self) -> Result<bool> {
         Ok(self.get_local_data_store()?.is_transfer_store)
     }
 
+    /// Sets if the local data store trusts file modification times, i.e. if it skips hashing
+    /// file contents during scans.
+    pub fn set_trust_mtime(&self, trust_mtime: bool) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::trust_mtime.eq(trust_mtime))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Queries if the local store trusts file modification times (and skips hashing on scans).
+    pub fn is_trust_mtime(&self) -> Result<bool> {
+        Ok(self.get_local_data_store()?.trust_mtime)
+    }
+
+    /// Sets if the local data store only holds the content of some of its indexed files.
+    pub fn set_partial_content(&self, partial_content: bool) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::partial_content.eq(partial_content))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Queries if the local store only holds the content of some of its indexed files.
+    pub fn is_partial_content(&self) -> Result<bool> {
+        Ok(self.get_local_data_store()?.partial_content)
+    }
+
+    /// Sets the seconds a file must stay unmodified before scans of the local data store index
+    /// it, None to index files right away.
+    pub fn set_min_file_age(&self, min_file_age: Option<i64>) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::min_file_age.eq(min_file_age))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Sets the name of the per-directory ignore files read by scans of the local data store,
+    /// None to not read any ignore files.
+    pub fn set_ignore_file_name(&self, ignore_file_name: Option<&str>) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::ignore_file_name.eq(ignore_file_name))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Sets if the local data store propagates case-only changes of item names, i.e. if it
+    /// renames items on disk and records case-only renames as modifications.
+    pub fn set_propagate_case_changes(&self, propagate_case_changes: bool) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::propagate_case_changes.eq(propagate_case_changes))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Queries if the local store propagates case-only changes of item names.
+    pub fn is_propagate_case_changes(&self) -> Result<bool> {
+        Ok(self.get_local_data_store()?.propagate_case_changes)
+    }
+
+    /// Sets if the local data store keys item names in unicode normalization form C.
+    /// MUST only be set on creation of the store, as it changes the keys of all items in the DB.
+    pub fn set_normalize_unicode(&self, normalize_unicode: bool) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::normalize_unicode.eq(normalize_unicode))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Queries if the local store keys item names in unicode normalization form C.
+    pub fn is_normalize_unicode(&self) -> Result<bool> {
+        Ok(self.get_local_data_store()?.normalize_unicode)
+    }
+
+    /// Sets the name of the algorithm all content hashes of the local store are computed with.
+    /// Also finishes a running re-hash of the store (see set_rehash_progress).
+    pub fn set_hash_algorithm(&self, hash_algorithm: &str) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set((
+                data_stores::hash_algorithm.eq(hash_algorithm),
+                data_stores::rehash_progress.eq(None::<i64>),
+            ))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Queries the name of the algorithm the content hashes of the local store are computed with.
+    pub fn get_hash_algorithm(&self) -> Result<String> {
+        Ok(self.get_local_data_store()?.hash_algorithm)
+    }
+
+    /// Records the id of the last item re-hashed, i.e. a re-hash resumes after it.
+    pub fn set_rehash_progress(&self, item_id: i64) -> Result<()> {
+        let local_store = self.get_local_data_store()?;
+        *self.local_datastore.borrow_mut() = None;
+        diesel::update(data_stores::table.find(local_store.id))
+            .set(data_stores::rehash_progress.eq(item_id))
+            .execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    pub fn get_rehash_progress(&self) -> Result<Option<i64>> {
+        Ok(self.get_local_data_store()?.rehash_progress)
+    }
+
+    /// Returns the next files (by item id) of the local store after the given item id with their
+    /// ids and (lower case) paths, e.g. to process all files in resumable batches.
+    pub fn get_local_files_after(
+        &self,
+        item_id: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, RelativePath)>> {
+        #[derive(QueryableByName)]
+        struct FileResult {
+            #[sql_type = "diesel::sql_types::BigInt"]
+            id: i64,
+            #[sql_type = "diesel::sql_types::Text"]
+            full_path: String,
+        }
+
+        let local_data_store = self.get_local_data_store()?;
+        let files = diesel::sql_query("SELECT items.id, path_components.full_path FROM items, path_components WHERE items.data_store_id = ? AND items.file_type = ? AND items.id > ? AND path_components.id = items.path_component_id ORDER BY items.id LIMIT ?")
+            .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+            .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+            .bind::<diesel::sql_types::BigInt, _>(item_id)
+            .bind::<diesel::sql_types::BigInt, _>(limit as i64)
+            .load::<FileResult>(&self.conn)?;
+
+        Ok(files
+            .into_iter()
+            .map(|file| (file.id, RelativePath::from_path(&file.full_path[1..])))
+            .collect())
+    }
+
     /// Returns the local data store of the open MetadataDB.
     pub fn get_local_data_store(&self) -> Result<DataStore> {
         use self::schema::data_stores::dsl::*;
@@ -347,7 +751,7 @@ impl MetadataDB {
 
     /// Queries a data item from the DB and returns it.
     /// Data items must always exist, as there is at least a deletion/ignore notice for everything.
-    // FIXME: Return ignore entry or deletion notice based on last seen item type.
+    /// Items within ignored folders are reported as ignored, all others without entry as deleted.
     pub fn get_local_data_item(
         &self,
         path: &RelativePath,
@@ -375,6 +779,12 @@ impl MetadataDB {
                     last_db_entry.sync_time = Some(VersionVector::new());
                     last_db_entry.mod_time = Some(VersionVector::new());
                 }
+                // ...unless it lies within an ignored folder, the folder's entry covers it.
+                if last_db_entry.item.file_type == FileType::IGNORED {
+                    let mut ignored_item = DBItem::from_internal_item(&path_items, last_db_entry);
+                    ignored_item.path = path.clone();
+                    return Ok(ignored_item);
+                }
                 Ok(DBItem {
                     path: path.clone(),
                     sync_time: last_db_entry.sync_time.unwrap(),
@@ -386,6 +796,7 @@ impl MetadataDB {
     }
 
     /// Queries all item names (NOT case sensitive) present in the given dir_path.
+    /// The items are listed in the canonical order of their names (see relative_path::cmp_names).
     pub fn get_local_child_items(
         &self,
         dir_path: &RelativePath,
@@ -393,6 +804,7 @@ impl MetadataDB {
     ) -> Result<Vec<DBItem>> {
         self.run_transaction(|| {
             let local_data_store = self.get_local_data_store()?;
+            let normalize_unicode = self.is_normalize_unicode()?;
             let mut dir_path_items =
                 self.load_data_items_on_path(&local_data_store, dir_path, load_timestamps)?;
 
@@ -401,12 +813,16 @@ impl MetadataDB {
                 // The last item in the chain of DB entries is the desired folder item.
                 let dir_item = dir_path_items.last_mut().unwrap();
 
-                // Query its content/children.
-                Ok(self
+                // Query its content/children (in the canonical order of names).
+                let mut child_items: Vec<DBItem> = self
                     .load_child_items(&dir_item, load_timestamps)?
                     .into_iter()
                     .map(|internal_item| DBItem::from_internal_item(&dir_path_items, internal_item))
-                    .collect())
+                    .collect();
+                child_items.sort_by(|a, b| {
+                    relative_path::cmp_names(a.path.name(), b.path.name(), normalize_unicode)
+                });
+                Ok(child_items)
             } else {
                 // The parent path is not in the DB, thus we have no child items.
                 Ok(vec![])
@@ -414,6 +830,72 @@ impl MetadataDB {
         })
     }
 
+    /// Calls func for every non-deleted item in the DB (excluding the root folder), e.g. to
+    /// export/back up the index. Folders are visited before their contents.
+    /// All items are loaded with their time stamps and from one consistent view of the DB.
+    pub fn for_each_item<F: FnMut(&DBItem)>(&self, mut func: F) -> Result<()> {
+        self.run_transaction(|| {
+            let mut open_folders = vec![RelativePath::from_path("")];
+            while let Some(folder_path) = open_folders.pop() {
+                for child_item in self.get_local_child_items(&folder_path, true)? {
+                    if child_item.is_deletion() {
+                        continue;
+                    }
+
+                    func(&child_item);
+                    if child_item.is_folder() {
+                        open_folders.push(child_item.path);
+                    }
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Lists all items of the local store last modified by the local store after the given
+    /// local time (see get_local_data_store), in the order they were modified, e.g. to collect
+    /// the changes since an earlier export. Items are loaded with their time stamps.
+    /// Deleted items carry no metadata and are not listed, the folders holding them are.
+    /// The root folder is listed like any other folder, i.e. once it is created along with the
+    /// store and whenever it holds changes of top level items (e.g. their deletion).
+    pub fn items_changed_since(&self, local_time: i64) -> Result<Vec<DBItem>> {
+        #[derive(QueryableByName)]
+        #[table_name = "path_components"]
+        struct PathResult {
+            full_path: String,
+        }
+
+        self.run_transaction(|| {
+            let local_data_store = self.get_local_data_store()?;
+            let changed_paths = diesel::sql_query("SELECT path_components.full_path FROM mod_metadatas, items, path_components WHERE mod_metadatas.last_mod_store_id = ? AND mod_metadatas.last_mod_store_time > ? AND items.id = mod_metadatas.id AND items.data_store_id = ? AND path_components.id = items.path_component_id ORDER BY mod_metadatas.last_mod_store_time")
+                .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+                .bind::<diesel::sql_types::BigInt, _>(local_time)
+                .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+                .load::<PathResult>(&self.conn)?;
+
+            changed_paths
+                .into_iter()
+                .map(|path| {
+                    self.get_local_data_item(&RelativePath::from_path(&path.full_path[1..]), true)
+                })
+                .collect()
+        })
+    }
+
+    /// Lists all items that are currently ignored, e.g. files excluded by the inclusion rules
+    /// or ignored items learned from other stores during a sync.
+    pub fn get_ignored_items(&self) -> Result<Vec<DBItem>> {
+        let mut ignored_items = Vec::new();
+        self.for_each_item(|item| {
+            if item.is_ignored() {
+                ignored_items.push(item.clone());
+            }
+        })?;
+
+        Ok(ignored_items)
+    }
+
     /// LOCAL DATA STORE EVENT, i.e. this is used to record changes of local data_items on disk.
     ///
     /// Modifies a data item for the local data store (making sure versions stay consistent).
@@ -427,6 +909,8 @@ impl MetadataDB {
         is_file: bool,
         hash: &str,
         is_read_only: bool,
+        hard_link_group: Option<&str>,
+        size: u64,
     ) -> Result<()> {
         self.run_transaction(|| {
             // We insert an item, bump the data stores version and mark all events with the version.
@@ -492,6 +976,10 @@ impl MetadataDB {
                     hash: &hash,
 
                     is_read_only: is_read_only,
+
+                    hard_link_group,
+
+                    size: size as i64,
                 }).execute(&self.conn)?;
             let fs_metadata = file_system_metadatas::table.find(item.id).first::<FileSystemMetadata>(&self.conn)?;
 
@@ -529,6 +1017,70 @@ impl MetadataDB {
         })
     }
 
+    /// Sets the content hash of a local item WITHOUT recording a modification.
+    /// Used to lazily fill in placeholder hashes of stores that trust modification times.
+    pub fn update_local_data_item_hash(&self, path: &RelativePath, hash: &str) -> Result<()> {
+        self.run_transaction(|| {
+            let local_data_store = self.get_local_data_store()?;
+            let path_items = self.load_data_items_on_path(&local_data_store, path, false)?;
+            if path_items.len() != path.get_path_components().len() {
+                return Err(MetadataDBError::ViolatesDBConsistency {
+                    message: "Must not set the hash of non-existing items!",
+                });
+            }
+
+            let item = path_items.last().unwrap();
+            diesel::update(file_system_metadatas::table.find(item.item.id))
+                .set(file_system_metadatas::hash.eq(hash))
+                .execute(&self.conn)?;
+            Ok(())
+        })
+    }
+
+    /// Sets the FS modification time of a local item WITHOUT recording a modification.
+    /// Used when re-building the index finds the same content with a different mod time.
+    pub fn update_local_data_item_mod_time(
+        &self,
+        path: &RelativePath,
+        mod_time: chrono::NaiveDateTime,
+    ) -> Result<()> {
+        self.run_transaction(|| {
+            let local_data_store = self.get_local_data_store()?;
+            let path_items = self.load_data_items_on_path(&local_data_store, path, false)?;
+            if path_items.len() != path.get_path_components().len() {
+                return Err(MetadataDBError::ViolatesDBConsistency {
+                    message: "Must not set the mod time of non-existing items!",
+                });
+            }
+
+            let item = path_items.last().unwrap();
+            diesel::update(file_system_metadatas::table.find(item.item.id))
+                .set(file_system_metadatas::mod_time.eq(mod_time))
+                .execute(&self.conn)?;
+            Ok(())
+        })
+    }
+
+    /// Sets the case sensitive name of a local item WITHOUT recording a modification.
+    /// Used by stores that do not propagate case-only changes of item names.
+    pub fn update_local_data_item_name(&self, path: &RelativePath, name: &str) -> Result<()> {
+        self.run_transaction(|| {
+            let local_data_store = self.get_local_data_store()?;
+            let path_items = self.load_data_items_on_path(&local_data_store, path, false)?;
+            if path_items.len() != path.get_path_components().len() {
+                return Err(MetadataDBError::ViolatesDBConsistency {
+                    message: "Must not set the name of non-existing items!",
+                });
+            }
+
+            let item = path_items.last().unwrap();
+            diesel::update(file_system_metadatas::table.find(item.item.id))
+                .set(file_system_metadatas::case_sensitive_name.eq(name))
+                .execute(&self.conn)?;
+            Ok(())
+        })
+    }
+
     /// LOCAL DATA STORE EVENT, i.e. this is used to record changes of local data_items on disk.
     ///
     /// Marks the given data item (and all its child items) as being deleted.
@@ -575,9 +1127,8 @@ impl MetadataDB {
     /// If the information in the folder was not already 'synced outwards' it is lost,
     /// if it was synced to another store, the other store will still keep it.
     ///
-    /// Does not affect any modification times.
-    // FIXME: This should really NOT BE a LOCAL DATA STORE EVENT, as it does not
-    //        add modification times!
+    /// Does not modify the item's own modification time, but pushes the modification
+    /// times of all parent folders (up to the root item), so the change becomes visible.
     pub fn ignore_local_data_item(&self, path: &RelativePath) -> Result<()> {
         self.run_transaction(|| {
             let local_data_store = self.get_local_data_store()?;
@@ -599,6 +1150,8 @@ impl MetadataDB {
                 // 'only the metadata' when fetching changes to an remote. This way, the mod/sync
                 // timestamps are consistent in respect ot sync=min(children) and mod=max(children).
 
+                self.add_mod_event_to_parents(&path_items, local_data_store.id)?;
+
                 self.notify_change_for_optimization()?;
                 Ok(())
             } else {
@@ -615,9 +1168,7 @@ impl MetadataDB {
     /// i.e. the items are set to an initial clean state, with no information on them.
     /// This means, that for all items we will have deletion notices at time 0.
     ///
-    /// Does not affect any modification times.
-    // FIXME: This should really NOT BE a LOCAL DATA STORE EVENT, as it does not
-    //        add modification times!
+    /// Pushes the modification times of all parent folders (up to the root item).
     pub fn reset_local_data_item(&self, path: &RelativePath) -> Result<()> {
         self.run_transaction(|| {
             let local_data_store = self.get_local_data_store()?;
@@ -634,6 +1185,7 @@ impl MetadataDB {
                     .set(items::file_type.eq(FileType::DELETED))
                     .execute(&self.conn)?;
                 self.delete_item_metadata(&existing_item)?;
+                self.add_mod_event_to_parents(&path_items, local_data_store.id)?;
                 // ...the last step is to reset the sync time of the item and all its parent items
                 // down to a zero vector. This requires 'bubbling' up the change and setting
                 // all other children's sync times on the way.
@@ -761,6 +1313,10 @@ impl MetadataDB {
                             hash: &target_item.metadata().hash,
 
                             is_read_only: target_item.metadata().is_read_only,
+
+                            hard_link_group: target_item.metadata().hard_link_group.as_deref(),
+
+                            size: target_item.metadata().size as i64,
                         })
                         .execute(&self.conn)?;
                 }
@@ -871,8 +1427,8 @@ impl MetadataDB {
         path: &RelativePath,
         load_timestamps: bool,
     ) -> Result<Vec<DBItemInternal>> {
-        // We handle all path's in lower case in here!
-        let path = path.to_lower_case();
+        // We handle all path's in lower case (and possibly normalized) in here!
+        let path = path.to_lookup_key(self.is_normalize_unicode()?);
 
         // Required for sync time compression in the DB.
         let mut current_sync_time = VersionVector::<i64>::new();
@@ -1079,7 +1635,11 @@ impl MetadataDB {
         parent: Option<&PathComponent>,
     ) -> Result<PathComponent> {
         let current_path_string = if let Some(parent) = parent {
-            format!("{}{}/", parent.full_path, name.to_lowercase())
+            format!(
+                "{}{}/",
+                parent.full_path,
+                relative_path::name_lookup_key(name, self.is_normalize_unicode()?)
+            )
         } else {
             assert_eq!(name, "", "Must not insert non empty root item!");
             "/".to_string()
@@ -1205,6 +1765,22 @@ impl MetadataDB {
         Ok(())
     }
 
+    /// Adds a new local modification event to all parent items of the last item in path_items,
+    /// i.e. it bubbles up a change of the item without touching the item's own metadata.
+    fn add_mod_event_to_parents(
+        &self,
+        path_items: &[DBItemInternal],
+        modifying_data_store_id: i64,
+    ) -> Result<()> {
+        if path_items.len() > 1 {
+            let parent_items = path_items[..path_items.len() - 1].to_vec();
+            let new_time = self.increase_local_time()?;
+            self.add_mod_event(&parent_items, modifying_data_store_id, new_time)?;
+        }
+
+        Ok(())
+    }
+
     /// Updates the sync times of an DB entry by replacing all
     /// existing entries with the given vector entries.
     ///
@@ -1226,6 +1802,145 @@ impl MetadataDB {
         Ok(())
     }
 
+    /// Searches for a local file that belongs to the given hard link group.
+    pub fn find_local_hard_link(&self, hard_link_group: &str) -> Result<Option<RelativePath>> {
+        #[derive(QueryableByName)]
+        #[table_name = "path_components"]
+        struct PathResult {
+            full_path: String,
+        }
+
+        let local_data_store = self.get_local_data_store()?;
+        let hard_links = diesel::sql_query("SELECT path_components.full_path FROM items, path_components, file_system_metadatas WHERE items.data_store_id = ? AND items.file_type = ? AND file_system_metadatas.hard_link_group = ? AND path_components.id = items.path_component_id AND file_system_metadatas.id = items.id LIMIT 1")
+            .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+            .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+            .bind::<diesel::sql_types::Text, _>(hard_link_group)
+            .load::<PathResult>(&self.conn)?;
+
+        Ok(hard_links
+            .into_iter()
+            .next()
+            .map(|item| RelativePath::from_path(&item.full_path[1..])))
+    }
+
+    /// Groups all local files with identical content (same hash and size) by their hash, largest
+    /// files first. Files already hard linked to each other only count once, i.e. groups list
+    /// only files still taking up their own space. Reports lower case paths.
+    /// Files without hash (placeholders of stores trusting mod times) are left out.
+    pub fn get_duplicate_files(&self) -> Result<Vec<(String, Vec<RelativePath>)>> {
+        #[derive(QueryableByName)]
+        struct DuplicateResult {
+            #[sql_type = "diesel::sql_types::Text"]
+            hash: String,
+            #[sql_type = "diesel::sql_types::Text"]
+            full_path: String,
+            #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::Text>"]
+            hard_link_group: Option<String>,
+        }
+
+        let local_data_store = self.get_local_data_store()?;
+        let files = diesel::sql_query("SELECT file_system_metadatas.hash, path_components.full_path, file_system_metadatas.hard_link_group FROM items, path_components, file_system_metadatas WHERE items.data_store_id = ? AND items.file_type = ? AND path_components.id = items.path_component_id AND file_system_metadatas.id = items.id AND file_system_metadatas.hash != '' AND (file_system_metadatas.hash, file_system_metadatas.size) IN (SELECT file_system_metadatas.hash, file_system_metadatas.size FROM items, file_system_metadatas WHERE items.data_store_id = ? AND items.file_type = ? AND file_system_metadatas.id = items.id GROUP BY file_system_metadatas.hash, file_system_metadatas.size HAVING COUNT(*) > 1) ORDER BY file_system_metadatas.size DESC, file_system_metadatas.hash, path_components.full_path")
+            .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+            .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+            .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+            .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+            .load::<DuplicateResult>(&self.conn)?;
+
+        let mut duplicates: Vec<(String, Vec<RelativePath>)> = Vec::new();
+        let mut seen_hard_link_groups = HashSet::new();
+        for file in files {
+            if let Some(hard_link_group) = file.hard_link_group {
+                if !seen_hard_link_groups.insert(hard_link_group) {
+                    continue;
+                }
+            }
+
+            let path = RelativePath::from_path(&file.full_path[1..]);
+            match duplicates.last_mut() {
+                Some((hash, paths)) if *hash == file.hash => paths.push(path),
+                _ => duplicates.push((file.hash, vec![path])),
+            }
+        }
+        duplicates.retain(|(_, paths)| paths.len() > 1);
+
+        Ok(duplicates)
+    }
+
+    /// Summarizes the sub-tree at the given path (including the item at path itself, but never
+    /// the root folder) using a single aggregate query. Reports the max_largest_files biggest
+    /// files with their (lower case) paths.
+    pub fn get_tree_summary(
+        &self,
+        path: &RelativePath,
+        max_largest_files: usize,
+    ) -> Result<TreeSummary> {
+        #[derive(QueryableByName)]
+        struct AggregateResult {
+            #[sql_type = "diesel::sql_types::BigInt"]
+            file_count: i64,
+            #[sql_type = "diesel::sql_types::BigInt"]
+            dir_count: i64,
+            #[sql_type = "diesel::sql_types::BigInt"]
+            total_bytes: i64,
+        }
+        #[derive(QueryableByName)]
+        struct FileSizeResult {
+            #[sql_type = "diesel::sql_types::Text"]
+            full_path: String,
+            #[sql_type = "diesel::sql_types::BigInt"]
+            size: i64,
+        }
+
+        // full_path has the form '/sub/file/', i.e. the prefix selects the sub-tree.
+        let normalize_unicode = self.is_normalize_unicode()?;
+        let path_prefix =
+            path.get_path_components()
+                .iter()
+                .skip(1)
+                .fold("/".to_string(), |prefix, component| {
+                    format!(
+                        "{}{}/",
+                        prefix,
+                        relative_path::name_lookup_key(component, normalize_unicode)
+                    )
+                });
+
+        let local_data_store = self.get_local_data_store()?;
+        self.run_transaction(|| {
+            let aggregate = diesel::sql_query("SELECT COUNT(CASE WHEN items.file_type = ? THEN 1 END) AS file_count, COUNT(CASE WHEN items.file_type = ? THEN 1 END) AS dir_count, COALESCE(SUM(CASE WHEN items.file_type = ? THEN file_system_metadatas.size END), 0) AS total_bytes FROM items, path_components, file_system_metadatas WHERE items.data_store_id = ? AND path_components.id = items.path_component_id AND file_system_metadatas.id = items.id AND path_components.full_path != '/' AND substr(path_components.full_path, 1, length(?)) = ?")
+                .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+                .bind::<diesel::sql_types::Integer, _>(FileType::DIRECTORY)
+                .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+                .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+                .bind::<diesel::sql_types::Text, _>(&path_prefix)
+                .bind::<diesel::sql_types::Text, _>(&path_prefix)
+                .get_result::<AggregateResult>(&self.conn)?;
+
+            let largest_files = diesel::sql_query("SELECT path_components.full_path, file_system_metadatas.size FROM items, path_components, file_system_metadatas WHERE items.data_store_id = ? AND items.file_type = ? AND path_components.id = items.path_component_id AND file_system_metadatas.id = items.id AND substr(path_components.full_path, 1, length(?)) = ? ORDER BY file_system_metadatas.size DESC, path_components.full_path LIMIT ?")
+                .bind::<diesel::sql_types::BigInt, _>(local_data_store.id)
+                .bind::<diesel::sql_types::Integer, _>(FileType::FILE)
+                .bind::<diesel::sql_types::Text, _>(&path_prefix)
+                .bind::<diesel::sql_types::Text, _>(&path_prefix)
+                .bind::<diesel::sql_types::BigInt, _>(max_largest_files as i64)
+                .load::<FileSizeResult>(&self.conn)?;
+
+            Ok(TreeSummary {
+                files: aggregate.file_count as usize,
+                directories: aggregate.dir_count as usize,
+                total_bytes: aggregate.total_bytes as u64,
+                largest_files: largest_files
+                    .into_iter()
+                    .map(|file| {
+                        (
+                            RelativePath::from_path(&file.full_path[1..]),
+                            file.size as u64,
+                        )
+                    })
+                    .collect(),
+            })
+        })
+    }
+
     /// Queries the DB for DBItems that hold 'significant sync times'.
     /// A sync time is significant, if it has entries stored in the DB.
     /// After running `clean_up_local_sync_times` this function should only return
@@ -1434,6 +2149,10 @@ impl MetadataDB {
                 hash: "",
 
                 is_read_only: false,
+
+                hard_link_group: None,
+
+                size: 0,
             })
             .execute(&self.conn)?;
 
@@ -1475,7 +2194,13 @@ impl MetadataDB {
 
     /// Changes the connection DB settings to our default usage pattern.
     fn default_db_settings(&self) -> Result<()> {
-        sql_query("PRAGMA locking_mode = EXCLUSIVE").execute(&self.conn)?;
+        if self.allows_readers {
+            // WAL readers (see open_reader) require the shared-memory index,
+            // which is not available for exclusive connections.
+            sql_query("PRAGMA locking_mode = NORMAL").execute(&self.conn)?;
+        } else {
+            sql_query("PRAGMA locking_mode = EXCLUSIVE").execute(&self.conn)?;
+        }
         sql_query("PRAGMA journal_mode = WAL").execute(&self.conn)?;
         sql_query("PRAGMA foreign_keys = 1").execute(&self.conn)?;
 
@@ -1486,7 +2211,19 @@ impl MetadataDB {
         Ok(())
     }
 
-    #[cfg(test)]
+    /// Changes the connection DB settings to read-only access next to a writing connection.
+    fn reader_db_settings(&self) -> Result<()> {
+        sql_query("PRAGMA query_only = 1").execute(&self.conn)?;
+        // Checkpoints of the writer might shortly lock the DB, wait for them to finish.
+        sql_query("PRAGMA busy_timeout = 5000").execute(&self.conn)?;
+
+        // Set 'about' 64MB limit for RAM used to cache
+        sql_query("PRAGMA cache_size = -64000").execute(&self.conn)?;
+
+        Ok(())
+    }
+
+    /// Writes a consistent copy of the DB to the target file (which must not exist yet).
     pub fn dump_db(&self, target: &str) -> Result<()> {
         sql_query("VACUUM INTO ?")
             .bind::<diesel::sql_types::Text, _>(target)
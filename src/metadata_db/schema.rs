@@ -21,6 +21,17 @@ table! {
         is_transfer_store -> Bool,
 
         time -> BigInt,
+
+        trust_mtime -> Bool,
+        last_sync_completed -> Nullable<Timestamp>,
+        propagate_case_changes -> Bool,
+        hash_algorithm -> Text,
+        rehash_progress -> Nullable<BigInt>,
+        partial_content -> Bool,
+        stalled_syncs -> BigInt,
+        normalize_unicode -> Bool,
+        min_file_age -> Nullable<BigInt>,
+        ignore_file_name -> Nullable<Text>,
     }
 }
 
@@ -31,6 +42,7 @@ table! {
 
         rule_glob -> Text,
         include -> Bool,
+        position -> BigInt,
     }
 }
 
@@ -63,6 +75,10 @@ table! {
         hash -> Text,
 
         is_read_only -> Bool,
+
+        hard_link_group -> Nullable<Text>,
+
+        size -> BigInt,
     }
 }
 
@@ -45,6 +45,8 @@ fn insert_data_item(metadata_store: &MetadataDB, name: &str, is_file: bool) {
             is_file,
             "",
             false,
+            None,
+            0,
         )
         .unwrap();
 }
@@ -129,6 +131,55 @@ fn clean_up_simple_deletions() {
     assert_eq!(metadata_store.count_path_components_in_db().unwrap(), 1);
 }
 
+#[test]
+fn clean_orphaned_metadata_rows() {
+    let metadata_store = open_metadata_store();
+    let (_data_set, data_store) = insert_sample_data_set(&metadata_store);
+    insert_data_item(&metadata_store, "folder-1", false);
+    insert_data_item(&metadata_store, "folder-1/file-1", true);
+    assert_eq!(metadata_store.clean_orphaned_metadata().unwrap(), 0);
+    let folder_mod_time = metadata_store
+        .get_local_data_item(&RelativePath::from_path("folder-1"), true)
+        .unwrap()
+        .mod_time()
+        .clone();
+
+    // Simulate a broken DB, i.e. metadata rows for the non-existing item 1000.
+    sql_query("PRAGMA foreign_keys = 0")
+        .execute(&metadata_store.conn)
+        .unwrap();
+    sql_query("INSERT INTO file_system_metadatas(id, case_sensitive_name, creation_time, mod_time, hash, is_read_only) VALUES (1000, 'orphan', '', '', '', 0)").execute(&metadata_store.conn).unwrap();
+    sql_query("INSERT INTO mod_metadatas(id, creator_store_id, creator_store_time, last_mod_store_id, last_mod_store_time) VALUES (1000, ?, 1, ?, 1)")
+        .bind::<diesel::sql_types::BigInt, _>(data_store.id)
+        .bind::<diesel::sql_types::BigInt, _>(data_store.id)
+        .execute(&metadata_store.conn)
+        .unwrap();
+    sql_query("INSERT INTO mod_times(mod_metadata_id, data_store_id, time) VALUES (1000, ?, 1)")
+        .bind::<diesel::sql_types::BigInt, _>(data_store.id)
+        .execute(&metadata_store.conn)
+        .unwrap();
+    sql_query("INSERT INTO sync_times(item_id, data_store_id, time) VALUES (1000, ?, 1)")
+        .bind::<diesel::sql_types::BigInt, _>(data_store.id)
+        .execute(&metadata_store.conn)
+        .unwrap();
+    sql_query("PRAGMA foreign_keys = 1")
+        .execute(&metadata_store.conn)
+        .unwrap();
+
+    assert_eq!(metadata_store.clean_orphaned_metadata().unwrap(), 4);
+    assert_eq!(metadata_store.clean_orphaned_metadata().unwrap(), 0);
+
+    // Valid items are untouched.
+    let item = metadata_store
+        .get_local_data_item(&RelativePath::from_path("folder-1/file-1"), true)
+        .unwrap();
+    assert!(item.is_file());
+    let folder = metadata_store
+        .get_local_data_item(&RelativePath::from_path("folder-1"), true)
+        .unwrap();
+    assert_eq!(folder.mod_time(), &folder_mod_time);
+}
+
 #[test]
 fn clean_up_synced_deletions() {
     let metadata_store = open_metadata_store();
@@ -608,3 +659,129 @@ fn store_inclusion_rules() {
     let required_rules = metadata_store.get_inclusion_rules(&data_store).unwrap();
     assert_eq!(rules, required_rules);
 }
+
+#[test]
+fn refuse_to_open_db_of_newer_builds() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let db_path = test_dir.path().join("metadata.sqlite");
+    {
+        let metadata_store = MetadataDB::open(db_path.to_str().unwrap()).unwrap();
+        sql_query("PRAGMA user_version = 10000")
+            .execute(&metadata_store.conn)
+            .unwrap();
+    }
+
+    match MetadataDB::open(db_path.to_str().unwrap()) {
+        Err(MetadataDBError::DBMigrationError {
+            source: db_migration::MigrationError::DBVersionTooNew { found: 10000, .. },
+        }) => (),
+        _ => panic!("Must not open DBs written by newer builds!"),
+    }
+}
+
+#[test]
+fn read_concurrently_from_reader_connection() {
+    let test_dir = tempfile::tempdir().unwrap();
+    let db_path = test_dir.path().join("metadata.sqlite");
+    let metadata_store = MetadataDB::open(db_path.to_str().unwrap()).unwrap();
+    let (_data_set, _data_store) = insert_sample_data_set(&metadata_store);
+    insert_data_item(&metadata_store, "folder-1", false);
+
+    // Readers are independent connections, that is not possible for in-memory DBs...
+    match open_metadata_store().open_reader() {
+        Err(MetadataDBError::ReaderUnavailable { .. }) => (),
+        _ => panic!("Must not open readers on in-memory DBs!"),
+    }
+    // ...or for DBs locked exclusively (the default).
+    match metadata_store.open_reader() {
+        Err(MetadataDBError::ReaderUnavailable { .. }) => (),
+        _ => panic!("Must not open readers on exclusively locked DBs!"),
+    }
+    drop(metadata_store);
+    let metadata_store = MetadataDB::open_shared(db_path.to_str().unwrap()).unwrap();
+
+    const NUMBER_OF_FILES: usize = 200;
+    let reader = metadata_store.open_reader().unwrap();
+    let reader_thread = std::thread::spawn(move || {
+        let mut last_seen = 0;
+        while last_seen < NUMBER_OF_FILES {
+            let child_items = reader
+                .get_local_child_items(&RelativePath::from_path("folder-1"), true)
+                .unwrap();
+            // We always see a consistent, committed state of the DB.
+            assert!(child_items.len() >= last_seen);
+            assert!(child_items.iter().all(|item| item.is_file()));
+            last_seen = child_items.len();
+        }
+
+        let item = reader
+            .get_local_data_item(&RelativePath::from_path("folder-1/file-0"), true)
+            .unwrap();
+        assert!(item.is_file());
+
+        // The reader connection must not modify the DB.
+        assert!(reader.update_data_set_name("xyz").is_err());
+    });
+
+    for i in 0..NUMBER_OF_FILES {
+        insert_data_item(&metadata_store, &format!("folder-1/file-{}", i), true);
+    }
+    reader_thread.join().unwrap();
+
+    assert_eq!(
+        metadata_store
+            .get_local_child_items(&RelativePath::from_path("folder-1"), true)
+            .unwrap()
+            .len(),
+        NUMBER_OF_FILES
+    );
+}
+
+#[test]
+fn list_items_changed_since_a_local_time() {
+    let metadata_store = open_metadata_store();
+    insert_sample_data_set(&metadata_store);
+
+    insert_data_item(&metadata_store, "folder", false);
+    insert_data_item(&metadata_store, "folder/file-1", true);
+    insert_data_item(&metadata_store, "folder/file-2", true);
+    let export_time = metadata_store.get_local_data_store().unwrap().time;
+
+    let changed_paths = |time: i64| -> Vec<String> {
+        metadata_store
+            .items_changed_since(time)
+            .unwrap()
+            .iter()
+            .map(|item| item.path.to_path_buf().to_str().unwrap().to_string())
+            .collect()
+    };
+    assert!(changed_paths(export_time).is_empty());
+    // The root folder is created along with the data store.
+    assert_eq!(
+        changed_paths(0),
+        vec!["", "folder", "folder/file-1", "folder/file-2"]
+    );
+
+    // Changes are listed in the order they happened.
+    insert_data_item(&metadata_store, "folder/file-3", true);
+    insert_data_item(&metadata_store, "folder/file-1", true);
+    assert_eq!(
+        changed_paths(export_time),
+        vec!["folder/file-3", "folder/file-1"]
+    );
+
+    // Deletions are reported through the folder holding them.
+    delete_data_item(&metadata_store, "folder/file-2");
+    assert_eq!(
+        changed_paths(export_time),
+        vec!["folder/file-3", "folder/file-1", "folder"]
+    );
+
+    // ...the root folder holds the deletions of top level items.
+    insert_data_item(&metadata_store, "file-4", true);
+    delete_data_item(&metadata_store, "file-4");
+    assert_eq!(
+        changed_paths(export_time),
+        vec!["folder/file-3", "folder/file-1", "folder", ""]
+    );
+}
@@ -0,0 +1,12 @@
+use crate::fs_interaction::relative_path::RelativePath;
+
+/// Aggregated statistics of a sub-tree of the local data store (e.g. for a 'du' like summary).
+/// Only non-deleted, non-ignored items are taken into account.
+#[derive(PartialEq, Debug, Clone)]
+pub struct TreeSummary {
+    pub files: usize,
+    pub directories: usize,
+    pub total_bytes: u64,
+    /// The largest files of the sub-tree with their sizes, largest first.
+    pub largest_files: Vec<(RelativePath, u64)>,
+}
@@ -31,15 +31,34 @@ impl<Key: PartialEq + Eq + Hash + Clone + Debug> VersionVector<Key> {
     }
 
     pub fn from_initial_values(values: Vec<(&Key, i64)>) -> Self {
+        Self::from_pairs(values.into_iter().map(|(key, time)| (key.clone(), time)))
+    }
+
+    /// Builds a vector from (key, time) pairs. Zero entries are dropped, as a missing key
+    /// is equal to a zero entry. Duplicate keys keep the last given time.
+    pub fn from_pairs<I: IntoIterator<Item = (Key, i64)>>(pairs: I) -> Self {
         let mut result = Self::new();
-        result.versions.reserve(values.len());
-        for (key, time) in values {
-            result[&key] = time;
+        for (key, time) in pairs {
+            if time == 0 {
+                result.versions.remove(&key);
+            } else {
+                result.versions.insert(key, time);
+            }
         }
 
         result
     }
 
+    /// Returns all non-zero entries of the vector as (key, time) pairs, e.g. for serialization.
+    /// The inverse of from_pairs, the order of the pairs is unspecified.
+    pub fn to_pairs(&self) -> Vec<(Key, i64)> {
+        self.versions
+            .iter()
+            .filter(|(_, time)| **time != 0)
+            .map(|(key, time)| (key.clone(), *time))
+            .collect()
+    }
+
     fn less_or_equal(&self, other: &Self) -> bool {
         for (key, self_value) in &self.versions {
             let other_value = other.versions.get(key).unwrap_or(&0);
@@ -194,6 +213,35 @@ mod tests {
         assert_eq!(v2 <= v3, false);
     }
 
+    #[test]
+    fn round_trip_through_pairs() {
+        let peer_a = String::from("A");
+        let peer_b = String::from("B");
+        let peer_c = String::from("C");
+
+        let mut vector = VersionVector::new();
+        vector[&peer_a] = 3;
+        vector[&peer_b] = 0;
+        vector[&peer_c] = 7;
+
+        let mut pairs = vector.to_pairs();
+        pairs.sort();
+        assert_eq!(pairs, vec![(peer_a.clone(), 3), (peer_c.clone(), 7)]);
+
+        let restored = VersionVector::from_pairs(pairs);
+        assert!(restored == vector);
+        assert_eq!(restored.iter().count(), 2);
+
+        // Explicit zeros are dropped on construction, later pairs override earlier ones.
+        let vector = VersionVector::from_pairs(vec![
+            (peer_a.clone(), 1),
+            (peer_b.clone(), 0),
+            (peer_a.clone(), 0),
+        ]);
+        assert_eq!(vector.iter().count(), 0);
+        assert!(vector == VersionVector::new());
+    }
+
     #[test]
     fn maximum() {
         let mut vec_1 = VersionVector::new();
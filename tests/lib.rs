@@ -67,6 +67,91 @@ mod tests {
         println!("{:?}", assert.get_output());
     }
 
+    fn collect_println_usages(path: &std::path::Path, usages: &mut Vec<String>) {
+        for entry in std::fs::read_dir(path).unwrap() {
+            let entry_path = entry.unwrap().path();
+            if entry_path.is_dir() {
+                collect_println_usages(&entry_path, usages);
+            } else if entry_path.extension().is_some_and(|ext| ext == "rs") {
+                let content = std::fs::read_to_string(&entry_path).unwrap();
+                for (line_number, line) in content.lines().enumerate() {
+                    // Tests are free to print whatever they like.
+                    if line.contains("#[cfg(test)]") {
+                        break;
+                    }
+                    if line.contains("println!") || line.contains("print!(") {
+                        usages.push(format!("{}:{}", entry_path.display(), line_number + 1));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn create_and_scan_in_memory_store() {
+        let dir = TempDir::new().unwrap();
+        let store_path = dir.path().join("in-memory-store");
+
+        main_cmd()
+            .arg(&store_path)
+            .args(vec!["--fs", "memory", "create", "--name", "XYZ"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Created new store"));
+        main_cmd()
+            .arg(&store_path)
+            .args(vec!["--fs", "memory", "scan"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Scan Complete"));
+
+        // Nothing must be written to the actual disk.
+        assert!(!store_path.exists());
+    }
+
+    #[test]
+    fn run_session_of_commands_on_in_memory_stores() {
+        let session = "\
+            ./store-1 create --name XYZ --store-name laptop
+            ./store-2 create --name XYZ --store-name desktop
+            # The stores created above are used by all following commands.
+            ./store-1 scan
+            ./store-2 sync-from ./store-1
+            ./store-2 stores
+            ./store-1 check-pair ./store-2
+            ./store-1 unlock
+        ";
+
+        main_cmd()
+            .args(vec!["--fs", "memory", "-"])
+            .write_stdin(session)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Sync Complete"))
+            .stdout(predicate::str::contains("(laptop, time: 0, last synced"))
+            .stdout(predicate::str::contains(
+                "Compatible: both stores belong to the data set 'XYZ'.",
+            ))
+            .stdout(predicate::str::contains(
+                "The lock is held by the running process",
+            ));
+    }
+
+    #[test]
+    fn library_code_does_not_print() {
+        let src_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut usages = Vec::new();
+        for module in ["data_store", "metadata_db", "fs_interaction"] {
+            collect_println_usages(&src_dir.join(module), &mut usages);
+        }
+
+        assert!(
+            usages.is_empty(),
+            "Library code must use the log macros instead of printing: {:?}",
+            usages
+        );
+    }
+
     #[test]
     fn basic_two_folder_sync() {
         let dir_1 = tempfile::tempdir().unwrap();
@@ -118,6 +203,43 @@ mod tests {
         assert_file(&dir_2, "file-1", "content 1");
     }
 
+    #[test]
+    fn config_file_supplies_flag_defaults() {
+        let dir_1 = tempfile::tempdir().unwrap();
+        let dir_2 = tempfile::tempdir().unwrap();
+        cmd_success(&dir_1, "create", vec!["--name='XYZ'"]);
+        cmd_success(&dir_2, "create", vec!["--name='XYZ'"]);
+        create_file(
+            &dir_1,
+            ".__data_squirrel__/config.toml",
+            "[sync-from]\nchoose-remote = true\n",
+        );
+
+        create_file(&dir_1, "file-1", "content 1");
+        create_file(&dir_2, "file-1", "content 2");
+        create_file(&dir_1, "file-2", "content 1");
+        create_file(&dir_2, "file-2", "content 2");
+        cmd_success(&dir_1, "scan", vec![]);
+        cmd_success(&dir_2, "scan", vec![]);
+
+        // The config file resolves all conflicts in favour of the remote store...
+        cmd_success(&dir_1, "sync-from", vec![dir_2.path().to_str().unwrap()]);
+        assert_file(&dir_1, "file-1", "content 2");
+        assert_file(&dir_1, "file-2", "content 2");
+
+        // ...unless the command line says otherwise.
+        create_file(&dir_1, "file-2", "local change");
+        create_file(&dir_2, "file-2", "remote change");
+        cmd_success(&dir_1, "scan", vec![]);
+        cmd_success(&dir_2, "scan", vec![]);
+        cmd_success(
+            &dir_1,
+            "sync-from",
+            vec![dir_2.path().to_str().unwrap(), "--choose-local"],
+        );
+        assert_file(&dir_1, "file-2", "local change");
+    }
+
     #[test]
     fn basic_ignore_rules() {
         let dir_1 = tempfile::tempdir().unwrap();
@@ -153,4 +275,44 @@ mod tests {
             vec![".__data_squirrel__", "file-2-1", "file-2-2", "file-1-2"],
         );
     }
+
+    #[test]
+    fn status_shows_store_overview() {
+        let dir = tempfile::tempdir().unwrap();
+        cmd_success(&dir, "create", vec!["--name=XYZ", "--store-name=laptop"]);
+
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+        create_file(&dir, "file-1", "content 1");
+        create_file(&dir, "sub/file-2", "content 2");
+        cmd_success(&dir, "scan", vec![]);
+
+        cmd_should_print(&dir, "status", vec![], "Data set: XYZ");
+        cmd_should_print(&dir, "status", vec![], "Local store: laptop-");
+        cmd_should_print(&dir, "status", vec![], "Items: 2 files, 1 folders");
+        cmd_should_print(&dir, "status", vec![], "Known data stores: 1");
+        cmd_should_print(&dir, "status", vec![], "Unresolved: nothing");
+    }
+
+    #[test]
+    fn check_pair_compares_data_sets() {
+        let dir_1 = tempfile::tempdir().unwrap();
+        let dir_2 = tempfile::tempdir().unwrap();
+        let dir_3 = tempfile::tempdir().unwrap();
+        cmd_success(&dir_1, "create", vec!["--name=XYZ"]);
+        cmd_success(&dir_2, "create", vec!["--name=XYZ"]);
+        cmd_success(&dir_3, "create", vec!["--name=ABC"]);
+
+        cmd_should_print(
+            &dir_1,
+            "check-pair",
+            vec![dir_2.path().to_str().unwrap()],
+            "Compatible: both stores belong to the data set 'XYZ'.",
+        );
+        cmd_should_print(
+            &dir_1,
+            "check-pair",
+            vec![dir_3.path().to_str().unwrap()],
+            "Incompatible: the stores belong to different data sets ('XYZ' and 'ABC').",
+        );
+    }
 }